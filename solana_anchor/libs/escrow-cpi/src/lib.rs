@@ -0,0 +1,216 @@
+//! Stable entry points for programs that open Mobazha escrows by CPI.
+//!
+//! A marketplace program can create the escrow inside its own instruction,
+//! for example `create_order`, with [`open_sol_escrow`] or [`open_token_escrow`].
+//! The payer may be a PDA of the calling program: pass its seeds as
+//! `signer_seeds` and the escrow program sees it as a signer through
+//! `invoke_signed`. A lamport-paying PDA must be owned by the system program
+//! and hold no data, because the system program moves its lamports. Both calls
+//! return the [`EscrowCreated`] that escrow-program writes as return data, so
+//! the caller can store the escrow address and unique_id with its order.
+//!
+//! The escrow address is fixed by its parties and unique_id. Callers can derive it
+//! before the CPI to pass it in the accounts list:
+//!
+//! ```
+//! use anchor_lang::prelude::Pubkey;
+//! use mobazha_escrow_cpi::{sol_escrow_address, token_escrow_address, ESCROW_PROGRAM_ID};
+//!
+//! let buyer = Pubkey::new_unique();
+//! let seller = Pubkey::new_unique();
+//! let unique_id = [7u8; 20];
+//!
+//! let escrow = sol_escrow_address(&buyer, &seller, false, &unique_id);
+//! let (expected, _) = Pubkey::find_program_address(
+//!     &[b"sol_escrow", buyer.as_ref(), seller.as_ref(), &[0], &unique_id],
+//!     &ESCROW_PROGRAM_ID,
+//! );
+//! assert_eq!(escrow, expected);
+//! assert_ne!(escrow, token_escrow_address(&buyer, &seller, false, &unique_id));
+//! ```
+
+use anchor_lang::prelude::*;
+use mobazha_escrow_program::cpi;
+
+pub use mobazha_escrow_program::state::{EscrowCreated, EscrowOptions};
+pub use mobazha_escrow_program::ID as ESCROW_PROGRAM_ID;
+
+/// Terms of a new escrow, the same for SOL and token escrows
+#[derive(Clone, Debug)]
+pub struct EscrowParams {
+    pub moderator: Option<Pubkey>,
+    pub unique_id: [u8; 20],
+    pub required_signatures: u8,
+    pub unlock_hours: u64,
+    pub amount: u64,
+    pub options: EscrowOptions,
+}
+
+/// Accounts for [`open_sol_escrow`]
+pub struct OpenSolEscrow<'info> {
+    pub escrow_program: AccountInfo<'info>,
+    /// Wallet or system-owned PDA funding the escrow and its rent
+    pub payer: AccountInfo<'info>,
+    pub buyer: AccountInfo<'info>,
+    pub seller: AccountInfo<'info>,
+    /// [`sol_escrow_address`] of the parties and unique_id
+    pub escrow_account: AccountInfo<'info>,
+    /// [`receipt_address`] of the escrow, which must not exist yet
+    pub receipt: AccountInfo<'info>,
+    /// Only for escrows created with `options.receipt_tree`
+    pub receipt_tree: Option<AccountInfo<'info>>,
+    /// Only for escrows created with `options.require_registered_moderator`
+    pub moderator_profile: Option<AccountInfo<'info>>,
+    /// The moderator registry's config PDA, whenever `moderator_profile` is passed
+    pub registry_config: Option<AccountInfo<'info>>,
+    /// Only for escrows created with `options.min_seller_settlements`
+    pub seller_reputation: Option<AccountInfo<'info>>,
+    pub system_program: AccountInfo<'info>,
+    pub rent: AccountInfo<'info>,
+    pub clock: AccountInfo<'info>,
+}
+
+/// Accounts for [`open_token_escrow`]
+pub struct OpenTokenEscrow<'info> {
+    pub escrow_program: AccountInfo<'info>,
+    /// Wallet or PDA funding the escrow; authority of `payer_token_account`
+    pub payer: AccountInfo<'info>,
+    pub buyer: AccountInfo<'info>,
+    pub seller: AccountInfo<'info>,
+    /// [`token_escrow_address`] of the parties and unique_id
+    pub escrow_account: AccountInfo<'info>,
+    /// [`receipt_address`] of the escrow, which must not exist yet
+    pub receipt: AccountInfo<'info>,
+    /// Only for escrows created with `options.receipt_tree`
+    pub receipt_tree: Option<AccountInfo<'info>>,
+    pub token_program: AccountInfo<'info>,
+    pub token_mint: AccountInfo<'info>,
+    pub associated_token_program: AccountInfo<'info>,
+    /// Source of the escrowed tokens; left out to wrap lamports for the native mint
+    pub payer_token_account: Option<AccountInfo<'info>>,
+    /// Associated token account of the escrow for `token_mint`; left out for ledger escrows
+    pub escrow_token_account: Option<AccountInfo<'info>>,
+    /// Seller's [`seller_balance_address`]; set together with `balance_token_account` to
+    /// deposit into the seller's payout ledger instead of a per-escrow vault
+    pub seller_balance: Option<AccountInfo<'info>>,
+    /// Associated token account of `seller_balance` for `token_mint`
+    pub balance_token_account: Option<AccountInfo<'info>>,
+    /// Only for escrows created with `options.require_registered_moderator`
+    pub moderator_profile: Option<AccountInfo<'info>>,
+    /// The moderator registry's config PDA, whenever `moderator_profile` is passed
+    pub registry_config: Option<AccountInfo<'info>>,
+    /// Only for escrows created with `options.min_seller_settlements`
+    pub seller_reputation: Option<AccountInfo<'info>>,
+    pub system_program: AccountInfo<'info>,
+    pub rent: AccountInfo<'info>,
+    pub clock: AccountInfo<'info>,
+}
+
+/// Open a SOL escrow. `signer_seeds` sign for a PDA payer and may be empty for a wallet.
+pub fn open_sol_escrow<'info>(
+    accounts: OpenSolEscrow<'info>,
+    params: EscrowParams,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<EscrowCreated> {
+    let ctx = CpiContext::new_with_signer(
+        accounts.escrow_program,
+        cpi::accounts::InitializeSol {
+            payer: accounts.payer,
+            buyer: accounts.buyer,
+            seller: accounts.seller,
+            escrow_account: accounts.escrow_account,
+            receipt: accounts.receipt,
+            receipt_tree: accounts.receipt_tree,
+            moderator_profile: accounts.moderator_profile,
+            registry_config: accounts.registry_config,
+            seller_reputation: accounts.seller_reputation,
+            system_program: accounts.system_program,
+            rent: accounts.rent,
+            clock: accounts.clock,
+        },
+        signer_seeds,
+    );
+
+    cpi::initialize_sol(
+        ctx,
+        params.moderator,
+        params.unique_id,
+        params.required_signatures,
+        params.unlock_hours,
+        params.amount,
+        params.options,
+    )
+    .map(|created| created.get())
+}
+
+/// Open an SPL token escrow. `signer_seeds` sign for a PDA payer and may be empty for a wallet.
+pub fn open_token_escrow<'info>(
+    accounts: OpenTokenEscrow<'info>,
+    params: EscrowParams,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<EscrowCreated> {
+    let ctx = CpiContext::new_with_signer(
+        accounts.escrow_program,
+        cpi::accounts::InitializeToken {
+            payer: accounts.payer,
+            buyer: accounts.buyer,
+            seller: accounts.seller,
+            escrow_account: accounts.escrow_account,
+            receipt: accounts.receipt,
+            receipt_tree: accounts.receipt_tree,
+            token_program: accounts.token_program,
+            token_mint: accounts.token_mint,
+            associated_token_program: accounts.associated_token_program,
+            payer_token_account: accounts.payer_token_account,
+            escrow_token_account: accounts.escrow_token_account,
+            seller_balance: accounts.seller_balance,
+            balance_token_account: accounts.balance_token_account,
+            moderator_profile: accounts.moderator_profile,
+            registry_config: accounts.registry_config,
+            seller_reputation: accounts.seller_reputation,
+            system_program: accounts.system_program,
+            rent: accounts.rent,
+            clock: accounts.clock,
+        },
+        signer_seeds,
+    );
+
+    cpi::initialize_token(
+        ctx,
+        params.moderator,
+        params.unique_id,
+        params.required_signatures,
+        params.unlock_hours,
+        params.amount,
+        params.options,
+    )
+    .map(|created| created.get())
+}
+
+/// Address of the SOL escrow for these parties and unique_id
+pub fn sol_escrow_address(buyer: &Pubkey, seller: &Pubkey, has_moderator: bool, unique_id: &[u8; 20]) -> Pubkey {
+    escrow_address(b"sol_escrow", buyer, seller, has_moderator, unique_id)
+}
+
+/// Address of the token escrow for these parties and unique_id
+pub fn token_escrow_address(buyer: &Pubkey, seller: &Pubkey, has_moderator: bool, unique_id: &[u8; 20]) -> Pubkey {
+    escrow_address(b"token_escrow", buyer, seller, has_moderator, unique_id)
+}
+
+/// Address of the settlement receipt an escrow leaves behind
+pub fn receipt_address(escrow: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"receipt", escrow.as_ref()], &ESCROW_PROGRAM_ID).0
+}
+
+/// Address of the seller's payout ledger for `mint`
+pub fn seller_balance_address(seller: &Pubkey, mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"seller_balance", seller.as_ref(), mint.as_ref()], &ESCROW_PROGRAM_ID).0
+}
+
+fn escrow_address(prefix: &[u8], buyer: &Pubkey, seller: &Pubkey, has_moderator: bool, unique_id: &[u8; 20]) -> Pubkey {
+    Pubkey::find_program_address(
+        &[prefix, buyer.as_ref(), seller.as_ref(), &[has_moderator as u8], unique_id],
+        &ESCROW_PROGRAM_ID,
+    )
+    .0
+}
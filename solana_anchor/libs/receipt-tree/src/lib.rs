@@ -0,0 +1,249 @@
+//! Append-only Merkle tree behind escrow-program's compressed receipts.
+//!
+//! The program stores only the rightmost path of the tree (`filled_subtrees`)
+//! and a ring of recent roots, and appends leaves with [`append`]. Clients
+//! rebuild the full tree from the `ReceiptLeafAppended` events with
+//! [`ReceiptTreeBuilder`] and pass the resulting proof to `verify_receipt`.
+//! A proof stays valid while its root is still in the program's root history,
+//! so leaves appended by other transactions in the meantime do not break it.
+//!
+//! ```
+//! use mobazha_receipt_tree::{append, empty_root, hash_leaf, root_from_proof, ReceiptTreeBuilder};
+//!
+//! let depth = 4;
+//! let mut filled_subtrees = vec![[0u8; 32]; depth];
+//! let mut builder = ReceiptTreeBuilder::new(depth);
+//! let mut root = empty_root(depth);
+//! assert_eq!(builder.root(), root);
+//!
+//! for data in [b"created".as_slice(), b"settled", b"created again"] {
+//!     let leaf = hash_leaf(data);
+//!     root = append(&mut filled_subtrees, builder.len() as u64, leaf).unwrap();
+//!     builder.push(leaf);
+//! }
+//! assert_eq!(builder.root(), root);
+//!
+//! let proof = builder.proof(1).unwrap();
+//! assert_eq!(root_from_proof(hash_leaf(b"settled"), 1, &proof), root);
+//! assert_ne!(root_from_proof(hash_leaf(b"settled"), 0, &proof), root);
+//! ```
+
+use solana_keccak_hasher::hashv;
+
+pub type Node = [u8; 32];
+
+// Domain separation so an inner node can never be passed off as a leaf
+const LEAF_PREFIX: &[u8] = &[0];
+const NODE_PREFIX: &[u8] = &[1];
+
+/// Hash serialized leaf data into a tree leaf
+pub fn hash_leaf(data: &[u8]) -> Node {
+    hashv(&[LEAF_PREFIX, data]).to_bytes()
+}
+
+/// Hash two child nodes into their parent
+pub fn hash_node(left: &Node, right: &Node) -> Node {
+    hashv(&[NODE_PREFIX, left, right]).to_bytes()
+}
+
+/// Root of an empty subtree `level` levels high; level 0 is an empty leaf
+pub fn empty_node(level: usize) -> Node {
+    (0..level).fold([0u8; 32], |node, _| hash_node(&node, &node))
+}
+
+/// Root of a tree of `depth` with no leaves
+pub fn empty_root(depth: usize) -> Node {
+    empty_node(depth)
+}
+
+/// Number of leaves a tree of `depth` can hold
+pub fn capacity(depth: usize) -> u64 {
+    1u64 << depth
+}
+
+/// Append `leaf` at `index` (the current leaf count) and return the new root.
+/// `filled_subtrees` has one entry per level and is updated in place. Returns
+/// None when the tree is full.
+pub fn append(filled_subtrees: &mut [Node], index: u64, leaf: Node) -> Option<Node> {
+    if index >= capacity(filled_subtrees.len()) {
+        return None;
+    }
+
+    let mut node = leaf;
+    let mut empty = [0u8; 32];
+    for (level, filled) in filled_subtrees.iter_mut().enumerate() {
+        if (index >> level) & 1 == 0 {
+            // Left child: remember it for the leaves that will land on its right
+            *filled = node;
+            node = hash_node(&node, &empty);
+        } else {
+            node = hash_node(filled, &node);
+        }
+        empty = hash_node(&empty, &empty);
+    }
+    Some(node)
+}
+
+/// Recompute the root from a leaf, its index and the sibling path from the leaf up.
+/// Only the low `proof.len()` bits of `index` are used, so check `index` against
+/// the tree's leaf count before trusting the result.
+pub fn root_from_proof(leaf: Node, index: u64, proof: &[Node]) -> Node {
+    proof.iter().enumerate().fold(leaf, |node, (level, sibling)| {
+        if (index >> level) & 1 == 0 {
+            hash_node(&node, sibling)
+        } else {
+            hash_node(sibling, &node)
+        }
+    })
+}
+
+/// Off-chain copy of a receipt tree, rebuilt from the appended leaves in order
+#[derive(Clone, Debug)]
+pub struct ReceiptTreeBuilder {
+    depth: usize,
+    leaves: Vec<Node>,
+}
+
+impl ReceiptTreeBuilder {
+    pub fn new(depth: usize) -> Self {
+        Self { depth, leaves: Vec::new() }
+    }
+
+    pub fn from_leaves(depth: usize, leaves: Vec<Node>) -> Self {
+        Self { depth, leaves }
+    }
+
+    /// Add the next leaf and return its index
+    pub fn push(&mut self, leaf: Node) -> u64 {
+        self.leaves.push(leaf);
+        self.leaves.len() as u64 - 1
+    }
+
+    pub fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    /// Root over all leaves pushed so far
+    pub fn root(&self) -> Node {
+        self.levels()
+            .last()
+            .and_then(|level| level.first().copied())
+            .unwrap_or_else(|| empty_root(self.depth))
+    }
+
+    /// Sibling path for the leaf at `index`, against the current root
+    pub fn proof(&self, index: u64) -> Option<Vec<Node>> {
+        if index >= self.leaves.len() as u64 {
+            return None;
+        }
+
+        let levels = self.levels();
+        let mut position = index as usize;
+        let mut empty = [0u8; 32];
+        let mut proof = Vec::with_capacity(self.depth);
+        for nodes in levels.iter().take(self.depth) {
+            proof.push(nodes.get(position ^ 1).copied().unwrap_or(empty));
+            position >>= 1;
+            empty = hash_node(&empty, &empty);
+        }
+        Some(proof)
+    }
+
+    // Non-empty nodes of every level, from the leaves (level 0) up to the root (level `depth`)
+    fn levels(&self) -> Vec<Vec<Node>> {
+        let mut levels = vec![self.leaves.clone()];
+        let mut empty = [0u8; 32];
+        for level in 0..self.depth {
+            let next = levels[level]
+                .chunks(2)
+                .map(|pair| hash_node(&pair[0], pair.get(1).unwrap_or(&empty)))
+                .collect();
+            levels.push(next);
+            empty = hash_node(&empty, &empty);
+        }
+        levels
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEPTH: usize = 3;
+
+    fn leaf(i: u64) -> Node {
+        hash_leaf(&i.to_le_bytes())
+    }
+
+    // Appends `count` leaves both on-chain style and to a builder, checking the roots agree
+    fn fill(count: u64) -> (Vec<Node>, ReceiptTreeBuilder, Node) {
+        let mut filled_subtrees = vec![[0u8; 32]; DEPTH];
+        let mut builder = ReceiptTreeBuilder::new(DEPTH);
+        let mut root = empty_root(DEPTH);
+        for i in 0..count {
+            root = append(&mut filled_subtrees, i, leaf(i)).unwrap();
+            builder.push(leaf(i));
+            assert_eq!(builder.root(), root);
+        }
+        (filled_subtrees, builder, root)
+    }
+
+    #[test]
+    fn full_tree_rejects_append_and_keeps_its_path() {
+        let (mut filled_subtrees, builder, root) = fill(capacity(DEPTH));
+        let before = filled_subtrees.clone();
+
+        assert_eq!(append(&mut filled_subtrees, capacity(DEPTH), leaf(99)), None);
+        assert_eq!(filled_subtrees, before);
+
+        // Every leaf of the full tree still proves against its root
+        for i in 0..capacity(DEPTH) {
+            let proof = builder.proof(i).unwrap();
+            assert_eq!(proof.len(), DEPTH);
+            assert_eq!(root_from_proof(leaf(i), i, &proof), root);
+        }
+    }
+
+    #[test]
+    fn out_of_range_proofs_fail() {
+        let (_, builder, root) = fill(5);
+
+        assert!(builder.proof(5).is_none());
+        assert!(builder.proof(capacity(DEPTH)).is_none());
+
+        // A valid proof presented with another index, or beyond the tree, gives a different root
+        let proof = builder.proof(4).unwrap();
+        assert_eq!(root_from_proof(leaf(4), 4, &proof), root);
+        assert_ne!(root_from_proof(leaf(4), 5, &proof), root);
+        // Index bits above the depth are not hashed, so callers must range-check the index
+        // against the leaf count themselves, as verify_receipt does
+        assert_eq!(root_from_proof(leaf(4), 4 + capacity(DEPTH), &proof), root);
+        // A leaf that was never appended cannot be proven in an unused slot
+        let sibling_path = builder.proof(4).unwrap();
+        assert_ne!(root_from_proof(leaf(5), 5, &sibling_path), root);
+    }
+
+    #[test]
+    fn stale_root_proofs_only_match_the_old_root() {
+        let (mut filled_subtrees, mut builder, old_root) = fill(3);
+        let stale_proof = builder.proof(1).unwrap();
+
+        let mut new_root = old_root;
+        for i in 3..6 {
+            new_root = append(&mut filled_subtrees, i, leaf(i)).unwrap();
+            builder.push(leaf(i));
+        }
+        assert_ne!(new_root, old_root);
+
+        // The program accepts the stale proof only while old_root is in its root history
+        assert_eq!(root_from_proof(leaf(1), 1, &stale_proof), old_root);
+        assert_ne!(root_from_proof(leaf(1), 1, &stale_proof), new_root);
+
+        let fresh_proof = builder.proof(1).unwrap();
+        assert_eq!(root_from_proof(leaf(1), 1, &fresh_proof), new_root);
+    }
+}
@@ -39,6 +39,7 @@ pub struct Contract {
 
 /// Contract Manager state
 #[account]
+#[derive(Default)]
 pub struct ContractManager {
     pub authority: Pubkey,
     pub contracts: Vec<Contract>,
@@ -85,13 +86,3 @@ impl ContractManager {
         self.find_contract(contract_name).map(|c| &c.versions)
     }
 }
-
-impl Default for ContractManager {
-    fn default() -> Self {
-        Self {
-            authority: Pubkey::default(),
-            contracts: Vec::new(),
-            bump: 0,
-        }
-    }
-}
@@ -0,0 +1,337 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+use crate::{error::*, state::{EscrowAccount, ReleaseApproval, SellerBalance}, utils::{close_escrow_and_return_rent, pay_lamports_from_escrow, process_release, transfer_escrowed_lamports}};
+
+/// PDA seeds of an escrow account, copied out so the vault can sign for it
+/// while the escrow itself stays borrowable
+#[derive(Clone, Copy)]
+pub struct EscrowSeeds {
+    prefix: &'static [u8],
+    buyer: Pubkey,
+    seller: Pubkey,
+    has_moderator: [u8; 1],
+    unique_id: [u8; 20],
+    bump: [u8; 1],
+}
+
+impl EscrowSeeds {
+    pub fn new(prefix: &'static [u8], base: &EscrowAccount) -> Self {
+        Self {
+            prefix,
+            buyer: base.buyer,
+            seller: base.seller,
+            has_moderator: [base.moderator.is_some() as u8],
+            unique_id: base.unique_id,
+            bump: [base.bump],
+        }
+    }
+
+    fn signer(&self) -> [&[u8]; 6] {
+        [
+            self.prefix,
+            self.buyer.as_ref(),
+            self.seller.as_ref(),
+            &self.has_moderator,
+            &self.unique_id,
+            &self.bump,
+        ]
+    }
+}
+
+// Where the escrowed funds sit
+enum Holding<'a, 'info> {
+    // Lamports on the escrow account above its rent-exempt minimum
+    Lamports,
+    // An SPL token account owned by the escrow PDA
+    Token {
+        vault: &'a Account<'info, TokenAccount>,
+        token_program: &'a Program<'info, Token>,
+    },
+    // The seller's SellerBalance pool: the seller's share is credited in place,
+    // anything else is transferred out with the ledger PDA as authority
+    Ledger {
+        pool: &'a Account<'info, TokenAccount>,
+        token_program: &'a Program<'info, Token>,
+        ledger: &'a mut Account<'info, SellerBalance>,
+        escrowed: u64,
+        spent: u64,
+    },
+}
+
+/// A party receiving funds: lamports go to the wallet, tokens to its token account
+pub struct Payee<'a, 'info> {
+    pub wallet: &'a AccountInfo<'info>,
+    pub token_account: Option<&'a Account<'info, TokenAccount>>,
+}
+
+impl<'a, 'info> Payee<'a, 'info> {
+    pub fn wallet(wallet: &'a AccountInfo<'info>) -> Self {
+        Self { wallet, token_account: None }
+    }
+
+    pub fn token(wallet: &'a AccountInfo<'info>, token_account: &'a Account<'info, TokenAccount>) -> Self {
+        Self { wallet, token_account: Some(token_account) }
+    }
+}
+
+/// The funds of a SOL or token escrow behind one interface, so every
+/// settlement mode pays out and closes the escrow the same way for both assets
+pub struct EscrowVault<'a, 'info> {
+    escrow: AccountInfo<'info>,
+    seeds: EscrowSeeds,
+    holding: Holding<'a, 'info>,
+    // Initiator and system program, used to fund recipients that do not exist yet
+    rent_funder: Option<(AccountInfo<'info>, AccountInfo<'info>)>,
+}
+
+impl<'a, 'info> EscrowVault<'a, 'info> {
+    pub fn lamports(escrow: AccountInfo<'info>, seeds: EscrowSeeds) -> Self {
+        Self { escrow, seeds, holding: Holding::Lamports, rent_funder: None }
+    }
+
+    pub fn token(
+        escrow: AccountInfo<'info>,
+        seeds: EscrowSeeds,
+        vault: &'a Account<'info, TokenAccount>,
+        token_program: &'a Program<'info, Token>,
+    ) -> Self {
+        Self { escrow, seeds, holding: Holding::Token { vault, token_program }, rent_funder: None }
+    }
+
+    /// A ledger escrow whose `base.amount` sits in the seller's SellerBalance pool
+    pub fn ledger(
+        escrow: AccountInfo<'info>,
+        seeds: EscrowSeeds,
+        base: &EscrowAccount,
+        pool: &'a Account<'info, TokenAccount>,
+        ledger: &'a mut Account<'info, SellerBalance>,
+        token_program: &'a Program<'info, Token>,
+    ) -> Result<Self> {
+        require!(base.ledger, EscrowError::InvalidSellerBalance);
+        require_keys_eq!(ledger.seller, base.seller, EscrowError::InvalidSellerBalance);
+        require_keys_eq!(pool.mint, ledger.mint, EscrowError::TokenMintMismatch);
+        require_keys_eq!(
+            pool.key(),
+            get_associated_token_address(&ledger.key(), &ledger.mint),
+            EscrowError::InvalidSellerBalance
+        );
+
+        let holding = Holding::Ledger { pool, token_program, ledger, escrowed: base.amount, spent: 0 };
+        Ok(Self { escrow, seeds, holding, rent_funder: None })
+    }
+
+    /// Let lamport payouts to empty wallets first receive the rent-exempt minimum from `initiator`
+    pub fn fund_new_recipients(mut self, initiator: AccountInfo<'info>, system_program: AccountInfo<'info>) -> Self {
+        self.rent_funder = Some((initiator, system_program));
+        self
+    }
+
+    pub fn mint(&self) -> Option<Pubkey> {
+        match &self.holding {
+            Holding::Lamports => None,
+            Holding::Token { vault, .. } => Some(vault.mint),
+            Holding::Ledger { pool, .. } => Some(pool.mint),
+        }
+    }
+
+    /// Funds currently held, excluding the escrow's own rent
+    pub fn balance(&self) -> Result<u64> {
+        match &self.holding {
+            Holding::Lamports => {
+                let rent_exempt = Rent::get()?.minimum_balance(self.escrow.data_len());
+                Ok(self.escrow.lamports().saturating_sub(rent_exempt))
+            }
+            Holding::Token { vault, .. } => Ok(vault.amount),
+            Holding::Ledger { escrowed, spent, .. } => Ok(escrowed - spent),
+        }
+    }
+
+    /// Pay `amount` to `payee`; zero amounts are skipped
+    pub fn pay(&mut self, payee: &Payee<'_, 'info>, amount: u64) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+
+        match &mut self.holding {
+            Holding::Lamports => match &self.rent_funder {
+                Some((initiator, system_program)) => {
+                    pay_lamports_from_escrow(&self.escrow, payee.wallet, amount, initiator, system_program)
+                }
+                None => transfer_escrowed_lamports(&self.escrow, payee.wallet, amount),
+            },
+            Holding::Token { vault, token_program } => {
+                let destination = payee.token_account.ok_or(EscrowError::InvalidRecipient)?;
+                require_keys_eq!(destination.mint, vault.mint, EscrowError::TokenMintMismatch);
+
+                msg!("Transfer {} tokens to account {}", amount, destination.key());
+                token::transfer(
+                    CpiContext::new_with_signer(
+                        token_program.to_account_info(),
+                        Transfer {
+                            from: vault.to_account_info(),
+                            to: destination.to_account_info(),
+                            authority: self.escrow.clone(),
+                        },
+                        &[&self.seeds.signer()],
+                    ),
+                    amount,
+                )
+            }
+            Holding::Ledger { pool, token_program, ledger, escrowed, spent } => {
+                *spent = spent.checked_add(amount).ok_or(EscrowError::InvalidAmount)?;
+                require!(*spent <= *escrowed, EscrowError::InsufficientFunds);
+
+                // The seller's share stays in the pool and is only credited
+                if payee.wallet.key() == ledger.seller {
+                    msg!("Credit {} tokens to seller balance {}", amount, ledger.key());
+                    ledger.credited = ledger.credited.checked_add(amount).ok_or(EscrowError::InvalidAmount)?;
+                    return Ok(());
+                }
+
+                let destination = payee.token_account.ok_or(EscrowError::InvalidRecipient)?;
+                require_keys_eq!(destination.mint, pool.mint, EscrowError::TokenMintMismatch);
+
+                msg!("Transfer {} tokens to account {}", amount, destination.key());
+                let ledger_seed: &[&[u8]] = &[
+                    b"seller_balance",
+                    ledger.seller.as_ref(),
+                    ledger.mint.as_ref(),
+                    &[ledger.bump],
+                ];
+                token::transfer(
+                    CpiContext::new_with_signer(
+                        token_program.to_account_info(),
+                        Transfer {
+                            from: pool.to_account_info(),
+                            to: destination.to_account_info(),
+                            authority: ledger.to_account_info(),
+                        },
+                        &[ledger_seed],
+                    ),
+                    amount,
+                )
+            }
+        }
+    }
+
+    /// Close the vault and the escrow account, returning what is left to the buyer
+    pub fn close(&mut self, buyer: &AccountInfo<'info>) -> Result<()> {
+        match &mut self.holding {
+            Holding::Lamports => {}
+            Holding::Token { vault, token_program } => {
+                let (vault, token_program) = (*vault, *token_program);
+                self.close_token_vault(vault, token_program, buyer)?;
+            }
+            // The pool is shared, so the whole escrow must have been paid out of it
+            Holding::Ledger { ledger, escrowed, spent, .. } => {
+                require!(spent == escrowed, EscrowError::PayoutMismatch);
+                ledger.escrowed = ledger.escrowed.checked_sub(*escrowed).ok_or(EscrowError::InsufficientFunds)?;
+            }
+        }
+        close_escrow_and_return_rent(&self.escrow, buyer)
+    }
+
+    /// A native-mint vault paying no token accounts settles in lamports:
+    /// closing the vault moves the wrapped SOL onto the escrow account
+    pub fn unwrap_native(self, pays_token_accounts: bool) -> Result<Self> {
+        let Holding::Token { vault, token_program } = &self.holding else {
+            return Ok(self);
+        };
+        if !vault.is_native() || pays_token_accounts {
+            return Ok(self);
+        }
+
+        self.close_token_vault(vault, token_program, &self.escrow)?;
+        Ok(Self { holding: Holding::Lamports, ..self })
+    }
+
+    fn close_token_vault(
+        &self,
+        vault: &Account<'info, TokenAccount>,
+        token_program: &Program<'info, Token>,
+        destination: &AccountInfo<'info>,
+    ) -> Result<()> {
+        token::close_account(CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            token::CloseAccount {
+                account: vault.to_account_info(),
+                destination: destination.clone(),
+                authority: self.escrow.clone(),
+            },
+            &[&self.seeds.signer()],
+        ))
+    }
+}
+
+/// Signature-approved release shared by SOL, token and fixed-layout escrows:
+/// cooperative payouts, the seller-approved refund, and the seller-only release
+/// once the timelock has expired. Pays each approved recipient and closes the escrow.
+#[allow(clippy::too_many_arguments)]
+pub fn release_from_vault<'info>(
+    vault: EscrowVault<'_, 'info>,
+    base: &EscrowAccount,
+    payees: &[Option<Payee<'_, 'info>>],
+    payment_amounts: &[u64],
+    signatures: &[Vec<u8>],
+    valid_until: i64,
+    current_time: i64,
+    instructions_sysvar: &AccountInfo<'info>,
+    buyer: &AccountInfo<'info>,
+) -> Result<ReleaseApproval> {
+    let recipients: Vec<Option<Pubkey>> = payees
+        .iter()
+        .map(|payee| payee.as_ref().map(|payee| payee.wallet.key()))
+        .collect();
+
+    release_from_vault_for(
+        vault,
+        base,
+        &recipients,
+        payees,
+        payment_amounts,
+        signatures,
+        valid_until,
+        current_time,
+        instructions_sysvar,
+        buyer,
+    )
+}
+
+/// [`release_from_vault`] where the signed `recipients` are paid through `payees`
+/// holding the funds on their behalf, such as claim tickets
+#[allow(clippy::too_many_arguments)]
+pub fn release_from_vault_for<'info>(
+    vault: EscrowVault<'_, 'info>,
+    base: &EscrowAccount,
+    recipients: &[Option<Pubkey>],
+    payees: &[Option<Payee<'_, 'info>>],
+    payment_amounts: &[u64],
+    signatures: &[Vec<u8>],
+    valid_until: i64,
+    current_time: i64,
+    instructions_sysvar: &AccountInfo<'info>,
+    buyer: &AccountInfo<'info>,
+) -> Result<ReleaseApproval> {
+    process_release(
+        base,
+        signatures,
+        payment_amounts,
+        recipients,
+        valid_until,
+        current_time,
+        instructions_sysvar,
+        || {
+            let pays_token_accounts = payees.iter().flatten().any(|payee| payee.token_account.is_some());
+            let mut vault = vault.unwrap_native(pays_token_accounts)?;
+            for (i, amount) in payment_amounts.iter().enumerate() {
+                let payee = payees
+                    .get(i)
+                    .and_then(Option::as_ref)
+                    .ok_or(EscrowError::InvalidRecipient)?;
+                vault.pay(payee, *amount)?;
+            }
+            vault.close(buyer)
+        },
+    )
+}
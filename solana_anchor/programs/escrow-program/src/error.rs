@@ -64,4 +64,124 @@ pub enum EscrowError {
 
     #[msg("Timelock has not expired yet")]
     TimelockNotExpired,
-} 
\ No newline at end of file
+
+    #[msg("Release Approval Has Expired")]
+    SignatureExpired,
+
+    #[msg("Nonce Overflow")]
+    NonceOverflow,
+
+    #[msg("Invalid Batch Size")]
+    InvalidBatchSize,
+
+    #[msg("Claim Ticket Has Not Expired Yet")]
+    ClaimNotExpired,
+
+    #[msg("Invalid Escrow Asset")]
+    InvalidAsset,
+
+    #[msg("Asset Already Deposited")]
+    AssetAlreadyDeposited,
+
+    #[msg("Escrow Is Already Fully Funded")]
+    EscrowFullyFunded,
+
+    #[msg("Payouts Must Distribute The Entire Vault Balance")]
+    PayoutMismatch,
+
+    #[msg("Escrow Is Not Hash-Time-Locked")]
+    NotHashlocked,
+
+    #[msg("Hash-Time-Locked Escrow Can Only Be Refunded After The Deadline")]
+    HashlockedEscrow,
+
+    #[msg("Preimage Does Not Match Hashlock")]
+    InvalidPreimage,
+
+    #[msg("Hashlock Deadline Has Passed")]
+    HashlockExpired,
+
+    #[msg("Escrow Is Not A Stream")]
+    NotStreaming,
+
+    #[msg("Streaming Escrow Cannot Be Released By Timeout")]
+    StreamingEscrow,
+
+    #[msg("Invalid Stream Schedule")]
+    InvalidStreamSchedule,
+
+    #[msg("Nothing Vested To Withdraw")]
+    NothingVested,
+
+    #[msg("Recurring Escrow Is Paused")]
+    RecurringPaused,
+
+    #[msg("Recurring Escrow Is Not Paused")]
+    RecurringNotPaused,
+
+    #[msg("No Period Is Due")]
+    NothingDue,
+
+    #[msg("Crowdfund Deadline Has Passed")]
+    CrowdfundClosed,
+
+    #[msg("Funding Target Not Reached")]
+    TargetNotReached,
+
+    #[msg("Funding Target Was Reached")]
+    TargetReached,
+
+    #[msg("Invalid Delivery Oracle")]
+    InvalidOracle,
+
+    #[msg("Invalid Delivery Attestation")]
+    InvalidDeliveryAttestation,
+
+    #[msg("Moderator Fee Exceeds The Escrow Cap")]
+    ModeratorFeeTooHigh,
+
+    #[msg("Invalid Moderator")]
+    InvalidModerator,
+
+    #[msg("Invalid Moderator Panel")]
+    InvalidModeratorPanel,
+
+    #[msg("Moderator Not Registered")]
+    ModeratorNotRegistered,
+
+    #[msg("Insufficient Seller History")]
+    InsufficientSellerHistory,
+
+    #[msg("Escrow Already Settled")]
+    EscrowAlreadySettled,
+
+    #[msg("Invalid Receipt Account")]
+    InvalidReceiptAccount,
+
+    #[msg("Receipt Tree Full")]
+    ReceiptTreeFull,
+
+    #[msg("Invalid Receipt Proof")]
+    InvalidReceiptProof,
+
+    #[msg("Escrow Already Migrated")]
+    EscrowAlreadyMigrated,
+
+    #[msg("Option Not Supported By Fixed Escrows")]
+    UnsupportedByFixedEscrow,
+
+    #[msg("Release Hook Accounts Missing")]
+    ReleaseHookMissing,
+
+    #[msg("Invalid Release Hook")]
+    InvalidReleaseHook,
+
+    #[msg("Invalid Seller Balance")]
+    InvalidSellerBalance,
+
+    #[msg("Option Not Supported By Ledger Escrows")]
+    UnsupportedByLedgerEscrow,
+
+    #[msg("Escrow Is Not Fully Funded")]
+    EscrowNotFullyFunded,
+}
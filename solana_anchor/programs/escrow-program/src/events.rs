@@ -0,0 +1,38 @@
+use anchor_lang::prelude::*;
+use crate::state::ReceiptLeaf;
+
+// Emitted when an HTLC escrow is claimed so counterparties on other chains can
+// pick up the secret and settle their side of the swap
+#[event]
+pub struct PreimageRevealed {
+    pub escrow: Pubkey,
+    pub unique_id: [u8; 20],
+    pub hash: [u8; 32],
+    pub preimage: Vec<u8>,
+}
+
+// Emitted by change_moderator so off-chain indexers can follow the new arbitrator
+#[event]
+pub struct ModeratorChanged {
+    pub escrow: Pubkey,
+    pub unique_id: [u8; 20],
+    pub old_moderator: Pubkey,
+    pub new_moderator: Pubkey,
+}
+
+// Emitted for every leaf appended to a receipt tree; indexers replay these in
+// order to rebuild the tree and produce proofs for verify_receipt
+#[event]
+pub struct ReceiptLeafAppended {
+    pub tree: Pubkey,
+    pub index: u64,
+    pub leaf_hash: [u8; 32],
+    pub leaf: ReceiptLeaf,
+}
+
+// Emitted when the initiator left an initiator_may_skip release hook out of the settling transaction
+#[event]
+pub struct ReleaseHookSkipped {
+    pub escrow: Pubkey,
+    pub program: Pubkey,
+}
@@ -0,0 +1,70 @@
+use anchor_lang::prelude::*;
+use crate::{state::*, error::*, utils::{bytes_to_hex_string, construct_delivery_message, format_timestamp, verify_ed25519_instructions}};
+
+#[derive(Accounts)]
+pub struct AttestDelivery<'info> {
+    // 任何人都可以提交预言机的交付证明
+    pub initiator: Signer<'info>,
+
+    // SOL 托管或代币托管，必须且只能提供一个
+    #[account(
+        mut,
+        constraint = sol_escrow.base.is_initialized @ EscrowError::ValidationFailed,
+    )]
+    pub sol_escrow: Option<Account<'info, SolEscrow>>,
+
+    #[account(
+        mut,
+        constraint = token_escrow.base.is_initialized @ EscrowError::ValidationFailed,
+    )]
+    pub token_escrow: Option<Account<'info, TokenEscrow>>,
+
+    pub clock: Sysvar<'info, Clock>,
+
+    /// CHECK: Sysvar Instructions account
+    #[account(address = solana_program::sysvar::instructions::ID)]
+    pub sysvar_instructions: UncheckedAccount<'info>,
+}
+
+// 前一条指令必须是预言机对 (escrow, delivered, timestamp) 的 Ed25519 签名验证
+pub fn handler(
+    ctx: Context<AttestDelivery>,
+    delivered: bool,
+    timestamp: i64,
+    signature: Vec<u8>,
+) -> Result<()> {
+    let (escrow_key, base) = match (
+        ctx.accounts.sol_escrow.as_mut(),
+        ctx.accounts.token_escrow.as_mut(),
+    ) {
+        (Some(escrow), None) => (escrow.key(), &mut escrow.base),
+        (None, Some(escrow)) => (escrow.key(), &mut escrow.base),
+        _ => return err!(EscrowError::InvalidOperation),
+    };
+
+    let oracle = base.oracle.ok_or(EscrowError::InvalidOracle)?;
+    require!(base.delivered_at.is_none(), EscrowError::InvalidOperation);
+    require!(
+        delivered && timestamp <= ctx.accounts.clock.unix_timestamp,
+        EscrowError::InvalidDeliveryAttestation
+    );
+
+    let message = construct_delivery_message(&escrow_key, delivered, timestamp);
+    let signers = verify_ed25519_instructions(
+        &ctx.accounts.sysvar_instructions,
+        &[signature],
+        &message,
+    )?;
+    require!(signers.contains(&oracle), EscrowError::InvalidDeliveryAttestation);
+
+    base.delivered_at = Some(timestamp);
+
+    msg!(
+        "Delivery attested: ID=0x{}, Oracle={}, Delivered at={}",
+        bytes_to_hex_string(&base.unique_id),
+        oracle,
+        format_timestamp(timestamp)
+    );
+
+    Ok(())
+}
@@ -0,0 +1,173 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Token, TokenAccount, Mint};
+use anchor_spl::associated_token::{self, AssociatedToken, get_associated_token_address};
+use crate::{state::*, error::*, utils::{bytes_to_hex_string, counted_settlement_minimum, create_pda_account, format_timestamp}};
+
+// remaining_accounts: 每个条目依次传入 [escrow_account, escrow_token_account, receipt]
+pub const ACCOUNTS_PER_ENTRY: usize = 3;
+
+#[derive(Accounts)]
+pub struct BatchInitialize<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: 买家账户，由客户端指定，所有托管共用
+    pub buyer: AccountInfo<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub token_mint: Account<'info, Mint>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    #[account(
+        mut,
+        token::mint = token_mint,
+        token::authority = payer,
+    )]
+    pub payer_token_account: Account<'info, TokenAccount>,
+
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+    pub clock: Sysvar<'info, Clock>,
+}
+
+pub fn handler<'info>(
+    ctx: Context<'_, '_, 'info, 'info, BatchInitialize<'info>>,
+    entries: Vec<BatchEscrowEntry>,
+) -> Result<()> {
+    require!(
+        !entries.is_empty() && entries.len() <= MAX_BATCH_ESCROWS,
+        EscrowError::InvalidBatchSize
+    );
+    require!(
+        ctx.remaining_accounts.len() == entries.len() * ACCOUNTS_PER_ENTRY,
+        EscrowError::InvalidBatchSize
+    );
+
+    let buyer_key = ctx.accounts.buyer.key();
+    let mint_key = ctx.accounts.token_mint.key();
+    let counted_min_amount = counted_settlement_minimum(Some(&ctx.accounts.token_mint));
+    let now = ctx.accounts.clock.unix_timestamp;
+    let total_amount = entries
+        .iter()
+        .try_fold(0u64, |acc, entry| acc.checked_add(entry.amount))
+        .ok_or(EscrowError::InvalidAmount)?;
+
+    require!(
+        ctx.accounts.payer_token_account.amount >= total_amount,
+        EscrowError::InsufficientFunds
+    );
+
+    for (entry, accounts) in entries.iter().zip(ctx.remaining_accounts.chunks(ACCOUNTS_PER_ENTRY)) {
+        let escrow_info = &accounts[0];
+        let escrow_token_info = &accounts[1];
+        let receipt_info = &accounts[2];
+
+        // 验证参数
+        require!(entry.amount > 0, EscrowError::InvalidPaymentParameters);
+
+        let moderator_flag = [entry.moderator.is_some() as u8];
+        let (escrow_key, bump) = Pubkey::find_program_address(
+            &[
+                b"token_escrow",
+                buyer_key.as_ref(),
+                entry.seller.as_ref(),
+                &moderator_flag,
+                &entry.unique_id,
+            ],
+            ctx.program_id,
+        );
+        require!(escrow_info.key() == escrow_key, EscrowError::ValidationFailed);
+        require!(
+            escrow_token_info.key() == get_associated_token_address(&escrow_key, &mint_key),
+            EscrowError::ValidationFailed
+        );
+
+        // 同一地址上已有结算收据说明该 unique_id 已使用过
+        require!(
+            receipt_info.key() == SettlementReceipt::address(&escrow_key),
+            EscrowError::ValidationFailed
+        );
+        require!(receipt_info.data_is_empty(), EscrowError::EscrowAlreadySettled);
+
+        let unlock_time = now + (entry.unlock_hours as i64 * 3600);
+        let mut escrow = TokenEscrow {
+            base: EscrowAccount::new(
+                buyer_key,
+                entry.seller,
+                entry.moderator,
+                ctx.accounts.payer.key(), // payer_address
+                entry.required_signatures,
+                unlock_time,
+                entry.unique_id,
+                entry.amount,
+                bump,
+            ),
+            mint: mint_key,
+        };
+
+        escrow.base.counted_min_amount = counted_min_amount;
+
+        // 验证签名要求
+        escrow.base.validate_required_signatures()?;
+
+        // 创建托管账户
+        let escrow_seed: &[&[u8]] = &[
+            b"token_escrow",
+            buyer_key.as_ref(),
+            entry.seller.as_ref(),
+            &moderator_flag,
+            &entry.unique_id,
+            &[bump],
+        ];
+        create_pda_account(
+            &ctx.accounts.payer.to_account_info(),
+            escrow_info,
+            &ctx.accounts.system_program.to_account_info(),
+            TokenEscrow::LEN,
+            ctx.program_id,
+            escrow_seed,
+        )?;
+        escrow.try_serialize(&mut &mut escrow_info.try_borrow_mut_data()?[..])?;
+
+        // 创建托管代币账户
+        associated_token::create_idempotent(CpiContext::new(
+            ctx.accounts.associated_token_program.to_account_info(),
+            associated_token::Create {
+                payer: ctx.accounts.payer.to_account_info(),
+                associated_token: escrow_token_info.clone(),
+                authority: escrow_info.clone(),
+                mint: ctx.accounts.token_mint.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+                token_program: ctx.accounts.token_program.to_account_info(),
+            },
+        ))?;
+
+        // 转移代币到escrow代币账户
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.payer_token_account.to_account_info(),
+                    to: escrow_token_info.clone(),
+                    authority: ctx.accounts.payer.to_account_info(),
+                },
+            ),
+            entry.amount,
+        )?;
+
+        msg!(
+            "Token escrow initialized: Buyer={}, Seller={}, Payer={}, ID=0x{}, Amount={} tokens, Required signatures={}, Unlock time={}",
+            buyer_key,
+            entry.seller,
+            ctx.accounts.payer.key(),
+            bytes_to_hex_string(&entry.unique_id),
+            entry.amount,
+            entry.required_signatures,
+            format_timestamp(unlock_time)
+        );
+    }
+
+    msg!("Batch initialized {} token escrows, Total={} tokens", entries.len(), total_amount);
+
+    Ok(())
+}
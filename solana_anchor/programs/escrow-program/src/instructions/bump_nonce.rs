@@ -0,0 +1,84 @@
+use anchor_lang::prelude::*;
+use crate::{state::*, error::*, utils::bytes_to_hex_string};
+
+#[derive(Accounts)]
+pub struct BumpNonce<'info> {
+    // 买家、卖家、moderator、仲裁小组成员或预言机，在处理函数中校验
+    pub signer: Signer<'info>,
+    
+    // SOL 托管、代币托管、多资产托管、众筹托管或固定布局托管，必须且只能提供一个
+    #[account(
+        mut,
+        constraint = sol_escrow.base.is_initialized @ EscrowError::ValidationFailed,
+    )]
+    pub sol_escrow: Option<Account<'info, SolEscrow>>,
+    
+    #[account(
+        mut,
+        constraint = token_escrow.base.is_initialized @ EscrowError::ValidationFailed,
+    )]
+    pub token_escrow: Option<Account<'info, TokenEscrow>>,
+    
+    #[account(
+        mut,
+        constraint = multi_asset_escrow.base.is_initialized @ EscrowError::ValidationFailed,
+    )]
+    pub multi_asset_escrow: Option<Account<'info, MultiAssetEscrow>>,
+    
+    #[account(
+        mut,
+        constraint = crowdfund_escrow.base.is_initialized @ EscrowError::ValidationFailed,
+    )]
+    pub crowdfund_escrow: Option<Account<'info, CrowdfundEscrow>>,
+    
+    #[account(mut)]
+    pub fixed_escrow: Option<AccountLoader<'info, FixedEscrow>>,
+}
+
+pub fn handler(ctx: Context<BumpNonce>) -> Result<()> {
+    if let Some(fixed_escrow) = ctx.accounts.fixed_escrow.as_ref() {
+        require!(
+            ctx.accounts.sol_escrow.is_none() &&
+            ctx.accounts.token_escrow.is_none() &&
+            ctx.accounts.multi_asset_escrow.is_none() &&
+            ctx.accounts.crowdfund_escrow.is_none(),
+            EscrowError::InvalidOperation
+        );
+        let mut loaded = fixed_escrow.load_mut()?;
+        let escrow = &mut *loaded;
+        let is_party = escrow.is_party(&ctx.accounts.signer.key());
+        return bump(&mut escrow.nonce, &escrow.unique_id, is_party, &ctx.accounts.signer);
+    }
+    
+    let base = match (
+        ctx.accounts.sol_escrow.as_mut(),
+        ctx.accounts.token_escrow.as_mut(),
+        ctx.accounts.multi_asset_escrow.as_mut(),
+        ctx.accounts.crowdfund_escrow.as_mut(),
+    ) {
+        (Some(escrow), None, None, None) => &mut escrow.base,
+        (None, Some(escrow), None, None) => &mut escrow.base,
+        (None, None, Some(escrow), None) => &mut escrow.base,
+        (None, None, None, Some(escrow)) => &mut escrow.base,
+        _ => return err!(EscrowError::InvalidOperation),
+    };
+    
+    // 任何可以参与批准的人都能撤回自己已签出的授权
+    let is_approver = base.is_approver(&ctx.accounts.signer.key());
+    bump(&mut base.nonce, &base.unique_id, is_approver, &ctx.accounts.signer)
+}
+
+fn bump(nonce: &mut u64, unique_id: &[u8; 20], is_approver: bool, signer: &Signer) -> Result<()> {
+    require!(is_approver, EscrowError::Unauthorized);
+    
+    *nonce = nonce.checked_add(1).ok_or(EscrowError::NonceOverflow)?;
+    
+    msg!(
+        "Escrow nonce bumped: ID=0x{}, Signer={}, Nonce={}",
+        bytes_to_hex_string(unique_id),
+        signer.key(),
+        nonce
+    );
+    
+    Ok(())
+}
@@ -0,0 +1,114 @@
+use anchor_lang::prelude::*;
+use mobazha_moderator_registry::state::{ModeratorProfile, RegistryConfig};
+use crate::{state::*, error::*, events::ModeratorChanged, utils::{bytes_to_hex_string, verify_registered_moderator}};
+
+// PDA 种子只包含“是否有moderator”标志而不包含其身份，因此可以原地替换moderator
+#[derive(Accounts)]
+pub struct ChangeModerator<'info> {
+    // 买家+卖家，或当前moderator+任一方，在处理函数中校验
+    pub first_signer: Signer<'info>,
+    pub second_signer: Signer<'info>,
+
+    // SOL 托管、代币托管、多资产托管或众筹托管，必须且只能提供一个
+    #[account(
+        mut,
+        constraint = sol_escrow.base.is_initialized @ EscrowError::ValidationFailed,
+    )]
+    pub sol_escrow: Option<Account<'info, SolEscrow>>,
+
+    #[account(
+        mut,
+        constraint = token_escrow.base.is_initialized @ EscrowError::ValidationFailed,
+    )]
+    pub token_escrow: Option<Account<'info, TokenEscrow>>,
+
+    #[account(
+        mut,
+        constraint = multi_asset_escrow.base.is_initialized @ EscrowError::ValidationFailed,
+    )]
+    pub multi_asset_escrow: Option<Account<'info, MultiAssetEscrow>>,
+
+    #[account(
+        mut,
+        constraint = crowdfund_escrow.base.is_initialized @ EscrowError::ValidationFailed,
+    )]
+    pub crowdfund_escrow: Option<Account<'info, CrowdfundEscrow>>,
+
+    // 新 moderator 在注册表中的档案，仅在托管创建时要求注册 moderator 时需要
+    pub moderator_profile: Option<Account<'info, ModeratorProfile>>,
+
+    // 注册表配置，用于按当前 min_stake 重新判断 moderator 是否有效
+    #[account(
+        seeds = [b"registry_config"],
+        seeds::program = mobazha_moderator_registry::ID,
+        bump = registry_config.bump,
+    )]
+    pub registry_config: Option<Account<'info, RegistryConfig>>,
+}
+
+pub fn handler(ctx: Context<ChangeModerator>, new_moderator: Pubkey) -> Result<()> {
+    let (escrow_key, mint, base) = match (
+        ctx.accounts.sol_escrow.as_mut(),
+        ctx.accounts.token_escrow.as_mut(),
+        ctx.accounts.multi_asset_escrow.as_mut(),
+        ctx.accounts.crowdfund_escrow.as_mut(),
+    ) {
+        (Some(escrow), None, None, None) => (escrow.key(), None, &mut escrow.base),
+        (None, Some(escrow), None, None) => (escrow.key(), Some(escrow.mint), &mut escrow.base),
+        (None, None, Some(escrow), None) => (escrow.key(), None, &mut escrow.base),
+        (None, None, None, Some(escrow)) => (escrow.key(), escrow.mint, &mut escrow.base),
+        _ => return err!(EscrowError::InvalidOperation),
+    };
+
+    // 没有moderator的托管无法添加（种子标志位会改变）
+    let old_moderator = base.moderator.ok_or(EscrowError::InvalidModerator)?;
+
+    let first = ctx.accounts.first_signer.key();
+    let second = ctx.accounts.second_signer.key();
+    require!(first != second, EscrowError::InsufficientSignatures);
+
+    let is_trader = |key: &Pubkey| *key == base.buyer || *key == base.seller;
+    let authorized = (is_trader(&first) && is_trader(&second)) ||
+        (first == old_moderator && is_trader(&second)) ||
+        (second == old_moderator && is_trader(&first));
+    require!(authorized, EscrowError::Unauthorized);
+
+    require!(
+        new_moderator != old_moderator &&
+        !is_trader(&new_moderator) &&
+        base.oracle != Some(new_moderator) &&
+        !base.panel.as_ref().is_some_and(|panel| panel.is_member(&new_moderator)),
+        EscrowError::InvalidModerator
+    );
+
+    // 创建时要求注册 moderator 的托管，替换后的 moderator 同样必须在注册表中有效
+    if base.require_registered_moderator {
+        verify_registered_moderator(
+            Some(new_moderator),
+            ctx.accounts.moderator_profile.as_deref(),
+            ctx.accounts.registry_config.as_deref(),
+            mint.as_ref(),
+        )?;
+    }
+
+    base.moderator = Some(new_moderator);
+    // 使旧moderator参与签署的释放授权全部失效
+    base.nonce = base.nonce.checked_add(1).ok_or(EscrowError::NonceOverflow)?;
+
+    emit!(ModeratorChanged {
+        escrow: escrow_key,
+        unique_id: base.unique_id,
+        old_moderator,
+        new_moderator,
+    });
+
+    msg!(
+        "Escrow moderator changed: ID=0x{}, Old={}, New={}, Nonce={}",
+        bytes_to_hex_string(&base.unique_id),
+        old_moderator,
+        new_moderator,
+        base.nonce
+    );
+
+    Ok(())
+}
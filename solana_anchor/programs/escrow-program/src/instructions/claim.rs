@@ -0,0 +1,169 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+use crate::{state::*, error::*};
+
+#[derive(Accounts)]
+pub struct Claim<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"claim_ticket", claim_ticket.escrow.as_ref(), owner.key().as_ref()],
+        bump = claim_ticket.bump,
+        has_one = owner @ EscrowError::Unauthorized,
+        close = rent_payer,
+    )]
+    pub claim_ticket: Account<'info, ClaimTicket>,
+
+    /// CHECK: 接收账户，由领取人自行指定（SOL 凭证为任意账户，代币凭证为同一 mint 的代币账户）
+    #[account(mut)]
+    pub destination: AccountInfo<'info>,
+
+    /// CHECK: 领取凭证租金的支付方
+    #[account(mut, address = claim_ticket.rent_payer @ EscrowError::ValidationFailed)]
+    pub rent_payer: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub ticket_token_account: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Option<Program<'info, Token>>,
+}
+
+pub fn claim_handler(ctx: Context<Claim>) -> Result<()> {
+    pay_out_ticket(
+        &ctx.accounts.claim_ticket,
+        &ctx.accounts.destination,
+        &ctx.accounts.rent_payer,
+        ctx.accounts.ticket_token_account.as_ref(),
+        ctx.accounts.token_program.as_ref(),
+    )?;
+
+    msg!(
+        "Claim ticket redeemed: Owner={}, Amount={}, Destination={}",
+        ctx.accounts.claim_ticket.owner,
+        ctx.accounts.claim_ticket.amount,
+        ctx.accounts.destination.key()
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ReclaimExpiredClaim<'info> {
+    #[account(
+        mut,
+        seeds = [b"claim_ticket", claim_ticket.escrow.as_ref(), claim_ticket.owner.as_ref()],
+        bump = claim_ticket.bump,
+        close = rent_payer,
+    )]
+    pub claim_ticket: Account<'info, ClaimTicket>,
+
+    /// CHECK: 付款方账户（SOL 凭证）或付款方持有的代币账户（代币凭证），在处理函数中校验
+    #[account(mut)]
+    pub destination: AccountInfo<'info>,
+
+    /// CHECK: 领取凭证租金的支付方
+    #[account(mut, address = claim_ticket.rent_payer @ EscrowError::ValidationFailed)]
+    pub rent_payer: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub ticket_token_account: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Option<Program<'info, Token>>,
+}
+
+pub fn reclaim_handler(ctx: Context<ReclaimExpiredClaim>) -> Result<()> {
+    let ticket = &ctx.accounts.claim_ticket;
+
+    require!(
+        Clock::get()?.unix_timestamp >= ticket.expires_at,
+        EscrowError::ClaimNotExpired
+    );
+
+    // 过期的凭证只能退回给原付款方
+    if ticket.mint.is_some() {
+        let destination = TokenAccount::try_deserialize(&mut &ctx.accounts.destination.try_borrow_data()?[..])?;
+        require!(destination.owner == ticket.payer_address, EscrowError::InvalidRecipient);
+    } else {
+        require!(
+            ctx.accounts.destination.key() == ticket.payer_address,
+            EscrowError::InvalidRecipient
+        );
+    }
+
+    pay_out_ticket(
+        ticket,
+        &ctx.accounts.destination,
+        &ctx.accounts.rent_payer,
+        ctx.accounts.ticket_token_account.as_ref(),
+        ctx.accounts.token_program.as_ref(),
+    )?;
+
+    msg!(
+        "Expired claim ticket returned to payer: Owner={}, Payer={}, Amount={}",
+        ticket.owner,
+        ticket.payer_address,
+        ticket.amount
+    );
+
+    Ok(())
+}
+
+// 将凭证资金转给 destination；凭证账户本身由 `close = rent_payer` 关闭
+fn pay_out_ticket<'info>(
+    ticket: &Account<'info, ClaimTicket>,
+    destination: &AccountInfo<'info>,
+    rent_payer: &AccountInfo<'info>,
+    ticket_token_account: Option<&Account<'info, TokenAccount>>,
+    token_program: Option<&Program<'info, Token>>,
+) -> Result<()> {
+    let ticket_info = ticket.to_account_info();
+
+    let Some(mint) = ticket.mint else {
+        require!(
+            ticket_info.lamports() >= ticket.amount,
+            EscrowError::InsufficientFunds
+        );
+        **ticket_info.try_borrow_mut_lamports()? -= ticket.amount;
+        **destination.try_borrow_mut_lamports()? += ticket.amount;
+        return Ok(());
+    };
+
+    let (Some(ticket_token_account), Some(token_program)) = (ticket_token_account, token_program) else {
+        return err!(EscrowError::InvalidOperation);
+    };
+    require!(
+        ticket_token_account.key() == anchor_spl::associated_token::get_associated_token_address(&ticket.key(), &mint),
+        EscrowError::ValidationFailed
+    );
+
+    let ticket_seed: &[&[u8]] = &[
+        b"claim_ticket",
+        ticket.escrow.as_ref(),
+        ticket.owner.as_ref(),
+        &[ticket.bump],
+    ];
+
+    token::transfer(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            Transfer {
+                from: ticket_token_account.to_account_info(),
+                to: destination.clone(),
+                authority: ticket_info.clone(),
+            },
+            &[ticket_seed],
+        ),
+        ticket.amount,
+    )?;
+
+    token::close_account(CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        token::CloseAccount {
+            account: ticket_token_account.to_account_info(),
+            destination: rent_payer.clone(),
+            authority: ticket_info,
+        },
+        &[ticket_seed],
+    ))
+}
@@ -0,0 +1,633 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+use anchor_spl::token::{self, Token, TokenAccount, Mint, Transfer};
+use anchor_spl::associated_token::{self, AssociatedToken, get_associated_token_address};
+use crate::{state::*, error::*, utils::{bytes_to_hex_string, close_escrow_and_return_rent, counted_settlement_minimum, format_timestamp, transfer_escrowed_lamports, verify_payment_amounts, verify_signatures_without_timelock, record_settlement, receipt_payouts, write_receipt}};
+
+#[derive(Accounts)]
+#[instruction(
+    moderator: Option<Pubkey>,
+    unique_id: [u8; 20]
+)]
+pub struct InitializeCrowdfund<'info> {
+    // 发起人即买家，支付托管账户租金
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+    /// CHECK: 卖家账户，由客户端指定
+    pub seller: AccountInfo<'info>,
+
+    #[account(
+        init,
+        payer = buyer,
+        space = CrowdfundEscrow::LEN,
+        seeds = [
+            b"crowdfund",
+            buyer.key().as_ref(),
+            seller.key().as_ref(),
+            &[moderator.is_some() as u8],
+            &unique_id
+        ],
+        bump
+    )]
+    pub crowdfund: Account<'info, CrowdfundEscrow>,
+
+    /// CHECK: 同一地址上已结算托管的收据，存在时拒绝重复使用 unique_id
+    #[account(
+        seeds = [b"receipt", crowdfund.key().as_ref()],
+        bump,
+        constraint = receipt.data_is_empty() @ EscrowError::EscrowAlreadySettled
+    )]
+    pub receipt: UncheckedAccount<'info>,
+
+    // 代币众筹时提供 mint 及托管代币账户（crowdfund 的 ATA），SOL 众筹时留空
+    pub token_mint: Option<Account<'info, Mint>>,
+
+    /// CHECK: crowdfund 的关联代币账户，在处理函数中创建
+    #[account(mut)]
+    pub crowdfund_token_account: Option<UncheckedAccount<'info>>,
+
+    pub token_program: Option<Program<'info, Token>>,
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
+    pub system_program: Program<'info, System>,
+    pub clock: Sysvar<'info, Clock>,
+}
+
+pub fn initialize_handler(
+    ctx: Context<InitializeCrowdfund>,
+    moderator: Option<Pubkey>,
+    unique_id: [u8; 20],
+    required_signatures: u8,
+    target_amount: u64,
+    funding_hours: u64,
+) -> Result<()> {
+    require!(target_amount > 0 && funding_hours > 0, EscrowError::InvalidPaymentParameters);
+
+    let deadline = ctx.accounts.clock.unix_timestamp + (funding_hours as i64 * 3600);
+    let crowdfund = &mut ctx.accounts.crowdfund;
+
+    crowdfund.base = EscrowAccount::new(
+        ctx.accounts.buyer.key(),
+        ctx.accounts.seller.key(),
+        moderator,
+        ctx.accounts.buyer.key(), // payer_address，退款按贡献凭证逐个返还
+        required_signatures,
+        deadline,
+        unique_id,
+        0,
+        ctx.bumps.crowdfund,
+    );
+    crowdfund.base.validate_required_signatures()?;
+    crowdfund.base.counted_min_amount = counted_settlement_minimum(ctx.accounts.token_mint.as_ref());
+    crowdfund.target_amount = target_amount;
+    crowdfund.mint = ctx.accounts.token_mint.as_ref().map(|mint| mint.key());
+
+    if let Some(mint) = &ctx.accounts.token_mint {
+        let (Some(vault), Some(token_program), Some(associated_token_program)) = (
+            &ctx.accounts.crowdfund_token_account,
+            &ctx.accounts.token_program,
+            &ctx.accounts.associated_token_program,
+        ) else {
+            return err!(EscrowError::InvalidOperation);
+        };
+
+        associated_token::create_idempotent(CpiContext::new(
+            associated_token_program.to_account_info(),
+            associated_token::Create {
+                payer: ctx.accounts.buyer.to_account_info(),
+                associated_token: vault.to_account_info(),
+                authority: crowdfund.to_account_info(),
+                mint: mint.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+                token_program: token_program.to_account_info(),
+            },
+        ))?;
+    }
+
+    msg!(
+        "Crowdfund initialized: Organizer={}, Seller={}, ID=0x{}, Target={}, Mint={:?}, Deadline={}",
+        crowdfund.base.buyer,
+        crowdfund.base.seller,
+        bytes_to_hex_string(&unique_id),
+        target_amount,
+        crowdfund.mint,
+        format_timestamp(deadline)
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct Contribute<'info> {
+    #[account(mut)]
+    pub contributor: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = crowdfund.base.is_initialized @ EscrowError::ValidationFailed,
+        seeds = [
+            b"crowdfund",
+            crowdfund.base.buyer.as_ref(),
+            crowdfund.base.seller.as_ref(),
+            &[crowdfund.base.moderator.is_some() as u8],
+            &crowdfund.base.unique_id
+        ],
+        bump = crowdfund.base.bump
+    )]
+    pub crowdfund: Account<'info, CrowdfundEscrow>,
+
+    #[account(
+        init_if_needed,
+        payer = contributor,
+        space = Contribution::LEN,
+        seeds = [b"contribution", crowdfund.key().as_ref(), contributor.key().as_ref()],
+        bump
+    )]
+    pub contribution: Account<'info, Contribution>,
+
+    // 代币众筹时提供
+    #[account(mut)]
+    pub contributor_token_account: Option<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub crowdfund_token_account: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Option<Program<'info, Token>>,
+    pub system_program: Program<'info, System>,
+    pub clock: Sysvar<'info, Clock>,
+}
+
+pub fn contribute_handler(ctx: Context<Contribute>, amount: u64) -> Result<()> {
+    require!(amount > 0, EscrowError::InvalidAmount);
+    require!(
+        ctx.accounts.clock.unix_timestamp < ctx.accounts.crowdfund.base.unlock_time,
+        EscrowError::CrowdfundClosed
+    );
+
+    if ctx.accounts.crowdfund.mint.is_some() {
+        let (Some(source), Some(token_program)) = (
+            &ctx.accounts.contributor_token_account,
+            &ctx.accounts.token_program,
+        ) else {
+            return err!(EscrowError::InvalidOperation);
+        };
+        let vault = crowdfund_vault(&ctx.accounts.crowdfund, ctx.accounts.crowdfund_token_account.as_ref())?;
+
+        token::transfer(
+            CpiContext::new(
+                token_program.to_account_info(),
+                Transfer {
+                    from: source.to_account_info(),
+                    to: vault.to_account_info(),
+                    authority: ctx.accounts.contributor.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+    } else {
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.contributor.to_account_info(),
+                    to: ctx.accounts.crowdfund.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+    }
+
+    let contribution = &mut ctx.accounts.contribution;
+    contribution.crowdfund = ctx.accounts.crowdfund.key();
+    contribution.contributor = ctx.accounts.contributor.key();
+    contribution.bump = ctx.bumps.contribution;
+    contribution.amount = contribution.amount
+        .checked_add(amount)
+        .ok_or(EscrowError::InvalidAmount)?;
+
+    let crowdfund = &mut ctx.accounts.crowdfund;
+    crowdfund.base.amount = crowdfund.base.amount
+        .checked_add(amount)
+        .ok_or(EscrowError::InvalidAmount)?;
+
+    msg!(
+        "Crowdfund contribution: ID=0x{}, Contributor={}, Amount={}, Raised={}/{}",
+        bytes_to_hex_string(&crowdfund.base.unique_id),
+        contribution.contributor,
+        amount,
+        crowdfund.base.amount,
+        crowdfund.target_amount
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(
+    payment_amounts: Vec<u64>,
+    signatures: Vec<Vec<u8>>,
+    valid_until: i64
+)]
+pub struct ReleaseCrowdfund<'info> {
+    #[account(mut)]
+    pub initiator: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = crowdfund.base.is_initialized @ EscrowError::ValidationFailed,
+        seeds = [
+            b"crowdfund",
+            crowdfund.base.buyer.as_ref(),
+            crowdfund.base.seller.as_ref(),
+            &[crowdfund.base.moderator.is_some() as u8],
+            &crowdfund.base.unique_id
+        ],
+        bump = crowdfund.base.bump
+    )]
+    pub crowdfund: Account<'info, CrowdfundEscrow>,
+
+    // 结算结果记入双方的信誉账户
+    #[account(
+        init_if_needed,
+        payer = initiator,
+        space = Reputation::LEN,
+        seeds = [b"seller_reputation", crowdfund.base.seller.as_ref()],
+        bump
+    )]
+    pub seller_reputation: Account<'info, Reputation>,
+
+    #[account(
+        init_if_needed,
+        payer = initiator,
+        space = Reputation::LEN,
+        seeds = [b"buyer_reputation", crowdfund.base.buyer.as_ref()],
+        bump
+    )]
+    pub buyer_reputation: Account<'info, Reputation>,
+
+    // 托管关闭后保留的结算收据
+    #[account(
+        init,
+        payer = initiator,
+        space = SettlementReceipt::LEN,
+        seeds = [b"receipt", crowdfund.key().as_ref()],
+        bump
+    )]
+    pub receipt: Account<'info, SettlementReceipt>,
+
+    #[account(mut)]
+    pub crowdfund_token_account: Option<Account<'info, TokenAccount>>,
+
+    // 发起人的代币账户，接收他人直接转入金库的多余代币；金库没有多余代币时可不传
+    #[account(
+        mut,
+        constraint = organizer_token_account.owner == crowdfund.base.buyer @ EscrowError::InvalidRecipient,
+    )]
+    pub organizer_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// CHECK: 发起人账户，接收托管账户租金
+    #[account(mut, address = crowdfund.base.buyer @ EscrowError::ValidationFailed)]
+    pub buyer: AccountInfo<'info>,
+
+    pub token_program: Option<Program<'info, Token>>,
+
+    /// CHECK: Sysvar Instructions account
+    #[account(address = solana_program::sysvar::instructions::ID)]
+    pub sysvar_instructions: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+// remaining_accounts: 每个支付对应一个接收账户（SOL 众筹为钱包，代币众筹为代币账户）
+pub fn release_handler<'info>(
+    ctx: Context<'_, '_, 'info, 'info, ReleaseCrowdfund<'info>>,
+    payment_amounts: Vec<u64>,
+    signatures: Vec<Vec<u8>>,
+    valid_until: i64,
+) -> Result<()> {
+    let crowdfund = &ctx.accounts.crowdfund;
+    let base = &crowdfund.base;
+
+    require!(crowdfund.is_funded(), EscrowError::TargetNotReached);
+    require!(
+        ctx.remaining_accounts.len() == payment_amounts.len(),
+        EscrowError::InvalidRecipientCount
+    );
+
+    // 验证支付金额，众筹资金必须全部分配
+    verify_payment_amounts(&payment_amounts, base)?;
+    require!(
+        payment_amounts.iter().sum::<u64>() == base.amount,
+        EscrowError::PayoutMismatch
+    );
+
+    // 代币众筹签名中的接收方为代币账户的 owner
+    let recipients = ctx.remaining_accounts
+        .iter()
+        .map(|info| {
+            if crowdfund.mint.is_some() {
+                let account = TokenAccount::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+                require!(Some(account.mint) == crowdfund.mint, EscrowError::TokenMintMismatch);
+                Ok(Some(account.owner))
+            } else {
+                Ok(Some(info.key()))
+            }
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    // 验证签名
+    let signers = verify_signatures_without_timelock(
+        base,
+        &signatures,
+        &payment_amounts,
+        &recipients,
+        valid_until,
+        Clock::get()?.unix_timestamp,
+        &ctx.accounts.sysvar_instructions,
+    )?;
+
+    for (amount, recipient_info) in payment_amounts.iter().zip(ctx.remaining_accounts.iter()) {
+        msg!("Transfer {} to account {}", amount, recipient_info.key());
+        pay_out(
+            crowdfund,
+            ctx.accounts.crowdfund_token_account.as_ref(),
+            ctx.accounts.token_program.as_ref(),
+            recipient_info,
+            *amount,
+        )?;
+    }
+
+    close_crowdfund(
+        crowdfund,
+        ctx.accounts.crowdfund_token_account.as_ref(),
+        ctx.accounts.organizer_token_account.as_ref(),
+        ctx.accounts.token_program.as_ref(),
+        &ctx.accounts.buyer,
+    )?;
+
+    record_settlement(
+        base,
+        &mut ctx.accounts.seller_reputation,
+        ctx.bumps.seller_reputation,
+        &mut ctx.accounts.buyer_reputation,
+        ctx.bumps.buyer_reputation,
+        SettlementOutcome::for_payout(base, &recipients),
+        base.amount,
+        crowdfund.mint,
+    );
+    let record = SettlementRecord::new(
+        crowdfund.key(),
+        base,
+        crowdfund.mint,
+        base.amount,
+        receipt_payouts(&recipients, &payment_amounts),
+        ReleasePath::Crowdfund,
+        SettlementOutcome::for_payout(base, &recipients),
+        &Clock::get()?,
+    )
+    .with_seller_approval(signers.contains(&base.seller));
+    write_receipt(
+        base,
+        record,
+        Some(&mut *ctx.accounts.receipt),
+        Some(ctx.bumps.receipt),
+        None,
+    )?;
+
+    msg!(
+        "Crowdfund released: Organizer={}, Seller={}, ID=0x{}, Raised={}",
+        base.buyer,
+        base.seller,
+        bytes_to_hex_string(&base.unique_id),
+        base.amount
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ReclaimContribution<'info> {
+    #[account(mut)]
+    pub contributor: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = crowdfund.base.is_initialized @ EscrowError::ValidationFailed,
+        seeds = [
+            b"crowdfund",
+            crowdfund.base.buyer.as_ref(),
+            crowdfund.base.seller.as_ref(),
+            &[crowdfund.base.moderator.is_some() as u8],
+            &crowdfund.base.unique_id
+        ],
+        bump = crowdfund.base.bump
+    )]
+    pub crowdfund: Account<'info, CrowdfundEscrow>,
+
+    #[account(
+        mut,
+        seeds = [b"contribution", crowdfund.key().as_ref(), contributor.key().as_ref()],
+        bump = contribution.bump,
+        has_one = contributor @ EscrowError::Unauthorized,
+        close = contributor,
+    )]
+    pub contribution: Account<'info, Contribution>,
+
+    // 代币众筹时提供，必须是贡献者自己的代币账户
+    #[account(
+        mut,
+        constraint = contributor_token_account.owner == contributor.key() @ EscrowError::InvalidRecipient,
+    )]
+    pub contributor_token_account: Option<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub crowdfund_token_account: Option<Account<'info, TokenAccount>>,
+
+    // 发起人的代币账户，最后一笔退款后接收金库中的多余代币
+    #[account(
+        mut,
+        constraint = organizer_token_account.owner == crowdfund.base.buyer @ EscrowError::InvalidRecipient,
+    )]
+    pub organizer_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// CHECK: 发起人账户，最后一笔退款后接收托管账户租金
+    #[account(mut, address = crowdfund.base.buyer @ EscrowError::ValidationFailed)]
+    pub buyer: AccountInfo<'info>,
+
+    pub token_program: Option<Program<'info, Token>>,
+    pub clock: Sysvar<'info, Clock>,
+}
+
+pub fn reclaim_handler(ctx: Context<ReclaimContribution>) -> Result<()> {
+    require!(
+        ctx.accounts.clock.unix_timestamp >= ctx.accounts.crowdfund.base.unlock_time,
+        EscrowError::TimelockNotExpired
+    );
+    require!(!ctx.accounts.crowdfund.is_funded(), EscrowError::TargetReached);
+
+    let amount = ctx.accounts.contribution.amount;
+    let destination = match (&ctx.accounts.crowdfund.mint, &ctx.accounts.contributor_token_account) {
+        (Some(_), Some(token_account)) => token_account.to_account_info(),
+        (Some(_), None) => return err!(EscrowError::InvalidOperation),
+        (None, _) => ctx.accounts.contributor.to_account_info(),
+    };
+
+    pay_out(
+        &ctx.accounts.crowdfund,
+        ctx.accounts.crowdfund_token_account.as_ref(),
+        ctx.accounts.token_program.as_ref(),
+        &destination,
+        amount,
+    )?;
+
+    let crowdfund = &mut ctx.accounts.crowdfund;
+    crowdfund.base.amount -= amount;
+
+    msg!(
+        "Crowdfund contribution reclaimed: ID=0x{}, Contributor={}, Amount={}, Remaining={}",
+        bytes_to_hex_string(&crowdfund.base.unique_id),
+        ctx.accounts.contributor.key(),
+        amount,
+        crowdfund.base.amount
+    );
+
+    // 最后一位贡献者取回后关闭众筹账户
+    if crowdfund.base.amount == 0 {
+        close_crowdfund(
+            crowdfund,
+            ctx.accounts.crowdfund_token_account.as_ref(),
+            ctx.accounts.organizer_token_account.as_ref(),
+            ctx.accounts.token_program.as_ref(),
+            &ctx.accounts.buyer,
+        )?;
+    }
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct CloseContribution<'info> {
+    #[account(mut)]
+    pub contributor: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = contributor @ EscrowError::Unauthorized,
+        close = contributor,
+    )]
+    pub contribution: Account<'info, Contribution>,
+
+    /// CHECK: 众筹账户，必须已经释放并关闭
+    #[account(address = contribution.crowdfund @ EscrowError::ValidationFailed)]
+    pub crowdfund: UncheckedAccount<'info>,
+}
+
+// 众筹释放后贡献凭证不再有用，贡献者可以关闭它取回租金
+pub fn close_contribution_handler(ctx: Context<CloseContribution>) -> Result<()> {
+    require!(ctx.accounts.crowdfund.data_is_empty(), EscrowError::InvalidOperation);
+
+    msg!(
+        "Contribution receipt closed: Crowdfund={}, Contributor={}, Amount={}",
+        ctx.accounts.contribution.crowdfund,
+        ctx.accounts.contributor.key(),
+        ctx.accounts.contribution.amount
+    );
+
+    Ok(())
+}
+
+fn crowdfund_vault<'a, 'info>(
+    crowdfund: &Account<'info, CrowdfundEscrow>,
+    vault: Option<&'a Account<'info, TokenAccount>>,
+) -> Result<&'a Account<'info, TokenAccount>> {
+    let (Some(mint), Some(vault)) = (crowdfund.mint, vault) else {
+        return err!(EscrowError::InvalidOperation);
+    };
+    require!(
+        vault.key() == get_associated_token_address(&crowdfund.key(), &mint),
+        EscrowError::ValidationFailed
+    );
+    Ok(vault)
+}
+
+// 从众筹账户（SOL）或其代币账户（代币）向 destination 转账
+fn pay_out<'info>(
+    crowdfund: &Account<'info, CrowdfundEscrow>,
+    vault: Option<&Account<'info, TokenAccount>>,
+    token_program: Option<&Program<'info, Token>>,
+    destination: &AccountInfo<'info>,
+    amount: u64,
+) -> Result<()> {
+    if crowdfund.mint.is_none() {
+        return transfer_escrowed_lamports(&crowdfund.to_account_info(), destination, amount);
+    }
+
+    let vault = crowdfund_vault(crowdfund, vault)?;
+    let token_program = token_program.ok_or(EscrowError::InvalidOperation)?;
+    let base = &crowdfund.base;
+    let crowdfund_seed: &[&[u8]] = &[
+        b"crowdfund",
+        base.buyer.as_ref(),
+        base.seller.as_ref(),
+        &[base.moderator.is_some() as u8],
+        &base.unique_id,
+        &[base.bump],
+    ];
+
+    token::transfer(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            Transfer {
+                from: vault.to_account_info(),
+                to: destination.clone(),
+                authority: crowdfund.to_account_info(),
+            },
+            &[crowdfund_seed],
+        ),
+        amount,
+    )
+}
+
+// 关闭代币账户（如有）和众筹账户，租金返还发起人。
+// 他人直接转入金库的多余代币先转给发起人，否则金库无法关闭；SOL 众筹的多余 lamports 随账户关闭归发起人
+fn close_crowdfund<'info>(
+    crowdfund: &Account<'info, CrowdfundEscrow>,
+    vault: Option<&Account<'info, TokenAccount>>,
+    organizer_token_account: Option<&Account<'info, TokenAccount>>,
+    token_program: Option<&Program<'info, Token>>,
+    buyer: &AccountInfo<'info>,
+) -> Result<()> {
+    if crowdfund.mint.is_some() {
+        let vault = crowdfund_vault(crowdfund, vault)?;
+        let token_program = token_program.ok_or(EscrowError::InvalidOperation)?;
+
+        // 金库余额在本指令中已变化，重新读取
+        let surplus = TokenAccount::try_deserialize(&mut &vault.to_account_info().try_borrow_data()?[..])?.amount;
+        if surplus > 0 {
+            let organizer_token_account = organizer_token_account.ok_or(EscrowError::InvalidRecipient)?;
+            msg!("Sweep {} surplus tokens to organizer account {}", surplus, organizer_token_account.key());
+            pay_out(crowdfund, Some(vault), Some(token_program), &organizer_token_account.to_account_info(), surplus)?;
+        }
+
+        let base = &crowdfund.base;
+        let crowdfund_seed: &[&[u8]] = &[
+            b"crowdfund",
+            base.buyer.as_ref(),
+            base.seller.as_ref(),
+            &[base.moderator.is_some() as u8],
+            &base.unique_id,
+            &[base.bump],
+        ];
+
+        token::close_account(CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            token::CloseAccount {
+                account: vault.to_account_info(),
+                destination: buyer.clone(),
+                authority: crowdfund.to_account_info(),
+            },
+            &[crowdfund_seed],
+        ))?;
+    }
+
+    close_escrow_and_return_rent(&crowdfund.to_account_info(), buyer)
+}
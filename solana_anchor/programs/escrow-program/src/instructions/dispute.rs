@@ -0,0 +1,414 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Token, TokenAccount, Mint};
+use anchor_spl::associated_token::AssociatedToken;
+use crate::{state::*, error::*, asset::{EscrowSeeds, EscrowVault, Payee}, utils::{bytes_to_hex_string, construct_ruling_message, finish_settlement, verify_ed25519_instructions, verify_valid_until}};
+
+#[derive(Accounts)]
+pub struct ResolveDisputeSol<'info> {
+    #[account(mut)]
+    pub initiator: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = escrow_account.base.is_initialized @ EscrowError::ValidationFailed,
+        seeds = [
+            b"sol_escrow",
+            escrow_account.base.buyer.as_ref(),
+            escrow_account.base.seller.as_ref(),
+            &[escrow_account.base.moderator.is_some() as u8],
+            &escrow_account.base.unique_id
+        ],
+        bump = escrow_account.base.bump
+    )]
+    pub escrow_account: Account<'info, SolEscrow>,
+
+    // 结算结果记入双方的信誉账户
+    #[account(
+        init_if_needed,
+        payer = initiator,
+        space = Reputation::LEN,
+        seeds = [b"seller_reputation", escrow_account.base.seller.as_ref()],
+        bump
+    )]
+    pub seller_reputation: Account<'info, Reputation>,
+
+    #[account(
+        init_if_needed,
+        payer = initiator,
+        space = Reputation::LEN,
+        seeds = [b"buyer_reputation", escrow_account.base.buyer.as_ref()],
+        bump
+    )]
+    pub buyer_reputation: Account<'info, Reputation>,
+
+    // 托管关闭后保留的结算收据，使用收据树的托管不传入
+    #[account(
+        init,
+        payer = initiator,
+        space = SettlementReceipt::LEN,
+        seeds = [b"receipt", escrow_account.key().as_ref()],
+        bump
+    )]
+    pub receipt: Option<Account<'info, SettlementReceipt>>,
+
+    // 使用压缩收据的托管在这棵树上追加结算叶子
+    #[account(mut)]
+    pub receipt_tree: Option<AccountLoader<'info, ReceiptTree>>,
+
+    /// CHECK: 付款方账户，接收买家一方的份额
+    #[account(mut, address = escrow_account.base.payer_address @ EscrowError::ValidationFailed)]
+    pub payer: AccountInfo<'info>,
+
+    /// CHECK: 卖家账户
+    #[account(mut, address = escrow_account.base.seller @ EscrowError::ValidationFailed)]
+    pub seller: AccountInfo<'info>,
+
+    /// CHECK: moderator账户，接收仲裁费；只有仲裁小组的托管不传
+    #[account(mut, constraint = escrow_account.base.moderator == Some(moderator.key()) @ EscrowError::ValidationFailed)]
+    pub moderator: Option<AccountInfo<'info>>,
+
+    /// CHECK: 买家账户，接收托管账户租金
+    #[account(mut, address = escrow_account.base.buyer @ EscrowError::ValidationFailed)]
+    pub buyer: AccountInfo<'info>,
+
+    pub clock: Sysvar<'info, Clock>,
+
+    /// CHECK: Sysvar Instructions account
+    #[account(address = solana_program::sysvar::instructions::ID)]
+    pub sysvar_instructions: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn sol_handler(
+    ctx: Context<ResolveDisputeSol>,
+    ruling: DisputeRuling,
+    valid_until: i64,
+    signatures: Vec<Vec<u8>>,
+) -> Result<()> {
+    let base = &ctx.accounts.escrow_account.base;
+    let signers = verify_ruling(base, &ruling, valid_until, ctx.accounts.clock.unix_timestamp, &signatures, &ctx.accounts.sysvar_instructions)?;
+
+    // 与释放相同，向空钱包支付小额份额时由发起人补足免租金额
+    let vault = EscrowVault::lamports(
+        ctx.accounts.escrow_account.to_account_info(),
+        EscrowSeeds::new(b"sol_escrow", base),
+    )
+    .fund_new_recipients(ctx.accounts.initiator.to_account_info(), ctx.accounts.system_program.to_account_info());
+    let (balance, [buyer_amount, seller_amount, fee]) = settle_ruling(
+        vault,
+        base,
+        &ruling,
+        [
+            Some(Payee::wallet(&ctx.accounts.payer)),
+            Some(Payee::wallet(&ctx.accounts.seller)),
+            ctx.accounts.moderator.as_ref().map(Payee::wallet),
+        ],
+        &ctx.accounts.buyer,
+    )?;
+
+    let record = SettlementRecord::new(
+        ctx.accounts.escrow_account.key(),
+        base,
+        None,
+        balance,
+        dispute_payouts(base, buyer_amount, seller_amount, fee),
+        ReleasePath::Dispute,
+        SettlementOutcome::Disputed,
+        &ctx.accounts.clock,
+    )
+    .with_seller_approval(signers.contains(&base.seller));
+    finish_settlement(
+        base,
+        record,
+        &mut ctx.accounts.seller_reputation,
+        ctx.bumps.seller_reputation,
+        &mut ctx.accounts.buyer_reputation,
+        ctx.bumps.buyer_reputation,
+        ctx.accounts.receipt.as_deref_mut(),
+        ctx.bumps.receipt,
+        ctx.accounts.receipt_tree.as_ref(),
+        ctx.remaining_accounts,
+    )?;
+
+    msg!(
+        "SOL dispute resolved: ID=0x{}, Buyer side={} lamports, Seller={} lamports, Moderator fee={} lamports",
+        bytes_to_hex_string(&base.unique_id),
+        buyer_amount,
+        seller_amount,
+        fee
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ResolveDisputeToken<'info> {
+    // 支付接收方 ATA 的租金
+    #[account(mut)]
+    pub initiator: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = escrow_account.base.is_initialized @ EscrowError::ValidationFailed,
+        // 账本托管的资金在卖家资金池中，只能通过 release_token 结算
+        constraint = !escrow_account.base.ledger @ EscrowError::UnsupportedByLedgerEscrow,
+        seeds = [
+            b"token_escrow",
+            escrow_account.base.buyer.as_ref(),
+            escrow_account.base.seller.as_ref(),
+            &[escrow_account.base.moderator.is_some() as u8],
+            &escrow_account.base.unique_id
+        ],
+        bump = escrow_account.base.bump
+    )]
+    pub escrow_account: Account<'info, TokenEscrow>,
+
+    // 结算结果记入双方的信誉账户
+    #[account(
+        init_if_needed,
+        payer = initiator,
+        space = Reputation::LEN,
+        seeds = [b"seller_reputation", escrow_account.base.seller.as_ref()],
+        bump
+    )]
+    pub seller_reputation: Account<'info, Reputation>,
+
+    #[account(
+        init_if_needed,
+        payer = initiator,
+        space = Reputation::LEN,
+        seeds = [b"buyer_reputation", escrow_account.base.buyer.as_ref()],
+        bump
+    )]
+    pub buyer_reputation: Account<'info, Reputation>,
+
+    // 托管关闭后保留的结算收据，使用收据树的托管不传入
+    #[account(
+        init,
+        payer = initiator,
+        space = SettlementReceipt::LEN,
+        seeds = [b"receipt", escrow_account.key().as_ref()],
+        bump
+    )]
+    pub receipt: Option<Account<'info, SettlementReceipt>>,
+
+    // 使用压缩收据的托管在这棵树上追加结算叶子
+    #[account(mut)]
+    pub receipt_tree: Option<AccountLoader<'info, ReceiptTree>>,
+
+    #[account(
+        mut,
+        constraint = escrow_token_account.mint == escrow_account.mint @ EscrowError::ValidationFailed,
+        constraint = escrow_token_account.owner == escrow_account.key() @ EscrowError::ValidationFailed,
+    )]
+    pub escrow_token_account: Account<'info, TokenAccount>,
+
+    #[account(address = escrow_account.mint @ EscrowError::TokenMintMismatch)]
+    pub token_mint: Account<'info, Mint>,
+
+    /// CHECK: 付款方账户；原生 SOL 托管直接向其支付 lamports
+    #[account(mut, address = escrow_account.base.payer_address @ EscrowError::ValidationFailed)]
+    pub payer: AccountInfo<'info>,
+
+    // 各方的 ATA，原生 SOL 托管不传
+    #[account(
+        init_if_needed,
+        payer = initiator,
+        associated_token::mint = token_mint,
+        associated_token::authority = payer,
+    )]
+    pub payer_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// CHECK: 卖家账户
+    #[account(mut, address = escrow_account.base.seller @ EscrowError::ValidationFailed)]
+    pub seller: AccountInfo<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = initiator,
+        associated_token::mint = token_mint,
+        associated_token::authority = seller,
+    )]
+    pub seller_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// CHECK: moderator账户；只有仲裁小组的托管不传
+    #[account(mut, constraint = escrow_account.base.moderator == Some(moderator.key()) @ EscrowError::ValidationFailed)]
+    pub moderator: Option<AccountInfo<'info>>,
+
+    #[account(
+        init_if_needed,
+        payer = initiator,
+        associated_token::mint = token_mint,
+        associated_token::authority = moderator,
+    )]
+    pub moderator_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// CHECK: 买家账户，接收托管账户租金
+    #[account(mut, address = escrow_account.base.buyer @ EscrowError::ValidationFailed)]
+    pub buyer: AccountInfo<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    pub clock: Sysvar<'info, Clock>,
+
+    /// CHECK: Sysvar Instructions account
+    #[account(address = solana_program::sysvar::instructions::ID)]
+    pub sysvar_instructions: UncheckedAccount<'info>,
+}
+
+pub fn token_handler(
+    ctx: Context<ResolveDisputeToken>,
+    ruling: DisputeRuling,
+    valid_until: i64,
+    signatures: Vec<Vec<u8>>,
+) -> Result<()> {
+    let base = &ctx.accounts.escrow_account.base;
+    let signers = verify_ruling(base, &ruling, valid_until, ctx.accounts.clock.unix_timestamp, &signatures, &ctx.accounts.sysvar_instructions)?;
+
+    let vault = EscrowVault::token(
+        ctx.accounts.escrow_account.to_account_info(),
+        EscrowSeeds::new(b"token_escrow", base),
+        &ctx.accounts.escrow_token_account,
+        &ctx.accounts.token_program,
+    )
+    .fund_new_recipients(ctx.accounts.initiator.to_account_info(), ctx.accounts.system_program.to_account_info());
+    let (balance, [buyer_amount, seller_amount, fee]) = settle_ruling(
+        vault,
+        base,
+        &ruling,
+        [
+            Some(Payee { wallet: &ctx.accounts.payer, token_account: ctx.accounts.payer_token_account.as_ref() }),
+            Some(Payee { wallet: &ctx.accounts.seller, token_account: ctx.accounts.seller_token_account.as_ref() }),
+            ctx.accounts.moderator.as_ref().map(|moderator| Payee {
+                wallet: moderator,
+                token_account: ctx.accounts.moderator_token_account.as_ref(),
+            }),
+        ],
+        &ctx.accounts.buyer,
+    )?;
+
+    let record = SettlementRecord::new(
+        ctx.accounts.escrow_account.key(),
+        base,
+        Some(ctx.accounts.escrow_account.mint),
+        balance,
+        dispute_payouts(base, buyer_amount, seller_amount, fee),
+        ReleasePath::Dispute,
+        SettlementOutcome::Disputed,
+        &ctx.accounts.clock,
+    )
+    .with_seller_approval(signers.contains(&base.seller));
+    finish_settlement(
+        base,
+        record,
+        &mut ctx.accounts.seller_reputation,
+        ctx.bumps.seller_reputation,
+        &mut ctx.accounts.buyer_reputation,
+        ctx.bumps.buyer_reputation,
+        ctx.accounts.receipt.as_deref_mut(),
+        ctx.bumps.receipt,
+        ctx.accounts.receipt_tree.as_ref(),
+        ctx.remaining_accounts,
+    )?;
+
+    msg!(
+        "Token dispute resolved: ID=0x{}, Buyer side={} tokens, Seller={} tokens, Moderator fee={} tokens",
+        bytes_to_hex_string(&base.unique_id),
+        buyer_amount,
+        seller_amount,
+        fee
+    );
+
+    Ok(())
+}
+
+// 按金库实际余额执行裁决：付款方、卖家与 moderator 依次收款后关闭托管。
+// 转账手续费或额外存入都不会导致金额不匹配。原生 SOL 代币托管在不传代币账户时以 lamports 支付
+fn settle_ruling<'info>(
+    vault: EscrowVault<'_, 'info>,
+    base: &EscrowAccount,
+    ruling: &DisputeRuling,
+    payees: [Option<Payee<'_, 'info>>; 3],
+    buyer: &AccountInfo<'info>,
+) -> Result<(u64, [u64; 3])> {
+    let balance = vault.balance()?;
+    let (buyer_amount, seller_amount, fee) = ruling.split(balance);
+    require!(fee <= base.max_moderator_fee(balance), EscrowError::ModeratorFeeTooHigh);
+
+    let pays_token_accounts = payees.iter().flatten().any(|payee| payee.token_account.is_some());
+    let mut vault = vault.unwrap_native(pays_token_accounts)?;
+
+    let amounts = [buyer_amount, seller_amount, fee];
+    for (payee, amount) in payees.iter().zip(amounts) {
+        if amount == 0 {
+            continue;
+        }
+        let payee = payee.as_ref().ok_or(EscrowError::InvalidRecipient)?;
+        vault.pay(payee, amount)?;
+    }
+    vault.close(buyer)?;
+
+    Ok((balance, amounts))
+}
+
+// 裁决的三方分配：付款方、卖家与 moderator 手续费
+fn dispute_payouts(base: &EscrowAccount, buyer_amount: u64, seller_amount: u64, fee: u64) -> Vec<ReceiptPayout> {
+    let mut payouts = vec![
+        ReceiptPayout { recipient: base.payer_address, amount: buyer_amount },
+        ReceiptPayout { recipient: base.seller, amount: seller_amount },
+    ];
+    if let Some(moderator) = base.moderator {
+        payouts.push(ReceiptPayout { recipient: moderator, amount: fee });
+    }
+    payouts
+}
+
+// 校验裁决参数以及前一条 Ed25519 指令中的签名：moderator 或仲裁小组的法定人数必须签名，且批准数达到
+// required_signatures，与普通释放相同（例如 2-of-3 托管需要 moderator 加一方当事人）。
+// 只有仲裁小组的托管没有 moderator 收取仲裁费。费用上限按实际余额在调用方校验。返回签名者
+fn verify_ruling(
+    base: &EscrowAccount,
+    ruling: &DisputeRuling,
+    valid_until: i64,
+    current_time: i64,
+    signatures: &[Vec<u8>],
+    instructions_sysvar: &AccountInfo,
+) -> Result<Vec<Pubkey>> {
+    require!(
+        base.moderator.is_some() || base.panel.is_some(),
+        EscrowError::Unauthorized
+    );
+    require!(
+        base.moderator.is_some() || ruling.moderator_fee_bps == 0,
+        EscrowError::InvalidPaymentParameters
+    );
+    require!(
+        ruling.buyer_bps as u64 <= BPS_DENOMINATOR,
+        EscrowError::InvalidPaymentParameters
+    );
+    require!(
+        ruling.moderator_fee_bps as u64 <= BPS_DENOMINATOR,
+        EscrowError::InvalidPaymentParameters
+    );
+    verify_valid_until(valid_until, current_time)?;
+
+    let message = construct_ruling_message(&base.unique_id, base.nonce, valid_until, ruling);
+    let signers = verify_ed25519_instructions(instructions_sysvar, signatures, &message)?;
+    let arbitrated = base.moderator.is_some_and(|moderator| signers.contains(&moderator)) ||
+        base.panel.as_ref().is_some_and(|panel| panel.has_quorum(&signers));
+    require!(arbitrated, EscrowError::InvalidSigner);
+
+    // 裁决可能付款的对象；只有全部付给卖家时交付证明才计入批准
+    let recipients = [
+        (ruling.buyer_bps > 0).then_some(&base.payer_address),
+        Some(&base.seller),
+        base.moderator.as_ref().filter(|_| ruling.moderator_fee_bps > 0),
+    ];
+    require!(
+        base.count_approvals(&signers, recipients.into_iter().flatten()) >= base.required_signatures as usize,
+        EscrowError::InsufficientSignatures
+    );
+
+    Ok(signers)
+}
@@ -0,0 +1,589 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Token, TokenAccount, Transfer, Mint};
+use anchor_spl::associated_token::AssociatedToken;
+use crate::{state::*, error::*, asset::{release_from_vault, EscrowSeeds, EscrowVault, Payee}, utils::{bytes_to_hex_string, counted_settlement_minimum, finish_settlement, format_timestamp, record_creation, receipt_payouts}};
+
+#[derive(Accounts)]
+#[instruction(
+    moderator: Option<Pubkey>,
+    unique_id: [u8; 20],
+    required_signatures: u8,
+    unlock_hours: u64,
+    amount: u64,
+    options: EscrowOptions
+)]
+pub struct InitializeFixedSol<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: 买家账户，由客户端指定
+    pub buyer: AccountInfo<'info>,
+
+    /// CHECK: 卖家账户，由客户端指定
+    pub seller: AccountInfo<'info>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = FixedEscrow::LEN,
+        seeds = [
+            b"fixed_escrow",
+            buyer.key().as_ref(),
+            seller.key().as_ref(),
+            &[moderator.is_some() as u8],  // 使用1字节表示是否有moderator
+            &unique_id
+        ],
+        bump
+    )]
+    pub escrow_account: AccountLoader<'info, FixedEscrow>,
+
+    /// CHECK: 同一地址上已结算托管的收据，存在时拒绝重复使用 unique_id
+    #[account(
+        seeds = [b"receipt", escrow_account.key().as_ref()],
+        bump,
+        constraint = receipt.data_is_empty() @ EscrowError::EscrowAlreadySettled
+    )]
+    pub receipt: UncheckedAccount<'info>,
+
+    // 使用压缩收据时记录创建叶子的收据树，须与 options.receipt_tree 一致
+    #[account(mut)]
+    pub receipt_tree: Option<AccountLoader<'info, ReceiptTree>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(
+    moderator: Option<Pubkey>,
+    unique_id: [u8; 20],
+    required_signatures: u8,
+    unlock_hours: u64,
+    amount: u64,
+    options: EscrowOptions
+)]
+pub struct InitializeFixedToken<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: 买家账户，由客户端指定
+    pub buyer: AccountInfo<'info>,
+
+    /// CHECK: 卖家账户，由客户端指定
+    pub seller: AccountInfo<'info>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = FixedEscrow::LEN,
+        seeds = [
+            b"fixed_escrow",
+            buyer.key().as_ref(),
+            seller.key().as_ref(),
+            &[moderator.is_some() as u8],
+            &unique_id
+        ],
+        bump
+    )]
+    pub escrow_account: AccountLoader<'info, FixedEscrow>,
+
+    /// CHECK: 同一地址上已结算托管的收据，存在时拒绝重复使用 unique_id
+    #[account(
+        seeds = [b"receipt", escrow_account.key().as_ref()],
+        bump,
+        constraint = receipt.data_is_empty() @ EscrowError::EscrowAlreadySettled
+    )]
+    pub receipt: UncheckedAccount<'info>,
+
+    // 使用压缩收据时记录创建叶子的收据树，须与 options.receipt_tree 一致
+    #[account(mut)]
+    pub receipt_tree: Option<AccountLoader<'info, ReceiptTree>>,
+
+    pub token_program: Program<'info, Token>,
+    pub token_mint: Account<'info, Mint>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    #[account(
+        mut,
+        token::mint = token_mint,
+        token::authority = payer,
+    )]
+    pub payer_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = token_mint,
+        associated_token::authority = escrow_account,
+    )]
+    pub escrow_token_account: Account<'info, TokenAccount>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseFixedSol<'info> {
+    #[account(mut)]
+    pub initiator: Signer<'info>,
+
+    // 只有 initialize_fixed_* 能创建该类型的账户，所有者和鉴别符校验已足以确认地址
+    #[account(
+        mut,
+        constraint = escrow_account.load()?.mint().is_none() @ EscrowError::TokenMintMismatch,
+    )]
+    pub escrow_account: AccountLoader<'info, FixedEscrow>,
+
+    /// CHECK: 买家账户，必须是托管账户中指定的买家
+    #[account(mut, address = escrow_account.load()?.buyer @ EscrowError::ValidationFailed)]
+    pub buyer: AccountInfo<'info>,
+
+    /// CHECK: 卖家账户，仅用于推导信誉账户地址
+    #[account(address = escrow_account.load()?.seller @ EscrowError::ValidationFailed)]
+    pub seller: AccountInfo<'info>,
+
+    // 结算结果记入双方的信誉账户
+    #[account(
+        init_if_needed,
+        payer = initiator,
+        space = Reputation::LEN,
+        seeds = [b"seller_reputation", seller.key().as_ref()],
+        bump
+    )]
+    pub seller_reputation: Account<'info, Reputation>,
+
+    #[account(
+        init_if_needed,
+        payer = initiator,
+        space = Reputation::LEN,
+        seeds = [b"buyer_reputation", buyer.key().as_ref()],
+        bump
+    )]
+    pub buyer_reputation: Account<'info, Reputation>,
+
+    // 托管关闭后保留的结算收据，使用收据树的托管不传入
+    #[account(
+        init,
+        payer = initiator,
+        space = SettlementReceipt::LEN,
+        seeds = [b"receipt", escrow_account.key().as_ref()],
+        bump
+    )]
+    pub receipt: Option<Account<'info, SettlementReceipt>>,
+
+    // 使用压缩收据的托管在这棵树上追加结算叶子
+    #[account(mut)]
+    pub receipt_tree: Option<AccountLoader<'info, ReceiptTree>>,
+
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: Sysvar Instructions account
+    #[account(address = solana_program::sysvar::instructions::ID)]
+    pub sysvar_instructions: UncheckedAccount<'info>,
+
+    /// CHECK: 第一个接收方账户
+    #[account(mut)]
+    pub recipient1: AccountInfo<'info>,
+
+    /// CHECK: 第二个接收方账户
+    #[account(mut)]
+    pub recipient2: Option<AccountInfo<'info>>,
+
+    /// CHECK: 第三个接收方账户
+    #[account(mut)]
+    pub recipient3: Option<AccountInfo<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseFixedToken<'info> {
+    #[account(mut)]
+    pub initiator: Signer<'info>,
+
+    // 只有 initialize_fixed_* 能创建该类型的账户，所有者和鉴别符校验已足以确认地址
+    #[account(mut)]
+    pub escrow_account: AccountLoader<'info, FixedEscrow>,
+
+    /// CHECK: 买家账户，接收代币账户和托管账户的租金
+    #[account(mut, address = escrow_account.load()?.buyer @ EscrowError::ValidationFailed)]
+    pub buyer: AccountInfo<'info>,
+
+    /// CHECK: 卖家账户，仅用于推导信誉账户地址
+    #[account(address = escrow_account.load()?.seller @ EscrowError::ValidationFailed)]
+    pub seller: AccountInfo<'info>,
+
+    // 结算结果记入双方的信誉账户
+    #[account(
+        init_if_needed,
+        payer = initiator,
+        space = Reputation::LEN,
+        seeds = [b"seller_reputation", seller.key().as_ref()],
+        bump
+    )]
+    pub seller_reputation: Account<'info, Reputation>,
+
+    #[account(
+        init_if_needed,
+        payer = initiator,
+        space = Reputation::LEN,
+        seeds = [b"buyer_reputation", buyer.key().as_ref()],
+        bump
+    )]
+    pub buyer_reputation: Account<'info, Reputation>,
+
+    // 托管关闭后保留的结算收据，使用收据树的托管不传入
+    #[account(
+        init,
+        payer = initiator,
+        space = SettlementReceipt::LEN,
+        seeds = [b"receipt", escrow_account.key().as_ref()],
+        bump
+    )]
+    pub receipt: Option<Account<'info, SettlementReceipt>>,
+
+    // 使用压缩收据的托管在这棵树上追加结算叶子
+    #[account(mut)]
+    pub receipt_tree: Option<AccountLoader<'info, ReceiptTree>>,
+
+    #[account(address = escrow_account.load()?.mint @ EscrowError::TokenMintMismatch)]
+    pub token_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = escrow_token_account.mint == token_mint.key() @ EscrowError::ValidationFailed,
+        constraint = escrow_token_account.owner == escrow_account.key() @ EscrowError::ValidationFailed,
+    )]
+    pub escrow_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    /// CHECK: Sysvar Instructions account
+    #[account(address = solana_program::sysvar::instructions::ID)]
+    pub sysvar_instructions: UncheckedAccount<'info>,
+
+    /// CHECK: 第一个接收方owner
+    pub recipient1: AccountInfo<'info>,
+    /// CHECK: 第一个接收方ATA
+    #[account(
+        init_if_needed,
+        payer = initiator,
+        associated_token::mint = token_mint,
+        associated_token::authority = recipient1,
+    )]
+    pub recipient1_ata: Account<'info, TokenAccount>,
+
+    /// CHECK: 第二个接收方owner
+    pub recipient2: Option<AccountInfo<'info>>,
+    /// CHECK: 第二个接收方ATA
+    #[account(
+        init_if_needed,
+        payer = initiator,
+        associated_token::mint = token_mint,
+        associated_token::authority = recipient2,
+    )]
+    pub recipient2_ata: Option<Account<'info, TokenAccount>>,
+
+    /// CHECK: 第三个接收方owner
+    pub recipient3: Option<AccountInfo<'info>>,
+    /// CHECK: 第三个接收方ATA
+    #[account(
+        init_if_needed,
+        payer = initiator,
+        associated_token::mint = token_mint,
+        associated_token::authority = recipient3,
+    )]
+    pub recipient3_ata: Option<Account<'info, TokenAccount>>,
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn initialize_sol_handler(
+    ctx: Context<InitializeFixedSol>,
+    moderator: Option<Pubkey>,
+    unique_id: [u8; 20],
+    required_signatures: u8,
+    unlock_hours: u64,
+    amount: u64,
+    options: EscrowOptions,
+) -> Result<()> {
+    let clock = Clock::get()?;
+    let mut base = fixed_base(
+        ctx.accounts.buyer.key(),
+        ctx.accounts.seller.key(),
+        moderator,
+        ctx.accounts.payer.key(),
+        required_signatures,
+        clock.unix_timestamp + (unlock_hours * 3600) as i64,
+        unique_id,
+        amount,
+        ctx.bumps.escrow_account,
+        &options,
+    )?;
+    base.counted_min_amount = counted_settlement_minimum(None);
+    ctx.accounts.escrow_account.load_init()?.initialize(&base, None)?;
+
+    // 使用压缩收据时把创建记录追加到收据树
+    record_creation(ctx.accounts.escrow_account.key(), &base, None, ctx.accounts.receipt_tree.as_ref(), &clock)?;
+
+    // 转移 SOL 到托管账户
+    anchor_lang::system_program::transfer(
+        CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            anchor_lang::system_program::Transfer {
+                from: ctx.accounts.payer.to_account_info(),
+                to: ctx.accounts.escrow_account.to_account_info(),
+            },
+        ),
+        amount,
+    )?;
+
+    msg!(
+        "Fixed SOL escrow initialized: Buyer={}, Seller={}, ID=0x{}, Amount={} lamports, Required signatures={}, Unlock time={}",
+        base.buyer,
+        base.seller,
+        bytes_to_hex_string(&unique_id),
+        amount,
+        required_signatures,
+        format_timestamp(base.unlock_time)
+    );
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn initialize_token_handler(
+    ctx: Context<InitializeFixedToken>,
+    moderator: Option<Pubkey>,
+    unique_id: [u8; 20],
+    required_signatures: u8,
+    unlock_hours: u64,
+    amount: u64,
+    options: EscrowOptions,
+) -> Result<()> {
+    let clock = Clock::get()?;
+    let mint = ctx.accounts.token_mint.key();
+    let mut base = fixed_base(
+        ctx.accounts.buyer.key(),
+        ctx.accounts.seller.key(),
+        moderator,
+        ctx.accounts.payer.key(),
+        required_signatures,
+        clock.unix_timestamp + (unlock_hours as i64 * 3600),
+        unique_id,
+        amount,
+        ctx.bumps.escrow_account,
+        &options,
+    )?;
+    base.counted_min_amount = counted_settlement_minimum(Some(&ctx.accounts.token_mint));
+    ctx.accounts.escrow_account.load_init()?.initialize(&base, Some(mint))?;
+
+    // 使用压缩收据时把创建记录追加到收据树
+    record_creation(ctx.accounts.escrow_account.key(), &base, Some(mint), ctx.accounts.receipt_tree.as_ref(), &clock)?;
+
+    // 转移代币到escrow代币账户
+    token::transfer(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.payer_token_account.to_account_info(),
+                to: ctx.accounts.escrow_token_account.to_account_info(),
+                authority: ctx.accounts.payer.to_account_info(),
+            },
+        ),
+        amount,
+    )?;
+
+    msg!(
+        "Fixed token escrow initialized: Buyer={}, Seller={}, Payer={}, ID=0x{}, Amount={} tokens, Required signatures={}, Unlock time={}",
+        base.buyer,
+        base.seller,
+        base.payer_address,
+        bytes_to_hex_string(&unique_id),
+        amount,
+        required_signatures,
+        format_timestamp(base.unlock_time)
+    );
+
+    Ok(())
+}
+
+pub fn release_sol_handler(
+    ctx: Context<ReleaseFixedSol>,
+    payment_amounts: Vec<u64>,
+    signatures: Vec<Vec<u8>>,
+    valid_until: i64,
+) -> Result<()> {
+    let clock = Clock::get()?;
+    let escrow_key = ctx.accounts.escrow_account.key();
+    // 校验逻辑与其它托管共用，复制到栈上的结构即可，无需反序列化整个账户
+    let base = ctx.accounts.escrow_account.load()?.to_base();
+
+    let payees = [
+        Some(Payee::wallet(&ctx.accounts.recipient1)),
+        ctx.accounts.recipient2.as_ref().map(Payee::wallet),
+        ctx.accounts.recipient3.as_ref().map(Payee::wallet),
+    ];
+
+    let vault = EscrowVault::lamports(
+        ctx.accounts.escrow_account.to_account_info(),
+        EscrowSeeds::new(b"fixed_escrow", &base),
+    )
+    .fund_new_recipients(
+        ctx.accounts.initiator.to_account_info(),
+        ctx.accounts.system_program.to_account_info(),
+    );
+
+    let ReleaseApproval { outcome, seller_approved } = release_from_vault(
+        vault,
+        &base,
+        &payees,
+        &payment_amounts,
+        &signatures,
+        valid_until,
+        clock.unix_timestamp,
+        &ctx.accounts.sysvar_instructions,
+        &ctx.accounts.buyer,
+    )?;
+
+    let recipient_pubkeys = payees.map(|payee| payee.map(|payee| payee.wallet.key()));
+    let record = SettlementRecord::new(
+        escrow_key,
+        &base,
+        None,
+        base.amount,
+        receipt_payouts(&recipient_pubkeys, &payment_amounts),
+        ReleasePath::for_release(outcome),
+        outcome,
+        &clock,
+    )
+    .with_seller_approval(seller_approved);
+    finish_settlement(
+        &base,
+        record,
+        &mut ctx.accounts.seller_reputation,
+        ctx.bumps.seller_reputation,
+        &mut ctx.accounts.buyer_reputation,
+        ctx.bumps.buyer_reputation,
+        ctx.accounts.receipt.as_deref_mut(),
+        ctx.bumps.receipt,
+        ctx.accounts.receipt_tree.as_ref(),
+        ctx.remaining_accounts,
+    )?;
+
+    msg!(
+        "Fixed SOL escrow completed: Buyer={}, Seller={}, ID=0x{}",
+        base.buyer,
+        base.seller,
+        bytes_to_hex_string(&base.unique_id)
+    );
+
+    Ok(())
+}
+
+pub fn release_token_handler(
+    ctx: Context<ReleaseFixedToken>,
+    payment_amounts: Vec<u64>,
+    signatures: Vec<Vec<u8>>,
+    valid_until: i64,
+) -> Result<()> {
+    let clock = Clock::get()?;
+    let escrow_key = ctx.accounts.escrow_account.key();
+    let mint = ctx.accounts.token_mint.key();
+    // 校验逻辑与其它托管共用，复制到栈上的结构即可，无需反序列化整个账户
+    let base = ctx.accounts.escrow_account.load()?.to_base();
+
+    let payees = [
+        Some(Payee::token(&ctx.accounts.recipient1, &ctx.accounts.recipient1_ata)),
+        ctx.accounts.recipient2.as_ref().map(|wallet| Payee { wallet, token_account: ctx.accounts.recipient2_ata.as_ref() }),
+        ctx.accounts.recipient3.as_ref().map(|wallet| Payee { wallet, token_account: ctx.accounts.recipient3_ata.as_ref() }),
+    ];
+
+    let vault = EscrowVault::token(
+        ctx.accounts.escrow_account.to_account_info(),
+        EscrowSeeds::new(b"fixed_escrow", &base),
+        &ctx.accounts.escrow_token_account,
+        &ctx.accounts.token_program,
+    );
+
+    let ReleaseApproval { outcome, seller_approved } = release_from_vault(
+        vault,
+        &base,
+        &payees,
+        &payment_amounts,
+        &signatures,
+        valid_until,
+        clock.unix_timestamp,
+        &ctx.accounts.sysvar_instructions,
+        &ctx.accounts.buyer,
+    )?;
+
+    let recipient_pubkeys = payees.map(|payee| payee.map(|payee| payee.wallet.key()));
+    let record = SettlementRecord::new(
+        escrow_key,
+        &base,
+        Some(mint),
+        base.amount,
+        receipt_payouts(&recipient_pubkeys, &payment_amounts),
+        ReleasePath::for_release(outcome),
+        outcome,
+        &clock,
+    )
+    .with_seller_approval(seller_approved);
+    finish_settlement(
+        &base,
+        record,
+        &mut ctx.accounts.seller_reputation,
+        ctx.bumps.seller_reputation,
+        &mut ctx.accounts.buyer_reputation,
+        ctx.bumps.buyer_reputation,
+        ctx.accounts.receipt.as_deref_mut(),
+        ctx.bumps.receipt,
+        ctx.accounts.receipt_tree.as_ref(),
+        ctx.remaining_accounts,
+    )?;
+
+    msg!(
+        "Fixed token escrow completed: Buyer={}, Seller={}, ID=0x{}",
+        base.buyer,
+        base.seller,
+        bytes_to_hex_string(&base.unique_id)
+    );
+
+    Ok(())
+}
+
+// 与常规托管相同的参数校验；固定布局放不下的模式在 FixedEscrow::initialize 中拒绝
+#[allow(clippy::too_many_arguments)]
+fn fixed_base(
+    buyer: Pubkey,
+    seller: Pubkey,
+    moderator: Option<Pubkey>,
+    payer: Pubkey,
+    required_signatures: u8,
+    unlock_time: i64,
+    unique_id: [u8; 20],
+    amount: u64,
+    bump: u8,
+    options: &EscrowOptions,
+) -> Result<EscrowAccount> {
+    require!(amount > 0, EscrowError::InvalidPaymentParameters);
+
+    // 注册表和卖家信誉检查需要额外账户，固定布局托管不提供
+    require!(
+        !options.require_registered_moderator && options.min_seller_settlements == 0,
+        EscrowError::UnsupportedByFixedEscrow
+    );
+
+    let mut base = EscrowAccount::new(
+        buyer,
+        seller,
+        moderator,
+        payer,
+        required_signatures,
+        unlock_time,
+        unique_id,
+        amount,
+        bump,
+    );
+    base.apply_options(options)?;
+    base.validate_required_signatures()?;
+    Ok(base)
+}
@@ -0,0 +1,547 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Token, TokenAccount, Mint};
+use anchor_spl::associated_token::AssociatedToken;
+use crate::{state::*, error::*, events::PreimageRevealed, asset::{EscrowSeeds, EscrowVault, Payee}, utils::{bytes_to_hex_string, finish_settlement}};
+
+#[derive(Accounts)]
+pub struct ClaimWithPreimageSol<'info> {
+    // 任何人都可以提交原像，资金只会转给卖家
+    #[account(mut)]
+    pub initiator: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = escrow_account.base.is_initialized @ EscrowError::ValidationFailed,
+        seeds = [
+            b"sol_escrow",
+            escrow_account.base.buyer.as_ref(),
+            escrow_account.base.seller.as_ref(),
+            &[escrow_account.base.moderator.is_some() as u8],
+            &escrow_account.base.unique_id
+        ],
+        bump = escrow_account.base.bump
+    )]
+    pub escrow_account: Account<'info, SolEscrow>,
+
+    // 结算结果记入双方的信誉账户
+    #[account(
+        init_if_needed,
+        payer = initiator,
+        space = Reputation::LEN,
+        seeds = [b"seller_reputation", escrow_account.base.seller.as_ref()],
+        bump
+    )]
+    pub seller_reputation: Account<'info, Reputation>,
+
+    #[account(
+        init_if_needed,
+        payer = initiator,
+        space = Reputation::LEN,
+        seeds = [b"buyer_reputation", escrow_account.base.buyer.as_ref()],
+        bump
+    )]
+    pub buyer_reputation: Account<'info, Reputation>,
+
+    // 托管关闭后保留的结算收据，使用收据树的托管不传入
+    #[account(
+        init,
+        payer = initiator,
+        space = SettlementReceipt::LEN,
+        seeds = [b"receipt", escrow_account.key().as_ref()],
+        bump
+    )]
+    pub receipt: Option<Account<'info, SettlementReceipt>>,
+
+    // 使用压缩收据的托管在这棵树上追加结算叶子
+    #[account(mut)]
+    pub receipt_tree: Option<AccountLoader<'info, ReceiptTree>>,
+
+    /// CHECK: 卖家账户
+    #[account(mut, address = escrow_account.base.seller @ EscrowError::ValidationFailed)]
+    pub seller: AccountInfo<'info>,
+
+    /// CHECK: 买家账户，接收托管账户租金
+    #[account(mut, address = escrow_account.base.buyer @ EscrowError::ValidationFailed)]
+    pub buyer: AccountInfo<'info>,
+
+    pub clock: Sysvar<'info, Clock>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn claim_sol_handler(ctx: Context<ClaimWithPreimageSol>, preimage: Vec<u8>) -> Result<()> {
+    let base = &ctx.accounts.escrow_account.base;
+    let hashlock = check_preimage(base, &preimage, ctx.accounts.clock.unix_timestamp)?;
+
+    let paid = pay_out_all(
+        EscrowVault::lamports(
+            ctx.accounts.escrow_account.to_account_info(),
+            EscrowSeeds::new(b"sol_escrow", base),
+        ),
+        &Payee::wallet(&ctx.accounts.seller),
+        &ctx.accounts.buyer,
+    )?;
+
+    let record = SettlementRecord::new(
+        ctx.accounts.escrow_account.key(),
+        base,
+        None,
+        paid,
+        vec![ReceiptPayout { recipient: base.seller, amount: paid }],
+        ReleasePath::Preimage,
+        SettlementOutcome::Released,
+        &ctx.accounts.clock,
+    )
+    .with_seller_approval(ctx.accounts.initiator.key() == base.seller);
+    finish_settlement(
+        base,
+        record,
+        &mut ctx.accounts.seller_reputation,
+        ctx.bumps.seller_reputation,
+        &mut ctx.accounts.buyer_reputation,
+        ctx.bumps.buyer_reputation,
+        ctx.accounts.receipt.as_deref_mut(),
+        ctx.bumps.receipt,
+        ctx.accounts.receipt_tree.as_ref(),
+        ctx.remaining_accounts,
+    )?;
+
+    emit!(PreimageRevealed {
+        escrow: ctx.accounts.escrow_account.key(),
+        unique_id: base.unique_id,
+        hash: hashlock.hash,
+        preimage,
+    });
+
+    msg!(
+        "SOL HTLC escrow claimed: Seller={}, ID=0x{}, Amount={} lamports",
+        base.seller,
+        bytes_to_hex_string(&base.unique_id),
+        paid
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ClaimWithPreimageToken<'info> {
+    // 任何人都可以提交原像，资金只会转给卖家；发起者支付卖家 ATA 的租金
+    #[account(mut)]
+    pub initiator: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = escrow_account.base.is_initialized @ EscrowError::ValidationFailed,
+        seeds = [
+            b"token_escrow",
+            escrow_account.base.buyer.as_ref(),
+            escrow_account.base.seller.as_ref(),
+            &[escrow_account.base.moderator.is_some() as u8],
+            &escrow_account.base.unique_id
+        ],
+        bump = escrow_account.base.bump
+    )]
+    pub escrow_account: Account<'info, TokenEscrow>,
+
+    // 结算结果记入双方的信誉账户
+    #[account(
+        init_if_needed,
+        payer = initiator,
+        space = Reputation::LEN,
+        seeds = [b"seller_reputation", escrow_account.base.seller.as_ref()],
+        bump
+    )]
+    pub seller_reputation: Account<'info, Reputation>,
+
+    #[account(
+        init_if_needed,
+        payer = initiator,
+        space = Reputation::LEN,
+        seeds = [b"buyer_reputation", escrow_account.base.buyer.as_ref()],
+        bump
+    )]
+    pub buyer_reputation: Account<'info, Reputation>,
+
+    // 托管关闭后保留的结算收据，使用收据树的托管不传入
+    #[account(
+        init,
+        payer = initiator,
+        space = SettlementReceipt::LEN,
+        seeds = [b"receipt", escrow_account.key().as_ref()],
+        bump
+    )]
+    pub receipt: Option<Account<'info, SettlementReceipt>>,
+
+    // 使用压缩收据的托管在这棵树上追加结算叶子
+    #[account(mut)]
+    pub receipt_tree: Option<AccountLoader<'info, ReceiptTree>>,
+
+    #[account(
+        mut,
+        constraint = escrow_token_account.mint == escrow_account.mint @ EscrowError::ValidationFailed,
+        constraint = escrow_token_account.owner == escrow_account.key() @ EscrowError::ValidationFailed,
+    )]
+    pub escrow_token_account: Account<'info, TokenAccount>,
+
+    #[account(address = escrow_account.mint @ EscrowError::TokenMintMismatch)]
+    pub token_mint: Account<'info, Mint>,
+
+    /// CHECK: 卖家账户；原生 SOL 托管直接向其支付 lamports
+    #[account(mut, address = escrow_account.base.seller @ EscrowError::ValidationFailed)]
+    pub seller: AccountInfo<'info>,
+
+    // 卖家的 ATA，原生 SOL 托管不传
+    #[account(
+        init_if_needed,
+        payer = initiator,
+        associated_token::mint = token_mint,
+        associated_token::authority = seller,
+    )]
+    pub seller_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// CHECK: 买家账户，接收托管账户租金
+    #[account(mut, address = escrow_account.base.buyer @ EscrowError::ValidationFailed)]
+    pub buyer: AccountInfo<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    pub clock: Sysvar<'info, Clock>,
+}
+
+pub fn claim_token_handler(ctx: Context<ClaimWithPreimageToken>, preimage: Vec<u8>) -> Result<()> {
+    let base = &ctx.accounts.escrow_account.base;
+    let hashlock = check_preimage(base, &preimage, ctx.accounts.clock.unix_timestamp)?;
+
+    let paid = pay_out_all(
+        EscrowVault::token(
+            ctx.accounts.escrow_account.to_account_info(),
+            EscrowSeeds::new(b"token_escrow", base),
+            &ctx.accounts.escrow_token_account,
+            &ctx.accounts.token_program,
+        )
+        .fund_new_recipients(ctx.accounts.initiator.to_account_info(), ctx.accounts.system_program.to_account_info()),
+        &Payee { wallet: &ctx.accounts.seller, token_account: ctx.accounts.seller_token_account.as_ref() },
+        &ctx.accounts.buyer,
+    )?;
+
+    let record = SettlementRecord::new(
+        ctx.accounts.escrow_account.key(),
+        base,
+        Some(ctx.accounts.escrow_account.mint),
+        paid,
+        vec![ReceiptPayout { recipient: base.seller, amount: paid }],
+        ReleasePath::Preimage,
+        SettlementOutcome::Released,
+        &ctx.accounts.clock,
+    )
+    .with_seller_approval(ctx.accounts.initiator.key() == base.seller);
+    finish_settlement(
+        base,
+        record,
+        &mut ctx.accounts.seller_reputation,
+        ctx.bumps.seller_reputation,
+        &mut ctx.accounts.buyer_reputation,
+        ctx.bumps.buyer_reputation,
+        ctx.accounts.receipt.as_deref_mut(),
+        ctx.bumps.receipt,
+        ctx.accounts.receipt_tree.as_ref(),
+        ctx.remaining_accounts,
+    )?;
+
+    emit!(PreimageRevealed {
+        escrow: ctx.accounts.escrow_account.key(),
+        unique_id: base.unique_id,
+        hash: hashlock.hash,
+        preimage,
+    });
+
+    msg!(
+        "Token HTLC escrow claimed: Seller={}, ID=0x{}, Amount={} tokens",
+        base.seller,
+        bytes_to_hex_string(&base.unique_id),
+        paid
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct RefundHtlcSol<'info> {
+    // 截止时间之后任何人都可以触发退款，资金只会退回付款方
+    #[account(mut)]
+    pub initiator: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = escrow_account.base.is_initialized @ EscrowError::ValidationFailed,
+        seeds = [
+            b"sol_escrow",
+            escrow_account.base.buyer.as_ref(),
+            escrow_account.base.seller.as_ref(),
+            &[escrow_account.base.moderator.is_some() as u8],
+            &escrow_account.base.unique_id
+        ],
+        bump = escrow_account.base.bump
+    )]
+    pub escrow_account: Account<'info, SolEscrow>,
+
+    // 结算结果记入双方的信誉账户
+    #[account(
+        init_if_needed,
+        payer = initiator,
+        space = Reputation::LEN,
+        seeds = [b"seller_reputation", escrow_account.base.seller.as_ref()],
+        bump
+    )]
+    pub seller_reputation: Account<'info, Reputation>,
+
+    #[account(
+        init_if_needed,
+        payer = initiator,
+        space = Reputation::LEN,
+        seeds = [b"buyer_reputation", escrow_account.base.buyer.as_ref()],
+        bump
+    )]
+    pub buyer_reputation: Account<'info, Reputation>,
+
+    // 托管关闭后保留的结算收据，使用收据树的托管不传入
+    #[account(
+        init,
+        payer = initiator,
+        space = SettlementReceipt::LEN,
+        seeds = [b"receipt", escrow_account.key().as_ref()],
+        bump
+    )]
+    pub receipt: Option<Account<'info, SettlementReceipt>>,
+
+    // 使用压缩收据的托管在这棵树上追加结算叶子
+    #[account(mut)]
+    pub receipt_tree: Option<AccountLoader<'info, ReceiptTree>>,
+
+    /// CHECK: 付款方账户
+    #[account(mut, address = escrow_account.base.payer_address @ EscrowError::ValidationFailed)]
+    pub payer: AccountInfo<'info>,
+
+    /// CHECK: 买家账户，接收托管账户租金
+    #[account(mut, address = escrow_account.base.buyer @ EscrowError::ValidationFailed)]
+    pub buyer: AccountInfo<'info>,
+
+    pub clock: Sysvar<'info, Clock>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn refund_sol_handler(ctx: Context<RefundHtlcSol>) -> Result<()> {
+    let base = &ctx.accounts.escrow_account.base;
+    check_refundable(base, ctx.accounts.clock.unix_timestamp)?;
+
+    let paid = pay_out_all(
+        EscrowVault::lamports(
+            ctx.accounts.escrow_account.to_account_info(),
+            EscrowSeeds::new(b"sol_escrow", base),
+        ),
+        &Payee::wallet(&ctx.accounts.payer),
+        &ctx.accounts.buyer,
+    )?;
+
+    let record = SettlementRecord::new(
+        ctx.accounts.escrow_account.key(),
+        base,
+        None,
+        paid,
+        vec![ReceiptPayout { recipient: base.payer_address, amount: paid }],
+        ReleasePath::HashlockRefund,
+        SettlementOutcome::Refunded,
+        &ctx.accounts.clock,
+    )
+    .with_seller_approval(ctx.accounts.initiator.key() == base.seller);
+    finish_settlement(
+        base,
+        record,
+        &mut ctx.accounts.seller_reputation,
+        ctx.bumps.seller_reputation,
+        &mut ctx.accounts.buyer_reputation,
+        ctx.bumps.buyer_reputation,
+        ctx.accounts.receipt.as_deref_mut(),
+        ctx.bumps.receipt,
+        ctx.accounts.receipt_tree.as_ref(),
+        ctx.remaining_accounts,
+    )?;
+
+    msg!(
+        "SOL HTLC escrow refunded: Payer={}, ID=0x{}, Amount={} lamports",
+        base.payer_address,
+        bytes_to_hex_string(&base.unique_id),
+        paid
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct RefundHtlcToken<'info> {
+    // 截止时间之后任何人都可以触发退款，资金只会退回付款方；发起者支付付款方 ATA 的租金
+    #[account(mut)]
+    pub initiator: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = escrow_account.base.is_initialized @ EscrowError::ValidationFailed,
+        seeds = [
+            b"token_escrow",
+            escrow_account.base.buyer.as_ref(),
+            escrow_account.base.seller.as_ref(),
+            &[escrow_account.base.moderator.is_some() as u8],
+            &escrow_account.base.unique_id
+        ],
+        bump = escrow_account.base.bump
+    )]
+    pub escrow_account: Account<'info, TokenEscrow>,
+
+    // 结算结果记入双方的信誉账户
+    #[account(
+        init_if_needed,
+        payer = initiator,
+        space = Reputation::LEN,
+        seeds = [b"seller_reputation", escrow_account.base.seller.as_ref()],
+        bump
+    )]
+    pub seller_reputation: Account<'info, Reputation>,
+
+    #[account(
+        init_if_needed,
+        payer = initiator,
+        space = Reputation::LEN,
+        seeds = [b"buyer_reputation", escrow_account.base.buyer.as_ref()],
+        bump
+    )]
+    pub buyer_reputation: Account<'info, Reputation>,
+
+    // 托管关闭后保留的结算收据，使用收据树的托管不传入
+    #[account(
+        init,
+        payer = initiator,
+        space = SettlementReceipt::LEN,
+        seeds = [b"receipt", escrow_account.key().as_ref()],
+        bump
+    )]
+    pub receipt: Option<Account<'info, SettlementReceipt>>,
+
+    // 使用压缩收据的托管在这棵树上追加结算叶子
+    #[account(mut)]
+    pub receipt_tree: Option<AccountLoader<'info, ReceiptTree>>,
+
+    #[account(
+        mut,
+        constraint = escrow_token_account.mint == escrow_account.mint @ EscrowError::ValidationFailed,
+        constraint = escrow_token_account.owner == escrow_account.key() @ EscrowError::ValidationFailed,
+    )]
+    pub escrow_token_account: Account<'info, TokenAccount>,
+
+    #[account(address = escrow_account.mint @ EscrowError::TokenMintMismatch)]
+    pub token_mint: Account<'info, Mint>,
+
+    /// CHECK: 付款方账户；原生 SOL 托管直接向其退回 lamports
+    #[account(mut, address = escrow_account.base.payer_address @ EscrowError::ValidationFailed)]
+    pub payer: AccountInfo<'info>,
+
+    // 付款方的 ATA，原生 SOL 托管不传
+    #[account(
+        init_if_needed,
+        payer = initiator,
+        associated_token::mint = token_mint,
+        associated_token::authority = payer,
+    )]
+    pub payer_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// CHECK: 买家账户，接收托管账户租金
+    #[account(mut, address = escrow_account.base.buyer @ EscrowError::ValidationFailed)]
+    pub buyer: AccountInfo<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    pub clock: Sysvar<'info, Clock>,
+}
+
+pub fn refund_token_handler(ctx: Context<RefundHtlcToken>) -> Result<()> {
+    let base = &ctx.accounts.escrow_account.base;
+    check_refundable(base, ctx.accounts.clock.unix_timestamp)?;
+
+    let paid = pay_out_all(
+        EscrowVault::token(
+            ctx.accounts.escrow_account.to_account_info(),
+            EscrowSeeds::new(b"token_escrow", base),
+            &ctx.accounts.escrow_token_account,
+            &ctx.accounts.token_program,
+        )
+        .fund_new_recipients(ctx.accounts.initiator.to_account_info(), ctx.accounts.system_program.to_account_info()),
+        &Payee { wallet: &ctx.accounts.payer, token_account: ctx.accounts.payer_token_account.as_ref() },
+        &ctx.accounts.buyer,
+    )?;
+
+    let record = SettlementRecord::new(
+        ctx.accounts.escrow_account.key(),
+        base,
+        Some(ctx.accounts.escrow_account.mint),
+        paid,
+        vec![ReceiptPayout { recipient: base.payer_address, amount: paid }],
+        ReleasePath::HashlockRefund,
+        SettlementOutcome::Refunded,
+        &ctx.accounts.clock,
+    )
+    .with_seller_approval(ctx.accounts.initiator.key() == base.seller);
+    finish_settlement(
+        base,
+        record,
+        &mut ctx.accounts.seller_reputation,
+        ctx.bumps.seller_reputation,
+        &mut ctx.accounts.buyer_reputation,
+        ctx.bumps.buyer_reputation,
+        ctx.accounts.receipt.as_deref_mut(),
+        ctx.bumps.receipt,
+        ctx.accounts.receipt_tree.as_ref(),
+        ctx.remaining_accounts,
+    )?;
+
+    msg!(
+        "Token HTLC escrow refunded: Payer={}, ID=0x{}, Amount={} tokens",
+        base.payer_address,
+        bytes_to_hex_string(&base.unique_id),
+        paid
+    );
+
+    Ok(())
+}
+
+// 把金库全部余额付给一方后关闭托管，返回实际支付的金额；
+// 原生 SOL 托管的收款方未传代币账户时解包后支付 lamports，金库租金随托管关闭退回买家
+fn pay_out_all<'info>(
+    vault: EscrowVault<'_, 'info>,
+    payee: &Payee<'_, 'info>,
+    buyer: &AccountInfo<'info>,
+) -> Result<u64> {
+    let balance = vault.balance()?;
+    let mut vault = vault.unwrap_native(payee.token_account.is_some())?;
+    vault.pay(payee, balance)?;
+    vault.close(buyer)?;
+    Ok(balance)
+}
+
+fn check_preimage(base: &EscrowAccount, preimage: &[u8], current_time: i64) -> Result<Hashlock> {
+    let hashlock = base.hashlock.ok_or(EscrowError::NotHashlocked)?;
+
+    require!(current_time < base.unlock_time, EscrowError::HashlockExpired);
+    require!(
+        !preimage.is_empty() && preimage.len() <= MAX_PREIMAGE_LEN,
+        EscrowError::InvalidPreimage
+    );
+    require!(hashlock.matches(preimage), EscrowError::InvalidPreimage);
+
+    Ok(hashlock)
+}
+
+fn check_refundable(base: &EscrowAccount, current_time: i64) -> Result<()> {
+    require!(base.hashlock.is_some(), EscrowError::NotHashlocked);
+    require!(current_time >= base.unlock_time, EscrowError::TimelockNotExpired);
+    Ok(())
+}
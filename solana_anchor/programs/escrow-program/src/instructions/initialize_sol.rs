@@ -1,6 +1,7 @@
 use anchor_lang::prelude::*;
-use crate::state::*;
-use crate::utils::{bytes_to_hex_string, format_timestamp};
+use crate::{state::*, error::*};
+use crate::utils::{bytes_to_hex_string, format_timestamp, verify_registered_moderator, verify_seller_history, record_creation, counted_settlement_minimum};
+use mobazha_moderator_registry::state::{ModeratorProfile, RegistryConfig};
 
 #[derive(Accounts)]
 #[instruction(
@@ -8,7 +9,8 @@ use crate::utils::{bytes_to_hex_string, format_timestamp};
     unique_id: [u8; 20],
     required_signatures: u8,
     unlock_hours: u64,
-    amount: u64
+    amount: u64,
+    options: EscrowOptions
 )]
 pub struct InitializeSol<'info> {
     #[account(mut)]
@@ -35,6 +37,36 @@ pub struct InitializeSol<'info> {
     )]
     pub escrow_account: Account<'info, SolEscrow>,
     
+    /// CHECK: 同一地址上已结算托管的收据，存在时拒绝重复使用 unique_id
+    #[account(
+        seeds = [b"receipt", escrow_account.key().as_ref()],
+        bump,
+        constraint = receipt.data_is_empty() @ EscrowError::EscrowAlreadySettled
+    )]
+    pub receipt: UncheckedAccount<'info>,
+    
+    // 使用压缩收据时记录创建叶子的收据树，须与 options.receipt_tree 一致
+    #[account(mut)]
+    pub receipt_tree: Option<AccountLoader<'info, ReceiptTree>>,
+    
+    // moderator 在注册表中的档案，仅在 options.require_registered_moderator 时需要
+    pub moderator_profile: Option<Account<'info, ModeratorProfile>>,
+
+    // 注册表配置，用于按当前 min_stake 重新判断 moderator 是否有效
+    #[account(
+        seeds = [b"registry_config"],
+        seeds::program = mobazha_moderator_registry::ID,
+        bump = registry_config.bump,
+    )]
+    pub registry_config: Option<Account<'info, RegistryConfig>>,
+    
+    // 卖家的信誉账户，仅在 options.min_seller_settlements 大于 0 时需要
+    #[account(
+        seeds = [b"seller_reputation", seller.key().as_ref()],
+        bump = seller_reputation.bump
+    )]
+    pub seller_reputation: Option<Account<'info, Reputation>>,
+    
     pub system_program: Program<'info, System>,
     pub rent: Sysvar<'info, Rent>,
     pub clock: Sysvar<'info, Clock>,
@@ -47,7 +79,8 @@ pub fn handler(
     required_signatures: u8,
     unlock_hours: u64,
     amount: u64,
-) -> Result<()> {
+    options: EscrowOptions,
+) -> Result<EscrowCreated> {
     let escrow = &mut ctx.accounts.escrow_account;
     
     // 初始化基础托管账户
@@ -63,9 +96,29 @@ pub fn handler(
         ctx.bumps.escrow_account,
     );
     
-    // 验证参数
+    if options.require_registered_moderator {
+        verify_registered_moderator(
+            moderator,
+            ctx.accounts.moderator_profile.as_deref(),
+            ctx.accounts.registry_config.as_deref(),
+            None,
+        )?;
+    }
+    
+    if options.min_seller_settlements > 0 {
+        verify_seller_history(ctx.accounts.seller_reputation.as_deref(), options.min_seller_settlements)?;
+    }
+    
+    // 可选模式：哈希时间锁（unlock_time 成为退款截止时间）、流式释放或交付预言机
+    escrow.base.apply_options(&options)?;
+    escrow.base.counted_min_amount = counted_settlement_minimum(None);
+    
+    // 验证参数（预言机也计入可用的签名角色）
     escrow.base.validate_required_signatures()?;
     
+    // 使用压缩收据时把创建记录追加到收据树
+    record_creation(escrow.key(), &escrow.base, None, ctx.accounts.receipt_tree.as_ref(), &ctx.accounts.clock)?;
+    
     // 转移 SOL 到托管账户
     anchor_lang::system_program::transfer(
         CpiContext::new(
@@ -93,5 +146,9 @@ pub fn handler(
         formatted_time
     );
     
-    Ok(())
+    // 通过 return data 返回托管地址，供 CPI 调用方记录
+    Ok(EscrowCreated {
+        escrow: ctx.accounts.escrow_account.key(),
+        unique_id,
+    })
 } 
\ No newline at end of file
@@ -1,7 +1,8 @@
 use anchor_lang::prelude::*;
 use anchor_spl::token::{self, Token, TokenAccount, Mint};
-use anchor_spl::associated_token::AssociatedToken;
-use crate::{state::*, error::*, utils::{bytes_to_hex_string, format_timestamp}};
+use anchor_spl::associated_token::{get_associated_token_address, AssociatedToken};
+use crate::{state::*, error::*, utils::{bytes_to_hex_string, format_timestamp, verify_registered_moderator, verify_seller_history, record_creation, counted_settlement_minimum}};
+use mobazha_moderator_registry::state::{ModeratorProfile, RegistryConfig};
 
 #[derive(Accounts)]
 #[instruction(
@@ -9,7 +10,8 @@ use crate::{state::*, error::*, utils::{bytes_to_hex_string, format_timestamp}};
     unique_id: [u8; 20],
     required_signatures: u8,
     unlock_hours: u64,
-    amount: u64
+    amount: u64,
+    options: EscrowOptions
 )]
 pub struct InitializeToken<'info> {
     #[account(mut)]
@@ -36,24 +38,67 @@ pub struct InitializeToken<'info> {
     )]
     pub escrow_account: Account<'info, TokenEscrow>,
     
+    /// CHECK: 同一地址上已结算托管的收据，存在时拒绝重复使用 unique_id
+    #[account(
+        seeds = [b"receipt", escrow_account.key().as_ref()],
+        bump,
+        constraint = receipt.data_is_empty() @ EscrowError::EscrowAlreadySettled
+    )]
+    pub receipt: UncheckedAccount<'info>,
+    
+    // 使用压缩收据时记录创建叶子的收据树，须与 options.receipt_tree 一致
+    #[account(mut)]
+    pub receipt_tree: Option<AccountLoader<'info, ReceiptTree>>,
+    
     pub token_program: Program<'info, Token>,
     pub token_mint: Account<'info, Mint>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     
+    // 付款方的代币账户；原生 SOL 铸币可以不传，直接从付款方的 lamports 包装
     #[account(
         mut,
         token::mint = token_mint,
         token::authority = payer,
     )]
-    pub payer_token_account: Account<'info, TokenAccount>,
+    pub payer_token_account: Option<Account<'info, TokenAccount>>,
     
+    // 托管自己的代币账户；账本托管不传，资金直接存入卖家账本的资金池
     #[account(
         init_if_needed,
         payer = payer,
         associated_token::mint = token_mint,
         associated_token::authority = escrow_account,
     )]
-    pub escrow_token_account: Account<'info, TokenAccount>,
+    pub escrow_token_account: Option<Account<'info, TokenAccount>>,
+    
+    // 卖家的收款账本及其资金池（账本 PDA 的关联代币账户），二者同时传入时创建账本托管
+    #[account(
+        mut,
+        seeds = [b"seller_balance", seller.key().as_ref(), token_mint.key().as_ref()],
+        bump = seller_balance.bump
+    )]
+    pub seller_balance: Option<Account<'info, SellerBalance>>,
+    
+    #[account(mut)]
+    pub balance_token_account: Option<Account<'info, TokenAccount>>,
+    
+    // moderator 在注册表中的档案，仅在 options.require_registered_moderator 时需要
+    pub moderator_profile: Option<Account<'info, ModeratorProfile>>,
+
+    // 注册表配置，用于按当前 min_stake 重新判断 moderator 是否有效
+    #[account(
+        seeds = [b"registry_config"],
+        seeds::program = mobazha_moderator_registry::ID,
+        bump = registry_config.bump,
+    )]
+    pub registry_config: Option<Account<'info, RegistryConfig>>,
+    
+    // 卖家的信誉账户，仅在 options.min_seller_settlements 大于 0 时需要
+    #[account(
+        seeds = [b"seller_reputation", seller.key().as_ref()],
+        bump = seller_reputation.bump
+    )]
+    pub seller_reputation: Option<Account<'info, Reputation>>,
     
     pub system_program: Program<'info, System>,
     pub rent: Sysvar<'info, Rent>,
@@ -67,7 +112,8 @@ pub fn handler(
     required_signatures: u8,
     unlock_hours: u64,
     amount: u64,
-) -> Result<()> {
+    options: EscrowOptions,
+) -> Result<EscrowCreated> {
     // 验证参数
     require!(amount > 0, EscrowError::InvalidPaymentParameters);
     
@@ -87,25 +133,100 @@ pub fn handler(
         ctx.bumps.escrow_account,
     );
     
-    // 验证签名要求
+    if options.require_registered_moderator {
+        verify_registered_moderator(
+            moderator,
+            ctx.accounts.moderator_profile.as_deref(),
+            ctx.accounts.registry_config.as_deref(),
+            Some(&ctx.accounts.token_mint.key()),
+        )?;
+    }
+    
+    if options.min_seller_settlements > 0 {
+        verify_seller_history(ctx.accounts.seller_reputation.as_deref(), options.min_seller_settlements)?;
+    }
+    
+    // 可选模式：哈希时间锁（unlock_time 成为退款截止时间）、流式释放或交付预言机
+    escrow.base.apply_options(&options)?;
+    // 计入卖家历史的最低金额按代币精度确定为一个整币
+    escrow.base.counted_min_amount = counted_settlement_minimum(Some(&ctx.accounts.token_mint));
+    
+    // 验证签名要求（预言机也计入可用的签名角色）
     escrow.base.validate_required_signatures()?;
     
     // 设置代币特有字段
     escrow.mint = ctx.accounts.token_mint.key();
-
-    // 转移代币到escrow代币账户
-    let transfer_to_escrow_ix = anchor_spl::token::Transfer {
-        from: ctx.accounts.payer_token_account.to_account_info(),
-        to: ctx.accounts.escrow_token_account.to_account_info(),
-        authority: ctx.accounts.payer.to_account_info(),
-    };
     
-    let escrow_transfer_ctx = CpiContext::new(
-        ctx.accounts.token_program.to_account_info(),
-        transfer_to_escrow_ix,
-    );
+    // 资金去向：托管自己的代币账户，或卖家账本的资金池
+    let destination = match (ctx.accounts.seller_balance.as_mut(), ctx.accounts.balance_token_account.as_ref()) {
+        (Some(seller_balance), Some(balance_token_account)) => {
+            require!(ctx.accounts.escrow_token_account.is_none(), EscrowError::InvalidSellerBalance);
+            require_keys_eq!(
+                balance_token_account.key(),
+                get_associated_token_address(&seller_balance.key(), &escrow.mint),
+                EscrowError::InvalidSellerBalance
+            );
+            // 哈希时间锁和流式释放按托管金库的余额结算，不支持共享资金池
+            require!(
+                escrow.base.hashlock.is_none() && escrow.base.stream.is_none(),
+                EscrowError::UnsupportedByLedgerEscrow
+            );
+            
+            seller_balance.escrowed = seller_balance.escrowed
+                .checked_add(amount)
+                .ok_or(EscrowError::InvalidAmount)?;
+            escrow.base.ledger = true;
+            balance_token_account.to_account_info()
+        }
+        (None, None) => ctx.accounts.escrow_token_account
+            .as_ref()
+            .ok_or(EscrowError::ValidationFailed)?
+            .to_account_info(),
+        _ => return err!(EscrowError::InvalidSellerBalance),
+    };
     
-    token::transfer(escrow_transfer_ctx, amount)?;
+    // 使用压缩收据时把创建记录追加到收据树
+    record_creation(escrow.key(), &escrow.base, Some(escrow.mint), ctx.accounts.receipt_tree.as_ref(), &ctx.accounts.clock)?;
+
+    match ctx.accounts.payer_token_account.as_ref() {
+        // 转移代币到escrow代币账户
+        Some(payer_token_account) => {
+            let transfer_to_escrow_ix = anchor_spl::token::Transfer {
+                from: payer_token_account.to_account_info(),
+                to: destination.clone(),
+                authority: ctx.accounts.payer.to_account_info(),
+            };
+            
+            let escrow_transfer_ctx = CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                transfer_to_escrow_ix,
+            );
+            
+            token::transfer(escrow_transfer_ctx, amount)?;
+        }
+        // 原生 SOL：把 lamports 转入托管的 wSOL 账户后同步余额
+        None => {
+            require!(escrow.is_native(), EscrowError::ValidationFailed);
+            
+            anchor_lang::system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    anchor_lang::system_program::Transfer {
+                        from: ctx.accounts.payer.to_account_info(),
+                        to: destination.clone(),
+                    },
+                ),
+                amount,
+            )?;
+            
+            token::sync_native(CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::SyncNative {
+                    account: destination,
+                },
+            ))?;
+        }
+    }
     
     // 将 ID 转换为十六进制字符串
     let id_hex = bytes_to_hex_string(&unique_id);
@@ -124,5 +245,9 @@ pub fn handler(
         formatted_time
     );
     
-    Ok(())
+    // 通过 return data 返回托管地址，供 CPI 调用方记录
+    Ok(EscrowCreated {
+        escrow: ctx.accounts.escrow_account.key(),
+        unique_id,
+    })
 } 
\ No newline at end of file
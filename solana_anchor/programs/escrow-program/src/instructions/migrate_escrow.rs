@@ -0,0 +1,102 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+use anchor_lang::Discriminator;
+use crate::{state::*, error::*, utils::bytes_to_hex_string};
+
+#[derive(Accounts)]
+pub struct MigrateEscrow<'info> {
+    // 支付扩容所需的租金差额，任何人都可以发起迁移
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    // 旧布局（版本1或版本2）的 SOL 托管或代币托管，布局在处理函数中解析并校验 PDA
+    /// CHECK: 旧布局无法按当前结构反序列化，处理函数手动校验所有者、鉴别符和地址
+    #[account(mut, owner = crate::ID @ EscrowError::InvalidOperation)]
+    pub escrow_account: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<MigrateEscrow>) -> Result<()> {
+    let escrow_info = ctx.accounts.escrow_account.to_account_info();
+
+    // 只有版本化之前（版本1）和版本2的 SOL 托管和代币托管需要迁移
+    let (prefix, new_len, base, mint) = {
+        let data = escrow_info.try_borrow_data()?;
+        let is_token = if data.starts_with(SolEscrow::DISCRIMINATOR) {
+            false
+        } else if data.starts_with(TokenEscrow::DISCRIMINATOR) {
+            true
+        } else {
+            return err!(EscrowError::InvalidOperation);
+        };
+
+        // 旧布局第一个字段是 is_initialized（恒为1），正好读作版本1；
+        // 版本2在 release_hook、ledger 和 require_registered_moderator 的位置是64个保留字节
+        let mut rest: &[u8] = &data[8..];
+        let base = match data.get(8) {
+            Some(&LEGACY_ESCROW_VERSION) => LegacyEscrowAccount::deserialize(&mut rest)?.upgrade(),
+            Some(&V2_ESCROW_VERSION) => V2EscrowAccount::deserialize(&mut rest)?.upgrade(),
+            _ => return err!(EscrowError::EscrowAlreadyMigrated),
+        };
+        if is_token {
+            let mint = Pubkey::deserialize(&mut rest)?;
+            (b"token_escrow".as_slice(), TokenEscrow::LEN, base, Some(mint))
+        } else {
+            (b"sol_escrow".as_slice(), SolEscrow::LEN, base, None)
+        }
+    };
+
+    // 确认账户确实是按这些字段派生的托管 PDA
+    let expected = Pubkey::create_program_address(
+        &[
+            prefix,
+            base.buyer.as_ref(),
+            base.seller.as_ref(),
+            &[base.moderator.is_some() as u8],
+            &base.unique_id,
+            &[base.bump],
+        ],
+        &crate::ID,
+    ).map_err(|_| EscrowError::ValidationFailed)?;
+    require_keys_eq!(expected, escrow_info.key(), EscrowError::ValidationFailed);
+
+    // 只补齐租金豁免的差额；SOL 托管的余额里还有托管资金，不能按总余额计算。
+    // 版本2与当前布局长度相同，不需要补充租金
+    let rent = Rent::get()?;
+    let top_up = rent
+        .minimum_balance(new_len)
+        .saturating_sub(rent.minimum_balance(escrow_info.data_len()));
+    if top_up > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.payer.to_account_info(),
+                    to: escrow_info.clone(),
+                },
+            ),
+            top_up,
+        )?;
+    }
+    escrow_info.realloc(new_len, true)?;
+
+    let unique_id = base.unique_id;
+    let mut data = escrow_info.try_borrow_mut_data()?;
+    let mut writer: &mut [u8] = &mut data[..];
+    match mint {
+        Some(mint) => TokenEscrow { base, mint }.try_serialize(&mut writer)?,
+        None => SolEscrow { base }.try_serialize(&mut writer)?,
+    }
+    // 清空旧数据可能残留的尾部字节
+    writer.fill(0);
+
+    msg!(
+        "Escrow migrated: ID=0x{}, Version={}, Len={}",
+        bytes_to_hex_string(&unique_id),
+        ESCROW_LAYOUT_VERSION,
+        new_len
+    );
+
+    Ok(())
+}
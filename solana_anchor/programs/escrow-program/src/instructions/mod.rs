@@ -1,4 +1,20 @@
 pub mod initialize_sol;
 pub mod initialize_token;
 pub mod release_sol;
-pub mod release_token; 
\ No newline at end of file
+pub mod release_token;
+pub mod bump_nonce;
+pub mod batch_initialize;
+pub mod seller_balance;
+pub mod release_to_claims;
+pub mod claim;
+pub mod multi_asset;
+pub mod htlc;
+pub mod stream;
+pub mod recurring;
+pub mod crowdfund;
+pub mod attest_delivery;
+pub mod dispute;
+pub mod change_moderator;
+pub mod receipt_tree;
+pub mod migrate_escrow;
+pub mod fixed_escrow;
@@ -0,0 +1,586 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Token, TokenAccount, Transfer, Mint};
+use anchor_spl::associated_token::{AssociatedToken, get_associated_token_address};
+use crate::{state::*, error::*, utils::{bytes_to_hex_string, close_escrow_and_return_rent, construct_multi_asset_message, format_timestamp, verify_ed25519_instructions, verify_message_signatures, verify_valid_until, record_settlement, write_receipt}};
+
+#[derive(Accounts)]
+#[instruction(
+    moderator: Option<Pubkey>,
+    unique_id: [u8; 20],
+    required_signatures: u8,
+    unlock_hours: u64,
+    legs: Vec<AssetLeg>
+)]
+pub struct InitializeMultiAsset<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: 买家账户，由客户端指定
+    pub buyer: AccountInfo<'info>,
+
+    /// CHECK: 卖家账户，由客户端指定
+    pub seller: AccountInfo<'info>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = MultiAssetEscrow::LEN,
+        seeds = [
+            b"multi_escrow",
+            buyer.key().as_ref(),
+            seller.key().as_ref(),
+            &[moderator.is_some() as u8],
+            &unique_id
+        ],
+        bump
+    )]
+    pub escrow_account: Account<'info, MultiAssetEscrow>,
+
+    /// CHECK: 同一地址上已结算托管的收据，存在时拒绝重复使用 unique_id
+    #[account(
+        seeds = [b"receipt", escrow_account.key().as_ref()],
+        bump,
+        constraint = receipt.data_is_empty() @ EscrowError::EscrowAlreadySettled
+    )]
+    pub receipt: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub clock: Sysvar<'info, Clock>,
+}
+
+pub fn initialize_handler(
+    ctx: Context<InitializeMultiAsset>,
+    moderator: Option<Pubkey>,
+    unique_id: [u8; 20],
+    required_signatures: u8,
+    unlock_hours: u64,
+    legs: Vec<AssetLeg>,
+) -> Result<()> {
+    require!(
+        !legs.is_empty() && legs.len() <= MAX_ESCROW_ASSETS,
+        EscrowError::InvalidAsset
+    );
+
+    let buyer = ctx.accounts.buyer.key();
+    let seller = ctx.accounts.seller.key();
+
+    for (i, leg) in legs.iter().enumerate() {
+        require!(leg.amount > 0, EscrowError::InvalidPaymentParameters);
+        require!(
+            leg.depositor == buyer || leg.depositor == seller,
+            EscrowError::InvalidAsset
+        );
+        require!(
+            !legs[..i].iter().any(|other| other.mint == leg.mint),
+            EscrowError::InvalidAsset
+        );
+    }
+
+    let unlock_time = ctx.accounts.clock.unix_timestamp + (unlock_hours as i64 * 3600);
+    let escrow = &mut ctx.accounts.escrow_account;
+
+    // 多资产托管不使用 base.amount，各资产金额记录在 assets 中
+    escrow.base = EscrowAccount::new(
+        buyer,
+        seller,
+        moderator,
+        ctx.accounts.payer.key(), // payer_address
+        required_signatures,
+        unlock_time,
+        unique_id,
+        0,
+        ctx.bumps.escrow_account,
+    );
+    escrow.base.validate_required_signatures()?;
+
+    escrow.assets = legs
+        .iter()
+        .map(|leg| EscrowAsset {
+            mint: leg.mint,
+            depositor: leg.depositor,
+            amount: leg.amount,
+            deposited: false,
+        })
+        .collect();
+
+    msg!(
+        "Multi-asset escrow initialized: Buyer={}, Seller={}, ID=0x{}, Assets={}, Required signatures={}, Unlock time={}",
+        buyer,
+        seller,
+        bytes_to_hex_string(&unique_id),
+        legs.len(),
+        required_signatures,
+        format_timestamp(unlock_time)
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct DepositAsset<'info> {
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = escrow_account.base.is_initialized @ EscrowError::ValidationFailed,
+        seeds = [
+            b"multi_escrow",
+            escrow_account.base.buyer.as_ref(),
+            escrow_account.base.seller.as_ref(),
+            &[escrow_account.base.moderator.is_some() as u8],
+            &escrow_account.base.unique_id
+        ],
+        bump = escrow_account.base.bump
+    )]
+    pub escrow_account: Account<'info, MultiAssetEscrow>,
+
+    pub token_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        token::mint = token_mint,
+        token::authority = depositor,
+    )]
+    pub depositor_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = depositor,
+        associated_token::mint = token_mint,
+        associated_token::authority = escrow_account,
+    )]
+    pub escrow_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn deposit_handler(ctx: Context<DepositAsset>) -> Result<()> {
+    let mint = ctx.accounts.token_mint.key();
+    let depositor = ctx.accounts.depositor.key();
+
+    let asset = ctx.accounts.escrow_account.assets
+        .iter_mut()
+        .find(|asset| asset.mint == mint)
+        .ok_or(EscrowError::InvalidAsset)?;
+
+    require!(asset.depositor == depositor, EscrowError::Unauthorized);
+    require!(!asset.deposited, EscrowError::AssetAlreadyDeposited);
+    asset.deposited = true;
+    let amount = asset.amount;
+
+    token::transfer(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.depositor_token_account.to_account_info(),
+                to: ctx.accounts.escrow_token_account.to_account_info(),
+                authority: ctx.accounts.depositor.to_account_info(),
+            },
+        ),
+        amount,
+    )?;
+
+    msg!(
+        "Multi-asset escrow deposit: ID=0x{}, Depositor={}, Mint={}, Amount={} tokens",
+        bytes_to_hex_string(&ctx.accounts.escrow_account.base.unique_id),
+        depositor,
+        mint,
+        amount
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(
+    payouts: Vec<AssetPayout>,
+    signatures: Vec<Vec<u8>>,
+    valid_until: i64
+)]
+pub struct ReleaseMultiAsset<'info> {
+    #[account(mut)]
+    pub initiator: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = escrow_account.base.is_initialized @ EscrowError::ValidationFailed,
+        seeds = [
+            b"multi_escrow",
+            escrow_account.base.buyer.as_ref(),
+            escrow_account.base.seller.as_ref(),
+            &[escrow_account.base.moderator.is_some() as u8],
+            &escrow_account.base.unique_id
+        ],
+        bump = escrow_account.base.bump
+    )]
+    pub escrow_account: Account<'info, MultiAssetEscrow>,
+
+    // 结算结果记入双方的信誉账户
+    #[account(
+        init_if_needed,
+        payer = initiator,
+        space = Reputation::LEN,
+        seeds = [b"seller_reputation", escrow_account.base.seller.as_ref()],
+        bump
+    )]
+    pub seller_reputation: Account<'info, Reputation>,
+
+    #[account(
+        init_if_needed,
+        payer = initiator,
+        space = Reputation::LEN,
+        seeds = [b"buyer_reputation", escrow_account.base.buyer.as_ref()],
+        bump
+    )]
+    pub buyer_reputation: Account<'info, Reputation>,
+
+    // 托管关闭后保留的结算收据
+    #[account(
+        init,
+        payer = initiator,
+        space = SettlementReceipt::LEN,
+        seeds = [b"receipt", escrow_account.key().as_ref()],
+        bump
+    )]
+    pub receipt: Account<'info, SettlementReceipt>,
+
+    /// CHECK: 买家账户
+    #[account(mut, address = escrow_account.base.buyer @ EscrowError::ValidationFailed)]
+    pub buyer: AccountInfo<'info>,
+
+    pub token_program: Program<'info, Token>,
+
+    /// CHECK: Sysvar Instructions account
+    #[account(address = solana_program::sysvar::instructions::ID)]
+    pub sysvar_instructions: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+// remaining_accounts: 先按 assets 顺序依次传入 [vault, depositor, depositor_token_account]，再按 payouts 顺序传入接收方代币账户
+pub fn release_handler<'info>(
+    ctx: Context<'_, '_, 'info, 'info, ReleaseMultiAsset<'info>>,
+    payouts: Vec<AssetPayout>,
+    signatures: Vec<Vec<u8>>,
+    valid_until: i64,
+) -> Result<()> {
+    let escrow = &ctx.accounts.escrow_account;
+    let asset_count = escrow.assets.len();
+
+    // 所有资产都存入后才能结算，否则一方可以在另一方注资前拿走已存入的资产
+    require!(escrow.is_fully_funded(), EscrowError::EscrowNotFullyFunded);
+    require!(
+        !payouts.is_empty() && payouts.len() <= MAX_ASSET_PAYOUTS,
+        EscrowError::TooManyRecipients
+    );
+    require!(
+        ctx.remaining_accounts.len() == asset_count * 3 + payouts.len(),
+        EscrowError::InvalidRecipientCount
+    );
+    for payout in &payouts {
+        require!(payout.amount > 0, EscrowError::ZeroPaymentAmount);
+        require!(escrow.find_asset(&payout.mint).is_some(), EscrowError::InvalidAsset);
+    }
+
+    // 仲裁方每种资产的所得不能超过创建时确定的上限
+    for asset in &escrow.assets {
+        let paid = payouts
+            .iter()
+            .filter(|payout| payout.mint == asset.mint && escrow.base.is_arbitrator(&payout.recipient))
+            .try_fold(0u64, |acc, payout| acc.checked_add(payout.amount))
+            .ok_or(EscrowError::InvalidAmount)?;
+        require!(
+            paid <= escrow.base.max_moderator_fee(asset.amount),
+            EscrowError::ModeratorFeeTooHigh
+        );
+    }
+
+    // 验证签名
+    let current_time = Clock::get()?.unix_timestamp;
+    verify_valid_until(valid_until, current_time)?;
+    let message = construct_multi_asset_message(
+        &escrow.base.unique_id,
+        escrow.base.nonce,
+        valid_until,
+        &payouts,
+    );
+    // 与 release_sol / release_token 相同：超时后只需卖家签名，避免一方失联时所有资产被永久锁定
+    let time_expired = current_time >= escrow.base.unlock_time;
+    let signers = if time_expired {
+        let signers = verify_ed25519_instructions(&ctx.accounts.sysvar_instructions, &signatures, &message)?;
+        require!(signers.contains(&escrow.base.seller), EscrowError::InvalidSigner);
+        signers
+    } else {
+        verify_message_signatures(
+            &escrow.base,
+            &signatures,
+            &message,
+            payouts.iter().map(|payout| &payout.recipient),
+            &ctx.accounts.sysvar_instructions,
+        )?
+    };
+    let seller_approved = signers.contains(&escrow.base.seller);
+
+    let (asset_accounts, recipient_accounts) = ctx.remaining_accounts.split_at(asset_count * 3);
+
+    // 每个资产的支付总额必须等于该资产存入的金额，保证两条腿同时完整结算
+    for asset in &escrow.assets {
+        let paid = payouts
+            .iter()
+            .filter(|payout| payout.mint == asset.mint)
+            .try_fold(0u64, |acc, payout| acc.checked_add(payout.amount))
+            .ok_or(EscrowError::InvalidAmount)?;
+        require!(paid == asset.amount, EscrowError::PayoutMismatch);
+    }
+
+    let base = &escrow.base;
+    let escrow_seed: &[&[u8]] = &[
+        b"multi_escrow",
+        base.buyer.as_ref(),
+        base.seller.as_ref(),
+        &[base.moderator.is_some() as u8],
+        &base.unique_id,
+        &[base.bump],
+    ];
+
+    // 他人转入金库的多余代币退回存入方，使金库能够关闭
+    let mut vault_accounts = Vec::with_capacity(asset_count * 2);
+    for (asset, accounts) in escrow.assets.iter().zip(asset_accounts.chunks(3)) {
+        let balance = vault_balance(&escrow.key(), asset, &accounts[0])?;
+        require!(balance >= asset.amount, EscrowError::InsufficientFunds);
+        refund_depositor(escrow, asset, accounts, balance - asset.amount, &ctx.accounts.token_program, escrow_seed)?;
+        vault_accounts.push(accounts[0].clone());
+        vault_accounts.push(accounts[1].clone());
+    }
+
+    for (payout, recipient_info) in payouts.iter().zip(recipient_accounts.iter()) {
+        let recipient = TokenAccount::try_deserialize(&mut &recipient_info.try_borrow_data()?[..])?;
+        require!(recipient.owner == payout.recipient, EscrowError::InvalidRecipient);
+        require!(recipient.mint == payout.mint, EscrowError::TokenMintMismatch);
+
+        let asset_index = escrow.assets
+            .iter()
+            .position(|asset| asset.mint == payout.mint)
+            .ok_or(EscrowError::InvalidAsset)?;
+
+        msg!("Transfer {} of mint {} to account {}", payout.amount, payout.mint, recipient_info.key());
+
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: vault_accounts[asset_index * 2].clone(),
+                    to: recipient_info.clone(),
+                    authority: escrow.to_account_info(),
+                },
+                &[escrow_seed],
+            ),
+            payout.amount,
+        )?;
+    }
+
+    close_multi_asset_escrow(
+        escrow,
+        &vault_accounts,
+        &ctx.accounts.buyer,
+        &ctx.accounts.token_program,
+        escrow_seed,
+    )?;
+
+    // 各资产的 mint 不同，只记录次数不累计金额
+    let payout_recipients: Vec<Option<Pubkey>> = payouts.iter().map(|payout| Some(payout.recipient)).collect();
+    let outcome = if time_expired {
+        SettlementOutcome::TimedOut
+    } else {
+        SettlementOutcome::for_payout(base, &payout_recipients)
+    };
+    record_settlement(
+        base,
+        &mut ctx.accounts.seller_reputation,
+        ctx.bumps.seller_reputation,
+        &mut ctx.accounts.buyer_reputation,
+        ctx.bumps.buyer_reputation,
+        outcome,
+        0,
+        None,
+    );
+    let record = SettlementRecord::new(
+        escrow.key(),
+        base,
+        None,
+        0,
+        Vec::new(),
+        ReleasePath::MultiAsset,
+        outcome,
+        &Clock::get()?,
+    )
+    .with_seller_approval(seller_approved);
+    write_receipt(
+        base,
+        record,
+        Some(&mut *ctx.accounts.receipt),
+        Some(ctx.bumps.receipt),
+        None,
+    )?;
+
+    msg!(
+        "Multi-asset escrow completed: Buyer={}, Seller={}, ID=0x{}",
+        base.buyer,
+        base.seller,
+        bytes_to_hex_string(&base.unique_id)
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct CancelMultiAsset<'info> {
+    pub initiator: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = escrow_account.base.is_initialized @ EscrowError::ValidationFailed,
+        seeds = [
+            b"multi_escrow",
+            escrow_account.base.buyer.as_ref(),
+            escrow_account.base.seller.as_ref(),
+            &[escrow_account.base.moderator.is_some() as u8],
+            &escrow_account.base.unique_id
+        ],
+        bump = escrow_account.base.bump
+    )]
+    pub escrow_account: Account<'info, MultiAssetEscrow>,
+
+    /// CHECK: 买家账户
+    #[account(mut, address = escrow_account.base.buyer @ EscrowError::ValidationFailed)]
+    pub buyer: AccountInfo<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub clock: Sysvar<'info, Clock>,
+}
+
+// 超时后仍未全部注资时，任一方可取消并将已存入的资产退回各自的存入方
+// remaining_accounts: 按 assets 顺序依次传入 [vault, depositor, depositor_token_account]
+pub fn cancel_handler<'info>(
+    ctx: Context<'_, '_, 'info, 'info, CancelMultiAsset<'info>>,
+) -> Result<()> {
+    let escrow = &ctx.accounts.escrow_account;
+    let base = &escrow.base;
+
+    require!(base.is_party(&ctx.accounts.initiator.key()), EscrowError::Unauthorized);
+    require!(
+        ctx.accounts.clock.unix_timestamp >= base.unlock_time,
+        EscrowError::TimelockNotExpired
+    );
+    require!(!escrow.is_fully_funded(), EscrowError::EscrowFullyFunded);
+    require!(
+        ctx.remaining_accounts.len() == escrow.assets.len() * 3,
+        EscrowError::InvalidRecipientCount
+    );
+
+    let escrow_seed: &[&[u8]] = &[
+        b"multi_escrow",
+        base.buyer.as_ref(),
+        base.seller.as_ref(),
+        &[base.moderator.is_some() as u8],
+        &base.unique_id,
+        &[base.bump],
+    ];
+
+    let mut vault_accounts = Vec::with_capacity(escrow.assets.len() * 2);
+    for (asset, accounts) in escrow.assets.iter().zip(ctx.remaining_accounts.chunks(3)) {
+        let balance = vault_balance(&escrow.key(), asset, &accounts[0])?;
+        refund_depositor(escrow, asset, accounts, balance, &ctx.accounts.token_program, escrow_seed)?;
+        vault_accounts.push(accounts[0].clone());
+        vault_accounts.push(accounts[1].clone());
+    }
+
+    close_multi_asset_escrow(
+        escrow,
+        &vault_accounts,
+        &ctx.accounts.buyer,
+        &ctx.accounts.token_program,
+        escrow_seed,
+    )?;
+
+    msg!(
+        "Multi-asset escrow cancelled: Buyer={}, Seller={}, ID=0x{}",
+        base.buyer,
+        base.seller,
+        bytes_to_hex_string(&base.unique_id)
+    );
+
+    Ok(())
+}
+
+// 未创建的金库（该资产从未存入）视为余额为 0
+fn vault_balance(escrow_key: &Pubkey, asset: &EscrowAsset, vault_info: &AccountInfo) -> Result<u64> {
+    require!(
+        vault_info.key() == get_associated_token_address(escrow_key, &asset.mint),
+        EscrowError::ValidationFailed
+    );
+    if vault_info.data_is_empty() {
+        return Ok(0);
+    }
+    let vault = TokenAccount::try_deserialize(&mut &vault_info.try_borrow_data()?[..])?;
+    Ok(vault.amount)
+}
+
+// accounts: 该资产的 [vault, depositor, depositor_token_account]；金额为 0 时不转账
+fn refund_depositor<'info>(
+    escrow: &Account<'info, MultiAssetEscrow>,
+    asset: &EscrowAsset,
+    accounts: &[AccountInfo<'info>],
+    amount: u64,
+    token_program: &Program<'info, Token>,
+    escrow_seed: &[&[u8]],
+) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+    let refund_account = TokenAccount::try_deserialize(&mut &accounts[2].try_borrow_data()?[..])?;
+    require!(refund_account.owner == asset.depositor, EscrowError::InvalidRecipient);
+
+    token::transfer(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            Transfer {
+                from: accounts[0].clone(),
+                to: accounts[2].clone(),
+                authority: escrow.to_account_info(),
+            },
+            &[escrow_seed],
+        ),
+        amount,
+    )
+}
+
+// vault_accounts: 按 assets 顺序依次为 [vault, depositor]；金库租金退回存入方
+fn close_multi_asset_escrow<'info>(
+    escrow: &Account<'info, MultiAssetEscrow>,
+    vault_accounts: &[AccountInfo<'info>],
+    buyer: &AccountInfo<'info>,
+    token_program: &Program<'info, Token>,
+    escrow_seed: &[&[u8]],
+) -> Result<()> {
+    for (asset, accounts) in escrow.assets.iter().zip(vault_accounts.chunks(2)) {
+        if accounts[0].data_is_empty() {
+            continue;
+        }
+        require!(accounts[1].key() == asset.depositor, EscrowError::InvalidRecipient);
+
+        token::close_account(CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            token::CloseAccount {
+                account: accounts[0].clone(),
+                destination: accounts[1].clone(),
+                authority: escrow.to_account_info(),
+            },
+            &[escrow_seed],
+        ))?;
+    }
+
+    close_escrow_and_return_rent(&escrow.to_account_info(), buyer)
+}
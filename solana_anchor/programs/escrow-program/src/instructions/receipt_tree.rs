@@ -0,0 +1,57 @@
+use anchor_lang::prelude::*;
+use crate::{state::*, error::*};
+
+#[derive(Accounts)]
+pub struct CreateReceiptTree<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    // 由客户端生成的新账户，整棵树只占一个固定大小的账户
+    #[account(
+        init,
+        payer = authority,
+        space = ReceiptTree::LEN,
+    )]
+    pub receipt_tree: AccountLoader<'info, ReceiptTree>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn create_handler(ctx: Context<CreateReceiptTree>) -> Result<()> {
+    let mut tree = ctx.accounts.receipt_tree.load_init()?;
+    tree.initialize(ctx.accounts.authority.key());
+
+    msg!(
+        "Receipt tree created: Tree={}, Authority={}, Depth={}",
+        ctx.accounts.receipt_tree.key(),
+        ctx.accounts.authority.key(),
+        RECEIPT_TREE_DEPTH
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct VerifyReceipt<'info> {
+    pub receipt_tree: AccountLoader<'info, ReceiptTree>,
+}
+
+// 叶子内容由调用方提供，校验通过即说明该记录确实写入过这棵树。
+// 压缩托管关闭后不留账户，同一 unique_id 可以再次创建托管，所以同一托管地址
+// 可能对应多条 Settled 叶子；需要"每个托管只处理一次"的调用方要自己记录已处理的托管
+pub fn verify_handler(
+    ctx: Context<VerifyReceipt>,
+    index: u64,
+    leaf: ReceiptLeaf,
+    proof: Vec<[u8; 32]>,
+) -> Result<()> {
+    require!(proof.len() == RECEIPT_TREE_DEPTH, EscrowError::InvalidReceiptProof);
+
+    let tree = ctx.accounts.receipt_tree.load()?;
+    require!(index < tree.leaf_count, EscrowError::InvalidReceiptProof);
+
+    let root = mobazha_receipt_tree::root_from_proof(leaf.hash()?, index, &proof);
+    require!(tree.has_root(&root), EscrowError::InvalidReceiptProof);
+
+    Ok(())
+}
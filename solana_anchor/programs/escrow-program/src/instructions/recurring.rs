@@ -0,0 +1,421 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Token, TokenAccount, Mint, Transfer};
+use anchor_spl::associated_token::AssociatedToken;
+use crate::{state::*, error::*, utils::bytes_to_hex_string};
+
+#[derive(Accounts)]
+#[instruction(unique_id: [u8; 20])]
+pub struct InitializeRecurring<'info> {
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+    /// CHECK: 卖家账户，由客户端指定
+    pub seller: AccountInfo<'info>,
+
+    #[account(
+        init,
+        payer = buyer,
+        space = RecurringEscrow::LEN,
+        seeds = [
+            b"recurring_escrow",
+            buyer.key().as_ref(),
+            seller.key().as_ref(),
+            &unique_id
+        ],
+        bump
+    )]
+    pub recurring_escrow: Account<'info, RecurringEscrow>,
+
+    pub token_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        token::mint = token_mint,
+        token::authority = buyer,
+    )]
+    pub buyer_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        associated_token::mint = token_mint,
+        associated_token::authority = recurring_escrow,
+    )]
+    pub escrow_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    pub clock: Sysvar<'info, Clock>,
+}
+
+pub fn initialize_handler(
+    ctx: Context<InitializeRecurring>,
+    unique_id: [u8; 20],
+    amount_per_period: u64,
+    period_seconds: i64,
+    deposit: u64,
+) -> Result<()> {
+    require!(amount_per_period > 0 && period_seconds > 0, EscrowError::InvalidPaymentParameters);
+    require!(deposit >= amount_per_period, EscrowError::InvalidAmount);
+
+    let recurring = &mut ctx.accounts.recurring_escrow;
+    recurring.buyer = ctx.accounts.buyer.key();
+    recurring.seller = ctx.accounts.seller.key();
+    recurring.mint = ctx.accounts.token_mint.key();
+    recurring.unique_id = unique_id;
+    recurring.amount_per_period = amount_per_period;
+    recurring.period_seconds = period_seconds;
+    // 第一个周期结束后卖家才能领取
+    recurring.next_claim_time = ctx.accounts.clock.unix_timestamp + period_seconds;
+    recurring.periods_owed = 0;
+    recurring.paused_at = None;
+    recurring.bump = ctx.bumps.recurring_escrow;
+
+    token::transfer(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.buyer_token_account.to_account_info(),
+                to: ctx.accounts.escrow_token_account.to_account_info(),
+                authority: ctx.accounts.buyer.to_account_info(),
+            },
+        ),
+        deposit,
+    )?;
+
+    msg!(
+        "Recurring escrow initialized: Buyer={}, Seller={}, ID=0x{}, Amount per period={} tokens, Period={}s, Deposit={} tokens",
+        recurring.buyer,
+        recurring.seller,
+        bytes_to_hex_string(&unique_id),
+        amount_per_period,
+        period_seconds,
+        deposit
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct FundRecurring<'info> {
+    pub buyer: Signer<'info>,
+
+    #[account(
+        seeds = [
+            b"recurring_escrow",
+            recurring_escrow.buyer.as_ref(),
+            recurring_escrow.seller.as_ref(),
+            &recurring_escrow.unique_id
+        ],
+        bump = recurring_escrow.bump,
+        has_one = buyer @ EscrowError::Unauthorized,
+    )]
+    pub recurring_escrow: Account<'info, RecurringEscrow>,
+
+    #[account(
+        mut,
+        token::mint = recurring_escrow.mint,
+        token::authority = buyer,
+    )]
+    pub buyer_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = recurring_escrow.mint,
+        associated_token::authority = recurring_escrow,
+    )]
+    pub escrow_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+pub fn fund_handler(ctx: Context<FundRecurring>, amount: u64) -> Result<()> {
+    require!(amount > 0, EscrowError::InvalidAmount);
+
+    token::transfer(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.buyer_token_account.to_account_info(),
+                to: ctx.accounts.escrow_token_account.to_account_info(),
+                authority: ctx.accounts.buyer.to_account_info(),
+            },
+        ),
+        amount,
+    )?;
+
+    msg!(
+        "Recurring escrow funded: ID=0x{}, Amount={} tokens, Balance={} tokens",
+        bytes_to_hex_string(&ctx.accounts.recurring_escrow.unique_id),
+        amount,
+        ctx.accounts.escrow_token_account.amount + amount
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ClaimRecurring<'info> {
+    #[account(mut)]
+    pub seller: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"recurring_escrow",
+            recurring_escrow.buyer.as_ref(),
+            recurring_escrow.seller.as_ref(),
+            &recurring_escrow.unique_id
+        ],
+        bump = recurring_escrow.bump,
+        has_one = seller @ EscrowError::Unauthorized,
+    )]
+    pub recurring_escrow: Account<'info, RecurringEscrow>,
+
+    #[account(
+        mut,
+        associated_token::mint = recurring_escrow.mint,
+        associated_token::authority = recurring_escrow,
+    )]
+    pub escrow_token_account: Account<'info, TokenAccount>,
+
+    #[account(address = recurring_escrow.mint @ EscrowError::TokenMintMismatch)]
+    pub token_mint: Account<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = seller,
+        associated_token::mint = token_mint,
+        associated_token::authority = seller,
+    )]
+    pub seller_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    pub clock: Sysvar<'info, Clock>,
+}
+
+pub fn claim_handler(ctx: Context<ClaimRecurring>) -> Result<()> {
+    let amount = settle_due_periods(
+        &mut ctx.accounts.recurring_escrow,
+        &ctx.accounts.escrow_token_account,
+        &ctx.accounts.seller_token_account,
+        &ctx.accounts.token_program,
+        ctx.accounts.clock.unix_timestamp,
+    )?;
+    require!(amount > 0, EscrowError::NothingDue);
+
+    msg!(
+        "Recurring escrow claimed: Seller={}, ID=0x{}, Amount={} tokens, Next claim time={}",
+        ctx.accounts.recurring_escrow.seller,
+        bytes_to_hex_string(&ctx.accounts.recurring_escrow.unique_id),
+        amount,
+        ctx.accounts.recurring_escrow.next_claim_time
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetRecurringPaused<'info> {
+    pub buyer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"recurring_escrow",
+            recurring_escrow.buyer.as_ref(),
+            recurring_escrow.seller.as_ref(),
+            &recurring_escrow.unique_id
+        ],
+        bump = recurring_escrow.bump,
+        has_one = buyer @ EscrowError::Unauthorized,
+    )]
+    pub recurring_escrow: Account<'info, RecurringEscrow>,
+
+    pub clock: Sysvar<'info, Clock>,
+}
+
+pub fn pause_handler(ctx: Context<SetRecurringPaused>) -> Result<()> {
+    let recurring = &mut ctx.accounts.recurring_escrow;
+    require!(recurring.paused_at.is_none(), EscrowError::RecurringPaused);
+
+    recurring.paused_at = Some(ctx.accounts.clock.unix_timestamp);
+
+    msg!("Recurring escrow paused: ID=0x{}", bytes_to_hex_string(&recurring.unique_id));
+
+    Ok(())
+}
+
+pub fn resume_handler(ctx: Context<SetRecurringPaused>) -> Result<()> {
+    let recurring = &mut ctx.accounts.recurring_escrow;
+    require!(recurring.paused_at.is_some(), EscrowError::RecurringNotPaused);
+
+    // 暂停期间不计入周期：进行中的周期顺延暂停的时长，已到期的周期仍可领取
+    recurring.resume(ctx.accounts.clock.unix_timestamp);
+
+    msg!(
+        "Recurring escrow resumed: ID=0x{}, Next claim time={}",
+        bytes_to_hex_string(&recurring.unique_id),
+        recurring.next_claim_time
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct CancelRecurring<'info> {
+    // 买家取消，同时支付卖家 ATA 的租金（如需创建）
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"recurring_escrow",
+            recurring_escrow.buyer.as_ref(),
+            recurring_escrow.seller.as_ref(),
+            &recurring_escrow.unique_id
+        ],
+        bump = recurring_escrow.bump,
+        has_one = buyer @ EscrowError::Unauthorized,
+        close = buyer,
+    )]
+    pub recurring_escrow: Account<'info, RecurringEscrow>,
+
+    #[account(
+        mut,
+        associated_token::mint = recurring_escrow.mint,
+        associated_token::authority = recurring_escrow,
+    )]
+    pub escrow_token_account: Account<'info, TokenAccount>,
+
+    #[account(address = recurring_escrow.mint @ EscrowError::TokenMintMismatch)]
+    pub token_mint: Account<'info, Mint>,
+
+    /// CHECK: 卖家账户
+    #[account(address = recurring_escrow.seller @ EscrowError::ValidationFailed)]
+    pub seller: AccountInfo<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        associated_token::mint = token_mint,
+        associated_token::authority = seller,
+    )]
+    pub seller_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = recurring_escrow.mint,
+        token::authority = buyer,
+    )]
+    pub buyer_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    pub clock: Sysvar<'info, Clock>,
+}
+
+pub fn cancel_handler(ctx: Context<CancelRecurring>) -> Result<()> {
+    // 已到期的周期先结算给卖家，剩余余额退回买家
+    let claimed = settle_due_periods(
+        &mut ctx.accounts.recurring_escrow,
+        &ctx.accounts.escrow_token_account,
+        &ctx.accounts.seller_token_account,
+        &ctx.accounts.token_program,
+        ctx.accounts.clock.unix_timestamp,
+    )?;
+
+    ctx.accounts.escrow_token_account.reload()?;
+    let refund = ctx.accounts.escrow_token_account.amount;
+
+    let recurring = &ctx.accounts.recurring_escrow;
+    let recurring_seed: &[&[u8]] = &[
+        b"recurring_escrow",
+        recurring.buyer.as_ref(),
+        recurring.seller.as_ref(),
+        &recurring.unique_id,
+        &[recurring.bump],
+    ];
+
+    if refund > 0 {
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.escrow_token_account.to_account_info(),
+                    to: ctx.accounts.buyer_token_account.to_account_info(),
+                    authority: recurring.to_account_info(),
+                },
+                &[recurring_seed],
+            ),
+            refund,
+        )?;
+    }
+
+    token::close_account(CpiContext::new_with_signer(
+        ctx.accounts.token_program.to_account_info(),
+        token::CloseAccount {
+            account: ctx.accounts.escrow_token_account.to_account_info(),
+            destination: ctx.accounts.buyer.to_account_info(),
+            authority: recurring.to_account_info(),
+        },
+        &[recurring_seed],
+    ))?;
+
+    msg!(
+        "Recurring escrow cancelled: ID=0x{}, Paid to seller={} tokens, Refunded to buyer={} tokens",
+        bytes_to_hex_string(&recurring.unique_id),
+        claimed,
+        refund
+    );
+
+    Ok(())
+}
+
+// 把所有已到期的周期付给卖家并推进 next_claim_time，返回支付的代币数量
+fn settle_due_periods<'info>(
+    recurring: &mut Account<'info, RecurringEscrow>,
+    escrow_token_account: &Account<'info, TokenAccount>,
+    seller_token_account: &Account<'info, TokenAccount>,
+    token_program: &Program<'info, Token>,
+    current_time: i64,
+) -> Result<u64> {
+    let periods = recurring.periods_due(escrow_token_account.amount, current_time);
+    if periods == 0 {
+        return Ok(0);
+    }
+
+    let amount = periods
+        .checked_mul(recurring.amount_per_period)
+        .ok_or(EscrowError::InvalidAmount)?;
+
+    let recurring_seed: &[&[u8]] = &[
+        b"recurring_escrow",
+        recurring.buyer.as_ref(),
+        recurring.seller.as_ref(),
+        &recurring.unique_id,
+        &[recurring.bump],
+    ];
+
+    token::transfer(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            Transfer {
+                from: escrow_token_account.to_account_info(),
+                to: seller_token_account.to_account_info(),
+                authority: recurring.to_account_info(),
+            },
+            &[recurring_seed],
+        ),
+        amount,
+    )?;
+
+    recurring.consume_periods(periods);
+
+    Ok(amount)
+}
@@ -1,10 +1,11 @@
 use anchor_lang::prelude::*;
-use crate::{state::*, error::*, utils::{close_escrow_and_return_rent, process_release, bytes_to_hex_string}};
+use crate::{state::*, error::*, asset::{release_from_vault, EscrowSeeds, EscrowVault, Payee}, utils::{bytes_to_hex_string, finish_settlement, receipt_payouts}};
 
 #[derive(Accounts)]
 #[instruction(
     payment_amounts: Vec<u64>,
-    signatures: Vec<Vec<u8>>
+    signatures: Vec<Vec<u8>>,
+    valid_until: i64
 )]
 pub struct ReleaseSol<'info> {
     // 验证发起者是否是买家、卖家或moderator（不用这个约束）
@@ -31,6 +32,39 @@ pub struct ReleaseSol<'info> {
         bump = escrow_account.base.bump
     )]
     pub escrow_account: Account<'info, SolEscrow>,
+
+    // 结算结果记入双方的信誉账户
+    #[account(
+        init_if_needed,
+        payer = initiator,
+        space = Reputation::LEN,
+        seeds = [b"seller_reputation", escrow_account.base.seller.as_ref()],
+        bump
+    )]
+    pub seller_reputation: Account<'info, Reputation>,
+
+    #[account(
+        init_if_needed,
+        payer = initiator,
+        space = Reputation::LEN,
+        seeds = [b"buyer_reputation", escrow_account.base.buyer.as_ref()],
+        bump
+    )]
+    pub buyer_reputation: Account<'info, Reputation>,
+
+    // 托管关闭后保留的结算收据，使用收据树的托管不传入
+    #[account(
+        init,
+        payer = initiator,
+        space = SettlementReceipt::LEN,
+        seeds = [b"receipt", escrow_account.key().as_ref()],
+        bump
+    )]
+    pub receipt: Option<Account<'info, SettlementReceipt>>,
+
+    // 使用压缩收据的托管在这棵树上追加结算叶子
+    #[account(mut)]
+    pub receipt_tree: Option<AccountLoader<'info, ReceiptTree>>,
     
     pub clock: Sysvar<'info, Clock>,
     pub system_program: Program<'info, System>,
@@ -63,100 +97,69 @@ pub struct ReleaseSol<'info> {
 pub fn handler(
     ctx: Context<ReleaseSol>,
     payment_amounts: Vec<u64>,
-    signatures: Vec<Vec<u8>>
+    signatures: Vec<Vec<u8>>,
+    valid_until: i64,
 ) -> Result<()> {
-    let recipient_pubkeys = [
-        Some(ctx.accounts.recipient1.key()),
-        ctx.accounts.recipient2.as_ref().map(|acc| acc.key()),
-        ctx.accounts.recipient3.as_ref().map(|acc| acc.key()),
+    let base = &ctx.accounts.escrow_account.base;
+    let payees = [
+        Some(Payee::wallet(&ctx.accounts.recipient1)),
+        ctx.accounts.recipient2.as_ref().map(Payee::wallet),
+        ctx.accounts.recipient3.as_ref().map(Payee::wallet),
     ];
-    
-    process_release(
-        &*ctx.accounts.escrow_account,
-        &signatures,
+
+    // 尚不存在的接收方由发起者补足免租金额
+    let vault = EscrowVault::lamports(
+        ctx.accounts.escrow_account.to_account_info(),
+        EscrowSeeds::new(b"sol_escrow", base),
+    )
+    .fund_new_recipients(
+        ctx.accounts.initiator.to_account_info(),
+        ctx.accounts.system_program.to_account_info(),
+    );
+
+    let ReleaseApproval { outcome, seller_approved } = release_from_vault(
+        vault,
+        base,
+        &payees,
         &payment_amounts,
-        &recipient_pubkeys,
+        &signatures,
+        valid_until,
         ctx.accounts.clock.unix_timestamp,
         &ctx.accounts.sysvar_instructions,
-        || {
-            transfer_sol_to_recipients(&ctx, &payment_amounts, &recipient_pubkeys)?;
-            
-            close_escrow_and_return_rent(
-                &ctx.accounts.escrow_account.to_account_info(),
-                &ctx.accounts.buyer,
-            )?;
-
-            let id_hex = bytes_to_hex_string(&ctx.accounts.escrow_account.base.unique_id);
+        &ctx.accounts.buyer,
+    )?;
 
-            msg!(
-                "SOL escrow completed: Buyer={}, Seller={}, ID=0x{}", 
-                ctx.accounts.escrow_account.base.buyer, 
-                ctx.accounts.escrow_account.base.seller,
-                id_hex
-            );
-            
-            Ok(())
-        },
+    let recipient_pubkeys = payees.map(|payee| payee.map(|payee| payee.wallet.key()));
+    let record = SettlementRecord::new(
+        ctx.accounts.escrow_account.key(),
+        base,
+        None,
+        base.amount,
+        receipt_payouts(&recipient_pubkeys, &payment_amounts),
+        ReleasePath::for_release(outcome),
+        outcome,
+        &ctx.accounts.clock,
     )
-}
-
-pub fn transfer_sol_to_recipients<'info>(
-    ctx: &Context<crate::instructions::release_sol::ReleaseSol<'info>>,
-    amounts: &[u64], 
-    recipients: &[Option<Pubkey>]
-) -> Result<()> {
-    let escrow_info = ctx.accounts.escrow_account.to_account_info();
-    let recipient1_info = ctx.accounts.recipient1.to_account_info();
-    let recipient_accounts = [
-        Some(&recipient1_info),  
-        ctx.accounts.recipient2.as_ref(),
-        ctx.accounts.recipient3.as_ref(),
-    ];
-    
-    for (i, amount) in amounts.iter().enumerate() {
-        if let Some(recipient_key) = recipients[i] {
-            if let Some(recipient) = recipient_accounts[i] {
-                require!(recipient.key() == recipient_key, EscrowError::InvalidRecipient);
-                
-                // 检查账户是否已初始化
-                if recipient.lamports() == 0 {
-                    // 创建账户，只支付必要的租金
-                    anchor_lang::system_program::create_account(
-                        CpiContext::new(
-                            ctx.accounts.system_program.to_account_info(),
-                            anchor_lang::system_program::CreateAccount {
-                                from: ctx.accounts.initiator.to_account_info(),
-                                to: recipient.clone(),
-                            },
-                        ),
-                        ctx.accounts.rent.minimum_balance(0),  // 只支付必要的租金
-                        0,
-                        &ctx.accounts.system_program.key(),
-                    )?;
-                    
-                    msg!(
-                        "Created account {} and transferring {} lamports from escrow", 
-                        recipient.key(),
-                        *amount
-                    );
-                }
+    .with_seller_approval(seller_approved);
+    finish_settlement(
+        base,
+        record,
+        &mut ctx.accounts.seller_reputation,
+        ctx.bumps.seller_reputation,
+        &mut ctx.accounts.buyer_reputation,
+        ctx.bumps.buyer_reputation,
+        ctx.accounts.receipt.as_deref_mut(),
+        ctx.bumps.receipt,
+        ctx.accounts.receipt_tree.as_ref(),
+        ctx.remaining_accounts,
+    )?;
 
-                let mut escrow_lamports = escrow_info.try_borrow_mut_lamports()?;
-                let mut recipient_lamports = recipient.try_borrow_mut_lamports()?;
-                
-                require!(**escrow_lamports >= *amount, EscrowError::InsufficientFunds);
+    msg!(
+        "SOL escrow completed: Buyer={}, Seller={}, ID=0x{}",
+        base.buyer,
+        base.seller,
+        bytes_to_hex_string(&base.unique_id)
+    );
 
-                msg!(
-                    "Transfer {} lamports to account {}", 
-                    amount, 
-                    recipient.key()
-                );
-                
-                **escrow_lamports -= amount;
-                **recipient_lamports += amount;
-            }
-        }
-    }
-    
     Ok(())
 }
@@ -0,0 +1,415 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Token, TokenAccount, Mint};
+use anchor_spl::associated_token::{self, AssociatedToken, get_associated_token_address};
+use crate::{state::*, error::*, asset::{release_from_vault_for, EscrowSeeds, EscrowVault, Payee}, utils::{bytes_to_hex_string, create_pda_account, finish_settlement, receipt_payouts}};
+
+#[derive(Accounts)]
+#[instruction(
+    payment_amounts: Vec<u64>,
+    signatures: Vec<Vec<u8>>,
+    valid_until: i64,
+    recipients: Vec<Pubkey>
+)]
+pub struct ReleaseSolToClaims<'info> {
+    // 支付领取凭证账户的租金
+    #[account(mut)]
+    pub initiator: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = escrow_account.base.is_initialized @ EscrowError::ValidationFailed,
+        seeds = [
+            b"sol_escrow",
+            escrow_account.base.buyer.as_ref(),
+            escrow_account.base.seller.as_ref(),
+            &[escrow_account.base.moderator.is_some() as u8],
+            &escrow_account.base.unique_id
+        ],
+        bump = escrow_account.base.bump
+    )]
+    pub escrow_account: Account<'info, SolEscrow>,
+
+    // 结算结果记入双方的信誉账户
+    #[account(
+        init_if_needed,
+        payer = initiator,
+        space = Reputation::LEN,
+        seeds = [b"seller_reputation", escrow_account.base.seller.as_ref()],
+        bump
+    )]
+    pub seller_reputation: Account<'info, Reputation>,
+
+    #[account(
+        init_if_needed,
+        payer = initiator,
+        space = Reputation::LEN,
+        seeds = [b"buyer_reputation", escrow_account.base.buyer.as_ref()],
+        bump
+    )]
+    pub buyer_reputation: Account<'info, Reputation>,
+
+    // 托管关闭后保留的结算收据，使用收据树的托管不传入
+    #[account(
+        init,
+        payer = initiator,
+        space = SettlementReceipt::LEN,
+        seeds = [b"receipt", escrow_account.key().as_ref()],
+        bump
+    )]
+    pub receipt: Option<Account<'info, SettlementReceipt>>,
+
+    // 使用压缩收据的托管在这棵树上追加结算叶子
+    #[account(mut)]
+    pub receipt_tree: Option<AccountLoader<'info, ReceiptTree>>,
+
+    /// CHECK: 买家账户，必须是托管账户中指定的买家
+    #[account(
+        mut,
+        constraint = buyer.key() == escrow_account.base.buyer @ EscrowError::ValidationFailed
+    )]
+    pub buyer: AccountInfo<'info>,
+
+    pub clock: Sysvar<'info, Clock>,
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: Sysvar Instructions account
+    #[account(address = solana_program::sysvar::instructions::ID)]
+    pub sysvar_instructions: UncheckedAccount<'info>,
+}
+
+// remaining_accounts: 每个接收方一个领取凭证 PDA，之后是 release hook 账户
+pub fn sol_handler<'info>(
+    ctx: Context<'_, '_, 'info, 'info, ReleaseSolToClaims<'info>>,
+    payment_amounts: Vec<u64>,
+    signatures: Vec<Vec<u8>>,
+    valid_until: i64,
+    recipients: Vec<Pubkey>,
+) -> Result<()> {
+    require!(
+        recipients.len() == payment_amounts.len() && recipients.len() <= 3,
+        EscrowError::InvalidRecipientCount
+    );
+    require!(
+        ctx.remaining_accounts.len() >= recipients.len(),
+        EscrowError::InvalidRecipientCount
+    );
+
+    let recipient_pubkeys: Vec<Option<Pubkey>> = recipients.iter().copied().map(Some).collect();
+    let now = ctx.accounts.clock.unix_timestamp;
+    let escrow = &ctx.accounts.escrow_account;
+    let ticket_infos = &ctx.remaining_accounts[..recipients.len()];
+
+    // 先为每个接收方创建领取凭证；签名校验失败时整笔交易回滚
+    for ((owner, amount), ticket_info) in recipients.iter().zip(payment_amounts.iter()).zip(ticket_infos) {
+        create_claim_ticket(
+            ctx.program_id,
+            &ctx.accounts.initiator.to_account_info(),
+            ticket_info,
+            &ctx.accounts.system_program.to_account_info(),
+            &escrow.key(),
+            &escrow.base,
+            *owner,
+            None,
+            *amount,
+            now,
+        )?;
+    }
+
+    // 与 release_sol 共用释放逻辑（包括超时后卖家单签的 timelock 分支），资金付给领取凭证
+    let payees: Vec<Option<Payee>> = ticket_infos.iter().map(|ticket| Some(Payee::wallet(ticket))).collect();
+    let ReleaseApproval { outcome, seller_approved } = release_from_vault_for(
+        EscrowVault::lamports(escrow.to_account_info(), EscrowSeeds::new(b"sol_escrow", &escrow.base)),
+        &escrow.base,
+        &recipient_pubkeys,
+        &payees,
+        &payment_amounts,
+        &signatures,
+        valid_until,
+        now,
+        &ctx.accounts.sysvar_instructions,
+        &ctx.accounts.buyer,
+    )?;
+
+    let record = SettlementRecord::new(
+        escrow.key(),
+        &escrow.base,
+        None,
+        escrow.base.amount,
+        receipt_payouts(&recipient_pubkeys, &payment_amounts),
+        ReleasePath::Claims,
+        outcome,
+        &ctx.accounts.clock,
+    )
+    .with_seller_approval(seller_approved);
+    finish_settlement(
+        &escrow.base,
+        record,
+        &mut ctx.accounts.seller_reputation,
+        ctx.bumps.seller_reputation,
+        &mut ctx.accounts.buyer_reputation,
+        ctx.bumps.buyer_reputation,
+        ctx.accounts.receipt.as_deref_mut(),
+        ctx.bumps.receipt,
+        ctx.accounts.receipt_tree.as_ref(),
+        &ctx.remaining_accounts[recipients.len()..],
+    )?;
+
+    msg!(
+        "SOL escrow released to claim tickets: Buyer={}, Seller={}, ID=0x{}",
+        escrow.base.buyer,
+        escrow.base.seller,
+        bytes_to_hex_string(&escrow.base.unique_id)
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(
+    payment_amounts: Vec<u64>,
+    signatures: Vec<Vec<u8>>,
+    valid_until: i64,
+    recipients: Vec<Pubkey>
+)]
+pub struct ReleaseTokenToClaims<'info> {
+    // 支付领取凭证账户及其代币账户的租金
+    #[account(mut)]
+    pub initiator: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = escrow_account.base.is_initialized @ EscrowError::ValidationFailed,
+        // 账本托管的资金在卖家资金池中，只能通过 release_token 结算
+        constraint = !escrow_account.base.ledger @ EscrowError::UnsupportedByLedgerEscrow,
+        seeds = [
+            b"token_escrow",
+            escrow_account.base.buyer.as_ref(),
+            escrow_account.base.seller.as_ref(),
+            &[escrow_account.base.moderator.is_some() as u8],
+            &escrow_account.base.unique_id
+        ],
+        bump = escrow_account.base.bump
+    )]
+    pub escrow_account: Account<'info, TokenEscrow>,
+
+    // 结算结果记入双方的信誉账户
+    #[account(
+        init_if_needed,
+        payer = initiator,
+        space = Reputation::LEN,
+        seeds = [b"seller_reputation", escrow_account.base.seller.as_ref()],
+        bump
+    )]
+    pub seller_reputation: Account<'info, Reputation>,
+
+    #[account(
+        init_if_needed,
+        payer = initiator,
+        space = Reputation::LEN,
+        seeds = [b"buyer_reputation", escrow_account.base.buyer.as_ref()],
+        bump
+    )]
+    pub buyer_reputation: Account<'info, Reputation>,
+
+    // 托管关闭后保留的结算收据，使用收据树的托管不传入
+    #[account(
+        init,
+        payer = initiator,
+        space = SettlementReceipt::LEN,
+        seeds = [b"receipt", escrow_account.key().as_ref()],
+        bump
+    )]
+    pub receipt: Option<Account<'info, SettlementReceipt>>,
+
+    // 使用压缩收据的托管在这棵树上追加结算叶子
+    #[account(mut)]
+    pub receipt_tree: Option<AccountLoader<'info, ReceiptTree>>,
+
+    #[account(
+        mut,
+        constraint = escrow_token_account.mint == escrow_account.mint @ EscrowError::ValidationFailed,
+        constraint = escrow_token_account.owner == escrow_account.key() @ EscrowError::ValidationFailed,
+    )]
+    pub escrow_token_account: Account<'info, TokenAccount>,
+
+    #[account(address = escrow_account.mint @ EscrowError::TokenMintMismatch)]
+    pub token_mint: Account<'info, Mint>,
+
+    /// CHECK: 买家账户
+    #[account(mut, address = escrow_account.base.buyer @ EscrowError::ValidationFailed)]
+    pub buyer: AccountInfo<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: Sysvar Instructions account
+    #[account(address = solana_program::sysvar::instructions::ID)]
+    pub sysvar_instructions: UncheckedAccount<'info>,
+}
+
+// remaining_accounts: 每个接收方依次传入 [claim_ticket, claim_ticket_token_account]，之后是 release hook 账户
+pub fn token_handler<'info>(
+    ctx: Context<'_, '_, 'info, 'info, ReleaseTokenToClaims<'info>>,
+    payment_amounts: Vec<u64>,
+    signatures: Vec<Vec<u8>>,
+    valid_until: i64,
+    recipients: Vec<Pubkey>,
+) -> Result<()> {
+    require!(
+        recipients.len() == payment_amounts.len() && recipients.len() <= 3,
+        EscrowError::InvalidRecipientCount
+    );
+    require!(
+        ctx.remaining_accounts.len() >= recipients.len() * 2,
+        EscrowError::InvalidRecipientCount
+    );
+
+    let recipient_pubkeys: Vec<Option<Pubkey>> = recipients.iter().copied().map(Some).collect();
+    let clock = Clock::get()?;
+    let escrow = &ctx.accounts.escrow_account;
+    let mint_key = escrow.mint;
+
+    // 先为每个接收方创建领取凭证及其代币账户；签名校验失败时整笔交易回滚
+    let mut tickets = Vec::with_capacity(recipients.len());
+    for ((owner, amount), accounts) in recipients.iter()
+        .zip(payment_amounts.iter())
+        .zip(ctx.remaining_accounts[..recipients.len() * 2].chunks(2))
+    {
+        let ticket_info = &accounts[0];
+        let ticket_token_info = &accounts[1];
+
+        create_claim_ticket(
+            ctx.program_id,
+            &ctx.accounts.initiator.to_account_info(),
+            ticket_info,
+            &ctx.accounts.system_program.to_account_info(),
+            &escrow.key(),
+            &escrow.base,
+            *owner,
+            Some(mint_key),
+            *amount,
+            clock.unix_timestamp,
+        )?;
+
+        require!(
+            ticket_token_info.key() == get_associated_token_address(&ticket_info.key(), &mint_key),
+            EscrowError::InvalidRecipient
+        );
+
+        associated_token::create_idempotent(CpiContext::new(
+            ctx.accounts.associated_token_program.to_account_info(),
+            associated_token::Create {
+                payer: ctx.accounts.initiator.to_account_info(),
+                associated_token: ticket_token_info.clone(),
+                authority: ticket_info.clone(),
+                mint: ctx.accounts.token_mint.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+                token_program: ctx.accounts.token_program.to_account_info(),
+            },
+        ))?;
+
+        tickets.push((ticket_info, Account::<TokenAccount>::try_from(ticket_token_info)?));
+    }
+
+    // 与 release_token 共用释放逻辑（包括超时后卖家单签的 timelock 分支），资金付给领取凭证的代币账户
+    let payees: Vec<Option<Payee>> = tickets
+        .iter()
+        .map(|(ticket, ticket_token_account)| Some(Payee::token(ticket, ticket_token_account)))
+        .collect();
+    let ReleaseApproval { outcome, seller_approved } = release_from_vault_for(
+        EscrowVault::token(
+            escrow.to_account_info(),
+            EscrowSeeds::new(b"token_escrow", &escrow.base),
+            &ctx.accounts.escrow_token_account,
+            &ctx.accounts.token_program,
+        ),
+        &escrow.base,
+        &recipient_pubkeys,
+        &payees,
+        &payment_amounts,
+        &signatures,
+        valid_until,
+        clock.unix_timestamp,
+        &ctx.accounts.sysvar_instructions,
+        &ctx.accounts.buyer,
+    )?;
+
+    let record = SettlementRecord::new(
+        escrow.key(),
+        &escrow.base,
+        Some(mint_key),
+        escrow.base.amount,
+        receipt_payouts(&recipient_pubkeys, &payment_amounts),
+        ReleasePath::Claims,
+        outcome,
+        &clock,
+    )
+    .with_seller_approval(seller_approved);
+    finish_settlement(
+        &escrow.base,
+        record,
+        &mut ctx.accounts.seller_reputation,
+        ctx.bumps.seller_reputation,
+        &mut ctx.accounts.buyer_reputation,
+        ctx.bumps.buyer_reputation,
+        ctx.accounts.receipt.as_deref_mut(),
+        ctx.bumps.receipt,
+        ctx.accounts.receipt_tree.as_ref(),
+        &ctx.remaining_accounts[recipients.len() * 2..],
+    )?;
+
+    msg!(
+        "Token escrow released to claim tickets: Buyer={}, Seller={}, ID=0x{}",
+        escrow.base.buyer,
+        escrow.base.seller,
+        bytes_to_hex_string(&escrow.base.unique_id)
+    );
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn create_claim_ticket<'info>(
+    program_id: &Pubkey,
+    rent_payer: &AccountInfo<'info>,
+    ticket_info: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    escrow_key: &Pubkey,
+    base: &EscrowAccount,
+    owner: Pubkey,
+    mint: Option<Pubkey>,
+    amount: u64,
+    now: i64,
+) -> Result<()> {
+    let (ticket_key, bump) = Pubkey::find_program_address(
+        &[b"claim_ticket", escrow_key.as_ref(), owner.as_ref()],
+        program_id,
+    );
+    require!(ticket_info.key() == ticket_key, EscrowError::InvalidRecipient);
+
+    create_pda_account(
+        rent_payer,
+        ticket_info,
+        system_program,
+        ClaimTicket::LEN,
+        program_id,
+        &[b"claim_ticket", escrow_key.as_ref(), owner.as_ref(), &[bump]],
+    )?;
+
+    let ticket = ClaimTicket {
+        escrow: *escrow_key,
+        owner,
+        mint,
+        amount,
+        payer_address: base.payer_address,
+        rent_payer: rent_payer.key(),
+        expires_at: now + CLAIM_EXPIRY_SECONDS,
+        bump,
+    };
+    ticket.try_serialize(&mut &mut ticket_info.try_borrow_mut_data()?[..])?;
+
+    msg!("Claim ticket created: Owner={}, Amount={}, Ticket={}", owner, amount, ticket_key);
+
+    Ok(())
+}
@@ -1,12 +1,13 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Token, TokenAccount, Transfer, Mint};
+use anchor_spl::token::{Token, TokenAccount, Mint};
 use anchor_spl::associated_token::AssociatedToken;
-use crate::{state::*, error::*, utils::{close_escrow_and_return_rent, bytes_to_hex_string, construct_message, verify_ed25519_instructions, verify_payment_amounts, verify_signatures_without_timelock}};
+use crate::{state::*, error::*, asset::{release_from_vault, EscrowSeeds, EscrowVault, Payee}, utils::{bytes_to_hex_string, finish_settlement, receipt_payouts}};
 
 #[derive(Accounts)]
 #[instruction(
     payment_amounts: Vec<u64>,
-    signatures: Vec<Vec<u8>>
+    signatures: Vec<Vec<u8>>,
+    valid_until: i64
 )]
 pub struct ReleaseToken<'info> {
     // 验证发起者是否是买家、卖家或moderator（不用这个约束）
@@ -33,13 +34,58 @@ pub struct ReleaseToken<'info> {
         bump = escrow_account.base.bump
     )]
     pub escrow_account: Account<'info, TokenEscrow>,
+
+    // 结算结果记入双方的信誉账户
+    #[account(
+        init_if_needed,
+        payer = initiator,
+        space = Reputation::LEN,
+        seeds = [b"seller_reputation", escrow_account.base.seller.as_ref()],
+        bump
+    )]
+    pub seller_reputation: Account<'info, Reputation>,
+
+    #[account(
+        init_if_needed,
+        payer = initiator,
+        space = Reputation::LEN,
+        seeds = [b"buyer_reputation", escrow_account.base.buyer.as_ref()],
+        bump
+    )]
+    pub buyer_reputation: Account<'info, Reputation>,
+
+    // 托管关闭后保留的结算收据，使用收据树的托管不传入
+    #[account(
+        init,
+        payer = initiator,
+        space = SettlementReceipt::LEN,
+        seeds = [b"receipt", escrow_account.key().as_ref()],
+        bump
+    )]
+    pub receipt: Option<Account<'info, SettlementReceipt>>,
+
+    // 使用压缩收据的托管在这棵树上追加结算叶子
+    #[account(mut)]
+    pub receipt_tree: Option<AccountLoader<'info, ReceiptTree>>,
     
+    // 托管自己的代币账户，账本托管不传
     #[account(
         mut,
         constraint = escrow_token_account.mint == escrow_account.mint @ EscrowError::ValidationFailed,
         constraint = escrow_token_account.owner == escrow_account.key() @ EscrowError::ValidationFailed,
     )]
-    pub escrow_token_account: Account<'info, TokenAccount>,
+    pub escrow_token_account: Option<Account<'info, TokenAccount>>,
+    
+    // 账本托管的卖家账本及其资金池；卖家份额直接记账，不再转账
+    #[account(
+        mut,
+        seeds = [b"seller_balance", escrow_account.base.seller.as_ref(), escrow_account.mint.as_ref()],
+        bump = seller_balance.bump
+    )]
+    pub seller_balance: Option<Account<'info, SellerBalance>>,
+    
+    #[account(mut)]
+    pub balance_token_account: Option<Account<'info, TokenAccount>>,
     
     pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
@@ -57,18 +103,20 @@ pub struct ReleaseToken<'info> {
     #[account(mut, address = escrow_account.base.buyer @ EscrowError::ValidationFailed)]
     pub buyer: AccountInfo<'info>,
     
-    /// CHECK: 第一个接收方owner
+    /// CHECK: 第一个接收方owner；原生 SOL 托管直接向其支付 lamports
+    #[account(mut)]
     pub recipient1: AccountInfo<'info>,
-    /// CHECK: 第一个接收方ATA
+    /// CHECK: 第一个接收方ATA，原生 SOL 托管不传
     #[account(
         init_if_needed,
         payer = initiator,
         associated_token::mint = token_mint,
         associated_token::authority = recipient1,
     )]
-    pub recipient1_ata: Account<'info, TokenAccount>,
+    pub recipient1_ata: Option<Account<'info, TokenAccount>>,
     
     /// CHECK: 第二个接收方owner
+    #[account(mut)]
     pub recipient2: Option<AccountInfo<'info>>,
     /// CHECK: 第二个接收方ATA
     #[account(
@@ -80,6 +128,7 @@ pub struct ReleaseToken<'info> {
     pub recipient2_ata: Option<Account<'info, TokenAccount>>,
     
     /// CHECK: 第三个接收方owner
+    #[account(mut)]
     pub recipient3: Option<AccountInfo<'info>>,
     /// CHECK: 第三个接收方ATA
     #[account(
@@ -94,125 +143,77 @@ pub struct ReleaseToken<'info> {
 pub fn handler(
     ctx: Context<ReleaseToken>,
     payment_amounts: Vec<u64>,
-    signatures: Vec<Vec<u8>>
+    signatures: Vec<Vec<u8>>,
+    valid_until: i64,
 ) -> Result<()> {
-    // 验证接收方数量
-    require!(
-        payment_amounts.len() >= 1 && payment_amounts.len() <= 3,
-        EscrowError::InvalidRecipientCount
-    );
-    
-    let recipient_accounts = [
-        Some(&ctx.accounts.recipient1_ata),
-        ctx.accounts.recipient2_ata.as_ref(),
-        ctx.accounts.recipient3_ata.as_ref(),
-    ];
-    
-    let recipient_pubkeys = [
-        Some(ctx.accounts.recipient1.key()),
-        ctx.accounts.recipient2.as_ref().map(|acc| acc.key()),
-        ctx.accounts.recipient3.as_ref().map(|acc| acc.key()),
+    let clock = Clock::get()?;
+    let escrow = &ctx.accounts.escrow_account;
+    let payees = [
+        Some(Payee { wallet: &ctx.accounts.recipient1, token_account: ctx.accounts.recipient1_ata.as_ref() }),
+        ctx.accounts.recipient2.as_ref().map(|wallet| Payee { wallet, token_account: ctx.accounts.recipient2_ata.as_ref() }),
+        ctx.accounts.recipient3.as_ref().map(|wallet| Payee { wallet, token_account: ctx.accounts.recipient3_ata.as_ref() }),
     ];
-    
-    let escrow_seed = &[
-        b"token_escrow",
-        ctx.accounts.escrow_account.base.buyer.as_ref(),
-        ctx.accounts.escrow_account.base.seller.as_ref(),
-        &[ctx.accounts.escrow_account.base.moderator.is_some() as u8],
-        &ctx.accounts.escrow_account.base.unique_id,
-        &[ctx.accounts.escrow_account.base.bump],
-    ];
-    
-    // 验证支付金额
-    verify_payment_amounts(&payment_amounts, &ctx.accounts.escrow_account.base)?;
-    
-    // 验证签名
-    verify_signatures_without_timelock(
-        &ctx.accounts.escrow_account.base,
-        &signatures,
+
+    // 与 SOL 托管共用释放逻辑，超时后卖家单签同样走 timelock 分支
+    let ReleaseApproval { outcome, seller_approved } = release_from_vault(
+        token_vault(
+            escrow,
+            ctx.accounts.escrow_token_account.as_ref(),
+            ctx.accounts.seller_balance.as_mut().zip(ctx.accounts.balance_token_account.as_ref()),
+            &ctx.accounts.token_program,
+            &ctx.accounts.initiator,
+            &ctx.accounts.system_program,
+        )?,
+        &escrow.base,
+        &payees,
         &payment_amounts,
-        &recipient_pubkeys,
+        &signatures,
+        valid_until,
+        clock.unix_timestamp,
         &ctx.accounts.sysvar_instructions,
-    )?;
-    
-    // 转账代币
-    transfer_tokens_to_recipients(
-        &ctx,
-        &payment_amounts,
-        &recipient_accounts,
-        escrow_seed
-    )?;
-
-    // 关闭代币账户
-    token::close_account(
-        CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            token::CloseAccount {
-                account: ctx.accounts.escrow_token_account.to_account_info(),
-                destination: ctx.accounts.buyer.to_account_info(),
-                authority: ctx.accounts.escrow_account.to_account_info(),
-            },
-            &[escrow_seed],
-        )
-    )?;
-    
-    // 关闭托管账户并返回租金
-    close_escrow_and_return_rent(
-        &ctx.accounts.escrow_account.to_account_info(),
         &ctx.accounts.buyer,
     )?;
 
-    let id_hex = bytes_to_hex_string(&ctx.accounts.escrow_account.base.unique_id);
+    let recipient_pubkeys = payees.map(|payee| payee.map(|payee| payee.wallet.key()));
+    let record = SettlementRecord::new(
+        escrow.key(),
+        &escrow.base,
+        Some(escrow.mint),
+        escrow.base.amount,
+        receipt_payouts(&recipient_pubkeys, &payment_amounts),
+        ReleasePath::for_release(outcome),
+        outcome,
+        &clock,
+    )
+    .with_seller_approval(seller_approved);
+    finish_settlement(
+        &escrow.base,
+        record,
+        &mut ctx.accounts.seller_reputation,
+        ctx.bumps.seller_reputation,
+        &mut ctx.accounts.buyer_reputation,
+        ctx.bumps.buyer_reputation,
+        ctx.accounts.receipt.as_deref_mut(),
+        ctx.bumps.receipt,
+        ctx.accounts.receipt_tree.as_ref(),
+        ctx.remaining_accounts,
+    )?;
 
     msg!(
-        "Token escrow completed: Buyer={}, Seller={}, ID=0x{}", 
-        ctx.accounts.escrow_account.base.buyer, 
-        ctx.accounts.escrow_account.base.seller,
-        id_hex
+        "Token escrow completed: Buyer={}, Seller={}, ID=0x{}",
+        escrow.base.buyer,
+        escrow.base.seller,
+        bytes_to_hex_string(&escrow.base.unique_id)
     );
 
     Ok(())
 }
 
-fn transfer_tokens_to_recipients<'info>(
-    ctx: &Context<ReleaseToken<'info>>,
-    amounts: &[u64],
-    recipients: &[Option<&Account<'info, TokenAccount>>],
-    escrow_seed: &[&[u8]],
-) -> Result<()> {
-    for recipient in recipients.iter().flatten() {
-        require!(
-            recipient.mint == ctx.accounts.escrow_account.mint,
-            EscrowError::TokenMintMismatch
-        );
-    }
-    
-    for (i, amount) in amounts.iter().enumerate() {
-        if let Some(recipient) = recipients[i] {
-            msg!("Transfer {} tokens to account {}",  amount, recipient.key());
-            
-            token::transfer(
-                CpiContext::new_with_signer(
-                    ctx.accounts.token_program.to_account_info(),
-                    Transfer {
-                        from: ctx.accounts.escrow_token_account.to_account_info(),
-                        to: recipient.to_account_info(),
-                        authority: ctx.accounts.escrow_account.to_account_info(),
-                    },
-                    &[escrow_seed],
-                ),
-                *amount,
-            )?;
-        }
-    }
-    
-    Ok(())
-}
-
 #[derive(Accounts)]
 #[instruction(
     payment_amounts: Vec<u64>,
-    signatures: Vec<Vec<u8>>
+    signatures: Vec<Vec<u8>>,
+    valid_until: i64
 )]
 pub struct ReleaseTokenAfterTimeout<'info> {
     #[account(mut)]
@@ -231,13 +232,59 @@ pub struct ReleaseTokenAfterTimeout<'info> {
         bump = escrow_account.base.bump
     )]
     pub escrow_account: Account<'info, TokenEscrow>,
+
+    // 结算结果记入双方的信誉账户
+    #[account(
+        init_if_needed,
+        payer = initiator,
+        space = Reputation::LEN,
+        seeds = [b"seller_reputation", escrow_account.base.seller.as_ref()],
+        bump
+    )]
+    pub seller_reputation: Account<'info, Reputation>,
+
+    #[account(
+        init_if_needed,
+        payer = initiator,
+        space = Reputation::LEN,
+        seeds = [b"buyer_reputation", escrow_account.base.buyer.as_ref()],
+        bump
+    )]
+    pub buyer_reputation: Account<'info, Reputation>,
+
+    // 托管关闭后保留的结算收据，使用收据树的托管不传入
+    #[account(
+        init,
+        payer = initiator,
+        space = SettlementReceipt::LEN,
+        seeds = [b"receipt", escrow_account.key().as_ref()],
+        bump
+    )]
+    pub receipt: Option<Account<'info, SettlementReceipt>>,
+
+    // 使用压缩收据的托管在这棵树上追加结算叶子
+    #[account(mut)]
+    pub receipt_tree: Option<AccountLoader<'info, ReceiptTree>>,
     
+    // 托管自己的代币账户，账本托管不传
     #[account(
         mut,
         constraint = escrow_token_account.mint == escrow_account.mint @ EscrowError::ValidationFailed,
         constraint = escrow_token_account.owner == escrow_account.key() @ EscrowError::ValidationFailed,
     )]
-    pub escrow_token_account: Account<'info, TokenAccount>,
+    pub escrow_token_account: Option<Account<'info, TokenAccount>>,
+    
+    // 账本托管的卖家账本及其资金池；卖家份额直接记账，不再转账
+    #[account(
+        mut,
+        seeds = [b"seller_balance", escrow_account.base.seller.as_ref(), escrow_account.mint.as_ref()],
+        bump = seller_balance.bump
+    )]
+    pub seller_balance: Option<Account<'info, SellerBalance>>,
+    
+    #[account(mut)]
+    pub balance_token_account: Option<Account<'info, TokenAccount>>,
+
     pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
 
@@ -255,18 +302,20 @@ pub struct ReleaseTokenAfterTimeout<'info> {
 
     pub clock: Sysvar<'info, Clock>,
     
-    /// CHECK: 第一个接收方owner
+    /// CHECK: 第一个接收方owner；原生 SOL 托管直接向其支付 lamports
+    #[account(mut)]
     pub recipient1: AccountInfo<'info>,
-    /// CHECK: 第一个接收方ATA
+    /// CHECK: 第一个接收方ATA，原生 SOL 托管不传
     #[account(
         init_if_needed,
         payer = initiator,
         associated_token::mint = token_mint,
         associated_token::authority = recipient1,
     )]
-    pub recipient1_ata: Account<'info, TokenAccount>,
+    pub recipient1_ata: Option<Account<'info, TokenAccount>>,
     
     /// CHECK: 第二个接收方owner
+    #[account(mut)]
     pub recipient2: Option<AccountInfo<'info>>,
     /// CHECK: 第二个接收方ATA
     #[account(
@@ -278,133 +327,107 @@ pub struct ReleaseTokenAfterTimeout<'info> {
     pub recipient2_ata: Option<Account<'info, TokenAccount>>,
 }
 
-fn transfer_tokens_to_recipients_after_timeout<'info>(
-    ctx: &Context<ReleaseTokenAfterTimeout<'info>>,
-    amounts: &[u64],
-    recipients: &[Option<&Account<'info, TokenAccount>>],
-    escrow_seed: &[&[u8]],
-) -> Result<()> {
-    for recipient in recipients.iter().flatten() {
-        require!(
-            recipient.mint == ctx.accounts.escrow_account.mint,
-            EscrowError::TokenMintMismatch
-        );
-    }
-    
-    for (i, amount) in amounts.iter().enumerate() {
-        if let Some(recipient) = recipients[i] {
-            msg!("Transfer {} tokens to account {}", amount, recipient.key());
-            
-            token::transfer(
-                CpiContext::new_with_signer(
-                    ctx.accounts.token_program.to_account_info(),
-                    Transfer {
-                        from: ctx.accounts.escrow_token_account.to_account_info(),
-                        to: recipient.to_account_info(),
-                        authority: ctx.accounts.escrow_account.to_account_info(),
-                    },
-                    &[escrow_seed],
-                ),
-                *amount,
-            )?;
-        }
-    }
-    
-    Ok(())
-}
-
 pub fn handler_after_timeout(
     ctx: Context<ReleaseTokenAfterTimeout>,
     payment_amounts: Vec<u64>,
-    signatures: Vec<Vec<u8>>
+    signatures: Vec<Vec<u8>>,
+    valid_until: i64,
 ) -> Result<()> {
-    // 验证接收方数量
-    require!(
-        payment_amounts.len() >= 1 && payment_amounts.len() <= 2,
-        EscrowError::InvalidRecipientCount
-    );
-    
-    let recipient_accounts = [
-        Some(&ctx.accounts.recipient1_ata),
-        ctx.accounts.recipient2_ata.as_ref(),
-    ];
-    
-    let recipient_pubkeys = [
-        Some(ctx.accounts.recipient1.key()),
-        ctx.accounts.recipient2.as_ref().map(|acc| acc.key()),
-    ];
-    
-    let escrow_seed = &[
-        b"token_escrow",
-        ctx.accounts.escrow_account.base.buyer.as_ref(),
-        ctx.accounts.escrow_account.base.seller.as_ref(),
-        &[ctx.accounts.escrow_account.base.moderator.is_some() as u8],
-        &ctx.accounts.escrow_account.base.unique_id,
-        &[ctx.accounts.escrow_account.base.bump],
-    ];
-    
-    // 验证是否超时
+    let escrow = &ctx.accounts.escrow_account;
+
+    // 仅在超时后可用；之后的校验与 release_token 的 timelock 分支完全相同
     require!(
-        ctx.accounts.clock.unix_timestamp >= ctx.accounts.escrow_account.base.unlock_time,
+        ctx.accounts.clock.unix_timestamp >= escrow.base.unlock_time,
         EscrowError::TimelockNotExpired
     );
-    
-    // 验证支付金额
-    verify_payment_amounts(&payment_amounts, &ctx.accounts.escrow_account.base)?;
-    
-    // 验证签名
-    let message = construct_message(
-        &ctx.accounts.escrow_account.base.unique_id,
-        &recipient_pubkeys,
-        &payment_amounts
-    );
-    
-    let all_signers = verify_ed25519_instructions(
-        &ctx.accounts.sysvar_instructions,
-        &signatures,
-        &message,
-    )?;
-    
-    require!(
-        all_signers.contains(&ctx.accounts.escrow_account.base.seller),
-        EscrowError::InvalidSigner
-    );
-    
-    // 转账代币
-    transfer_tokens_to_recipients_after_timeout(
-        &ctx,
+
+    let payees = [
+        Some(Payee { wallet: &ctx.accounts.recipient1, token_account: ctx.accounts.recipient1_ata.as_ref() }),
+        ctx.accounts.recipient2.as_ref().map(|wallet| Payee { wallet, token_account: ctx.accounts.recipient2_ata.as_ref() }),
+    ];
+
+    let ReleaseApproval { outcome, seller_approved } = release_from_vault(
+        token_vault(
+            escrow,
+            ctx.accounts.escrow_token_account.as_ref(),
+            ctx.accounts.seller_balance.as_mut().zip(ctx.accounts.balance_token_account.as_ref()),
+            &ctx.accounts.token_program,
+            &ctx.accounts.initiator,
+            &ctx.accounts.system_program,
+        )?,
+        &escrow.base,
+        &payees,
         &payment_amounts,
-        &recipient_accounts,
-        escrow_seed
-    )?;
-    
-    // 关闭代币账户
-    token::close_account(
-        CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            token::CloseAccount {
-                account: ctx.accounts.escrow_token_account.to_account_info(),
-                destination: ctx.accounts.buyer.to_account_info(),
-                authority: ctx.accounts.escrow_account.to_account_info(),
-            },
-            &[escrow_seed],
-        )
-    )?;
-    
-    // 关闭托管账户并返回租金
-    close_escrow_and_return_rent(
-        &ctx.accounts.escrow_account.to_account_info(),
+        &signatures,
+        valid_until,
+        ctx.accounts.clock.unix_timestamp,
+        &ctx.accounts.sysvar_instructions,
         &ctx.accounts.buyer,
     )?;
 
-    let id_hex = bytes_to_hex_string(&ctx.accounts.escrow_account.base.unique_id);
+    let recipient_pubkeys = payees.map(|payee| payee.map(|payee| payee.wallet.key()));
+    let record = SettlementRecord::new(
+        escrow.key(),
+        &escrow.base,
+        Some(escrow.mint),
+        escrow.base.amount,
+        receipt_payouts(&recipient_pubkeys, &payment_amounts),
+        ReleasePath::Timeout,
+        outcome,
+        &ctx.accounts.clock,
+    )
+    .with_seller_approval(seller_approved);
+    finish_settlement(
+        &escrow.base,
+        record,
+        &mut ctx.accounts.seller_reputation,
+        ctx.bumps.seller_reputation,
+        &mut ctx.accounts.buyer_reputation,
+        ctx.bumps.buyer_reputation,
+        ctx.accounts.receipt.as_deref_mut(),
+        ctx.bumps.receipt,
+        ctx.accounts.receipt_tree.as_ref(),
+        ctx.remaining_accounts,
+    )?;
 
     msg!(
-        "Token escrow completed after timeout: Buyer={}, Seller={}, ID=0x{}", 
-        ctx.accounts.escrow_account.base.buyer, 
-        ctx.accounts.escrow_account.base.seller,
-        id_hex
+        "Token escrow completed after timeout: Buyer={}, Seller={}, ID=0x{}",
+        escrow.base.buyer,
+        escrow.base.seller,
+        bytes_to_hex_string(&escrow.base.unique_id)
     );
 
     Ok(())
-} 
\ No newline at end of file
+}
+
+// 代币托管的金库：托管自己的代币账户，或账本托管所在的卖家资金池；
+// 原生 SOL 铸币解包后向尚不存在的接收方支付时由发起者补足租金
+fn token_vault<'a, 'info>(
+    escrow: &Account<'info, TokenEscrow>,
+    escrow_token_account: Option<&'a Account<'info, TokenAccount>>,
+    ledger: Option<(&'a mut Account<'info, SellerBalance>, &'a Account<'info, TokenAccount>)>,
+    token_program: &'a Program<'info, Token>,
+    initiator: &Signer<'info>,
+    system_program: &Program<'info, System>,
+) -> Result<EscrowVault<'a, 'info>> {
+    let seeds = EscrowSeeds::new(b"token_escrow", &escrow.base);
+    let vault = if escrow.base.ledger {
+        let (seller_balance, balance_token_account) = ledger.ok_or(EscrowError::InvalidSellerBalance)?;
+        EscrowVault::ledger(
+            escrow.to_account_info(),
+            seeds,
+            &escrow.base,
+            balance_token_account,
+            seller_balance,
+            token_program,
+        )?
+    } else {
+        EscrowVault::token(
+            escrow.to_account_info(),
+            seeds,
+            escrow_token_account.ok_or(EscrowError::ValidationFailed)?,
+            token_program,
+        )
+    };
+    Ok(vault.fund_new_recipients(initiator.to_account_info(), system_program.to_account_info()))
+}
@@ -0,0 +1,116 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Token, TokenAccount, Mint, Transfer};
+use anchor_spl::associated_token::AssociatedToken;
+use crate::{state::*, error::*};
+
+#[derive(Accounts)]
+pub struct OpenSellerBalance<'info> {
+    #[account(mut)]
+    pub seller: Signer<'info>,
+    
+    #[account(
+        init,
+        payer = seller,
+        space = SellerBalance::LEN,
+        seeds = [b"seller_balance", seller.key().as_ref(), token_mint.key().as_ref()],
+        bump
+    )]
+    pub seller_balance: Account<'info, SellerBalance>,
+    
+    // 账本资金存放在 seller_balance 的关联代币账户中
+    #[account(
+        init_if_needed,
+        payer = seller,
+        associated_token::mint = token_mint,
+        associated_token::authority = seller_balance,
+    )]
+    pub balance_token_account: Account<'info, TokenAccount>,
+    
+    pub token_mint: Account<'info, Mint>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn open_handler(ctx: Context<OpenSellerBalance>) -> Result<()> {
+    let seller_balance = &mut ctx.accounts.seller_balance;
+    seller_balance.seller = ctx.accounts.seller.key();
+    seller_balance.mint = ctx.accounts.token_mint.key();
+    seller_balance.bump = ctx.bumps.seller_balance;
+    
+    msg!(
+        "Seller balance opened: Seller={}, Mint={}, Address={}",
+        seller_balance.seller,
+        seller_balance.mint,
+        seller_balance.key()
+    );
+    
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct WithdrawBalance<'info> {
+    pub seller: Signer<'info>,
+    
+    #[account(
+        seeds = [b"seller_balance", seller.key().as_ref(), seller_balance.mint.as_ref()],
+        bump = seller_balance.bump,
+        has_one = seller @ EscrowError::Unauthorized,
+    )]
+    pub seller_balance: Account<'info, SellerBalance>,
+    
+    #[account(
+        mut,
+        associated_token::mint = seller_balance.mint,
+        associated_token::authority = seller_balance,
+    )]
+    pub balance_token_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        constraint = destination.mint == seller_balance.mint @ EscrowError::TokenMintMismatch,
+    )]
+    pub destination: Account<'info, TokenAccount>,
+    
+    pub token_program: Program<'info, Token>,
+}
+
+pub fn withdraw_handler(ctx: Context<WithdrawBalance>, amount: u64) -> Result<()> {
+    require!(amount > 0, EscrowError::ZeroPaymentAmount);
+    
+    // 仍在为未结算账本托管担保的资金不可提取
+    let seller_balance = &ctx.accounts.seller_balance;
+    let available = seller_balance.available(ctx.accounts.balance_token_account.amount);
+    require!(available >= amount, EscrowError::InsufficientFunds);
+    
+    let balance_seed: &[&[u8]] = &[
+        b"seller_balance",
+        seller_balance.seller.as_ref(),
+        seller_balance.mint.as_ref(),
+        &[seller_balance.bump],
+    ];
+    
+    token::transfer(
+        CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.balance_token_account.to_account_info(),
+                to: ctx.accounts.destination.to_account_info(),
+                authority: seller_balance.to_account_info(),
+            },
+            &[balance_seed],
+        ),
+        amount,
+    )?;
+    
+    msg!(
+        "Seller balance withdrawn: Seller={}, Mint={}, Amount={} tokens, Available={} tokens, Escrowed={} tokens",
+        seller_balance.seller,
+        seller_balance.mint,
+        amount,
+        available - amount,
+        seller_balance.escrowed
+    );
+    
+    Ok(())
+}
@@ -0,0 +1,595 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Token, TokenAccount, Mint};
+use anchor_spl::associated_token::AssociatedToken;
+use crate::{state::*, error::*, asset::{EscrowSeeds, EscrowVault, Payee}, utils::{bytes_to_hex_string, finish_settlement}};
+
+#[derive(Accounts)]
+pub struct WithdrawVestedSol<'info> {
+    #[account(mut, address = escrow_account.base.seller @ EscrowError::Unauthorized)]
+    pub seller: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = escrow_account.base.is_initialized @ EscrowError::ValidationFailed,
+        seeds = [
+            b"sol_escrow",
+            escrow_account.base.buyer.as_ref(),
+            escrow_account.base.seller.as_ref(),
+            &[escrow_account.base.moderator.is_some() as u8],
+            &escrow_account.base.unique_id
+        ],
+        bump = escrow_account.base.bump
+    )]
+    pub escrow_account: Account<'info, SolEscrow>,
+
+    // 结算结果记入双方的信誉账户
+    #[account(
+        init_if_needed,
+        payer = seller,
+        space = Reputation::LEN,
+        seeds = [b"seller_reputation", escrow_account.base.seller.as_ref()],
+        bump
+    )]
+    pub seller_reputation: Account<'info, Reputation>,
+
+    #[account(
+        init_if_needed,
+        payer = seller,
+        space = Reputation::LEN,
+        seeds = [b"buyer_reputation", escrow_account.base.buyer.as_ref()],
+        bump
+    )]
+    pub buyer_reputation: Account<'info, Reputation>,
+
+    // 托管关闭后保留的结算收据，仅在最后一次提取且未使用收据树时传入
+    #[account(
+        init,
+        payer = seller,
+        space = SettlementReceipt::LEN,
+        seeds = [b"receipt", escrow_account.key().as_ref()],
+        bump
+    )]
+    pub receipt: Option<Account<'info, SettlementReceipt>>,
+
+    // 使用压缩收据的托管在这棵树上追加结算叶子
+    #[account(mut)]
+    pub receipt_tree: Option<AccountLoader<'info, ReceiptTree>>,
+
+    /// CHECK: 买家账户，全部领取后接收托管账户租金
+    #[account(mut, address = escrow_account.base.buyer @ EscrowError::ValidationFailed)]
+    pub buyer: AccountInfo<'info>,
+
+    pub clock: Sysvar<'info, Clock>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn withdraw_sol_handler(ctx: Context<WithdrawVestedSol>) -> Result<()> {
+    let available = take_vested(&mut ctx.accounts.escrow_account.base, ctx.accounts.clock.unix_timestamp)?;
+    require!(available > 0, EscrowError::NothingVested);
+
+    let base = &ctx.accounts.escrow_account.base;
+    let mut vault = EscrowVault::lamports(
+        ctx.accounts.escrow_account.to_account_info(),
+        EscrowSeeds::new(b"sol_escrow", base),
+    );
+    vault.pay(&Payee::wallet(&ctx.accounts.seller.to_account_info()), available)?;
+
+    msg!(
+        "SOL stream withdrawn: Seller={}, ID=0x{}, Amount={} lamports, Remaining={} lamports",
+        base.seller,
+        bytes_to_hex_string(&base.unique_id),
+        available,
+        base.amount
+    );
+
+    // 只有领完最后一笔时才关闭托管并写入收据，收据账户不能提前传入
+    require!(
+        ctx.accounts.receipt.is_none() || base.amount == 0,
+        EscrowError::InvalidReceiptAccount
+    );
+
+    if base.amount == 0 {
+        let escrow_key = ctx.accounts.escrow_account.key();
+        vault.close(&ctx.accounts.buyer)?;
+        let total = stream_total(base);
+        // 提取由卖家本人签名提交，视为卖家批准
+        let record = SettlementRecord::new(
+            escrow_key,
+            base,
+            None,
+            total,
+            vec![ReceiptPayout { recipient: base.seller, amount: total }],
+            ReleasePath::StreamWithdraw,
+            SettlementOutcome::Released,
+            &ctx.accounts.clock,
+        )
+        .with_seller_approval(true);
+        finish_settlement(
+            base,
+            record,
+            &mut ctx.accounts.seller_reputation,
+            ctx.bumps.seller_reputation,
+            &mut ctx.accounts.buyer_reputation,
+            ctx.bumps.buyer_reputation,
+            ctx.accounts.receipt.as_deref_mut(),
+            ctx.bumps.receipt,
+            ctx.accounts.receipt_tree.as_ref(),
+            ctx.remaining_accounts,
+        )?;
+    }
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct WithdrawVestedToken<'info> {
+    #[account(mut, address = escrow_account.base.seller @ EscrowError::Unauthorized)]
+    pub seller: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = escrow_account.base.is_initialized @ EscrowError::ValidationFailed,
+        seeds = [
+            b"token_escrow",
+            escrow_account.base.buyer.as_ref(),
+            escrow_account.base.seller.as_ref(),
+            &[escrow_account.base.moderator.is_some() as u8],
+            &escrow_account.base.unique_id
+        ],
+        bump = escrow_account.base.bump
+    )]
+    pub escrow_account: Account<'info, TokenEscrow>,
+
+    // 结算结果记入双方的信誉账户
+    #[account(
+        init_if_needed,
+        payer = seller,
+        space = Reputation::LEN,
+        seeds = [b"seller_reputation", escrow_account.base.seller.as_ref()],
+        bump
+    )]
+    pub seller_reputation: Account<'info, Reputation>,
+
+    #[account(
+        init_if_needed,
+        payer = seller,
+        space = Reputation::LEN,
+        seeds = [b"buyer_reputation", escrow_account.base.buyer.as_ref()],
+        bump
+    )]
+    pub buyer_reputation: Account<'info, Reputation>,
+
+    // 托管关闭后保留的结算收据，仅在最后一次提取且未使用收据树时传入
+    #[account(
+        init,
+        payer = seller,
+        space = SettlementReceipt::LEN,
+        seeds = [b"receipt", escrow_account.key().as_ref()],
+        bump
+    )]
+    pub receipt: Option<Account<'info, SettlementReceipt>>,
+
+    // 使用压缩收据的托管在这棵树上追加结算叶子
+    #[account(mut)]
+    pub receipt_tree: Option<AccountLoader<'info, ReceiptTree>>,
+
+    #[account(
+        mut,
+        constraint = escrow_token_account.mint == escrow_account.mint @ EscrowError::ValidationFailed,
+        constraint = escrow_token_account.owner == escrow_account.key() @ EscrowError::ValidationFailed,
+    )]
+    pub escrow_token_account: Account<'info, TokenAccount>,
+
+    #[account(address = escrow_account.mint @ EscrowError::TokenMintMismatch)]
+    pub token_mint: Account<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = seller,
+        associated_token::mint = token_mint,
+        associated_token::authority = seller,
+    )]
+    pub seller_token_account: Account<'info, TokenAccount>,
+
+    /// CHECK: 买家账户，全部领取后接收托管账户租金
+    #[account(mut, address = escrow_account.base.buyer @ EscrowError::ValidationFailed)]
+    pub buyer: AccountInfo<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    pub clock: Sysvar<'info, Clock>,
+}
+
+pub fn withdraw_token_handler(ctx: Context<WithdrawVestedToken>) -> Result<()> {
+    let available = take_vested(&mut ctx.accounts.escrow_account.base, ctx.accounts.clock.unix_timestamp)?;
+    require!(available > 0, EscrowError::NothingVested);
+
+    let base = &ctx.accounts.escrow_account.base;
+    let mut vault = EscrowVault::token(
+        ctx.accounts.escrow_account.to_account_info(),
+        EscrowSeeds::new(b"token_escrow", base),
+        &ctx.accounts.escrow_token_account,
+        &ctx.accounts.token_program,
+    );
+    vault.pay(&Payee::token(&ctx.accounts.seller.to_account_info(), &ctx.accounts.seller_token_account), available)?;
+
+    msg!(
+        "Token stream withdrawn: Seller={}, ID=0x{}, Amount={} tokens, Remaining={} tokens",
+        base.seller,
+        bytes_to_hex_string(&base.unique_id),
+        available,
+        base.amount
+    );
+
+    // 只有领完最后一笔时才关闭托管并写入收据，收据账户不能提前传入
+    require!(
+        ctx.accounts.receipt.is_none() || base.amount == 0,
+        EscrowError::InvalidReceiptAccount
+    );
+
+    if base.amount == 0 {
+        let escrow_key = ctx.accounts.escrow_account.key();
+        vault.close(&ctx.accounts.buyer)?;
+        let total = stream_total(base);
+        // 提取由卖家本人签名提交，视为卖家批准
+        let record = SettlementRecord::new(
+            escrow_key,
+            base,
+            Some(ctx.accounts.escrow_account.mint),
+            total,
+            vec![ReceiptPayout { recipient: base.seller, amount: total }],
+            ReleasePath::StreamWithdraw,
+            SettlementOutcome::Released,
+            &ctx.accounts.clock,
+        )
+        .with_seller_approval(true);
+        finish_settlement(
+            base,
+            record,
+            &mut ctx.accounts.seller_reputation,
+            ctx.bumps.seller_reputation,
+            &mut ctx.accounts.buyer_reputation,
+            ctx.bumps.buyer_reputation,
+            ctx.accounts.receipt.as_deref_mut(),
+            ctx.bumps.receipt,
+            ctx.accounts.receipt_tree.as_ref(),
+            ctx.remaining_accounts,
+        )?;
+    }
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct CancelStreamSol<'info> {
+    // 买家、卖家或moderator，在处理函数中校验
+    #[account(mut)]
+    pub initiator: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = escrow_account.base.is_initialized @ EscrowError::ValidationFailed,
+        seeds = [
+            b"sol_escrow",
+            escrow_account.base.buyer.as_ref(),
+            escrow_account.base.seller.as_ref(),
+            &[escrow_account.base.moderator.is_some() as u8],
+            &escrow_account.base.unique_id
+        ],
+        bump = escrow_account.base.bump
+    )]
+    pub escrow_account: Account<'info, SolEscrow>,
+
+    // 结算结果记入双方的信誉账户
+    #[account(
+        init_if_needed,
+        payer = initiator,
+        space = Reputation::LEN,
+        seeds = [b"seller_reputation", escrow_account.base.seller.as_ref()],
+        bump
+    )]
+    pub seller_reputation: Account<'info, Reputation>,
+
+    #[account(
+        init_if_needed,
+        payer = initiator,
+        space = Reputation::LEN,
+        seeds = [b"buyer_reputation", escrow_account.base.buyer.as_ref()],
+        bump
+    )]
+    pub buyer_reputation: Account<'info, Reputation>,
+
+    // 托管关闭后保留的结算收据，使用收据树的托管不传入
+    #[account(
+        init,
+        payer = initiator,
+        space = SettlementReceipt::LEN,
+        seeds = [b"receipt", escrow_account.key().as_ref()],
+        bump
+    )]
+    pub receipt: Option<Account<'info, SettlementReceipt>>,
+
+    // 使用压缩收据的托管在这棵树上追加结算叶子
+    #[account(mut)]
+    pub receipt_tree: Option<AccountLoader<'info, ReceiptTree>>,
+
+    /// CHECK: 卖家账户，接收已归属部分
+    #[account(mut, address = escrow_account.base.seller @ EscrowError::ValidationFailed)]
+    pub seller: AccountInfo<'info>,
+
+    /// CHECK: 付款方账户，接收未归属部分
+    #[account(mut, address = escrow_account.base.payer_address @ EscrowError::ValidationFailed)]
+    pub payer: AccountInfo<'info>,
+
+    /// CHECK: 买家账户，接收托管账户租金
+    #[account(mut, address = escrow_account.base.buyer @ EscrowError::ValidationFailed)]
+    pub buyer: AccountInfo<'info>,
+
+    pub clock: Sysvar<'info, Clock>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn cancel_sol_handler(ctx: Context<CancelStreamSol>) -> Result<()> {
+    require!(
+        ctx.accounts.escrow_account.base.is_party(&ctx.accounts.initiator.key()),
+        EscrowError::Unauthorized
+    );
+
+    let vested = take_vested(&mut ctx.accounts.escrow_account.base, ctx.accounts.clock.unix_timestamp)?;
+    let unvested = ctx.accounts.escrow_account.base.amount;
+
+    let base = &ctx.accounts.escrow_account.base;
+    let vault = EscrowVault::lamports(
+        ctx.accounts.escrow_account.to_account_info(),
+        EscrowSeeds::new(b"sol_escrow", base),
+    );
+    split_cancelled(
+        vault,
+        [Payee::wallet(&ctx.accounts.seller), Payee::wallet(&ctx.accounts.payer)],
+        [vested, unvested],
+        &ctx.accounts.buyer,
+    )?;
+
+    let record = SettlementRecord::new(
+        ctx.accounts.escrow_account.key(),
+        base,
+        None,
+        stream_total(base),
+        cancel_payouts(base),
+        ReleasePath::StreamCancel,
+        cancel_outcome(base),
+        &ctx.accounts.clock,
+    )
+    .with_seller_approval(ctx.accounts.initiator.key() == base.seller);
+    finish_settlement(
+        base,
+        record,
+        &mut ctx.accounts.seller_reputation,
+        ctx.bumps.seller_reputation,
+        &mut ctx.accounts.buyer_reputation,
+        ctx.bumps.buyer_reputation,
+        ctx.accounts.receipt.as_deref_mut(),
+        ctx.bumps.receipt,
+        ctx.accounts.receipt_tree.as_ref(),
+        ctx.remaining_accounts,
+    )?;
+
+    msg!(
+        "SOL stream cancelled: ID=0x{}, Vested to seller={} lamports, Refunded to payer={} lamports",
+        bytes_to_hex_string(&ctx.accounts.escrow_account.base.unique_id),
+        vested,
+        unvested
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct CancelStreamToken<'info> {
+    // 买家、卖家或moderator，在处理函数中校验；支付接收方 ATA 的租金
+    #[account(mut)]
+    pub initiator: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = escrow_account.base.is_initialized @ EscrowError::ValidationFailed,
+        seeds = [
+            b"token_escrow",
+            escrow_account.base.buyer.as_ref(),
+            escrow_account.base.seller.as_ref(),
+            &[escrow_account.base.moderator.is_some() as u8],
+            &escrow_account.base.unique_id
+        ],
+        bump = escrow_account.base.bump
+    )]
+    pub escrow_account: Account<'info, TokenEscrow>,
+
+    // 结算结果记入双方的信誉账户
+    #[account(
+        init_if_needed,
+        payer = initiator,
+        space = Reputation::LEN,
+        seeds = [b"seller_reputation", escrow_account.base.seller.as_ref()],
+        bump
+    )]
+    pub seller_reputation: Account<'info, Reputation>,
+
+    #[account(
+        init_if_needed,
+        payer = initiator,
+        space = Reputation::LEN,
+        seeds = [b"buyer_reputation", escrow_account.base.buyer.as_ref()],
+        bump
+    )]
+    pub buyer_reputation: Account<'info, Reputation>,
+
+    // 托管关闭后保留的结算收据，使用收据树的托管不传入
+    #[account(
+        init,
+        payer = initiator,
+        space = SettlementReceipt::LEN,
+        seeds = [b"receipt", escrow_account.key().as_ref()],
+        bump
+    )]
+    pub receipt: Option<Account<'info, SettlementReceipt>>,
+
+    // 使用压缩收据的托管在这棵树上追加结算叶子
+    #[account(mut)]
+    pub receipt_tree: Option<AccountLoader<'info, ReceiptTree>>,
+
+    #[account(
+        mut,
+        constraint = escrow_token_account.mint == escrow_account.mint @ EscrowError::ValidationFailed,
+        constraint = escrow_token_account.owner == escrow_account.key() @ EscrowError::ValidationFailed,
+    )]
+    pub escrow_token_account: Account<'info, TokenAccount>,
+
+    #[account(address = escrow_account.mint @ EscrowError::TokenMintMismatch)]
+    pub token_mint: Account<'info, Mint>,
+
+    /// CHECK: 卖家账户
+    #[account(address = escrow_account.base.seller @ EscrowError::ValidationFailed)]
+    pub seller: AccountInfo<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = initiator,
+        associated_token::mint = token_mint,
+        associated_token::authority = seller,
+    )]
+    pub seller_token_account: Account<'info, TokenAccount>,
+
+    /// CHECK: 付款方账户
+    #[account(address = escrow_account.base.payer_address @ EscrowError::ValidationFailed)]
+    pub payer: AccountInfo<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = initiator,
+        associated_token::mint = token_mint,
+        associated_token::authority = payer,
+    )]
+    pub payer_token_account: Account<'info, TokenAccount>,
+
+    /// CHECK: 买家账户，接收托管账户租金
+    #[account(mut, address = escrow_account.base.buyer @ EscrowError::ValidationFailed)]
+    pub buyer: AccountInfo<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    pub clock: Sysvar<'info, Clock>,
+}
+
+pub fn cancel_token_handler(ctx: Context<CancelStreamToken>) -> Result<()> {
+    require!(
+        ctx.accounts.escrow_account.base.is_party(&ctx.accounts.initiator.key()),
+        EscrowError::Unauthorized
+    );
+
+    let vested = take_vested(&mut ctx.accounts.escrow_account.base, ctx.accounts.clock.unix_timestamp)?;
+    let unvested = ctx.accounts.escrow_account.base.amount;
+
+    let base = &ctx.accounts.escrow_account.base;
+    let vault = EscrowVault::token(
+        ctx.accounts.escrow_account.to_account_info(),
+        EscrowSeeds::new(b"token_escrow", base),
+        &ctx.accounts.escrow_token_account,
+        &ctx.accounts.token_program,
+    );
+    split_cancelled(
+        vault,
+        [
+            Payee::token(&ctx.accounts.seller, &ctx.accounts.seller_token_account),
+            Payee::token(&ctx.accounts.payer, &ctx.accounts.payer_token_account),
+        ],
+        [vested, unvested],
+        &ctx.accounts.buyer,
+    )?;
+
+    let record = SettlementRecord::new(
+        ctx.accounts.escrow_account.key(),
+        base,
+        Some(ctx.accounts.escrow_account.mint),
+        stream_total(base),
+        cancel_payouts(base),
+        ReleasePath::StreamCancel,
+        cancel_outcome(base),
+        &ctx.accounts.clock,
+    )
+    .with_seller_approval(ctx.accounts.initiator.key() == base.seller);
+    finish_settlement(
+        base,
+        record,
+        &mut ctx.accounts.seller_reputation,
+        ctx.bumps.seller_reputation,
+        &mut ctx.accounts.buyer_reputation,
+        ctx.bumps.buyer_reputation,
+        ctx.accounts.receipt.as_deref_mut(),
+        ctx.bumps.receipt,
+        ctx.accounts.receipt_tree.as_ref(),
+        ctx.remaining_accounts,
+    )?;
+
+    msg!(
+        "Token stream cancelled: ID=0x{}, Vested to seller={} tokens, Refunded to payer={} tokens",
+        bytes_to_hex_string(&ctx.accounts.escrow_account.base.unique_id),
+        vested,
+        unvested
+    );
+
+    Ok(())
+}
+
+// 取消时已归属部分付给卖家、未归属部分退还付款方，然后关闭托管
+fn split_cancelled<'info>(
+    mut vault: EscrowVault<'_, 'info>,
+    payees: [Payee<'_, 'info>; 2],
+    amounts: [u64; 2],
+    buyer: &AccountInfo<'info>,
+) -> Result<()> {
+    for (payee, amount) in payees.iter().zip(amounts) {
+        vault.pay(payee, amount)?;
+    }
+    vault.close(buyer)
+}
+
+// 流的总额：剩余金额加上已领取金额
+fn stream_total(base: &EscrowAccount) -> u64 {
+    base.amount + base.stream.map_or(0, |stream| stream.withdrawn)
+}
+
+// 卖家已获得任何归属金额时按释放记录，否则视为全额退款
+fn cancel_outcome(base: &EscrowAccount) -> SettlementOutcome {
+    if base.stream.is_some_and(|stream| stream.withdrawn > 0) {
+        SettlementOutcome::Released
+    } else {
+        SettlementOutcome::Refunded
+    }
+}
+
+// 取消时卖家累计领取的部分归卖家，未归属部分退还付款方
+fn cancel_payouts(base: &EscrowAccount) -> Vec<ReceiptPayout> {
+    vec![
+        ReceiptPayout { recipient: base.seller, amount: base.stream.map_or(0, |stream| stream.withdrawn) },
+        ReceiptPayout { recipient: base.payer_address, amount: base.amount },
+    ]
+}
+
+// 计算尚未领取的已归属金额，并从 base.amount 中扣除、计入 withdrawn
+fn take_vested(base: &mut EscrowAccount, current_time: i64) -> Result<u64> {
+    let stream = base.stream.as_mut().ok_or(EscrowError::NotStreaming)?;
+
+    let total = base.amount
+        .checked_add(stream.withdrawn)
+        .ok_or(EscrowError::InvalidAmount)?;
+    let available = stream
+        .vested_amount(total, current_time)
+        .saturating_sub(stream.withdrawn);
+
+    stream.withdrawn += available;
+    base.amount -= available;
+
+    Ok(available)
+}
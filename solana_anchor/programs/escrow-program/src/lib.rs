@@ -5,8 +5,11 @@ pub mod instructions;
 pub mod error;
 pub mod ed25519;
 pub mod utils;
+pub mod events;
+pub mod asset;
 
-use instructions::{initialize_sol::*, initialize_token::*, release_sol::*, release_token::*};
+use state::{BatchEscrowEntry, EscrowCreated, AssetLeg, AssetPayout, EscrowOptions, DisputeRuling, ReceiptLeaf};
+use instructions::{initialize_sol::*, initialize_token::*, release_sol::*, release_token::*, bump_nonce::*, batch_initialize::*, seller_balance::*, release_to_claims::*, claim::*, multi_asset::*, htlc::*, stream::*, recurring::*, crowdfund::*, attest_delivery::*, dispute::*, change_moderator::*, receipt_tree::*, migrate_escrow::*, fixed_escrow::*};
 
 declare_id!("25ecY9sGUkFyy78aYaSbdWGMgySSKZvPjQunf6Uk23qk");
 
@@ -14,7 +17,7 @@ declare_id!("25ecY9sGUkFyy78aYaSbdWGMgySSKZvPjQunf6Uk23qk");
 pub mod escrow_program {
     use super::*;
 
-    // SOL initialize instruction
+    // SOL initialize instruction; returns the escrow address and unique_id for CPI callers
     pub fn initialize_sol(
         ctx: Context<InitializeSol>,
         moderator: Option<Pubkey>,
@@ -22,7 +25,8 @@ pub mod escrow_program {
         required_signatures: u8,
         unlock_hours: u64,
         amount: u64,
-    ) -> Result<()> {
+        options: EscrowOptions,
+    ) -> Result<EscrowCreated> {
         instructions::initialize_sol::handler(
             ctx,
             moderator,
@@ -30,18 +34,20 @@ pub mod escrow_program {
             required_signatures,
             unlock_hours,
             amount,
+            options,
         )
     }
 
     pub fn release_sol(
         ctx: Context<ReleaseSol>,
         payment_amounts: Vec<u64>,
-        signatures: Vec<Vec<u8>>
+        signatures: Vec<Vec<u8>>,
+        valid_until: i64,
     ) -> Result<()> {
-        instructions::release_sol::handler(ctx, payment_amounts, signatures)
+        instructions::release_sol::handler(ctx, payment_amounts, signatures, valid_until)
     }
 
-    // SPL token initialize instruction
+    // SPL token initialize instruction; returns the escrow address and unique_id for CPI callers
     pub fn initialize_token(
         ctx: Context<InitializeToken>,
         moderator: Option<Pubkey>,
@@ -49,7 +55,8 @@ pub mod escrow_program {
         required_signatures: u8,
         unlock_hours: u64,
         amount: u64,
-    ) -> Result<()> {
+        options: EscrowOptions,
+    ) -> Result<EscrowCreated> {
         instructions::initialize_token::handler(
             ctx,
             moderator,
@@ -57,22 +64,340 @@ pub mod escrow_program {
             required_signatures,
             unlock_hours,
             amount,
+            options,
         )
     }
 
+    // Create several token escrows from one payer and source token account
+    pub fn batch_initialize<'info>(
+        ctx: Context<'_, '_, 'info, 'info, BatchInitialize<'info>>,
+        entries: Vec<BatchEscrowEntry>,
+    ) -> Result<()> {
+        instructions::batch_initialize::handler(ctx, entries)
+    }
+
     pub fn release_token(
         ctx: Context<ReleaseToken>,
         payment_amounts: Vec<u64>,
-        signatures: Vec<Vec<u8>>
+        signatures: Vec<Vec<u8>>,
+        valid_until: i64,
     ) -> Result<()> {
-        instructions::release_token::handler(ctx, payment_amounts, signatures)
+        instructions::release_token::handler(ctx, payment_amounts, signatures, valid_until)
     }
 
     pub fn release_token_after_timeout(
         ctx: Context<ReleaseTokenAfterTimeout>,
         payment_amounts: Vec<u64>,
-        signatures: Vec<Vec<u8>>
+        signatures: Vec<Vec<u8>>,
+        valid_until: i64,
+    ) -> Result<()> {
+        instructions::release_token::handler_after_timeout(ctx, payment_amounts, signatures, valid_until)
+    }
+
+    // HTLC: anyone revealing the preimage before the deadline pays the seller
+    pub fn claim_with_preimage_sol(ctx: Context<ClaimWithPreimageSol>, preimage: Vec<u8>) -> Result<()> {
+        instructions::htlc::claim_sol_handler(ctx, preimage)
+    }
+
+    pub fn claim_with_preimage_token(ctx: Context<ClaimWithPreimageToken>, preimage: Vec<u8>) -> Result<()> {
+        instructions::htlc::claim_token_handler(ctx, preimage)
+    }
+
+    // HTLC: after the deadline the escrow can only go back to the payer
+    pub fn refund_htlc_sol(ctx: Context<RefundHtlcSol>) -> Result<()> {
+        instructions::htlc::refund_sol_handler(ctx)
+    }
+
+    pub fn refund_htlc_token(ctx: Context<RefundHtlcToken>) -> Result<()> {
+        instructions::htlc::refund_token_handler(ctx)
+    }
+
+    // Streaming escrow: seller withdraws whatever has vested so far
+    pub fn withdraw_vested_sol(ctx: Context<WithdrawVestedSol>) -> Result<()> {
+        instructions::stream::withdraw_sol_handler(ctx)
+    }
+
+    pub fn withdraw_vested_token(ctx: Context<WithdrawVestedToken>) -> Result<()> {
+        instructions::stream::withdraw_token_handler(ctx)
+    }
+
+    // Streaming escrow: pay out the vested part to the seller and refund the rest
+    pub fn cancel_stream_sol(ctx: Context<CancelStreamSol>) -> Result<()> {
+        instructions::stream::cancel_sol_handler(ctx)
+    }
+
+    pub fn cancel_stream_token(ctx: Context<CancelStreamToken>) -> Result<()> {
+        instructions::stream::cancel_token_handler(ctx)
+    }
+
+    // Prepaid subscription escrow paying the seller a fixed amount per period
+    pub fn initialize_recurring(
+        ctx: Context<InitializeRecurring>,
+        unique_id: [u8; 20],
+        amount_per_period: u64,
+        period_seconds: i64,
+        deposit: u64,
+    ) -> Result<()> {
+        instructions::recurring::initialize_handler(ctx, unique_id, amount_per_period, period_seconds, deposit)
+    }
+
+    pub fn fund_recurring(ctx: Context<FundRecurring>, amount: u64) -> Result<()> {
+        instructions::recurring::fund_handler(ctx, amount)
+    }
+
+    pub fn claim_recurring(ctx: Context<ClaimRecurring>) -> Result<()> {
+        instructions::recurring::claim_handler(ctx)
+    }
+
+    pub fn pause_recurring(ctx: Context<SetRecurringPaused>) -> Result<()> {
+        instructions::recurring::pause_handler(ctx)
+    }
+
+    pub fn resume_recurring(ctx: Context<SetRecurringPaused>) -> Result<()> {
+        instructions::recurring::resume_handler(ctx)
+    }
+
+    // Pay out periods already due, refund the rest to the buyer and close
+    pub fn cancel_recurring(ctx: Context<CancelRecurring>) -> Result<()> {
+        instructions::recurring::cancel_handler(ctx)
+    }
+
+    // Pooled escrow for group buys with a funding target and deadline
+    pub fn initialize_crowdfund(
+        ctx: Context<InitializeCrowdfund>,
+        moderator: Option<Pubkey>,
+        unique_id: [u8; 20],
+        required_signatures: u8,
+        target_amount: u64,
+        funding_hours: u64,
+    ) -> Result<()> {
+        instructions::crowdfund::initialize_handler(ctx, moderator, unique_id, required_signatures, target_amount, funding_hours)
+    }
+
+    pub fn contribute(ctx: Context<Contribute>, amount: u64) -> Result<()> {
+        instructions::crowdfund::contribute_handler(ctx, amount)
+    }
+
+    // Release a crowdfund that reached its target, under the normal signature rules
+    pub fn release_crowdfund<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ReleaseCrowdfund<'info>>,
+        payment_amounts: Vec<u64>,
+        signatures: Vec<Vec<u8>>,
+        valid_until: i64,
+    ) -> Result<()> {
+        instructions::crowdfund::release_handler(ctx, payment_amounts, signatures, valid_until)
+    }
+
+    // Contributor takes back their share after the deadline passes short of the target
+    pub fn reclaim_contribution(ctx: Context<ReclaimContribution>) -> Result<()> {
+        instructions::crowdfund::reclaim_handler(ctx)
+    }
+
+    pub fn close_contribution(ctx: Context<CloseContribution>) -> Result<()> {
+        instructions::crowdfund::close_contribution_handler(ctx)
+    }
+
+    // Escrow holding several mints, funded by buyer and/or seller
+    pub fn initialize_multi_asset(
+        ctx: Context<InitializeMultiAsset>,
+        moderator: Option<Pubkey>,
+        unique_id: [u8; 20],
+        required_signatures: u8,
+        unlock_hours: u64,
+        legs: Vec<AssetLeg>,
+    ) -> Result<()> {
+        instructions::multi_asset::initialize_handler(
+            ctx,
+            moderator,
+            unique_id,
+            required_signatures,
+            unlock_hours,
+            legs,
+        )
+    }
+
+    pub fn deposit_asset(ctx: Context<DepositAsset>) -> Result<()> {
+        instructions::multi_asset::deposit_handler(ctx)
+    }
+
+    // Settle every leg of a multi-asset escrow atomically
+    pub fn release_multi_asset<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ReleaseMultiAsset<'info>>,
+        payouts: Vec<AssetPayout>,
+        signatures: Vec<Vec<u8>>,
+        valid_until: i64,
+    ) -> Result<()> {
+        instructions::multi_asset::release_handler(ctx, payouts, signatures, valid_until)
+    }
+
+    // Return deposited legs once the unlock time passes without full funding
+    pub fn cancel_multi_asset<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CancelMultiAsset<'info>>,
+    ) -> Result<()> {
+        instructions::multi_asset::cancel_handler(ctx)
+    }
+
+    // Release SOL into per-recipient claim tickets instead of pushing to wallets
+    pub fn release_sol_to_claims<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ReleaseSolToClaims<'info>>,
+        payment_amounts: Vec<u64>,
+        signatures: Vec<Vec<u8>>,
+        valid_until: i64,
+        recipients: Vec<Pubkey>,
+    ) -> Result<()> {
+        instructions::release_to_claims::sol_handler(ctx, payment_amounts, signatures, valid_until, recipients)
+    }
+
+    // Release tokens into per-recipient claim tickets instead of pushing to ATAs
+    pub fn release_token_to_claims<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ReleaseTokenToClaims<'info>>,
+        payment_amounts: Vec<u64>,
+        signatures: Vec<Vec<u8>>,
+        valid_until: i64,
+        recipients: Vec<Pubkey>,
+    ) -> Result<()> {
+        instructions::release_to_claims::token_handler(ctx, payment_amounts, signatures, valid_until, recipients)
+    }
+
+    // Recipient pulls the funds held by their claim ticket
+    pub fn claim(ctx: Context<Claim>) -> Result<()> {
+        instructions::claim::claim_handler(ctx)
+    }
+
+    // Return an unclaimed, expired ticket to the escrow payer
+    pub fn reclaim_expired_claim(ctx: Context<ReclaimExpiredClaim>) -> Result<()> {
+        instructions::claim::reclaim_handler(ctx)
+    }
+
+    // Open the seller's payout ledger for one mint
+    pub fn open_seller_balance(ctx: Context<OpenSellerBalance>) -> Result<()> {
+        instructions::seller_balance::open_handler(ctx)
+    }
+
+    // Move accumulated proceeds from the ledger to a seller token account
+    pub fn withdraw_balance(ctx: Context<WithdrawBalance>, amount: u64) -> Result<()> {
+        instructions::seller_balance::withdraw_handler(ctx, amount)
+    }
+
+    // Moderator ruling as a basis-point split, approved like a release; amounts come from the vault balance
+    pub fn resolve_dispute_sol(
+        ctx: Context<ResolveDisputeSol>,
+        ruling: DisputeRuling,
+        valid_until: i64,
+        signatures: Vec<Vec<u8>>,
+    ) -> Result<()> {
+        instructions::dispute::sol_handler(ctx, ruling, valid_until, signatures)
+    }
+
+    pub fn resolve_dispute_token(
+        ctx: Context<ResolveDisputeToken>,
+        ruling: DisputeRuling,
+        valid_until: i64,
+        signatures: Vec<Vec<u8>>,
+    ) -> Result<()> {
+        instructions::dispute::token_handler(ctx, ruling, valid_until, signatures)
+    }
+
+    // Record a delivery oracle attestation; it then counts as one release approval
+    pub fn attest_delivery(
+        ctx: Context<AttestDelivery>,
+        delivered: bool,
+        timestamp: i64,
+        signature: Vec<u8>,
+    ) -> Result<()> {
+        instructions::attest_delivery::handler(ctx, delivered, timestamp, signature)
+    }
+
+    // Replace an unresponsive moderator with buyer+seller or moderator+party consent
+    pub fn change_moderator(ctx: Context<ChangeModerator>, new_moderator: Pubkey) -> Result<()> {
+        instructions::change_moderator::handler(ctx, new_moderator)
+    }
+
+    // Invalidate every release approval signed against the current nonce; any approver may call it
+    pub fn bump_nonce(ctx: Context<BumpNonce>) -> Result<()> {
+        instructions::bump_nonce::handler(ctx)
+    }
+
+    // Create a Merkle tree that escrows can record compressed receipts in
+    pub fn create_receipt_tree(ctx: Context<CreateReceiptTree>) -> Result<()> {
+        instructions::receipt_tree::create_handler(ctx)
+    }
+
+    // Check a Merkle proof that a receipt leaf was appended to a receipt tree. Escrow
+    // addresses of compressed escrows can be reused, so one escrow may have several leaves
+    pub fn verify_receipt(
+        ctx: Context<VerifyReceipt>,
+        index: u64,
+        leaf: ReceiptLeaf,
+        proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        instructions::receipt_tree::verify_handler(ctx, index, leaf, proof)
+    }
+
+    // Convert an escrow written before the layout was versioned to the current layout
+    pub fn migrate_escrow(ctx: Context<MigrateEscrow>) -> Result<()> {
+        instructions::migrate_escrow::handler(ctx)
+    }
+
+    // Create a fixed-layout SOL escrow that release reads without Borsh deserialization
+    pub fn initialize_fixed_sol(
+        ctx: Context<InitializeFixedSol>,
+        moderator: Option<Pubkey>,
+        unique_id: [u8; 20],
+        required_signatures: u8,
+        unlock_hours: u64,
+        amount: u64,
+        options: EscrowOptions,
+    ) -> Result<()> {
+        instructions::fixed_escrow::initialize_sol_handler(
+            ctx,
+            moderator,
+            unique_id,
+            required_signatures,
+            unlock_hours,
+            amount,
+            options,
+        )
+    }
+
+    // Create a fixed-layout SPL token escrow
+    pub fn initialize_fixed_token(
+        ctx: Context<InitializeFixedToken>,
+        moderator: Option<Pubkey>,
+        unique_id: [u8; 20],
+        required_signatures: u8,
+        unlock_hours: u64,
+        amount: u64,
+        options: EscrowOptions,
+    ) -> Result<()> {
+        instructions::fixed_escrow::initialize_token_handler(
+            ctx,
+            moderator,
+            unique_id,
+            required_signatures,
+            unlock_hours,
+            amount,
+            options,
+        )
+    }
+
+    // Release a fixed-layout SOL escrow; same signature and timelock rules as release_sol
+    pub fn release_fixed_sol(
+        ctx: Context<ReleaseFixedSol>,
+        payment_amounts: Vec<u64>,
+        signatures: Vec<Vec<u8>>,
+        valid_until: i64,
+    ) -> Result<()> {
+        instructions::fixed_escrow::release_sol_handler(ctx, payment_amounts, signatures, valid_until)
+    }
+
+    // Release a fixed-layout token escrow, including the seller-only release after the timelock
+    pub fn release_fixed_token(
+        ctx: Context<ReleaseFixedToken>,
+        payment_amounts: Vec<u64>,
+        signatures: Vec<Vec<u8>>,
+        valid_until: i64,
     ) -> Result<()> {
-        instructions::release_token::handler_after_timeout(ctx, payment_amounts, signatures)
+        instructions::fixed_escrow::release_token_handler(ctx, payment_amounts, signatures, valid_until)
     }
 } 
\ No newline at end of file
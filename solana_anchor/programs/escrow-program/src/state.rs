@@ -3,9 +3,141 @@ use crate::error::*;
 
 pub const MAX_PAYMENT_TARGETS: usize = 4;
 pub const MAX_REQUIRED_SIGNATURES: u8 = 2;
+pub const MAX_BATCH_ESCROWS: usize = 5;
+pub const MAX_ESCROW_ASSETS: usize = 3;
+pub const MAX_ASSET_PAYOUTS: usize = 6;
+pub const MAX_PREIMAGE_LEN: usize = 64;
+pub const MAX_PANEL_MEMBERS: usize = 5;
+pub const BPS_DENOMINATOR: u64 = 10_000;
+pub const CLAIM_EXPIRY_SECONDS: i64 = 180 * 24 * 3600; // unclaimed tickets roll back to the payer after 180 days
+pub const ESCROW_LAYOUT_VERSION: u8 = 3;
+pub const V2_ESCROW_VERSION: u8 = 2; // before release_hook, ledger and require_registered_moderator; see V2EscrowAccount
+pub const LEGACY_ESCROW_VERSION: u8 = 1; // layout before versioning; see LegacyEscrowAccount
+pub const ESCROW_RESERVED_BYTES: usize = 20;
+pub const FIXED_ESCROW_RESERVED_BYTES: usize = 47; // also pads FixedEscrow to a multiple of 8
+pub const RECEIPT_TREE_DEPTH: usize = 20; // about a million leaves per tree
+pub const RECEIPT_ROOT_HISTORY: usize = 64; // proofs against any of the last 64 roots still verify
+pub const HOOK_AUTHORITY_SEED: &[u8] = b"hook_authority"; // PDA that signs release hook calls
+pub const MIN_COUNTED_SETTLEMENT_LAMPORTS: u64 = 10_000_000; // 0.01 SOL; smaller SOL settlements do not count toward min_seller_settlements
+pub const MIN_COUNTED_SETTLEMENT_TOKEN_UNITS: u64 = 1_000_000; // fallback for token escrows that committed no counted_min_amount
+pub const MAX_REPUTATION_MINTS: usize = 4; // token volume is kept for the first four mints a party settles in
 
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    Sha256,
+    Keccak256,
+}
+
+// Hash-time-lock for cross-chain atomic swaps
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub struct Hashlock {
+    pub algorithm: HashAlgorithm,
+    pub hash: [u8; 32],
+}
+
+impl Hashlock {
+    pub fn matches(&self, preimage: &[u8]) -> bool {
+        let digest = match self.algorithm {
+            HashAlgorithm::Sha256 => anchor_lang::solana_program::hash::hash(preimage).to_bytes(),
+            HashAlgorithm::Keccak256 => solana_keccak_hasher::hash(preimage).to_bytes(),
+        };
+        digest == self.hash
+    }
+}
+
+// Linear vesting requested at initialize time; no cliff means vesting starts at start_time
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub struct StreamParams {
+    pub start_time: i64,
+    pub cliff_time: Option<i64>,
+    pub end_time: i64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub struct StreamSchedule {
+    pub start_time: i64,
+    pub cliff_time: i64,
+    pub end_time: i64,
+    pub withdrawn: u64, // already paid to the seller; base.amount holds what is left
+}
+
+impl StreamSchedule {
+    // Amount vested out of `total` (remaining + withdrawn) at `current_time`
+    pub fn vested_amount(&self, total: u64, current_time: i64) -> u64 {
+        if current_time < self.cliff_time {
+            return 0;
+        }
+        if current_time >= self.end_time {
+            return total;
+        }
+        let elapsed = (current_time - self.start_time) as u128;
+        let duration = (self.end_time - self.start_time) as u128;
+        (total as u128 * elapsed / duration) as u64
+    }
+}
+
+// Program notified after the escrow settles; see utils::call_release_hook
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReleaseHook {
+    pub program: Pubkey,
+    pub initiator_may_skip: bool, // whoever submits a settlement may leave the hook accounts out and skip the call
+}
+
+impl ReleaseHook {
+    pub const LEN: usize = 32 + 1;
+}
+
+// k-of-n arbitration panel; a quorum of members counts as a single release approval
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct ModeratorPanel {
+    pub members: Vec<Pubkey>,
+    pub threshold: u8,
+}
+
+impl ModeratorPanel {
+    pub const LEN: usize = 4 + MAX_PANEL_MEMBERS * 32 + 1;
+
+    pub fn is_member(&self, key: &Pubkey) -> bool {
+        self.members.contains(key)
+    }
+
+    // `signers` must already be deduplicated
+    pub fn has_quorum(&self, signers: &[Pubkey]) -> bool {
+        let approvals = signers.iter().filter(|signer| self.is_member(signer)).count();
+        approvals >= self.threshold as usize
+    }
+}
+
+// Returned from initialize_sol / initialize_token as return data, so a program
+// opening the escrow by CPI can record where it lives
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EscrowCreated {
+    pub escrow: Pubkey,
+    pub unique_id: [u8; 20],
+}
+
+// Optional escrow modes chosen at initialize time
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default)]
+pub struct EscrowOptions {
+    pub hashlock: Option<Hashlock>,
+    pub stream: Option<StreamParams>,
+    pub oracle: Option<Pubkey>,
+    pub moderator_fee_bps_max: u16,
+    pub moderator_fee_fixed: u64,
+    pub panel: Option<ModeratorPanel>,
+    pub require_registered_moderator: bool, // moderator must hold an active profile in the moderator registry
+    pub min_seller_settlements: u64, // completed, non-refunded escrows the seller must already have
+    pub receipt_tree: Option<Pubkey>, // record creation and settlement as leaves of this tree instead of a receipt account
+    pub release_hook: Option<ReleaseHook>, // program called with the settlement record after release or refund
+}
+
+// Fields shared by every escrow kind. Only fixed-width fields whose zero bytes
+// are a safe default may be carved out of `reserved`. An Option is one byte when
+// None and shifts everything after it, so it needs a version bump and a
+// conversion in migrate_escrow like any other layout change.
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
 pub struct EscrowAccount {
+    pub version: u8, // ESCROW_LAYOUT_VERSION; stays first so migrate_escrow can tell layouts apart
     pub is_initialized: bool,
     pub buyer: Pubkey,
     pub seller: Pubkey,
@@ -16,6 +148,197 @@ pub struct EscrowAccount {
     pub unique_id: [u8; 20],
     pub amount: u64,
     pub bump: u8,
+    pub nonce: u64, // bumped by any party to invalidate previously signed releases
+    pub hashlock: Option<Hashlock>, // HTLC mode: unlock_time becomes the refund-to-payer deadline
+    pub stream: Option<StreamSchedule>, // streaming mode: seller withdraws the vested portion over time
+    pub oracle: Option<Pubkey>, // delivery oracle, e.g. a shipping-carrier attestation key
+    pub delivered_at: Option<i64>, // set by attest_delivery; counts as one release approval
+    pub moderator_fee_bps_max: u16, // moderator payouts are capped at bps_max of the escrow plus the fixed fee
+    pub moderator_fee_fixed: u64,
+    pub panel: Option<ModeratorPanel>, // arbitration panel, approving alongside or instead of the single moderator
+    pub receipt_tree: Option<Pubkey>, // compressed receipts: settlement appends a leaf here instead of creating a receipt account, so the unique_id can be reused
+    pub release_hook: Option<ReleaseHook>, // added in version 3
+    pub ledger: bool, // added in version 3; funds sit in the seller's SellerBalance pool instead of a per-escrow vault
+    pub require_registered_moderator: bool, // added in version 3; change_moderator checks replacements against the registry too
+    pub counted_min_amount: u64, // carved from reserved; smallest settlement that counts toward seller history, 0 for the defaults
+    pub reserved: [u8; ESCROW_RESERVED_BYTES],
+}
+
+impl EscrowAccount {
+    // Serialized size with every Option set, in field order
+    pub const LEN: usize = 1 + // version
+                          1 + // is_initialized
+                          32 + // buyer
+                          32 + // seller
+                          33 + // moderator (Option<Pubkey>)
+                          32 + // payer_address
+                          1 + // required_signatures
+                          8 + // unlock_time
+                          20 + // unique_id
+                          8 + // amount
+                          1 + // bump
+                          8 + // nonce
+                          34 + // hashlock (Option<Hashlock>)
+                          33 + // stream (Option<StreamSchedule>)
+                          33 + // oracle (Option<Pubkey>)
+                          9 + // delivered_at (Option<i64>)
+                          2 + // moderator_fee_bps_max
+                          8 + // moderator_fee_fixed
+                          1 + ModeratorPanel::LEN + // panel (Option<ModeratorPanel>)
+                          33 + // receipt_tree (Option<Pubkey>)
+                          1 + ReleaseHook::LEN + // release_hook (Option<ReleaseHook>)
+                          1 + // ledger
+                          1 + // require_registered_moderator
+                          8 + // counted_min_amount
+                          ESCROW_RESERVED_BYTES; // reserved
+}
+
+// Escrow fields as written before the layout was versioned. Accounts in this
+// layout start with is_initialized (always 1), which reads as version 1.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct LegacyEscrowAccount {
+    pub is_initialized: bool,
+    pub buyer: Pubkey,
+    pub seller: Pubkey,
+    pub moderator: Option<Pubkey>,
+    pub payer_address: Pubkey,
+    pub required_signatures: u8,
+    pub unlock_time: i64,
+    pub unique_id: [u8; 20],
+    pub amount: u64,
+    pub bump: u8,
+}
+
+impl LegacyEscrowAccount {
+    pub const SOL_ESCROW_LEN: usize = 176;
+    pub const TOKEN_ESCROW_LEN: usize = 208;
+
+    // Fields added since then take their defaults, which match how the old program behaved
+    pub fn upgrade(self) -> EscrowAccount {
+        let mut base = EscrowAccount::new(
+            self.buyer,
+            self.seller,
+            self.moderator,
+            self.payer_address,
+            self.required_signatures,
+            self.unlock_time,
+            self.unique_id,
+            self.amount,
+            self.bump,
+        );
+        base.is_initialized = self.is_initialized;
+        base
+    }
+}
+
+// Escrow fields as written by version 2, which ended in 64 reserved bytes where
+// version 3 has release_hook, ledger and require_registered_moderator
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct V2EscrowAccount {
+    pub version: u8,
+    pub is_initialized: bool,
+    pub buyer: Pubkey,
+    pub seller: Pubkey,
+    pub moderator: Option<Pubkey>,
+    pub payer_address: Pubkey,
+    pub required_signatures: u8,
+    pub unlock_time: i64,
+    pub unique_id: [u8; 20],
+    pub amount: u64,
+    pub bump: u8,
+    pub nonce: u64,
+    pub hashlock: Option<Hashlock>,
+    pub stream: Option<StreamSchedule>,
+    pub oracle: Option<Pubkey>,
+    pub delivered_at: Option<i64>,
+    pub moderator_fee_bps_max: u16,
+    pub moderator_fee_fixed: u64,
+    pub panel: Option<ModeratorPanel>,
+    pub receipt_tree: Option<Pubkey>,
+    pub reserved: [u8; 64],
+}
+
+impl V2EscrowAccount {
+    // The version 3 fields take their defaults; version 2 had no release hooks,
+    // ledger escrows or registry requirement stored on the escrow
+    pub fn upgrade(self) -> EscrowAccount {
+        let mut base = EscrowAccount::new(
+            self.buyer,
+            self.seller,
+            self.moderator,
+            self.payer_address,
+            self.required_signatures,
+            self.unlock_time,
+            self.unique_id,
+            self.amount,
+            self.bump,
+        );
+        base.is_initialized = self.is_initialized;
+        base.nonce = self.nonce;
+        base.hashlock = self.hashlock;
+        base.stream = self.stream;
+        base.oracle = self.oracle;
+        base.delivered_at = self.delivered_at;
+        base.moderator_fee_bps_max = self.moderator_fee_bps_max;
+        base.moderator_fee_fixed = self.moderator_fee_fixed;
+        base.panel = self.panel;
+        base.receipt_tree = self.receipt_tree;
+        base
+    }
+}
+
+// Moderator ruling for resolve_dispute, in basis points. The fee comes off the
+// top; the buyer side gets buyer_bps of the rest and the seller the remainder.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub struct DisputeRuling {
+    pub buyer_bps: u16,
+    pub moderator_fee_bps: u16,
+}
+
+impl DisputeRuling {
+    // Returns (buyer_amount, seller_amount, moderator_fee); both shares round
+    // down and the seller receives any remainder, so the parts always sum to balance
+    pub fn split(&self, balance: u64) -> (u64, u64, u64) {
+        let fee = (balance as u128 * self.moderator_fee_bps as u128 / BPS_DENOMINATOR as u128) as u64;
+        let rest = balance - fee;
+        let buyer_amount = (rest as u128 * self.buyer_bps as u128 / BPS_DENOMINATOR as u128) as u64;
+        (buyer_amount, rest - buyer_amount, fee)
+    }
+}
+
+// Per-order settings for batch_initialize; the buyer, payer and mint are shared
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct BatchEscrowEntry {
+    pub seller: Pubkey,
+    pub moderator: Option<Pubkey>,
+    pub unique_id: [u8; 20],
+    pub required_signatures: u8,
+    pub unlock_hours: u64,
+    pub amount: u64,
+}
+
+// One leg of a multi-asset escrow as requested at initialize time
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct AssetLeg {
+    pub mint: Pubkey,
+    pub depositor: Pubkey, // buyer or seller
+    pub amount: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct EscrowAsset {
+    pub mint: Pubkey,
+    pub depositor: Pubkey,
+    pub amount: u64,
+    pub deposited: bool,
+}
+
+// Signed per-mint payout in a multi-asset release
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct AssetPayout {
+    pub mint: Pubkey,
+    pub recipient: Pubkey, // token account owner
+    pub amount: u64,
 }
 
 // SOL Escrow Account
@@ -31,39 +354,677 @@ pub struct TokenEscrow {
     pub mint: Pubkey
 }
 
-impl SolEscrow {
+// Multi-asset Escrow Account, one vault (ATA of this PDA) per mint
+#[account]
+pub struct MultiAssetEscrow {
+    pub base: EscrowAccount,
+    pub assets: Vec<EscrowAsset>,
+}
+
+impl MultiAssetEscrow {
+    pub const LEN: usize = 8 + // discriminator
+                          EscrowAccount::LEN + // base
+                          4 + MAX_ESCROW_ASSETS * (32 + 32 + 8 + 1); // assets
+
+    pub fn find_asset(&self, mint: &Pubkey) -> Option<&EscrowAsset> {
+        self.assets.iter().find(|asset| asset.mint == *mint)
+    }
+
+    pub fn is_fully_funded(&self) -> bool {
+        self.assets.iter().all(|asset| asset.deposited)
+    }
+}
+
+impl AsRef<EscrowAccount> for MultiAssetEscrow {
+    fn as_ref(&self) -> &EscrowAccount {
+        &self.base
+    }
+}
+
+// Pooled escrow for group buys. base.amount is the total raised so far and
+// base.unlock_time is the funding deadline. SOL contributions stay on the PDA;
+// token contributions sit in the PDA's associated token account.
+#[account]
+pub struct CrowdfundEscrow {
+    pub base: EscrowAccount,
+    pub mint: Option<Pubkey>, // None for SOL crowdfunds
+    pub target_amount: u64,
+}
+
+impl CrowdfundEscrow {
+    pub const LEN: usize = 8 + // discriminator
+                          EscrowAccount::LEN + // base
+                          33 + // mint (Option<Pubkey>)
+                          8;  // target_amount
+
+    pub fn is_funded(&self) -> bool {
+        self.base.amount >= self.target_amount
+    }
+}
+
+impl AsRef<EscrowAccount> for CrowdfundEscrow {
+    fn as_ref(&self) -> &EscrowAccount {
+        &self.base
+    }
+}
+
+// Contribution receipt, one per (crowdfund, contributor); repeat deposits add up
+#[account]
+pub struct Contribution {
+    pub crowdfund: Pubkey,
+    pub contributor: Pubkey,
+    pub amount: u64,
+    pub bump: u8,
+}
+
+impl Contribution {
+    pub const LEN: usize = 8 + // discriminator
+                          32 + // crowdfund
+                          32 + // contributor
+                          8 + // amount
+                          1;  // bump
+}
+
+// Seller payout ledger per (seller, mint). Ledger escrows deposit into the
+// pool (this PDA's associated token account) at creation; releasing one
+// credits the seller's share in place, and the seller sweeps everything not
+// still escrowed with withdraw_balance.
+#[account]
+pub struct SellerBalance {
+    pub seller: Pubkey,
+    pub mint: Pubkey,
+    pub escrowed: u64, // pool funds still backing open ledger escrows
+    pub credited: u64, // lifetime proceeds credited by ledger releases
+    pub bump: u8,
+}
+
+impl SellerBalance {
     pub const LEN: usize = 8 + // discriminator
-                          1 + // is_initialized
-                          32 + // buyer
                           32 + // seller
-                          33 + // moderator (Option<Pubkey>)
-                          32 + // payer_address
+                          32 + // mint
+                          8 + // escrowed
+                          8 + // credited
+                          1;  // bump
+
+    // Pool funds the seller may withdraw
+    pub fn available(&self, pool_amount: u64) -> u64 {
+        pool_amount.saturating_sub(self.escrowed)
+    }
+}
+
+// Pull-based payout written by release_*_to_claims. SOL tickets hold the
+// lamports themselves; token tickets own an associated token account.
+#[account]
+pub struct ClaimTicket {
+    pub escrow: Pubkey,
+    pub owner: Pubkey,
+    pub mint: Option<Pubkey>, // None for SOL tickets
+    pub amount: u64,
+    pub payer_address: Pubkey, // receives the funds if the ticket expires unclaimed
+    pub rent_payer: Pubkey, // initiator who funded the ticket rent
+    pub expires_at: i64,
+    pub bump: u8,
+}
+
+impl ClaimTicket {
+    pub const LEN: usize = 8 + // discriminator
+                          32 + // escrow
+                          32 + // owner
+                          33 + // mint (Option<Pubkey>)
                           8 + // amount
-                          8 + // unlock_time
-                          1 + // required_signatures
-                          20 + // unique_id
+                          32 + // payer_address
+                          32 + // rent_payer
+                          8 + // expires_at
                           1;  // bump
 }
 
-impl TokenEscrow {
+// How an escrow was settled, as recorded on the parties' reputation accounts
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SettlementOutcome {
+    Released,  // paid out with the parties' approval
+    Refunded,  // everything went back to the payer
+    Disputed,  // split by a moderator ruling
+    TimedOut,  // seller-only release after unlock_time
+}
+
+impl SettlementOutcome {
+    // Approved payouts that all go back to the payer count as refunds
+    pub fn for_payout(base: &EscrowAccount, recipients: &[Option<Pubkey>]) -> Self {
+        if recipients.iter().flatten().all(|recipient| *recipient == base.payer_address) {
+            SettlementOutcome::Refunded
+        } else {
+            SettlementOutcome::Released
+        }
+    }
+}
+
+// Outcome of a signed release and whether the seller was among its signers
+#[derive(Clone, Copy, Debug)]
+pub struct ReleaseApproval {
+    pub outcome: SettlementOutcome,
+    pub seller_approved: bool,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default)]
+pub struct OutcomeStats {
+    pub count: u64,
+    pub sol_volume: u64, // lamports; token volume is kept per mint in Reputation::mint_volumes
+    pub counted: u64, // settlements of at least the escrow's counted_min_amount, so dust cannot build history
+}
+
+impl OutcomeStats {
+    pub const LEN: usize = 8 + 8 + 8;
+}
+
+// Raw token volume per outcome in one mint; units of different mints are never summed
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default)]
+pub struct MintVolume {
+    pub mint: Pubkey,
+    pub released: u64,
+    pub refunded: u64,
+    pub disputed: u64,
+    pub timed_out: u64,
+}
+
+impl MintVolume {
+    pub const LEN: usize = 32 + 4 * 8;
+
+    fn add(&mut self, outcome: SettlementOutcome, amount: u64) {
+        let volume = match outcome {
+            SettlementOutcome::Released => &mut self.released,
+            SettlementOutcome::Refunded => &mut self.refunded,
+            SettlementOutcome::Disputed => &mut self.disputed,
+            SettlementOutcome::TimedOut => &mut self.timed_out,
+        };
+        *volume = volume.saturating_add(amount);
+    }
+}
+
+// Settlement history of one wallet in one role, written only by this program.
+// Seeds are ["seller_reputation", wallet] or ["buyer_reputation", wallet].
+#[account]
+pub struct Reputation {
+    pub party: Pubkey,
+    pub released: OutcomeStats,
+    pub refunded: OutcomeStats,
+    pub disputed: OutcomeStats,
+    pub timed_out: OutcomeStats,
+    pub bump: u8,
+    pub mint_volumes: Vec<MintVolume>, // at most MAX_REPUTATION_MINTS, in the order first settled
+}
+
+impl Reputation {
     pub const LEN: usize = 8 + // discriminator
-                          1 + // is_initialized
+                          32 + // party
+                          4 * OutcomeStats::LEN + // per-outcome stats
+                          1 + // bump
+                          4 + MAX_REPUTATION_MINTS * MintVolume::LEN; // mint_volumes
+
+    // `mint` is None for SOL; multi-asset escrows pass a zero amount because their legs mix mints,
+    // so they are counted but never meet the minimum for `counted`. `counted_min_amount` is the
+    // escrow's committed threshold in the units of `amount`
+    pub fn record(
+        &mut self,
+        party: Pubkey,
+        bump: u8,
+        outcome: SettlementOutcome,
+        amount: u64,
+        mint: Option<Pubkey>,
+        counted_min_amount: u64,
+    ) {
+        // Set on first use, since the account is created with init_if_needed
+        self.party = party;
+        self.bump = bump;
+        let stats = match outcome {
+            SettlementOutcome::Released => &mut self.released,
+            SettlementOutcome::Refunded => &mut self.refunded,
+            SettlementOutcome::Disputed => &mut self.disputed,
+            SettlementOutcome::TimedOut => &mut self.timed_out,
+        };
+        stats.count = stats.count.saturating_add(1);
+        if amount >= counted_min_amount {
+            stats.counted = stats.counted.saturating_add(1);
+        }
+        match mint {
+            Some(mint) => self.add_mint_volume(mint, outcome, amount),
+            None => stats.sol_volume = stats.sol_volume.saturating_add(amount),
+        }
+    }
+
+    // Mints beyond the first MAX_REPUTATION_MINTS still count, but their volume is not kept
+    fn add_mint_volume(&mut self, mint: Pubkey, outcome: SettlementOutcome, amount: u64) {
+        let index = match self.mint_volumes.iter().position(|volume| volume.mint == mint) {
+            Some(index) => index,
+            None if self.mint_volumes.len() < MAX_REPUTATION_MINTS => {
+                self.mint_volumes.push(MintVolume { mint, ..Default::default() });
+                self.mint_volumes.len() - 1
+            }
+            None => return,
+        };
+        self.mint_volumes[index].add(outcome, amount);
+    }
+
+    // Completed escrows of at least the minimum amount that did not end in a refund
+    pub fn settled_count(&self) -> u64 {
+        self.released.counted
+            .saturating_add(self.disputed.counted)
+            .saturating_add(self.timed_out.counted)
+    }
+}
+
+// Instruction that settled an escrow
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReleasePath {
+    Signatures,     // release_sol / release_token
+    Claims,         // release_*_to_claims
+    Timeout,        // seller-only release once the timelock expired
+    Preimage,       // claim_with_preimage_*
+    HashlockRefund, // refund_htlc_*
+    StreamWithdraw, // last withdraw_vested_* of a stream
+    StreamCancel,   // cancel_stream_*
+    Dispute,        // resolve_dispute_*
+    MultiAsset,     // release_multi_asset
+    Crowdfund,      // release_crowdfund
+}
+
+impl ReleasePath {
+    // Signature releases that went through the timelock branch are timeouts
+    pub fn for_release(outcome: SettlementOutcome) -> Self {
+        if outcome == SettlementOutcome::TimedOut {
+            ReleasePath::Timeout
+        } else {
+            ReleasePath::Signatures
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub struct ReceiptPayout {
+    pub recipient: Pubkey,
+    pub amount: u64,
+}
+
+impl ReceiptPayout {
+    pub const LEN: usize = 32 + 8;
+}
+
+// Contents of a settlement receipt, stored in a SettlementReceipt account or
+// hashed into a ReceiptTree leaf
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct SettlementRecord {
+    pub escrow: Pubkey,
+    pub unique_id: [u8; 20],
+    pub buyer: Pubkey,
+    pub seller: Pubkey,
+    pub moderator: Option<Pubkey>,
+    pub payer_address: Pubkey,
+    pub mint: Option<Pubkey>, // None for SOL and multi-asset escrows
+    pub total: u64, // amount the escrow settled; 0 for multi-asset escrows
+    pub payouts: Vec<ReceiptPayout>, // empty for multi-asset escrows
+    pub path: ReleasePath,
+    pub outcome: SettlementOutcome,
+    pub slot: u64,
+    pub settled_at: i64,
+    pub seller_approved: bool, // the seller signed the settlement's approvals or submitted it themself
+}
+
+impl SettlementRecord {
+    pub const LEN: usize = 32 + // escrow
+                          20 + // unique_id
                           32 + // buyer
                           32 + // seller
                           33 + // moderator (Option<Pubkey>)
                           32 + // payer_address
+                          33 + // mint (Option<Pubkey>)
+                          8 + // total
+                          4 + MAX_PAYMENT_TARGETS * ReceiptPayout::LEN + // payouts
+                          1 + // path
+                          1 + // outcome
+                          8 + // slot
+                          8 + // settled_at
+                          1;  // seller_approved
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        escrow: Pubkey,
+        base: &EscrowAccount,
+        mint: Option<Pubkey>,
+        total: u64,
+        payouts: Vec<ReceiptPayout>,
+        path: ReleasePath,
+        outcome: SettlementOutcome,
+        clock: &Clock,
+    ) -> Self {
+        Self {
+            escrow,
+            unique_id: base.unique_id,
+            buyer: base.buyer,
+            seller: base.seller,
+            moderator: base.moderator,
+            payer_address: base.payer_address,
+            mint,
+            total,
+            payouts,
+            path,
+            outcome,
+            slot: clock.slot,
+            settled_at: clock.unix_timestamp,
+            seller_approved: false,
+        }
+    }
+
+    pub fn with_seller_approval(mut self, seller_approved: bool) -> Self {
+        self.seller_approved = seller_approved;
+        self
+    }
+
+    // Total paid to `recipient` by this settlement
+    pub fn paid_to(&self, recipient: &Pubkey) -> u64 {
+        self.payouts
+            .iter()
+            .filter(|payout| payout.recipient == *recipient)
+            .map(|payout| payout.amount)
+            .sum()
+    }
+}
+
+// Durable record of a settled escrow, written when the escrow PDA closes and
+// never modified afterwards. Seeds are ["receipt", escrow]; the escrow address
+// commits to the parties and unique_id, so a receipt also blocks reusing them.
+#[account]
+pub struct SettlementReceipt {
+    pub record: SettlementRecord,
+    pub bump: u8,
+}
+
+impl SettlementReceipt {
+    pub const LEN: usize = 8 + // discriminator
+                          SettlementRecord::LEN + // record
+                          1;  // bump
+
+    pub fn address(escrow: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(&[b"receipt", escrow.as_ref()], &crate::ID).0
+    }
+}
+
+// Escrow creation as recorded in a receipt tree
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct CreationRecord {
+    pub escrow: Pubkey,
+    pub unique_id: [u8; 20],
+    pub buyer: Pubkey,
+    pub seller: Pubkey,
+    pub moderator: Option<Pubkey>,
+    pub payer_address: Pubkey,
+    pub mint: Option<Pubkey>, // None for SOL
+    pub amount: u64,
+    pub slot: u64,
+    pub created_at: i64,
+}
+
+impl CreationRecord {
+    pub fn new(escrow: Pubkey, base: &EscrowAccount, mint: Option<Pubkey>, clock: &Clock) -> Self {
+        Self {
+            escrow,
+            unique_id: base.unique_id,
+            buyer: base.buyer,
+            seller: base.seller,
+            moderator: base.moderator,
+            payer_address: base.payer_address,
+            mint,
+            amount: base.amount,
+            slot: clock.slot,
+            created_at: clock.unix_timestamp,
+        }
+    }
+}
+
+// A receipt tree leaf is the hash of the Borsh encoding of this enum
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub enum ReceiptLeaf {
+    Created(CreationRecord),
+    Settled(SettlementRecord),
+}
+
+impl ReceiptLeaf {
+    pub fn hash(&self) -> Result<[u8; 32]> {
+        Ok(mobazha_receipt_tree::hash_leaf(&self.try_to_vec()?))
+    }
+}
+
+// Append-only Merkle tree of escrow receipts (see libs/receipt-tree). Only the
+// rightmost path and the recent roots are stored; the leaves themselves are
+// emitted in ReceiptLeafAppended events.
+#[account(zero_copy)]
+pub struct ReceiptTree {
+    pub authority: Pubkey, // wallet that created the tree
+    pub leaf_count: u64,
+    pub root_index: u64, // slot of the current root in `roots`
+    pub filled_subtrees: [[u8; 32]; RECEIPT_TREE_DEPTH],
+    pub roots: [[u8; 32]; RECEIPT_ROOT_HISTORY],
+}
+
+impl ReceiptTree {
+    pub const LEN: usize = 8 + std::mem::size_of::<ReceiptTree>();
+
+    pub fn initialize(&mut self, authority: Pubkey) {
+        self.authority = authority;
+        self.leaf_count = 0;
+        self.root_index = 0;
+        self.roots[0] = mobazha_receipt_tree::empty_root(RECEIPT_TREE_DEPTH);
+    }
+
+    pub fn current_root(&self) -> [u8; 32] {
+        self.roots[self.root_index as usize]
+    }
+
+    // Appends the leaf and returns its index
+    pub fn append(&mut self, leaf: [u8; 32]) -> Result<u64> {
+        let index = self.leaf_count;
+        let root = mobazha_receipt_tree::append(&mut self.filled_subtrees, index, leaf)
+            .ok_or(EscrowError::ReceiptTreeFull)?;
+        self.root_index = (self.root_index + 1) % RECEIPT_ROOT_HISTORY as u64;
+        self.roots[self.root_index as usize] = root;
+        self.leaf_count += 1;
+        Ok(index)
+    }
+
+    // Anyone can point new escrows at a tree, so settlement checks this and falls
+    // back to a receipt account rather than failing on a full tree
+    pub fn is_full(&self) -> bool {
+        self.leaf_count >= mobazha_receipt_tree::capacity(RECEIPT_TREE_DEPTH)
+    }
+
+    // Only roots that have actually been produced count; unused history slots are zero
+    pub fn has_root(&self, root: &[u8; 32]) -> bool {
+        let produced = (self.leaf_count + 1).min(RECEIPT_ROOT_HISTORY as u64) as usize;
+        (0..produced).any(|age| {
+            let slot = (self.root_index as usize + RECEIPT_ROOT_HISTORY - age) % RECEIPT_ROOT_HISTORY;
+            self.roots[slot] == *root
+        })
+    }
+}
+
+// Plain order escrow (SOL or one SPL mint) in a fixed layout that release reads
+// in place through AccountLoader, skipping the Borsh round trip of SolEscrow and
+// TokenEscrow. It supports a moderator, the timelock, the moderator fee cap and
+// compressed receipts; hashlocks, streams, oracles and panels need the regular
+// escrows. Absent keys are stored as Pubkey::default(), and so is `mint` for SOL.
+#[account(zero_copy)]
+pub struct FixedEscrow {
+    pub unlock_time: i64,
+    pub amount: u64,
+    pub nonce: u64,
+    pub moderator_fee_fixed: u64,
+    pub moderator_fee_bps_max: u16,
+    pub buyer: Pubkey,
+    pub seller: Pubkey,
+    pub moderator: Pubkey,
+    pub payer_address: Pubkey,
+    pub mint: Pubkey,
+    pub receipt_tree: Pubkey,
+    pub unique_id: [u8; 20],
+    pub version: u8,
+    pub required_signatures: u8,
+    pub bump: u8,
+    pub counted_min_amount: [u8; 8], // little-endian u64, carved from reserved without changing alignment
+    pub reserved: [u8; FIXED_ESCROW_RESERVED_BYTES],
+}
+
+impl FixedEscrow {
+    pub const LEN: usize = 8 + std::mem::size_of::<FixedEscrow>();
+
+    // Copy a validated escrow in; rejects the modes this layout has no room for
+    pub fn initialize(&mut self, base: &EscrowAccount, mint: Option<Pubkey>) -> Result<()> {
+        require!(
+            base.hashlock.is_none() &&
+            base.stream.is_none() &&
+            base.oracle.is_none() &&
+            base.panel.is_none() &&
+            base.release_hook.is_none(),
+            EscrowError::UnsupportedByFixedEscrow
+        );
+        self.unlock_time = base.unlock_time;
+        self.amount = base.amount;
+        self.nonce = base.nonce;
+        self.moderator_fee_fixed = base.moderator_fee_fixed;
+        self.moderator_fee_bps_max = base.moderator_fee_bps_max;
+        self.buyer = base.buyer;
+        self.seller = base.seller;
+        self.moderator = base.moderator.unwrap_or_default();
+        self.payer_address = base.payer_address;
+        self.mint = mint.unwrap_or_default();
+        self.receipt_tree = base.receipt_tree.unwrap_or_default();
+        self.unique_id = base.unique_id;
+        self.version = ESCROW_LAYOUT_VERSION;
+        self.required_signatures = base.required_signatures;
+        self.bump = base.bump;
+        self.counted_min_amount = base.counted_min_amount.to_le_bytes();
+        Ok(())
+    }
+
+    // Stack copy for the release checks and receipts shared with the other escrow kinds
+    pub fn to_base(&self) -> EscrowAccount {
+        let mut base = EscrowAccount::new(
+            self.buyer,
+            self.seller,
+            self.moderator(),
+            self.payer_address,
+            self.required_signatures,
+            self.unlock_time,
+            self.unique_id,
+            self.amount,
+            self.bump,
+        );
+        base.nonce = self.nonce;
+        base.moderator_fee_bps_max = self.moderator_fee_bps_max;
+        base.moderator_fee_fixed = self.moderator_fee_fixed;
+        base.receipt_tree = Self::optional(self.receipt_tree);
+        base.counted_min_amount = u64::from_le_bytes(self.counted_min_amount);
+        base
+    }
+
+    pub fn moderator(&self) -> Option<Pubkey> {
+        Self::optional(self.moderator)
+    }
+
+    // None for SOL escrows
+    pub fn mint(&self) -> Option<Pubkey> {
+        Self::optional(self.mint)
+    }
+
+    pub fn is_party(&self, key: &Pubkey) -> bool {
+        *key == self.buyer ||
+        *key == self.seller ||
+        self.moderator() == Some(*key)
+    }
+
+    fn optional(key: Pubkey) -> Option<Pubkey> {
+        (key != Pubkey::default()).then_some(key)
+    }
+}
+
+// Prepaid subscription escrow. The buyer's balance sits in the ATA of this PDA
+// and the seller claims amount_per_period for every period that has elapsed.
+#[account]
+pub struct RecurringEscrow {
+    pub buyer: Pubkey,
+    pub seller: Pubkey,
+    pub mint: Pubkey,
+    pub unique_id: [u8; 20],
+    pub amount_per_period: u64,
+    pub period_seconds: i64,
+    pub next_claim_time: i64, // end of the oldest unclaimed period
+    pub periods_owed: u64, // periods that ended before a pause and are still unclaimed
+    pub paused_at: Option<i64>, // no periods accrue while paused
+    pub bump: u8,
+}
+
+impl RecurringEscrow {
+    pub const LEN: usize = 8 + // discriminator
+                          32 + // buyer
+                          32 + // seller
                           32 + // mint
-                          8 + // amount
-                          8 + // unlock_time
-                          1 + // required_signatures
                           20 + // unique_id
+                          8 + // amount_per_period
+                          8 + // period_seconds
+                          8 + // next_claim_time
+                          8 + // periods_owed
+                          9 + // paused_at (Option<i64>)
                           1;  // bump
+
+    fn periods_elapsed(&self, current_time: i64) -> u64 {
+        let effective_time = self.paused_at.unwrap_or(current_time);
+        if effective_time < self.next_claim_time {
+            return 0;
+        }
+        ((effective_time - self.next_claim_time) / self.period_seconds) as u64 + 1
+    }
+
+    // Number of whole periods the seller can claim, capped by the prepaid balance
+    pub fn periods_due(&self, balance: u64, current_time: i64) -> u64 {
+        (self.periods_owed + self.periods_elapsed(current_time)).min(balance / self.amount_per_period)
+    }
+
+    pub fn consume_periods(&mut self, periods: u64) {
+        let from_owed = periods.min(self.periods_owed);
+        self.periods_owed -= from_owed;
+        self.next_claim_time += (periods - from_owed) as i64 * self.period_seconds;
+    }
+
+    // Periods finished before the pause stay claimable; the one in progress is pushed back by the pause length
+    pub fn resume(&mut self, current_time: i64) {
+        let Some(paused_at) = self.paused_at else {
+            return;
+        };
+        let elapsed = self.periods_elapsed(current_time);
+        self.periods_owed += elapsed;
+        self.next_claim_time += elapsed as i64 * self.period_seconds + (current_time - paused_at);
+        self.paused_at = None;
+    }
+}
+
+impl SolEscrow {
+    pub const LEN: usize = 8 + // discriminator
+                          EscrowAccount::LEN; // base
+}
+
+impl TokenEscrow {
+    pub const LEN: usize = 8 + // discriminator
+                          EscrowAccount::LEN + // base
+                          32; // mint
+
+    // Wrapped SOL escrows take and pay out plain lamports
+    pub fn is_native(&self) -> bool {
+        self.mint == anchor_spl::token::spl_token::native_mint::ID
+    }
 }
 
 impl Default for SolEscrow {
     fn default() -> Self {
         Self {
             base: EscrowAccount {
+                version: ESCROW_LAYOUT_VERSION,
                 is_initialized: false,
                 buyer: Pubkey::default(),
                 seller: Pubkey::default(),
@@ -74,6 +1035,20 @@ impl Default for SolEscrow {
                 unique_id: [0; 20],
                 amount: 0,
                 bump: 0,
+                nonce: 0,
+                hashlock: None,
+                stream: None,
+                oracle: None,
+                delivered_at: None,
+                moderator_fee_bps_max: 0,
+                moderator_fee_fixed: 0,
+                panel: None,
+                receipt_tree: None,
+                release_hook: None,
+                ledger: false,
+                require_registered_moderator: false,
+                counted_min_amount: 0,
+                reserved: [0; ESCROW_RESERVED_BYTES],
             },
         }
     }
@@ -83,6 +1058,7 @@ impl Default for TokenEscrow {
     fn default() -> Self {
         Self {
             base: EscrowAccount {
+                version: ESCROW_LAYOUT_VERSION,
                 is_initialized: false,
                 buyer: Pubkey::default(),
                 seller: Pubkey::default(),
@@ -93,6 +1069,20 @@ impl Default for TokenEscrow {
                 unique_id: [0; 20],
                 amount: 0,
                 bump: 0,
+                nonce: 0,
+                hashlock: None,
+                stream: None,
+                oracle: None,
+                delivered_at: None,
+                moderator_fee_bps_max: 0,
+                moderator_fee_fixed: 0,
+                panel: None,
+                receipt_tree: None,
+                release_hook: None,
+                ledger: false,
+                require_registered_moderator: false,
+                counted_min_amount: 0,
+                reserved: [0; ESCROW_RESERVED_BYTES],
             },
             mint: Pubkey::default()
         }
@@ -111,7 +1101,14 @@ impl AsRef<EscrowAccount> for TokenEscrow {
     }
 }
 
+impl AsRef<EscrowAccount> for EscrowAccount {
+    fn as_ref(&self) -> &EscrowAccount {
+        self
+    }
+}
+
 impl EscrowAccount {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         buyer: Pubkey,
         seller: Pubkey,
@@ -124,6 +1121,7 @@ impl EscrowAccount {
         bump: u8,
     ) -> Self {
         Self {
+            version: ESCROW_LAYOUT_VERSION,
             is_initialized: true,
             buyer,
             seller,
@@ -134,11 +1132,172 @@ impl EscrowAccount {
             unique_id,
             amount,
             bump,
+            nonce: 0,
+            hashlock: None,
+            stream: None,
+            oracle: None,
+            delivered_at: None,
+            moderator_fee_bps_max: 0,
+            moderator_fee_fixed: 0,
+            panel: None,
+            receipt_tree: None,
+            release_hook: None,
+            ledger: false,
+            require_registered_moderator: false,
+            counted_min_amount: 0,
+            reserved: [0; ESCROW_RESERVED_BYTES],
+        }
+    }
+
+    // Escrows that committed no threshold fall back to the fixed defaults
+    pub fn counted_threshold(&self, is_token: bool) -> u64 {
+        match self.counted_min_amount {
+            0 if is_token => MIN_COUNTED_SETTLEMENT_TOKEN_UNITS,
+            0 => MIN_COUNTED_SETTLEMENT_LAMPORTS,
+            amount => amount,
+        }
+    }
+
+    pub fn apply_options(&mut self, options: &EscrowOptions) -> Result<()> {
+        require!(
+            options.hashlock.is_none() || options.stream.is_none(),
+            EscrowError::InvalidOperation
+        );
+        
+        self.hashlock = options.hashlock;
+        
+        if let Some(params) = options.stream {
+            let cliff_time = params.cliff_time.unwrap_or(params.start_time);
+            require!(
+                params.start_time < params.end_time &&
+                params.start_time <= cliff_time &&
+                cliff_time <= params.end_time,
+                EscrowError::InvalidStreamSchedule
+            );
+            self.stream = Some(StreamSchedule {
+                start_time: params.start_time,
+                cliff_time,
+                end_time: params.end_time,
+                withdrawn: 0,
+            });
+        }
+        
+        // The oracle is an extra approval role and must not double as a party
+        if let Some(oracle) = options.oracle {
+            require!(!self.is_party(&oracle), EscrowError::InvalidOracle);
+        }
+        self.oracle = options.oracle;
+        
+        require!(
+            options.moderator_fee_bps_max as u64 <= BPS_DENOMINATOR,
+            EscrowError::InvalidPaymentParameters
+        );
+        self.moderator_fee_bps_max = options.moderator_fee_bps_max;
+        self.moderator_fee_fixed = options.moderator_fee_fixed;
+        
+        if let Some(panel) = &options.panel {
+            self.validate_panel(panel)?;
         }
+        self.panel = options.panel.clone();
+        self.receipt_tree = options.receipt_tree;
+        self.release_hook = options.release_hook;
+        self.require_registered_moderator = options.require_registered_moderator;
+        
+        Ok(())
     }
     
+    // HTLC and streaming escrows never fall back to the seller-only timeout release
+    pub fn check_timeout_release(&self) -> Result<()> {
+        require!(self.hashlock.is_none(), EscrowError::HashlockedEscrow);
+        require!(self.stream.is_none(), EscrowError::StreamingEscrow);
+        Ok(())
+    }
+
+    // Panel members are distinct and none of them holds another role
+    fn validate_panel(&self, panel: &ModeratorPanel) -> Result<()> {
+        require!(
+            !panel.members.is_empty() && panel.members.len() <= MAX_PANEL_MEMBERS,
+            EscrowError::InvalidModeratorPanel
+        );
+        require!(
+            panel.threshold > 0 && panel.threshold as usize <= panel.members.len(),
+            EscrowError::InvalidModeratorPanel
+        );
+        for (i, member) in panel.members.iter().enumerate() {
+            require!(
+                !self.is_party(member) &&
+                self.oracle.as_ref() != Some(member) &&
+                !panel.members[..i].contains(member),
+                EscrowError::InvalidModeratorPanel
+            );
+        }
+        Ok(())
+    }
+
+    // Anyone who may be paid an arbitration fee: the moderator or a panel member
+    pub fn is_arbitrator(&self, key: &Pubkey) -> bool {
+        self.moderator.as_ref() == Some(key) ||
+        self.panel.as_ref().is_some_and(|panel| panel.is_member(key))
+    }
+
+    pub fn is_party(&self, key: &Pubkey) -> bool {
+        *key == self.buyer ||
+        *key == self.seller ||
+        self.moderator.as_ref() == Some(key)
+    }
+    
+    // Anyone whose signature or attestation can count toward a release approval
+    pub fn is_approver(&self, key: &Pubkey) -> bool {
+        self.is_party(key) ||
+        self.oracle.as_ref() == Some(key) ||
+        self.panel.as_ref().is_some_and(|panel| panel.is_member(key))
+    }
+    
+    // Most the moderator may be paid out of `amount`
+    pub fn max_moderator_fee(&self, amount: u64) -> u64 {
+        let bps_fee = (amount as u128 * self.moderator_fee_bps_max as u128 / BPS_DENOMINATOR as u128) as u64;
+        bps_fee.saturating_add(self.moderator_fee_fixed)
+    }
+
+    // Reject releases that pay the arbitrators more than the cap fixed at creation
+    pub fn check_moderator_payout(&self, recipients: &[Option<Pubkey>], amounts: &[u64]) -> Result<()> {
+        let paid = recipients
+            .iter()
+            .zip(amounts.iter())
+            .filter(|(recipient, _)| recipient.is_some_and(|key| self.is_arbitrator(&key)))
+            .try_fold(0u64, |acc, (_, amount)| acc.checked_add(*amount))
+            .ok_or(EscrowError::InvalidAmount)?;
+        require!(paid <= self.max_moderator_fee(self.amount), EscrowError::ModeratorFeeTooHigh);
+        Ok(())
+    }
+
+    // Distinct party signers, plus one approval for a panel quorum and one
+    // once the oracle has attested delivery. Delivery only vouches for paying
+    // the seller, so it counts only when the seller is the sole recipient.
+    pub fn count_approvals<'a>(
+        &self,
+        signers: &[Pubkey],
+        recipients: impl IntoIterator<Item = &'a Pubkey>,
+    ) -> usize {
+        let mut signers = signers.to_vec();
+        signers.sort_unstable();
+        signers.dedup();
+        let party_signers = signers.iter().filter(|signer| self.is_party(signer)).count();
+        party_signers +
+            if self.panel.as_ref().is_some_and(|panel| panel.has_quorum(&signers)) { 1 } else { 0 } +
+            if self.delivered_at.is_some() && self.pays_only_seller(recipients) { 1 } else { 0 }
+    }
+
+    fn pays_only_seller<'a>(&self, recipients: impl IntoIterator<Item = &'a Pubkey>) -> bool {
+        let mut recipients = recipients.into_iter().peekable();
+        recipients.peek().is_some() && recipients.all(|recipient| *recipient == self.seller)
+    }
+
     pub fn validate_required_signatures(&self) -> Result<()> {
-        let max_possible = 2 + if self.moderator.is_some() { 1 } else { 0 };
+        let max_possible = 2 +
+            if self.moderator.is_some() { 1 } else { 0 } +
+            if self.panel.is_some() { 1 } else { 0 } +
+            if self.oracle.is_some() { 1 } else { 0 };
         require!(
             self.required_signatures > 0,
             EscrowError::InvalidRequiredSignatures
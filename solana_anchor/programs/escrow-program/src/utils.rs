@@ -1,13 +1,16 @@
 use anchor_lang::prelude::*;
-use crate::{error::*, state::{MAX_PAYMENT_TARGETS, EscrowAccount}, ed25519};
+use anchor_lang::solana_program::{instruction::Instruction, program::invoke_signed};
+use crate::{error::*, events::{ReceiptLeafAppended, ReleaseHookSkipped}, state::{MAX_PAYMENT_TARGETS, MIN_COUNTED_SETTLEMENT_LAMPORTS, HOOK_AUTHORITY_SEED, EscrowAccount, AssetPayout, ReleaseApproval, CreationRecord, DisputeRuling, ReceiptLeaf, ReceiptPayout, ReceiptTree, Reputation, SettlementOutcome, SettlementReceipt, SettlementRecord}, ed25519};
+use anchor_spl::token::{spl_token::native_mint, Mint};
 use chrono::{TimeZone, Utc};
+use mobazha_moderator_registry::state::{ModeratorProfile, RegistryConfig};
 
 pub fn verify_payment_amounts(
     payment_amounts: &[u64],
     escrow_account: &EscrowAccount,
 ) -> Result<()> {
     require!(payment_amounts.len() <= MAX_PAYMENT_TARGETS, EscrowError::TooManyRecipients);
-    require!(payment_amounts.len() > 0, EscrowError::InvalidPaymentParameters);
+    require!(!payment_amounts.is_empty(), EscrowError::InvalidPaymentParameters);
     
     for amount in payment_amounts {
         require!(*amount > 0, EscrowError::ZeroPaymentAmount);
@@ -19,9 +22,20 @@ pub fn verify_payment_amounts(
     Ok(())
 }
 
-pub fn construct_message(unique_id: &[u8; 20], recipients: &[Option<Pubkey>], amounts: &[u64]) -> Vec<u8> {
+/// Release approval message: unique_id || nonce || valid_until || (recipient || amount)*
+///
+/// `valid_until` of 0 means the approval never expires.
+pub fn construct_message(
+    unique_id: &[u8; 20],
+    nonce: u64,
+    valid_until: i64,
+    recipients: &[Option<Pubkey>],
+    amounts: &[u64],
+) -> Vec<u8> {
     let mut message = Vec::new();
     message.extend_from_slice(unique_id);
+    message.extend_from_slice(&nonce.to_le_bytes());
+    message.extend_from_slice(&valid_until.to_le_bytes());
     
     let len = std::cmp::min(amounts.len(), recipients.len());
     
@@ -37,25 +51,130 @@ pub fn construct_message(unique_id: &[u8; 20], recipients: &[Option<Pubkey>], am
     message
 }
 
+/// Multi-asset release message: unique_id || nonce || valid_until || (mint || recipient || amount)*
+pub fn construct_multi_asset_message(
+    unique_id: &[u8; 20],
+    nonce: u64,
+    valid_until: i64,
+    payouts: &[AssetPayout],
+) -> Vec<u8> {
+    let mut message = Vec::new();
+    message.extend_from_slice(unique_id);
+    message.extend_from_slice(&nonce.to_le_bytes());
+    message.extend_from_slice(&valid_until.to_le_bytes());
+    
+    for payout in payouts {
+        message.extend_from_slice(payout.mint.as_ref());
+        message.extend_from_slice(payout.recipient.as_ref());
+        message.extend_from_slice(&payout.amount.to_le_bytes());
+    }
+    
+    message
+}
+
+/// Dispute ruling message: unique_id || nonce || valid_until || buyer_bps(u16 le) || moderator_fee_bps(u16 le)
+pub fn construct_ruling_message(
+    unique_id: &[u8; 20],
+    nonce: u64,
+    valid_until: i64,
+    ruling: &DisputeRuling,
+) -> Vec<u8> {
+    let mut message = Vec::with_capacity(20 + 8 + 8 + 2 + 2);
+    message.extend_from_slice(unique_id);
+    message.extend_from_slice(&nonce.to_le_bytes());
+    message.extend_from_slice(&valid_until.to_le_bytes());
+    message.extend_from_slice(&ruling.buyer_bps.to_le_bytes());
+    message.extend_from_slice(&ruling.moderator_fee_bps.to_le_bytes());
+    message
+}
+
+/// Delivery oracle attestation message: escrow || delivered(u8) || timestamp(i64 le)
+pub fn construct_delivery_message(escrow: &Pubkey, delivered: bool, timestamp: i64) -> Vec<u8> {
+    let mut message = Vec::with_capacity(32 + 1 + 8);
+    message.extend_from_slice(escrow.as_ref());
+    message.push(delivered as u8);
+    message.extend_from_slice(&timestamp.to_le_bytes());
+    message
+}
+
+// Registry-checked escrows need the moderator's profile in good standing, which must accept the escrow mint.
+// Standing is recomputed against the registry config because the cached `active` flag is only
+// refreshed when the profile itself changes, not when governance raises min_stake
+pub fn verify_registered_moderator(
+    moderator: Option<Pubkey>,
+    profile: Option<&ModeratorProfile>,
+    config: Option<&RegistryConfig>,
+    mint: Option<&Pubkey>,
+) -> Result<()> {
+    let moderator = moderator.ok_or(EscrowError::ModeratorNotRegistered)?;
+    // Profiles are only created by the registry, signed by the moderator themself,
+    // so matching the stored key is enough to tie the profile to this moderator
+    let profile = profile.ok_or(EscrowError::ModeratorNotRegistered)?;
+    let config = config.ok_or(EscrowError::ModeratorNotRegistered)?;
+    require!(
+        profile.moderator == moderator && profile.is_active(config),
+        EscrowError::ModeratorNotRegistered
+    );
+    if let Some(mint) = mint {
+        require!(profile.supports_mint(mint), EscrowError::ModeratorNotRegistered);
+    }
+    Ok(())
+}
+
+/// Smallest settlement that counts toward seller history, committed at creation
+/// in the escrow's own units: 0.01 SOL for SOL and wrapped SOL, one whole token
+/// of any other mint
+pub fn counted_settlement_minimum(mint: Option<&Account<Mint>>) -> u64 {
+    match mint {
+        Some(mint) if mint.key() != native_mint::ID => 10u64.saturating_pow(mint.decimals as u32),
+        _ => MIN_COUNTED_SETTLEMENT_LAMPORTS,
+    }
+}
+
+// Gate escrows on the seller's settlement history; a missing reputation account means no history
+pub fn verify_seller_history(seller_reputation: Option<&Reputation>, min_settlements: u64) -> Result<()> {
+    let settled = seller_reputation.map_or(0, |reputation| reputation.settled_count());
+    require!(settled >= min_settlements, EscrowError::InsufficientSellerHistory);
+    Ok(())
+}
+
+pub fn verify_valid_until(valid_until: i64, current_time: i64) -> Result<()> {
+    require!(
+        valid_until == 0 || current_time <= valid_until,
+        EscrowError::SignatureExpired
+    );
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn verify_signatures_with_timelock<T>(
     escrow_account: &T,
     signatures: &[Vec<u8>],
     payment_amounts: &[u64],
     recipients: &[Option<Pubkey>],
+    valid_until: i64,
     current_time: i64,
     instructions_sysvar: &AccountInfo,
     required_signatures: u8,
-) -> Result<()>
+) -> Result<ReleaseApproval>
 where
     T: AsRef<EscrowAccount>,
 {
     let base = escrow_account.as_ref();
     
-    let message = construct_message(&base.unique_id, recipients, payment_amounts);
+    verify_valid_until(valid_until, current_time)?;
+    base.check_moderator_payout(recipients, payment_amounts)?;
+    
+    let message = construct_message(
+        &base.unique_id,
+        base.nonce,
+        valid_until,
+        recipients,
+        payment_amounts,
+    );
     
     let time_expired = current_time >= base.unlock_time;
     
-    let buyer = base.buyer;
     let seller = base.seller;
 
     let all_signers = verify_ed25519_instructions(
@@ -63,6 +182,7 @@ where
         signatures,
         &message,
     )?;
+    let seller_approved = all_signers.contains(&seller);
     
     if !time_expired {
         // Check if this is a seller refund case
@@ -72,30 +192,21 @@ where
                               all_signers.contains(&seller);
         
         if !is_seller_refund {
-            // Filter valid signers
-            let valid_signers: Vec<_> = all_signers.into_iter()
-                .filter(|signer| {
-                    *signer == buyer || 
-                    *signer == seller || 
-                    (base.moderator.is_some() && *signer == base.moderator.unwrap())
-                })
-                .collect();
-
-            // Check the number of valid signatures
+            // Check the number of valid approvals (party signatures plus oracle delivery)
             require!(
-                valid_signers.len() >= required_signatures as usize,
+                base.count_approvals(&all_signers, recipients.iter().flatten()) >= required_signatures as usize,
                 EscrowError::InsufficientSignatures
             );
         }
+        Ok(ReleaseApproval { outcome: SettlementOutcome::for_payout(base, recipients), seller_approved })
     } else {
+        // Hash-time-locked and streaming escrows have their own post-deadline rules
+        base.check_timeout_release()?;
+        
         // Timelock has expired - only seller signature is required
-        require!(
-            all_signers.contains(&seller),
-            EscrowError::InvalidSigner
-        );
+        require!(seller_approved, EscrowError::InvalidSigner);
+        Ok(ReleaseApproval { outcome: SettlementOutcome::TimedOut, seller_approved })
     }
-    
-    Ok(())
 }
 
 pub fn verify_ed25519_instructions(
@@ -135,6 +246,78 @@ pub fn verify_ed25519_instructions(
     Ok(valid_signers)
 }
 
+/// Create a program-owned PDA the same way Anchor's `init` does, including the
+/// case where someone has already sent lamports to the address.
+pub fn create_pda_account<'info>(
+    payer: &AccountInfo<'info>,
+    target: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    space: usize,
+    owner: &Pubkey,
+    signer_seeds: &[&[u8]],
+) -> Result<()> {
+    require!(
+        target.data_is_empty() && *target.owner == System::id(),
+        EscrowError::EscrowAlreadyExists
+    );
+    
+    let required_lamports = Rent::get()?.minimum_balance(space);
+    let current_lamports = target.lamports();
+    
+    if current_lamports == 0 {
+        anchor_lang::system_program::create_account(
+            CpiContext::new_with_signer(
+                system_program.clone(),
+                anchor_lang::system_program::CreateAccount {
+                    from: payer.clone(),
+                    to: target.clone(),
+                },
+                &[signer_seeds],
+            ),
+            required_lamports,
+            space as u64,
+            owner,
+        )?;
+        return Ok(());
+    }
+    
+    let top_up = required_lamports.saturating_sub(current_lamports);
+    if top_up > 0 {
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                system_program.clone(),
+                anchor_lang::system_program::Transfer {
+                    from: payer.clone(),
+                    to: target.clone(),
+                },
+            ),
+            top_up,
+        )?;
+    }
+    
+    anchor_lang::system_program::allocate(
+        CpiContext::new_with_signer(
+            system_program.clone(),
+            anchor_lang::system_program::Allocate {
+                account_to_allocate: target.clone(),
+            },
+            &[signer_seeds],
+        ),
+        space as u64,
+    )?;
+    
+    anchor_lang::system_program::assign(
+        CpiContext::new_with_signer(
+            system_program.clone(),
+            anchor_lang::system_program::Assign {
+                account_to_assign: target.clone(),
+            },
+            &[signer_seeds],
+        ),
+        owner,
+    )
+}
+
 pub fn close_escrow_and_return_rent<'info>(
     escrow_account: &AccountInfo<'info>,
     buyer: &AccountInfo<'info>,
@@ -145,15 +328,55 @@ pub fn close_escrow_and_return_rent<'info>(
     Ok(())
 }
 
+pub fn transfer_escrowed_lamports<'info>(
+    escrow_info: &AccountInfo<'info>,
+    recipient: &AccountInfo<'info>,
+    amount: u64,
+) -> Result<()> {
+    require!(escrow_info.lamports() >= amount, EscrowError::InsufficientFunds);
+    **escrow_info.try_borrow_mut_lamports()? -= amount;
+    **recipient.try_borrow_mut_lamports()? += amount;
+    Ok(())
+}
+
+/// Pay lamports held by a program-owned escrow to a wallet. An empty recipient
+/// is first funded with the rent-exempt minimum by the initiator, so amounts
+/// below that minimum can still be paid to a fresh address.
+pub fn pay_lamports_from_escrow<'info>(
+    escrow_info: &AccountInfo<'info>,
+    recipient: &AccountInfo<'info>,
+    amount: u64,
+    initiator: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    if recipient.lamports() == 0 {
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                system_program.clone(),
+                anchor_lang::system_program::Transfer {
+                    from: initiator.clone(),
+                    to: recipient.clone(),
+                },
+            ),
+            Rent::get()?.minimum_balance(0),
+        )?;
+    }
+
+    msg!("Transfer {} lamports to account {}", amount, recipient.key());
+    transfer_escrowed_lamports(escrow_info, recipient, amount)
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn process_release<T>(
     escrow_account: &T,
     signatures: &[Vec<u8>],
     payment_amounts: &[u64],
     recipients: &[Option<Pubkey>],
+    valid_until: i64,
     current_time: i64,
     instructions_sysvar: &AccountInfo,
     transfer_function: impl FnOnce() -> Result<()>,
-) -> Result<()> 
+) -> Result<ReleaseApproval> 
 where 
     T: AsRef<EscrowAccount>,
 {
@@ -161,17 +384,195 @@ where
     
     verify_payment_amounts(payment_amounts, base)?;
     
-    verify_signatures_with_timelock(
+    let approval = verify_signatures_with_timelock(
         escrow_account,
         signatures,
         payment_amounts,
         recipients,
+        valid_until,
         current_time,
         instructions_sysvar,
         base.required_signatures,
     )?;
     
-    transfer_function()
+    transfer_function()?;
+    Ok(approval)
+}
+
+/// Record how an escrow ended on the seller's and buyer's reputation accounts
+#[allow(clippy::too_many_arguments)]
+pub fn record_settlement(
+    base: &EscrowAccount,
+    seller_reputation: &mut Reputation,
+    seller_bump: u8,
+    buyer_reputation: &mut Reputation,
+    buyer_bump: u8,
+    outcome: SettlementOutcome,
+    amount: u64,
+    mint: Option<Pubkey>,
+) {
+    let counted_min_amount = base.counted_threshold(mint.is_some());
+    seller_reputation.record(base.seller, seller_bump, outcome, amount, mint, counted_min_amount);
+    buyer_reputation.record(base.buyer, buyer_bump, outcome, amount, mint, counted_min_amount);
+}
+
+/// Store a settlement record: in the receipt account, or as a leaf of the
+/// escrow's receipt tree when it was created with compressed receipts. If that
+/// tree has filled up since creation, the record goes to the receipt account
+/// instead, so a full tree can never lock the escrow's funds.
+pub fn write_receipt<'info>(
+    base: &EscrowAccount,
+    record: SettlementRecord,
+    receipt: Option<&mut SettlementReceipt>,
+    bump: Option<u8>,
+    receipt_tree: Option<&AccountLoader<'info, ReceiptTree>>,
+) -> Result<()> {
+    if let Some(tree_key) = base.receipt_tree {
+        let tree = receipt_tree.ok_or(EscrowError::InvalidReceiptAccount)?;
+        require_keys_eq!(tree.key(), tree_key, EscrowError::InvalidReceiptAccount);
+        if !tree.load()?.is_full() {
+            require!(receipt.is_none(), EscrowError::InvalidReceiptAccount);
+            return append_receipt_leaf(tree, ReceiptLeaf::Settled(record));
+        }
+    }
+
+    let receipt = receipt.ok_or(EscrowError::InvalidReceiptAccount)?;
+    receipt.record = record;
+    receipt.bump = bump.ok_or(EscrowError::InvalidReceiptAccount)?;
+    Ok(())
+}
+
+/// Record a closed escrow on both reputation accounts, notify its release hook
+/// and store its receipt. `hook_accounts` are the instruction's trailing
+/// accounts, see [`call_release_hook`].
+#[allow(clippy::too_many_arguments)]
+pub fn finish_settlement<'info, 'h>(
+    base: &EscrowAccount,
+    record: SettlementRecord,
+    seller_reputation: &mut Reputation,
+    seller_bump: u8,
+    buyer_reputation: &mut Reputation,
+    buyer_bump: u8,
+    receipt: Option<&mut SettlementReceipt>,
+    receipt_bump: Option<u8>,
+    receipt_tree: Option<&AccountLoader<'info, ReceiptTree>>,
+    hook_accounts: &[AccountInfo<'h>],
+) -> Result<()> {
+    call_release_hook(base, &record, hook_accounts)?;
+    record_settlement(
+        base,
+        seller_reputation,
+        seller_bump,
+        buyer_reputation,
+        buyer_bump,
+        record.outcome,
+        record.total,
+        record.mint,
+    );
+    write_receipt(base, record, receipt, receipt_bump, receipt_tree)
+}
+
+/// Call `on_escrow_settled(record)` on the escrow's release hook program.
+///
+/// `hook_accounts` are `[hook_program, hook_authority, forwarded...]`. The hook
+/// authority PDA is the only signer the hook sees; forwarded accounts keep their
+/// writable flag but never sign, so the hook cannot move funds of the parties.
+/// A failing CPI aborts the whole transaction, so with `initiator_may_skip` the
+/// hook is skipped whenever the settlement's initiator leaves its accounts out.
+/// That is the initiator's choice alone, so such a hook is only a notification;
+/// a hook that must always run has to be fixed before the escrow can settle.
+pub fn call_release_hook<'h>(
+    base: &EscrowAccount,
+    record: &SettlementRecord,
+    hook_accounts: &[AccountInfo<'h>],
+) -> Result<()> {
+    let Some(hook) = base.release_hook else {
+        return Ok(());
+    };
+    let [hook_program, hook_authority, forwarded @ ..] = hook_accounts else {
+        require!(hook.initiator_may_skip, EscrowError::ReleaseHookMissing);
+        emit!(ReleaseHookSkipped {
+            escrow: record.escrow,
+            program: hook.program,
+        });
+        return Ok(());
+    };
+
+    require_keys_eq!(hook_program.key(), hook.program, EscrowError::InvalidReleaseHook);
+    require!(hook_program.executable, EscrowError::InvalidReleaseHook);
+    let (authority, bump) = Pubkey::find_program_address(&[HOOK_AUTHORITY_SEED], &crate::ID);
+    require_keys_eq!(hook_authority.key(), authority, EscrowError::InvalidReleaseHook);
+
+    let mut data = release_hook_discriminator().to_vec();
+    record.serialize(&mut data)?;
+
+    let mut metas = vec![AccountMeta::new_readonly(authority, true)];
+    metas.extend(forwarded.iter().map(|account| AccountMeta {
+        pubkey: account.key(),
+        is_signer: false,
+        is_writable: account.is_writable,
+    }));
+
+    let mut infos = vec![hook_authority.clone()];
+    infos.extend(forwarded.iter().cloned());
+    infos.push(hook_program.clone());
+
+    invoke_signed(
+        &Instruction {
+            program_id: hook.program,
+            accounts: metas,
+            data,
+        },
+        &infos,
+        &[&[HOOK_AUTHORITY_SEED, &[bump]]],
+    )?;
+    Ok(())
+}
+
+/// Anchor discriminator of `on_escrow_settled`, the instruction every release hook implements
+pub fn release_hook_discriminator() -> [u8; 8] {
+    let hash = anchor_lang::solana_program::hash::hash(b"global:on_escrow_settled").to_bytes();
+    let mut discriminator = [0u8; 8];
+    discriminator.copy_from_slice(&hash[..8]);
+    discriminator
+}
+
+/// Append the creation leaf for escrows that use compressed receipts
+pub fn record_creation<'info>(
+    escrow: Pubkey,
+    base: &EscrowAccount,
+    mint: Option<Pubkey>,
+    receipt_tree: Option<&AccountLoader<'info, ReceiptTree>>,
+    clock: &Clock,
+) -> Result<()> {
+    let Some(tree_key) = base.receipt_tree else {
+        return Ok(());
+    };
+    let tree = receipt_tree.ok_or(EscrowError::InvalidReceiptAccount)?;
+    require_keys_eq!(tree.key(), tree_key, EscrowError::InvalidReceiptAccount);
+    append_receipt_leaf(tree, ReceiptLeaf::Created(CreationRecord::new(escrow, base, mint, clock)))
+}
+
+/// Append a leaf to a receipt tree and emit its contents for indexers
+pub fn append_receipt_leaf<'info>(tree: &AccountLoader<'info, ReceiptTree>, leaf: ReceiptLeaf) -> Result<()> {
+    let leaf_hash = leaf.hash()?;
+    let index = tree.load_mut()?.append(leaf_hash)?;
+    emit!(ReceiptLeafAppended {
+        tree: tree.key(),
+        index,
+        leaf_hash,
+        leaf,
+    });
+    Ok(())
+}
+
+/// Pair signed recipients with their amounts, skipping empty recipient slots
+pub fn receipt_payouts(recipients: &[Option<Pubkey>], amounts: &[u64]) -> Vec<ReceiptPayout> {
+    recipients
+        .iter()
+        .zip(amounts)
+        .filter_map(|(recipient, amount)| recipient.map(|recipient| ReceiptPayout { recipient, amount: *amount }))
+        .collect()
 }
 
 /// Convert byte array to hexadecimal string
@@ -197,30 +598,44 @@ pub fn verify_signatures_without_timelock(
     signatures: &[Vec<u8>],
     payment_amounts: &[u64],
     recipients: &[Option<Pubkey>],
+    valid_until: i64,
+    current_time: i64,
     instructions_sysvar: &AccountInfo,
-) -> Result<()> {
-    let message = construct_message(&escrow_account.unique_id, recipients, payment_amounts);
+) -> Result<Vec<Pubkey>> {
+    verify_valid_until(valid_until, current_time)?;
+    escrow_account.check_moderator_payout(recipients, payment_amounts)?;
     
+    let message = construct_message(
+        &escrow_account.unique_id,
+        escrow_account.nonce,
+        valid_until,
+        recipients,
+        payment_amounts,
+    );
+    
+    verify_message_signatures(escrow_account, signatures, &message, recipients.iter().flatten(), instructions_sysvar)
+}
+
+/// Check that enough escrow parties signed `message`, which pays `recipients`, in the preceding Ed25519 instruction.
+/// Returns the verified signers
+pub fn verify_message_signatures<'a>(
+    escrow_account: &EscrowAccount,
+    signatures: &[Vec<u8>],
+    message: &[u8],
+    recipients: impl IntoIterator<Item = &'a Pubkey>,
+    instructions_sysvar: &AccountInfo,
+) -> Result<Vec<Pubkey>> {
     let all_signers = verify_ed25519_instructions(
         instructions_sysvar,
         signatures,
-        &message,
+        message,
     )?;
     
-    // Filter valid signers
-    let valid_signers: Vec<_> = all_signers.into_iter()
-        .filter(|signer| {
-            *signer == escrow_account.buyer || 
-            *signer == escrow_account.seller || 
-            (escrow_account.moderator.is_some() && *signer == escrow_account.moderator.unwrap())
-        })
-        .collect();
-
-    // Check the number of valid signatures
+    // Check the number of valid approvals; an oracle-attested delivery counts as one for seller-only payouts
     require!(
-        valid_signers.len() >= escrow_account.required_signatures as usize,
+        escrow_account.count_approvals(&all_signers, recipients) >= escrow_account.required_signatures as usize,
         EscrowError::InsufficientSignatures
     );
     
-    Ok(())
+    Ok(all_signers)
 }
@@ -0,0 +1,31 @@
+use anchor_lang::prelude::*;
+
+#[error_code]
+pub enum RegistryError {
+    #[msg("Fee basis points cannot exceed 10000")]
+    InvalidFeeSchedule,
+
+    #[msg("Too many supported mints")]
+    TooManyMints,
+
+    #[msg("Metadata URI is too long")]
+    MetadataUriTooLong,
+
+    #[msg("Amount must be greater than zero")]
+    ZeroAmount,
+
+    #[msg("Insufficient stake")]
+    InsufficientStake,
+
+    #[msg("Unstake has not been requested")]
+    UnstakeNotRequested,
+
+    #[msg("Unstake cooldown has not elapsed")]
+    CooldownNotElapsed,
+
+    #[msg("Invalid cooldown")]
+    InvalidCooldown,
+
+    #[msg("Unauthorized access")]
+    Unauthorized,
+}
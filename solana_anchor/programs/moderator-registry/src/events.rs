@@ -0,0 +1,17 @@
+use anchor_lang::prelude::*;
+
+/// Emitted when governance slashes a moderator's stake
+#[event]
+pub struct ModeratorSlashed {
+    pub moderator: Pubkey,
+    pub amount: u64,
+    pub remaining_stake: u64,
+    pub evidence_hash: [u8; 32], // hash of the off-chain misconduct evidence
+}
+
+/// Emitted when governance suspends or reinstates a moderator
+#[event]
+pub struct ModeratorSuspended {
+    pub moderator: Pubkey,
+    pub suspended: bool,
+}
@@ -0,0 +1,54 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Mint, TokenAccount};
+use crate::state::RegistryConfig;
+use crate::error::RegistryError;
+
+#[derive(Accounts)]
+pub struct InitializeRegistry<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = RegistryConfig::LEN,
+        seeds = [b"registry_config"],
+        bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+
+    pub stake_mint: Account<'info, Mint>,
+
+    #[account(constraint = slash_treasury.mint == stake_mint.key() @ RegistryError::Unauthorized)]
+    pub slash_treasury: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(
+    ctx: Context<InitializeRegistry>,
+    governance: Pubkey,
+    min_stake: u64,
+    unstake_cooldown: i64,
+) -> Result<()> {
+    require!(unstake_cooldown >= 0, RegistryError::InvalidCooldown);
+
+    let config = &mut ctx.accounts.config;
+    config.authority = ctx.accounts.authority.key();
+    config.governance = governance;
+    config.stake_mint = ctx.accounts.stake_mint.key();
+    config.slash_treasury = ctx.accounts.slash_treasury.key();
+    config.min_stake = min_stake;
+    config.unstake_cooldown = unstake_cooldown;
+    config.bump = ctx.bumps.config;
+
+    msg!(
+        "Moderator registry initialized: Authority={}, Governance={}, Stake mint={}, Min stake={}",
+        config.authority,
+        governance,
+        config.stake_mint,
+        min_stake
+    );
+
+    Ok(())
+}
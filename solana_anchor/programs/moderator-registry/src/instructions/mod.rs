@@ -0,0 +1,9 @@
+pub mod initialize_registry;
+pub mod update_registry;
+pub mod register_moderator;
+pub mod update_profile;
+pub mod stake;
+pub mod request_unstake;
+pub mod withdraw_stake;
+pub mod slash_moderator;
+pub mod set_suspended;
@@ -0,0 +1,65 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::{Mint, Token, TokenAccount};
+use crate::state::{ModeratorProfile, ProfileParams, RegistryConfig};
+
+#[derive(Accounts)]
+pub struct RegisterModerator<'info> {
+    #[account(mut)]
+    pub moderator: Signer<'info>,
+
+    #[account(seeds = [b"registry_config"], bump = config.bump)]
+    pub config: Account<'info, RegistryConfig>,
+
+    #[account(
+        init,
+        payer = moderator,
+        space = ModeratorProfile::LEN,
+        seeds = [b"moderator", moderator.key().as_ref()],
+        bump
+    )]
+    pub profile: Account<'info, ModeratorProfile>,
+
+    #[account(address = config.stake_mint)]
+    pub stake_mint: Account<'info, Mint>,
+
+    /// Stake vault owned by the profile PDA
+    #[account(
+        init,
+        payer = moderator,
+        associated_token::mint = stake_mint,
+        associated_token::authority = profile,
+    )]
+    pub stake_vault: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<RegisterModerator>, params: ProfileParams) -> Result<()> {
+    params.validate()?;
+
+    let profile = &mut ctx.accounts.profile;
+    profile.moderator = ctx.accounts.moderator.key();
+    profile.fee_schedule = params.fee_schedule;
+    profile.supported_mints = params.supported_mints;
+    profile.metadata_uri = params.metadata_uri;
+    profile.stake = 0;
+    profile.slashed_total = 0;
+    profile.unstake_requested_at = None;
+    profile.suspended = false;
+    profile.registered_at = Clock::get()?.unix_timestamp;
+    profile.bump = ctx.bumps.profile;
+    profile.refresh_active(&ctx.accounts.config);
+
+    msg!(
+        "Moderator registered: Moderator={}, Fee={} bps + {}, URI={}",
+        profile.moderator,
+        profile.fee_schedule.fee_bps,
+        profile.fee_schedule.fee_fixed,
+        profile.metadata_uri
+    );
+
+    Ok(())
+}
@@ -0,0 +1,34 @@
+use anchor_lang::prelude::*;
+use crate::state::{ModeratorProfile, RegistryConfig};
+
+#[derive(Accounts)]
+pub struct RequestUnstake<'info> {
+    pub moderator: Signer<'info>,
+
+    #[account(seeds = [b"registry_config"], bump = config.bump)]
+    pub config: Account<'info, RegistryConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"moderator", moderator.key().as_ref()],
+        bump = profile.bump,
+    )]
+    pub profile: Account<'info, ModeratorProfile>,
+}
+
+/// Deactivates the moderator immediately; stake stays slashable until the cooldown ends
+pub fn handler(ctx: Context<RequestUnstake>) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+
+    let profile = &mut ctx.accounts.profile;
+    profile.unstake_requested_at = Some(now);
+    profile.refresh_active(&ctx.accounts.config);
+
+    msg!(
+        "Moderator unstake requested: Moderator={}, Withdrawable after={}",
+        profile.moderator,
+        now + ctx.accounts.config.unstake_cooldown
+    );
+
+    Ok(())
+}
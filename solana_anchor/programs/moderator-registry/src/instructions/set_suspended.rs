@@ -0,0 +1,43 @@
+use anchor_lang::prelude::*;
+use crate::state::{ModeratorProfile, RegistryConfig};
+use crate::error::RegistryError;
+use crate::events::ModeratorSuspended;
+
+#[derive(Accounts)]
+pub struct SetSuspended<'info> {
+    pub governance: Signer<'info>,
+
+    #[account(
+        seeds = [b"registry_config"],
+        bump = config.bump,
+        has_one = governance @ RegistryError::Unauthorized,
+    )]
+    pub config: Account<'info, RegistryConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"moderator", profile.moderator.as_ref()],
+        bump = profile.bump,
+    )]
+    pub profile: Account<'info, ModeratorProfile>,
+}
+
+pub fn handler(ctx: Context<SetSuspended>, suspended: bool) -> Result<()> {
+    let profile = &mut ctx.accounts.profile;
+    profile.suspended = suspended;
+    profile.refresh_active(&ctx.accounts.config);
+
+    emit!(ModeratorSuspended {
+        moderator: profile.moderator,
+        suspended,
+    });
+
+    msg!(
+        "Moderator suspension updated: Moderator={}, Suspended={}, Active={}",
+        profile.moderator,
+        suspended,
+        profile.active
+    );
+
+    Ok(())
+}
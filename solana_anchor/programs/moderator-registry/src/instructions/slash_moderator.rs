@@ -0,0 +1,81 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+use crate::state::{ModeratorProfile, RegistryConfig};
+use crate::error::RegistryError;
+use crate::events::ModeratorSlashed;
+
+#[derive(Accounts)]
+pub struct SlashModerator<'info> {
+    pub governance: Signer<'info>,
+
+    #[account(
+        seeds = [b"registry_config"],
+        bump = config.bump,
+        has_one = governance @ RegistryError::Unauthorized,
+        has_one = slash_treasury @ RegistryError::Unauthorized,
+    )]
+    pub config: Account<'info, RegistryConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"moderator", profile.moderator.as_ref()],
+        bump = profile.bump,
+    )]
+    pub profile: Account<'info, ModeratorProfile>,
+
+    #[account(
+        mut,
+        associated_token::mint = config.stake_mint,
+        associated_token::authority = profile,
+    )]
+    pub stake_vault: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub slash_treasury: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+/// Moves up to `amount` of the moderator's stake to the slash treasury
+pub fn handler(ctx: Context<SlashModerator>, amount: u64, evidence_hash: [u8; 32]) -> Result<()> {
+    let amount = amount.min(ctx.accounts.profile.stake);
+    require!(amount > 0, RegistryError::InsufficientStake);
+
+    let profile = &ctx.accounts.profile;
+    let moderator_key = profile.moderator;
+    let seeds: &[&[u8]] = &[b"moderator", moderator_key.as_ref(), &[profile.bump]];
+    token::transfer(
+        CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.stake_vault.to_account_info(),
+                to: ctx.accounts.slash_treasury.to_account_info(),
+                authority: ctx.accounts.profile.to_account_info(),
+            },
+            &[seeds],
+        ),
+        amount,
+    )?;
+
+    let profile = &mut ctx.accounts.profile;
+    profile.stake -= amount;
+    profile.slashed_total = profile.slashed_total.saturating_add(amount);
+    profile.refresh_active(&ctx.accounts.config);
+
+    emit!(ModeratorSlashed {
+        moderator: moderator_key,
+        amount,
+        remaining_stake: profile.stake,
+        evidence_hash,
+    });
+
+    msg!(
+        "Moderator slashed: Moderator={}, Amount={}, Remaining stake={}, Active={}",
+        moderator_key,
+        amount,
+        profile.stake,
+        profile.active
+    );
+
+    Ok(())
+}
@@ -0,0 +1,66 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+use crate::state::{ModeratorProfile, RegistryConfig};
+use crate::error::RegistryError;
+
+#[derive(Accounts)]
+pub struct Stake<'info> {
+    pub moderator: Signer<'info>,
+
+    #[account(seeds = [b"registry_config"], bump = config.bump)]
+    pub config: Account<'info, RegistryConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"moderator", moderator.key().as_ref()],
+        bump = profile.bump,
+    )]
+    pub profile: Account<'info, ModeratorProfile>,
+
+    #[account(
+        mut,
+        constraint = moderator_token_account.mint == config.stake_mint @ RegistryError::Unauthorized,
+    )]
+    pub moderator_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = config.stake_mint,
+        associated_token::authority = profile,
+    )]
+    pub stake_vault: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+/// Staking also cancels a pending unstake request
+pub fn handler(ctx: Context<Stake>, amount: u64) -> Result<()> {
+    require!(amount > 0, RegistryError::ZeroAmount);
+
+    token::transfer(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.moderator_token_account.to_account_info(),
+                to: ctx.accounts.stake_vault.to_account_info(),
+                authority: ctx.accounts.moderator.to_account_info(),
+            },
+        ),
+        amount,
+    )?;
+
+    let profile = &mut ctx.accounts.profile;
+    profile.stake += amount;
+    profile.unstake_requested_at = None;
+    profile.refresh_active(&ctx.accounts.config);
+
+    msg!(
+        "Moderator staked: Moderator={}, Amount={}, Total stake={}, Active={}",
+        profile.moderator,
+        amount,
+        profile.stake,
+        profile.active
+    );
+
+    Ok(())
+}
@@ -0,0 +1,32 @@
+use anchor_lang::prelude::*;
+use crate::state::{ModeratorProfile, ProfileParams};
+
+#[derive(Accounts)]
+pub struct UpdateProfile<'info> {
+    pub moderator: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"moderator", moderator.key().as_ref()],
+        bump = profile.bump,
+    )]
+    pub profile: Account<'info, ModeratorProfile>,
+}
+
+pub fn handler(ctx: Context<UpdateProfile>, params: ProfileParams) -> Result<()> {
+    params.validate()?;
+
+    let profile = &mut ctx.accounts.profile;
+    profile.fee_schedule = params.fee_schedule;
+    profile.supported_mints = params.supported_mints;
+    profile.metadata_uri = params.metadata_uri;
+
+    msg!(
+        "Moderator profile updated: Moderator={}, Fee={} bps + {}",
+        profile.moderator,
+        profile.fee_schedule.fee_bps,
+        profile.fee_schedule.fee_fixed
+    );
+
+    Ok(())
+}
@@ -0,0 +1,41 @@
+use anchor_lang::prelude::*;
+use crate::state::RegistryConfig;
+use crate::error::RegistryError;
+
+#[derive(Accounts)]
+pub struct UpdateRegistry<'info> {
+    #[account(
+        mut,
+        seeds = [b"registry_config"],
+        bump = config.bump,
+        has_one = authority @ RegistryError::Unauthorized
+    )]
+    pub config: Account<'info, RegistryConfig>,
+
+    pub authority: Signer<'info>,
+}
+
+/// Cached `active` flags pick up a new `min_stake` the next time a profile is
+/// touched; escrow programs check against the config directly
+pub fn handler(
+    ctx: Context<UpdateRegistry>,
+    governance: Pubkey,
+    min_stake: u64,
+    unstake_cooldown: i64,
+) -> Result<()> {
+    require!(unstake_cooldown >= 0, RegistryError::InvalidCooldown);
+
+    let config = &mut ctx.accounts.config;
+    config.governance = governance;
+    config.min_stake = min_stake;
+    config.unstake_cooldown = unstake_cooldown;
+
+    msg!(
+        "Moderator registry updated: Governance={}, Min stake={}, Unstake cooldown={}s",
+        governance,
+        min_stake,
+        unstake_cooldown
+    );
+
+    Ok(())
+}
@@ -0,0 +1,73 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+use crate::state::{ModeratorProfile, RegistryConfig};
+use crate::error::RegistryError;
+
+#[derive(Accounts)]
+pub struct WithdrawStake<'info> {
+    pub moderator: Signer<'info>,
+
+    #[account(seeds = [b"registry_config"], bump = config.bump)]
+    pub config: Account<'info, RegistryConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"moderator", moderator.key().as_ref()],
+        bump = profile.bump,
+    )]
+    pub profile: Account<'info, ModeratorProfile>,
+
+    #[account(
+        mut,
+        constraint = moderator_token_account.mint == config.stake_mint @ RegistryError::Unauthorized,
+    )]
+    pub moderator_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = config.stake_mint,
+        associated_token::authority = profile,
+    )]
+    pub stake_vault: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+pub fn handler(ctx: Context<WithdrawStake>, amount: u64) -> Result<()> {
+    require!(amount > 0, RegistryError::ZeroAmount);
+
+    let profile = &ctx.accounts.profile;
+    let requested_at = profile.unstake_requested_at.ok_or(RegistryError::UnstakeNotRequested)?;
+    require!(
+        Clock::get()?.unix_timestamp >= requested_at + ctx.accounts.config.unstake_cooldown,
+        RegistryError::CooldownNotElapsed
+    );
+    require!(amount <= profile.stake, RegistryError::InsufficientStake);
+
+    let moderator_key = profile.moderator;
+    let seeds: &[&[u8]] = &[b"moderator", moderator_key.as_ref(), &[profile.bump]];
+    token::transfer(
+        CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.stake_vault.to_account_info(),
+                to: ctx.accounts.moderator_token_account.to_account_info(),
+                authority: ctx.accounts.profile.to_account_info(),
+            },
+            &[seeds],
+        ),
+        amount,
+    )?;
+
+    let profile = &mut ctx.accounts.profile;
+    profile.stake -= amount;
+
+    msg!(
+        "Moderator stake withdrawn: Moderator={}, Amount={}, Remaining stake={}",
+        profile.moderator,
+        amount,
+        profile.stake
+    );
+
+    Ok(())
+}
@@ -0,0 +1,86 @@
+use anchor_lang::prelude::*;
+
+pub mod state;
+pub mod instructions;
+pub mod error;
+pub mod events;
+
+use state::ProfileParams;
+
+use instructions::{
+    initialize_registry::*,
+    update_registry::*,
+    register_moderator::*,
+    update_profile::*,
+    stake::*,
+    request_unstake::*,
+    withdraw_stake::*,
+    slash_moderator::*,
+    set_suspended::*,
+};
+
+declare_id!("HUH6LQhWvkJ92i8V4vAsKTQgGkMudmCwTQq9GRMv5VuY");
+
+#[program]
+pub mod moderator_registry {
+    use super::*;
+
+    /// Create the registry config with its stake mint and governance key
+    pub fn initialize_registry(
+        ctx: Context<InitializeRegistry>,
+        governance: Pubkey,
+        min_stake: u64,
+        unstake_cooldown: i64,
+    ) -> Result<()> {
+        instructions::initialize_registry::handler(ctx, governance, min_stake, unstake_cooldown)
+    }
+
+    /// Update governance, minimum stake and unstake cooldown
+    pub fn update_registry(
+        ctx: Context<UpdateRegistry>,
+        governance: Pubkey,
+        min_stake: u64,
+        unstake_cooldown: i64,
+    ) -> Result<()> {
+        instructions::update_registry::handler(ctx, governance, min_stake, unstake_cooldown)
+    }
+
+    /// Register a moderator profile and its stake vault
+    pub fn register_moderator(ctx: Context<RegisterModerator>, params: ProfileParams) -> Result<()> {
+        instructions::register_moderator::handler(ctx, params)
+    }
+
+    /// Update fee schedule, supported mints and metadata URI
+    pub fn update_profile(ctx: Context<UpdateProfile>, params: ProfileParams) -> Result<()> {
+        instructions::update_profile::handler(ctx, params)
+    }
+
+    /// Add tokens to the moderator's stake
+    pub fn stake(ctx: Context<Stake>, amount: u64) -> Result<()> {
+        instructions::stake::handler(ctx, amount)
+    }
+
+    /// Deactivate the moderator and start the unstake cooldown
+    pub fn request_unstake(ctx: Context<RequestUnstake>) -> Result<()> {
+        instructions::request_unstake::handler(ctx)
+    }
+
+    /// Withdraw stake once the cooldown has elapsed
+    pub fn withdraw_stake(ctx: Context<WithdrawStake>, amount: u64) -> Result<()> {
+        instructions::withdraw_stake::handler(ctx, amount)
+    }
+
+    /// Governance penalises a moderator for proven misconduct
+    pub fn slash_moderator(
+        ctx: Context<SlashModerator>,
+        amount: u64,
+        evidence_hash: [u8; 32],
+    ) -> Result<()> {
+        instructions::slash_moderator::handler(ctx, amount, evidence_hash)
+    }
+
+    /// Governance suspends or reinstates a moderator
+    pub fn set_suspended(ctx: Context<SetSuspended>, suspended: bool) -> Result<()> {
+        instructions::set_suspended::handler(ctx, suspended)
+    }
+}
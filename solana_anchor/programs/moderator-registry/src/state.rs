@@ -0,0 +1,111 @@
+use anchor_lang::prelude::*;
+
+pub const MAX_SUPPORTED_MINTS: usize = 8;
+pub const MAX_METADATA_URI_LEN: usize = 200;
+pub const BPS_DENOMINATOR: u64 = 10_000;
+
+/// Registry-wide settings
+#[account]
+pub struct RegistryConfig {
+    pub authority: Pubkey,
+    pub governance: Pubkey, // may slash and suspend moderators
+    pub stake_mint: Pubkey,
+    pub slash_treasury: Pubkey, // token account receiving slashed stake
+    pub min_stake: u64,
+    pub unstake_cooldown: i64, // seconds between request_unstake and withdraw_stake
+    pub bump: u8,
+}
+
+impl RegistryConfig {
+    pub const LEN: usize = 8 + // discriminator
+                          32 + // authority
+                          32 + // governance
+                          32 + // stake_mint
+                          32 + // slash_treasury
+                          8 + // min_stake
+                          8 + // unstake_cooldown
+                          1; // bump
+}
+
+/// Fees a moderator advertises for arbitrating an escrow
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default)]
+pub struct FeeSchedule {
+    pub fee_bps: u16,
+    pub fee_fixed: u64,
+}
+
+/// Profile fields chosen by the moderator
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct ProfileParams {
+    pub fee_schedule: FeeSchedule,
+    pub supported_mints: Vec<Pubkey>, // empty means any mint
+    pub metadata_uri: String,
+}
+
+/// Moderator profile, one per moderator key
+#[account]
+pub struct ModeratorProfile {
+    pub moderator: Pubkey,
+    pub fee_schedule: FeeSchedule,
+    pub supported_mints: Vec<Pubkey>,
+    pub metadata_uri: String,
+    pub stake: u64,
+    pub slashed_total: u64,
+    pub unstake_requested_at: Option<i64>,
+    pub suspended: bool,
+    pub active: bool, // cached so escrow programs need only this account
+    pub registered_at: i64,
+    pub bump: u8,
+}
+
+impl ModeratorProfile {
+    pub const LEN: usize = 8 + // discriminator
+                          32 + // moderator
+                          2 + 8 + // fee_schedule
+                          4 + MAX_SUPPORTED_MINTS * 32 + // supported_mints
+                          4 + MAX_METADATA_URI_LEN + // metadata_uri
+                          8 + // stake
+                          8 + // slashed_total
+                          9 + // unstake_requested_at (Option<i64>)
+                          1 + // suspended
+                          1 + // active
+                          8 + // registered_at
+                          1; // bump
+
+    /// Recompute `active` after any change to stake, suspension or unstaking
+    pub fn refresh_active(&mut self, config: &RegistryConfig) {
+        self.active = self.is_active(config);
+    }
+
+    /// Whether the moderator is in good standing under the current `config`.
+    /// Unlike the cached `active`, this sees a `min_stake` raised since the
+    /// profile was last written
+    pub fn is_active(&self, config: &RegistryConfig) -> bool {
+        !self.suspended &&
+            self.unstake_requested_at.is_none() &&
+            self.stake >= config.min_stake
+    }
+
+    /// Whether the moderator accepts escrows in `mint`
+    pub fn supports_mint(&self, mint: &Pubkey) -> bool {
+        self.supported_mints.is_empty() || self.supported_mints.contains(mint)
+    }
+}
+
+impl ProfileParams {
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            self.fee_schedule.fee_bps as u64 <= BPS_DENOMINATOR,
+            crate::error::RegistryError::InvalidFeeSchedule
+        );
+        require!(
+            self.supported_mints.len() <= MAX_SUPPORTED_MINTS,
+            crate::error::RegistryError::TooManyMints
+        );
+        require!(
+            self.metadata_uri.len() <= MAX_METADATA_URI_LEN,
+            crate::error::RegistryError::MetadataUriTooLong
+        );
+        Ok(())
+    }
+}
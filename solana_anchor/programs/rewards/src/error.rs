@@ -0,0 +1,61 @@
+use anchor_lang::prelude::*;
+
+#[error_code]
+pub enum RewardsError {
+    #[msg("Maximum reward must be greater than 0")]
+    InvalidMaxReward,
+
+    #[msg("Time window must be greater than 0")]
+    InvalidTimeWindow,
+
+    #[msg("Zero address cannot be a promoted seller")]
+    InvalidSeller,
+
+    #[msg("One of the sellers is already promoted")]
+    SellerAlreadyPromoted,
+
+    #[msg("Too many promoted sellers")]
+    TooManyPromotedSellers,
+
+    #[msg("Rewards distribution is already on")]
+    RewardsAlreadyOn,
+
+    #[msg("End date should be greater than current date")]
+    InvalidEndDate,
+
+    #[msg("Reward distribution is not running")]
+    RewardsNotRunning,
+
+    #[msg("Receipt does not match its escrow")]
+    InvalidReceipt,
+
+    #[msg("Only the buyer of the escrow can claim")]
+    NotBuyer,
+
+    #[msg("Seller is not promoted")]
+    SellerNotPromoted,
+
+    #[msg("Escrow was not released to the seller")]
+    NotReleasedToSeller,
+
+    #[msg("Claim window has closed")]
+    ClaimWindowClosed,
+
+    #[msg("No reward available")]
+    NoRewardAvailable,
+
+    #[msg("Reward already claimed")]
+    AlreadyClaimed,
+
+    #[msg("Arithmetic overflow")]
+    MathOverflow,
+
+    #[msg("Unauthorized access")]
+    Unauthorized,
+
+    #[msg("Seller did not approve the release")]
+    SellerApprovalMissing,
+
+    #[msg("Settlement is below the promotion minimum")]
+    SettlementBelowMinimum,
+}
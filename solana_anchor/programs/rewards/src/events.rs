@@ -0,0 +1,38 @@
+use anchor_lang::prelude::*;
+
+/// Emitted for every reward payment, including top-ups of a claim the vault could not cover
+#[event]
+pub struct RewardClaimed {
+    pub promotion: Pubkey,
+    pub escrow: Pubkey,
+    pub seller: Pubkey,
+    pub buyer: Pubkey,
+    pub amount: u64,
+}
+
+/// Emitted when the authority promotes sellers
+#[event]
+pub struct PromotedSellersAdded {
+    pub promotion: Pubkey,
+    pub sellers: Vec<Pubkey>,
+}
+
+/// Emitted when the authority stops promoting sellers
+#[event]
+pub struct PromotedSellersRemoved {
+    pub promotion: Pubkey,
+    pub sellers: Vec<Pubkey>,
+}
+
+/// Emitted once, when reward distribution starts
+#[event]
+pub struct RewardsOn {
+    pub promotion: Pubkey,
+}
+
+/// Emitted when the authority moves the end of the promotion
+#[event]
+pub struct EndDateChanged {
+    pub promotion: Pubkey,
+    pub end_date: i64,
+}
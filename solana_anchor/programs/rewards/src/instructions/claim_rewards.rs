@@ -0,0 +1,163 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+use mobazha_escrow_program::state::{SettlementOutcome, SettlementReceipt};
+use crate::state::{BuyerReward, Promotion, RewardClaim, SellerRewards};
+use crate::error::RewardsError;
+use crate::events::RewardClaimed;
+
+#[derive(Accounts)]
+pub struct ClaimRewards<'info> {
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+    #[account(mut, has_one = reward_vault)]
+    pub promotion: Account<'info, Promotion>,
+
+    /// Receipt escrow-program left when the escrow settled; Account checks its owner.
+    /// Escrows whose record went to a receipt tree have no such account and cannot claim.
+    #[account(
+        constraint = receipt.key() == SettlementReceipt::address(&receipt.record.escrow) @ RewardsError::InvalidReceipt,
+        constraint = receipt.record.buyer == buyer.key() @ RewardsError::NotBuyer,
+    )]
+    pub receipt: Box<Account<'info, SettlementReceipt>>,
+
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        space = SellerRewards::LEN,
+        seeds = [b"seller_rewards", promotion.key().as_ref(), receipt.record.seller.as_ref()],
+        bump
+    )]
+    pub seller_rewards: Account<'info, SellerRewards>,
+
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        space = BuyerReward::LEN,
+        seeds = [b"buyer_reward", promotion.key().as_ref(), receipt.record.seller.as_ref(), buyer.key().as_ref()],
+        bump
+    )]
+    pub buyer_reward: Account<'info, BuyerReward>,
+
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        space = RewardClaim::LEN,
+        seeds = [b"reward_claim", promotion.key().as_ref(), receipt.record.escrow.as_ref()],
+        bump
+    )]
+    pub claim: Account<'info, RewardClaim>,
+
+    #[account(mut)]
+    pub reward_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = buyer_token_account.mint == promotion.reward_mint @ RewardsError::Unauthorized,
+        constraint = buyer_token_account.owner == buyer.key() @ RewardsError::Unauthorized,
+    )]
+    pub buyer_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+/// The first claim for a receipt fixes the reward and counts it against the
+/// buyer's and seller's caps. If the vault cannot cover it, the rest can be
+/// claimed with the same receipt once the vault is topped up.
+///
+/// Only receipt accounts are accepted. Leaves of a receipt tree are not: their
+/// proofs only check against the tree's recent roots and could expire mid-window.
+pub fn handler(ctx: Context<ClaimRewards>) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let record = &ctx.accounts.receipt.record;
+    let promotion = &ctx.accounts.promotion;
+
+    require!(promotion.is_running(now), RewardsError::RewardsNotRunning);
+    require!(promotion.is_promoted(&record.seller), RewardsError::SellerNotPromoted);
+    // Like a seller-signed release on EVM: refunds, disputes and payouts that skip the seller earn nothing
+    let paid_to_seller = record.paid_to(&record.seller);
+    require!(
+        matches!(record.outcome, SettlementOutcome::Released | SettlementOutcome::TimedOut) &&
+        paid_to_seller > 0,
+        RewardsError::NotReleasedToSeller
+    );
+    // A buyer releasing to a seller on their own proves no purchase; timeout releases are seller-signed
+    require!(record.seller_approved, RewardsError::SellerApprovalMissing);
+    require!(
+        promotion.meets_minimum(record.mint, paid_to_seller),
+        RewardsError::SettlementBelowMinimum
+    );
+    require!(
+        now <= record.settled_at.saturating_add(promotion.time_window),
+        RewardsError::ClaimWindowClosed
+    );
+
+    let claim = &mut ctx.accounts.claim;
+    if claim.escrow == Pubkey::default() {
+        let seller_rewards = &mut ctx.accounts.seller_rewards;
+        let buyer_reward = &mut ctx.accounts.buyer_reward;
+        if seller_rewards.seller == Pubkey::default() {
+            seller_rewards.promotion = promotion.key();
+            seller_rewards.seller = record.seller;
+            seller_rewards.bump = ctx.bumps.seller_rewards;
+        }
+        if buyer_reward.buyer == Pubkey::default() {
+            buyer_reward.promotion = promotion.key();
+            buyer_reward.seller = record.seller;
+            buyer_reward.buyer = record.buyer;
+            buyer_reward.bump = ctx.bumps.buyer_reward;
+        }
+
+        let owed = promotion.reward_for(seller_rewards, buyer_reward);
+        require!(owed > 0, RewardsError::NoRewardAvailable);
+
+        if buyer_reward.rewarded == 0 {
+            seller_rewards.rewarded_buyers += 1;
+        }
+        seller_rewards.distributed += owed;
+        buyer_reward.rewarded += owed;
+
+        claim.promotion = promotion.key();
+        claim.escrow = record.escrow;
+        claim.buyer = record.buyer;
+        claim.seller = record.seller;
+        claim.owed = owed;
+        claim.paid = 0;
+        claim.claimed_at = now;
+        claim.bump = ctx.bumps.claim;
+    }
+
+    require!(claim.paid < claim.owed, RewardsError::AlreadyClaimed);
+    let amount = (claim.owed - claim.paid).min(ctx.accounts.reward_vault.amount);
+    require!(amount > 0, RewardsError::NoRewardAvailable);
+
+    let id = promotion.promotion_id.to_le_bytes();
+    let seeds: &[&[u8]] = &[b"promotion", promotion.authority.as_ref(), &id, &[promotion.bump]];
+    token::transfer(
+        CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.reward_vault.to_account_info(),
+                to: ctx.accounts.buyer_token_account.to_account_info(),
+                authority: promotion.to_account_info(),
+            },
+            &[seeds],
+        ),
+        amount,
+    )?;
+
+    claim.paid += amount;
+    let promotion = &mut ctx.accounts.promotion;
+    promotion.total_distributed += amount;
+
+    emit!(RewardClaimed {
+        promotion: promotion.key(),
+        escrow: claim.escrow,
+        seller: claim.seller,
+        buyer: claim.buyer,
+        amount,
+    });
+
+    Ok(())
+}
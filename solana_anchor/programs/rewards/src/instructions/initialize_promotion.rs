@@ -0,0 +1,81 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::{Mint, Token, TokenAccount};
+use crate::state::{Promotion, REWARD_TOKENS_PER_BUYER};
+use crate::error::RewardsError;
+
+#[derive(Accounts)]
+#[instruction(promotion_id: u64)]
+pub struct InitializePromotion<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = Promotion::LEN,
+        seeds = [b"promotion", authority.key().as_ref(), &promotion_id.to_le_bytes()],
+        bump
+    )]
+    pub promotion: Account<'info, Promotion>,
+
+    pub reward_mint: Account<'info, Mint>,
+
+    /// Reward vault owned by the promotion PDA
+    #[account(
+        init,
+        payer = authority,
+        associated_token::mint = reward_mint,
+        associated_token::authority = promotion,
+    )]
+    pub reward_vault: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Rewards stay off until `turn_on_rewards` and `set_end_date`
+pub fn handler(
+    ctx: Context<InitializePromotion>,
+    promotion_id: u64,
+    max_reward_per_seller: u64,
+    time_window: i64,
+    settlement_mint: Option<Pubkey>,
+    min_settlement_amount: u64,
+) -> Result<()> {
+    Promotion::validate_limits(max_reward_per_seller, time_window)?;
+
+    let max_reward_per_buyer = 10u64
+        .checked_pow(ctx.accounts.reward_mint.decimals as u32)
+        .and_then(|unit| unit.checked_mul(REWARD_TOKENS_PER_BUYER))
+        .ok_or(RewardsError::MathOverflow)?;
+
+    let promotion = &mut ctx.accounts.promotion;
+    promotion.authority = ctx.accounts.authority.key();
+    promotion.promotion_id = promotion_id;
+    promotion.reward_mint = ctx.accounts.reward_mint.key();
+    promotion.reward_vault = ctx.accounts.reward_vault.key();
+    promotion.max_reward_per_seller = max_reward_per_seller;
+    promotion.max_reward_per_buyer = max_reward_per_buyer;
+    promotion.time_window = time_window;
+    promotion.rewards_on = false;
+    promotion.end_date = 0;
+    promotion.total_distributed = 0;
+    promotion.promoted_sellers = Vec::new();
+    promotion.bump = ctx.bumps.promotion;
+    promotion.settlement_mint = settlement_mint;
+    promotion.min_settlement_amount = min_settlement_amount;
+
+    msg!(
+        "Promotion initialized: Authority={}, ID={}, Reward mint={}, Max per seller={}, Time window={}s, Min settlement={}",
+        promotion.authority,
+        promotion_id,
+        promotion.reward_mint,
+        max_reward_per_seller,
+        time_window,
+        min_settlement_amount
+    );
+
+    Ok(())
+}
@@ -0,0 +1,7 @@
+pub mod initialize_promotion;
+pub mod update_promotion;
+pub mod promoted_sellers;
+pub mod turn_on_rewards;
+pub mod set_end_date;
+pub mod transfer_remaining_tokens;
+pub mod claim_rewards;
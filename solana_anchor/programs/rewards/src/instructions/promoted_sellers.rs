@@ -0,0 +1,48 @@
+use anchor_lang::prelude::*;
+use crate::state::{Promotion, MAX_PROMOTED_SELLERS};
+use crate::error::RewardsError;
+use crate::events::{PromotedSellersAdded, PromotedSellersRemoved};
+
+#[derive(Accounts)]
+pub struct UpdatePromotedSellers<'info> {
+    #[account(mut, has_one = authority @ RewardsError::Unauthorized)]
+    pub promotion: Account<'info, Promotion>,
+
+    pub authority: Signer<'info>,
+}
+
+/// Sellers already promoted stay untouched; passing one of them fails the whole call
+pub fn add_handler(ctx: Context<UpdatePromotedSellers>, sellers: Vec<Pubkey>) -> Result<()> {
+    let promotion = &mut ctx.accounts.promotion;
+
+    for seller in &sellers {
+        require_keys_neq!(*seller, Pubkey::default(), RewardsError::InvalidSeller);
+        // Also rejects the same seller passed twice
+        require!(!promotion.is_promoted(seller), RewardsError::SellerAlreadyPromoted);
+        promotion.promoted_sellers.push(*seller);
+    }
+    require!(
+        promotion.promoted_sellers.len() <= MAX_PROMOTED_SELLERS,
+        RewardsError::TooManyPromotedSellers
+    );
+
+    emit!(PromotedSellersAdded {
+        promotion: promotion.key(),
+        sellers,
+    });
+
+    Ok(())
+}
+
+/// Sellers that are not promoted are ignored
+pub fn remove_handler(ctx: Context<UpdatePromotedSellers>, sellers: Vec<Pubkey>) -> Result<()> {
+    let promotion = &mut ctx.accounts.promotion;
+    promotion.promoted_sellers.retain(|seller| !sellers.contains(seller));
+
+    emit!(PromotedSellersRemoved {
+        promotion: promotion.key(),
+        sellers,
+    });
+
+    Ok(())
+}
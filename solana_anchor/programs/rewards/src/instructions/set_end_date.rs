@@ -0,0 +1,26 @@
+use anchor_lang::prelude::*;
+use crate::state::Promotion;
+use crate::error::RewardsError;
+use crate::events::EndDateChanged;
+
+#[derive(Accounts)]
+pub struct SetEndDate<'info> {
+    #[account(mut, has_one = authority @ RewardsError::Unauthorized)]
+    pub promotion: Account<'info, Promotion>,
+
+    pub authority: Signer<'info>,
+}
+
+pub fn handler(ctx: Context<SetEndDate>, end_date: i64) -> Result<()> {
+    require!(end_date > Clock::get()?.unix_timestamp, RewardsError::InvalidEndDate);
+
+    let promotion = &mut ctx.accounts.promotion;
+    promotion.end_date = end_date;
+
+    emit!(EndDateChanged {
+        promotion: promotion.key(),
+        end_date,
+    });
+
+    Ok(())
+}
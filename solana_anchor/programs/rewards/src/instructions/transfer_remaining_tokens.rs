@@ -0,0 +1,53 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+use crate::state::Promotion;
+use crate::error::RewardsError;
+
+#[derive(Accounts)]
+pub struct TransferRemainingTokens<'info> {
+    #[account(has_one = authority @ RewardsError::Unauthorized, has_one = reward_vault)]
+    pub promotion: Account<'info, Promotion>,
+
+    #[account(mut)]
+    pub reward_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = receiver.mint == promotion.reward_mint @ RewardsError::Unauthorized,
+    )]
+    pub receiver: Account<'info, TokenAccount>,
+
+    pub authority: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+/// Sweep the whole vault to a token account of the authority's choosing
+pub fn handler(ctx: Context<TransferRemainingTokens>) -> Result<()> {
+    let amount = ctx.accounts.reward_vault.amount;
+    let promotion = &ctx.accounts.promotion;
+    let id = promotion.promotion_id.to_le_bytes();
+    let seeds: &[&[u8]] = &[b"promotion", promotion.authority.as_ref(), &id, &[promotion.bump]];
+
+    token::transfer(
+        CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.reward_vault.to_account_info(),
+                to: ctx.accounts.receiver.to_account_info(),
+                authority: ctx.accounts.promotion.to_account_info(),
+            },
+            &[seeds],
+        ),
+        amount,
+    )?;
+
+    msg!(
+        "Remaining reward tokens transferred: Promotion={}, Receiver={}, Amount={}",
+        ctx.accounts.promotion.key(),
+        ctx.accounts.receiver.key(),
+        amount
+    );
+
+    Ok(())
+}
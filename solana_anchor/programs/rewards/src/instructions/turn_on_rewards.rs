@@ -0,0 +1,25 @@
+use anchor_lang::prelude::*;
+use crate::state::Promotion;
+use crate::error::RewardsError;
+use crate::events::RewardsOn;
+
+#[derive(Accounts)]
+pub struct TurnOnRewards<'info> {
+    #[account(mut, has_one = authority @ RewardsError::Unauthorized)]
+    pub promotion: Account<'info, Promotion>,
+
+    pub authority: Signer<'info>,
+}
+
+pub fn handler(ctx: Context<TurnOnRewards>) -> Result<()> {
+    let promotion = &mut ctx.accounts.promotion;
+    require!(!promotion.rewards_on, RewardsError::RewardsAlreadyOn);
+
+    promotion.rewards_on = true;
+
+    emit!(RewardsOn {
+        promotion: promotion.key(),
+    });
+
+    Ok(())
+}
@@ -0,0 +1,38 @@
+use anchor_lang::prelude::*;
+use crate::state::Promotion;
+use crate::error::RewardsError;
+
+#[derive(Accounts)]
+pub struct UpdatePromotion<'info> {
+    #[account(mut, has_one = authority @ RewardsError::Unauthorized)]
+    pub promotion: Account<'info, Promotion>,
+
+    pub authority: Signer<'info>,
+}
+
+/// Rewards already claimed are kept when the limits change
+pub fn handler(
+    ctx: Context<UpdatePromotion>,
+    max_reward_per_seller: u64,
+    time_window: i64,
+    settlement_mint: Option<Pubkey>,
+    min_settlement_amount: u64,
+) -> Result<()> {
+    Promotion::validate_limits(max_reward_per_seller, time_window)?;
+
+    let promotion = &mut ctx.accounts.promotion;
+    promotion.max_reward_per_seller = max_reward_per_seller;
+    promotion.time_window = time_window;
+    promotion.settlement_mint = settlement_mint;
+    promotion.min_settlement_amount = min_settlement_amount;
+
+    msg!(
+        "Promotion updated: Promotion={}, Max per seller={}, Time window={}s, Min settlement={}",
+        promotion.key(),
+        max_reward_per_seller,
+        time_window,
+        min_settlement_amount
+    );
+
+    Ok(())
+}
@@ -0,0 +1,84 @@
+use anchor_lang::prelude::*;
+
+pub mod state;
+pub mod instructions;
+pub mod error;
+pub mod events;
+
+use instructions::{
+    initialize_promotion::*,
+    update_promotion::*,
+    promoted_sellers::*,
+    turn_on_rewards::*,
+    set_end_date::*,
+    transfer_remaining_tokens::*,
+    claim_rewards::*,
+};
+
+declare_id!("E7Jd3yZMR7pdVhDtLkg1dBE7EqVwFxhxgS8HGSWXimFK");
+
+#[program]
+pub mod rewards {
+    use super::*;
+
+    /// Create a promotion with its reward vault, per-seller cap, claim window and
+    /// minimum qualifying settlement
+    pub fn initialize_promotion(
+        ctx: Context<InitializePromotion>,
+        promotion_id: u64,
+        max_reward_per_seller: u64,
+        time_window: i64,
+        settlement_mint: Option<Pubkey>,
+        min_settlement_amount: u64,
+    ) -> Result<()> {
+        instructions::initialize_promotion::handler(
+            ctx,
+            promotion_id,
+            max_reward_per_seller,
+            time_window,
+            settlement_mint,
+            min_settlement_amount,
+        )
+    }
+
+    /// Change the per-seller cap, the claim window and the minimum qualifying settlement
+    pub fn update_promotion(
+        ctx: Context<UpdatePromotion>,
+        max_reward_per_seller: u64,
+        time_window: i64,
+        settlement_mint: Option<Pubkey>,
+        min_settlement_amount: u64,
+    ) -> Result<()> {
+        instructions::update_promotion::handler(ctx, max_reward_per_seller, time_window, settlement_mint, min_settlement_amount)
+    }
+
+    /// Add sellers whose buyers earn rewards
+    pub fn add_promoted_sellers(ctx: Context<UpdatePromotedSellers>, sellers: Vec<Pubkey>) -> Result<()> {
+        instructions::promoted_sellers::add_handler(ctx, sellers)
+    }
+
+    /// Stop promoting sellers; rewards already claimed are kept
+    pub fn remove_promoted_sellers(ctx: Context<UpdatePromotedSellers>, sellers: Vec<Pubkey>) -> Result<()> {
+        instructions::promoted_sellers::remove_handler(ctx, sellers)
+    }
+
+    /// Start reward distribution; it cannot be turned off, only ended with the end date
+    pub fn turn_on_rewards(ctx: Context<TurnOnRewards>) -> Result<()> {
+        instructions::turn_on_rewards::handler(ctx)
+    }
+
+    /// Set the time after which no rewards can be claimed
+    pub fn set_end_date(ctx: Context<SetEndDate>, end_date: i64) -> Result<()> {
+        instructions::set_end_date::handler(ctx, end_date)
+    }
+
+    /// Move every token left in the reward vault to the receiver
+    pub fn transfer_remaining_tokens(ctx: Context<TransferRemainingTokens>) -> Result<()> {
+        instructions::transfer_remaining_tokens::handler(ctx)
+    }
+
+    /// Buyer claims the reward for an escrow released to a promoted seller
+    pub fn claim_rewards(ctx: Context<ClaimRewards>) -> Result<()> {
+        instructions::claim_rewards::handler(ctx)
+    }
+}
@@ -0,0 +1,134 @@
+use anchor_lang::prelude::*;
+use crate::error::RewardsError;
+
+pub const MAX_PROMOTED_SELLERS: usize = 64;
+pub const REWARD_TOKENS_PER_BUYER: u64 = 50; // whole tokens a buyer can earn per promoted seller
+
+/// One promotion: its promoted sellers, limits and reward vault
+#[account]
+pub struct Promotion {
+    pub authority: Pubkey,
+    pub promotion_id: u64,
+    pub reward_mint: Pubkey,
+    pub reward_vault: Pubkey, // associated token account of the promotion PDA
+    pub max_reward_per_seller: u64,
+    pub max_reward_per_buyer: u64, // REWARD_TOKENS_PER_BUYER in raw units of reward_mint
+    pub time_window: i64, // seconds after settlement during which the buyer can claim
+    pub rewards_on: bool,
+    pub end_date: i64, // no claims at or after this time
+    pub total_distributed: u64,
+    pub promoted_sellers: Vec<Pubkey>,
+    pub bump: u8,
+    pub settlement_mint: Option<Pubkey>, // mint of qualifying purchases, None for SOL; only checked with a minimum
+    pub min_settlement_amount: u64, // smallest payout to the seller that earns a reward, 0 for no minimum
+}
+
+impl Promotion {
+    pub const LEN: usize = 8 + // discriminator
+                          32 + // authority
+                          8 + // promotion_id
+                          32 + // reward_mint
+                          32 + // reward_vault
+                          8 + // max_reward_per_seller
+                          8 + // max_reward_per_buyer
+                          8 + // time_window
+                          1 + // rewards_on
+                          8 + // end_date
+                          8 + // total_distributed
+                          4 + MAX_PROMOTED_SELLERS * 32 + // promoted_sellers
+                          1 + // bump
+                          33 + // settlement_mint (Option<Pubkey>)
+                          8; // min_settlement_amount
+
+    pub fn is_running(&self, now: i64) -> bool {
+        self.rewards_on && self.end_date > now
+    }
+
+    pub fn is_promoted(&self, seller: &Pubkey) -> bool {
+        self.promoted_sellers.contains(seller)
+    }
+
+    /// Reward still available to `buyer_reward` for buying from `seller_rewards`' seller:
+    /// the rest of the per-buyer allowance, limited by what is left of the seller's cap
+    pub fn reward_for(&self, seller_rewards: &SellerRewards, buyer_reward: &BuyerReward) -> u64 {
+        let seller_left = self.max_reward_per_seller.saturating_sub(seller_rewards.distributed);
+        let buyer_left = self.max_reward_per_buyer.saturating_sub(buyer_reward.rewarded);
+        buyer_left.min(seller_left)
+    }
+
+    /// Whether a settlement that paid the seller `paid` of `mint` is large enough to earn a reward
+    pub fn meets_minimum(&self, mint: Option<Pubkey>, paid: u64) -> bool {
+        self.min_settlement_amount == 0 ||
+            (mint == self.settlement_mint && paid >= self.min_settlement_amount)
+    }
+
+    pub fn validate_limits(max_reward_per_seller: u64, time_window: i64) -> Result<()> {
+        require!(max_reward_per_seller > 0, RewardsError::InvalidMaxReward);
+        require!(time_window > 0, RewardsError::InvalidTimeWindow);
+        Ok(())
+    }
+}
+
+/// Rewards handed out for purchases from one promoted seller
+#[account]
+pub struct SellerRewards {
+    pub promotion: Pubkey,
+    pub seller: Pubkey,
+    pub distributed: u64, // counts owed rewards as soon as they are claimed, paid or not
+    pub rewarded_buyers: u32,
+    pub bump: u8,
+}
+
+impl SellerRewards {
+    pub const LEN: usize = 8 + // discriminator
+                          32 + // promotion
+                          32 + // seller
+                          8 + // distributed
+                          4 + // rewarded_buyers
+                          1; // bump
+}
+
+/// Reward a buyer earned from one promoted seller
+#[account]
+pub struct BuyerReward {
+    pub promotion: Pubkey,
+    pub seller: Pubkey,
+    pub buyer: Pubkey,
+    pub rewarded: u64,
+    pub bump: u8,
+}
+
+impl BuyerReward {
+    pub const LEN: usize = 8 + // discriminator
+                          32 + // promotion
+                          32 + // seller
+                          32 + // buyer
+                          8 + // rewarded
+                          1; // bump
+}
+
+/// Claim made with one settlement receipt. Seeds are ["reward_claim", promotion, escrow],
+/// so each settled escrow is rewarded at most once per promotion.
+#[account]
+pub struct RewardClaim {
+    pub promotion: Pubkey,
+    pub escrow: Pubkey,
+    pub buyer: Pubkey,
+    pub seller: Pubkey,
+    pub owed: u64, // fixed by the first claim
+    pub paid: u64, // below `owed` only while the vault was short
+    pub claimed_at: i64,
+    pub bump: u8,
+}
+
+impl RewardClaim {
+    pub const LEN: usize = 8 + // discriminator
+                          32 + // promotion
+                          32 + // escrow
+                          32 + // buyer
+                          32 + // seller
+                          8 + // owed
+                          8 + // paid
+                          8 + // claimed_at
+                          1; // bump
+}
@@ -0,0 +1,66 @@
+import { PublicKey } from "@solana/web3.js";
+import { createHash } from "crypto";
+import fs from 'fs';
+import path from 'path';
+
+// 生成已过期的领取凭证，供 solana-test-validator 通过 Anchor.toml 预加载，
+// 用于测试 reclaim_expired_claim（本地验证器无法把时钟推进 180 天）。
+// 输入固定，重新生成的文件应与仓库中的一致。
+const PROGRAM_ID = new PublicKey("25ecY9sGUkFyy78aYaSbdWGMgySSKZvPjQunf6Uk23qk");
+const fixturesDir = path.join(process.cwd(), 'tests', 'fixtures');
+
+const key = byte => new PublicKey(Buffer.alloc(32, byte));
+const ESCROW = key(5);
+const OWNER = key(6);
+const PAYER = key(7);
+const RENT_PAYER = key(8);
+
+const AMOUNT = 1_000_000n;
+const EXPIRES_AT = 1_700_000_000n;
+const CLAIM_TICKET_LEN = 186;
+
+const discriminator = name =>
+  createHash('sha256').update(`account:${name}`).digest().subarray(0, 8);
+
+// 与 Rent::default() 一致：(128 + 数据长度) * 每字节每年 3480 lamports * 2 年
+const rentExempt = len => (128 + len) * 6960;
+
+const u64 = value => {
+  const buf = Buffer.alloc(8);
+  buf.writeBigUInt64LE(BigInt.asUintN(64, value));
+  return buf;
+};
+
+// ClaimTicket：escrow, owner, mint, amount, payer_address, rent_payer, expires_at, bump
+const [address, bump] = PublicKey.findProgramAddressSync(
+  [Buffer.from('claim_ticket'), ESCROW.toBuffer(), OWNER.toBuffer()],
+  PROGRAM_ID
+);
+const data = Buffer.concat([
+  discriminator('ClaimTicket'),
+  ESCROW.toBuffer(),
+  OWNER.toBuffer(),
+  Buffer.from([0]), // SOL 凭证没有 mint
+  u64(AMOUNT),
+  PAYER.toBuffer(),
+  RENT_PAYER.toBuffer(),
+  u64(EXPIRES_AT),
+  Buffer.from([bump]),
+]);
+
+// SOL 凭证的资金以 lamports 形式存放在凭证账户中
+const fixture = {
+  pubkey: address.toBase58(),
+  account: {
+    lamports: rentExempt(CLAIM_TICKET_LEN) + Number(AMOUNT),
+    data: [data.toString('base64'), 'base64'],
+    owner: PROGRAM_ID.toBase58(),
+    executable: false,
+    rentEpoch: 0,
+    space: CLAIM_TICKET_LEN,
+  },
+};
+
+fs.mkdirSync(fixturesDir, { recursive: true });
+fs.writeFileSync(path.join(fixturesDir, 'expired-claim-ticket.json'), JSON.stringify(fixture, null, 2) + '\n');
+console.log(`已生成 expired-claim-ticket.json: ${address.toBase58()}`);
@@ -0,0 +1,131 @@
+import { PublicKey } from "@solana/web3.js";
+import { createHash } from "crypto";
+import fs from 'fs';
+import path from 'path';
+
+// 生成版本化之前（版本1）和版本2布局的托管账户，供 solana-test-validator 通过 Anchor.toml 预加载，
+// 用于测试 migrate_escrow。输入固定，重新生成的文件应与仓库中的一致。
+const PROGRAM_ID = new PublicKey("25ecY9sGUkFyy78aYaSbdWGMgySSKZvPjQunf6Uk23qk");
+const fixturesDir = path.join(process.cwd(), 'tests', 'fixtures');
+
+const key = byte => new PublicKey(Buffer.alloc(32, byte));
+const BUYER = key(1);
+const SELLER = key(2);
+const MODERATOR = key(3);
+const MINT = key(4);
+const ORACLE = key(5);
+
+const AMOUNT = 1_000_000n;
+const UNLOCK_TIME = 1_700_000_000n;
+const REQUIRED_SIGNATURES = 2;
+const V2_NONCE = 7n;
+const V2_MODERATOR_FEE_BPS_MAX = 500;
+
+const discriminator = name =>
+  createHash('sha256').update(`account:${name}`).digest().subarray(0, 8);
+
+// 与 Rent::default() 一致：(128 + 数据长度) * 每字节每年 3480 lamports * 2 年
+const rentExempt = len => (128 + len) * 6960;
+
+const u64 = value => {
+  const buf = Buffer.alloc(8);
+  buf.writeBigUInt64LE(BigInt.asUintN(64, value));
+  return buf;
+};
+
+// 旧版 EscrowAccount：is_initialized, buyer, seller, moderator, payer_address,
+// required_signatures, unlock_time, unique_id, amount, bump
+function legacyBase(moderator, uniqueId, bump) {
+  return Buffer.concat([
+    Buffer.from([1]),
+    BUYER.toBuffer(),
+    SELLER.toBuffer(),
+    moderator ? Buffer.concat([Buffer.from([1]), moderator.toBuffer()]) : Buffer.from([0]),
+    BUYER.toBuffer(),
+    Buffer.from([REQUIRED_SIGNATURES]),
+    u64(UNLOCK_TIME),
+    uniqueId,
+    u64(AMOUNT),
+    Buffer.from([bump]),
+  ]);
+}
+
+// 版本2 EscrowAccount：version, 旧版字段, nonce, hashlock, stream, oracle, delivered_at,
+// moderator_fee_bps_max, moderator_fee_fixed, panel, receipt_tree, 64 个保留字节
+function v2Base(moderator, uniqueId, bump) {
+  const feeBpsMax = Buffer.alloc(2);
+  feeBpsMax.writeUInt16LE(V2_MODERATOR_FEE_BPS_MAX);
+  return Buffer.concat([
+    Buffer.from([2]),
+    legacyBase(moderator, uniqueId, bump),
+    u64(V2_NONCE),
+    Buffer.from([0, 0]), // hashlock, stream
+    Buffer.concat([Buffer.from([1]), ORACLE.toBuffer()]),
+    Buffer.from([0]), // delivered_at
+    feeBpsMax,
+    u64(0n),
+    Buffer.from([0, 0]), // panel, receipt_tree
+    Buffer.alloc(64),
+  ]);
+}
+
+function writeFixture(file, { name, prefix, len, moderator, uniqueId, mint, base = legacyBase }) {
+  const [address, bump] = PublicKey.findProgramAddressSync(
+    [Buffer.from(prefix), BUYER.toBuffer(), SELLER.toBuffer(), Buffer.from([moderator ? 1 : 0]), uniqueId],
+    PROGRAM_ID
+  );
+
+  // 旧程序按完整长度分配账户，moderator 为空时尾部留零
+  const data = Buffer.alloc(len);
+  Buffer.concat([
+    discriminator(name),
+    base(moderator, uniqueId, bump),
+    mint ? mint.toBuffer() : Buffer.alloc(0),
+  ]).copy(data);
+
+  // SOL 托管的资金以 lamports 形式存放在托管账户中
+  const lamports = rentExempt(len) + (mint ? 0 : Number(AMOUNT));
+
+  const fixture = {
+    pubkey: address.toBase58(),
+    account: {
+      lamports,
+      data: [data.toString('base64'), 'base64'],
+      owner: PROGRAM_ID.toBase58(),
+      executable: false,
+      rentEpoch: 0,
+      space: len,
+    },
+  };
+  fs.writeFileSync(path.join(fixturesDir, file), JSON.stringify(fixture, null, 2) + '\n');
+  console.log(`已生成 ${file}: ${address.toBase58()}`);
+}
+
+fs.mkdirSync(fixturesDir, { recursive: true });
+
+writeFixture('legacy-sol-escrow.json', {
+  name: 'SolEscrow',
+  prefix: 'sol_escrow',
+  len: 176,
+  moderator: null,
+  uniqueId: Buffer.alloc(20, 0x11),
+});
+
+writeFixture('legacy-token-escrow.json', {
+  name: 'TokenEscrow',
+  prefix: 'token_escrow',
+  len: 208,
+  moderator: MODERATOR,
+  uniqueId: Buffer.alloc(20, 0x22),
+  mint: MINT,
+});
+
+// 版本2账户与当前布局长度相同：8 + EscrowAccount::LEN
+writeFixture('v2-sol-escrow.json', {
+  name: 'SolEscrow',
+  prefix: 'sol_escrow',
+  len: 567,
+  moderator: MODERATOR,
+  uniqueId: Buffer.alloc(20, 0x33),
+  base: v2Base,
+});
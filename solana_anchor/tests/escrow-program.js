@@ -1,6 +1,6 @@
 import * as anchor from "@coral-xyz/anchor";
 import { Keypair, PublicKey, SystemProgram, LAMPORTS_PER_SOL } from "@solana/web3.js";
-import { TOKEN_PROGRAM_ID, ASSOCIATED_TOKEN_PROGRAM_ID, createMint, createAccount, mintTo, getAccount } from "@solana/spl-token";
+import { TOKEN_PROGRAM_ID, ASSOCIATED_TOKEN_PROGRAM_ID, NATIVE_MINT, createMint, createAccount, mintTo, transfer, createSyncNativeInstruction, getAccount, getAssociatedTokenAddressSync } from "@solana/spl-token";
 import chai from "chai";
 import { randomBytes, createHash } from "crypto";
 import { keccak_256 } from '@noble/hashes/sha3';
@@ -83,6 +83,128 @@ describe("escrow-program 测试", () => {
     console.log(`已向 ${toKeypair.publicKey.toString()} 转账 ${amount} SOL`);
   };
   
+  // 初始化选项的默认值，按需覆盖个别字段
+  const escrowOptions = (overrides = {}) => ({
+    hashlock: null,
+    stream: null,
+    oracle: null,
+    moderatorFeeBpsMax: 0,
+    moderatorFeeFixed: new BN(0),
+    panel: null,
+    requireRegisteredModerator: false,
+    minSellerSettlements: new BN(0),
+    receiptTree: null,
+    releaseHook: null,
+    ...overrides,
+  });
+  
+  const reputationAccounts = (sellerKey, buyerKey) => ({
+    sellerReputation: PublicKey.findProgramAddressSync(
+      [Buffer.from("seller_reputation"), sellerKey.toBuffer()],
+      program.programId
+    )[0],
+    buyerReputation: PublicKey.findProgramAddressSync(
+      [Buffer.from("buyer_reputation"), buyerKey.toBuffer()],
+      program.programId
+    )[0],
+  });
+  
+  // 释放批准消息：unique_id || nonce || valid_until || (recipient || amount)*
+  const releaseMessage = (id, nonce, validUntil, payouts) => Buffer.concat([
+    id,
+    Buffer.from(new BN(nonce).toArray('le', 8)),
+    Buffer.from(new BN(validUntil).toTwos(64).toArray('le', 8)),
+    ...payouts.flatMap(([recipient, amount]) => [
+      recipient.toBuffer(),
+      Buffer.from(new BN(amount).toArray('le', 8)),
+    ]),
+  ]);
+  
+  // 一条 Ed25519 指令携带多个签名者对同一消息的签名
+  const ed25519Instruction = (message, keypairs) => {
+    const headerSize = 2 + keypairs.length * 14;
+    const entrySize = 32 + 64;
+    const messageOffset = headerSize + keypairs.length * entrySize;
+    const data = Buffer.alloc(messageOffset + message.length);
+    data.writeUInt8(keypairs.length, 0);
+    keypairs.forEach((keypair, i) => {
+      const publicKeyOffset = headerSize + i * entrySize;
+      const signatureOffset = publicKeyOffset + 32;
+      const offsets = 2 + i * 14;
+      data.writeUInt16LE(signatureOffset, offsets);
+      data.writeUInt16LE(0xffff, offsets + 2);
+      data.writeUInt16LE(publicKeyOffset, offsets + 4);
+      data.writeUInt16LE(0xffff, offsets + 6);
+      data.writeUInt16LE(messageOffset, offsets + 8);
+      data.writeUInt16LE(message.length, offsets + 10);
+      data.writeUInt16LE(0xffff, offsets + 12);
+      keypair.publicKey.toBuffer().copy(data, publicKeyOffset);
+      Buffer.from(nacl.sign.detached(message, keypair.secretKey)).copy(data, signatureOffset);
+    });
+    message.copy(data, messageOffset);
+    return new anchor.web3.TransactionInstruction({ programId: ED25519_PROGRAM_ID, keys: [], data });
+  };
+  
+  // 创建由买家出资的 SOL 托管
+  const openSolEscrow = async ({
+    amount,
+    requiredSignatures = 1,
+    moderatorKey = null,
+    sellerKey = seller.publicKey,
+    hours = unlockHours,
+    options = escrowOptions(),
+    accounts = {},
+  }) => {
+    const id = generateRandomUniqueId();
+    const [escrow] = PublicKey.findProgramAddressSync(
+      [Buffer.from("sol_escrow"), buyer.publicKey.toBuffer(), sellerKey.toBuffer(), Buffer.from([moderatorKey ? 1 : 0]), id],
+      program.programId
+    );
+    await program.methods
+      .initializeSol(moderatorKey, Array.from(id), requiredSignatures, new BN(hours), new BN(amount), options)
+      .accounts({
+        payer: buyer.publicKey,
+        buyer: buyer.publicKey,
+        seller: sellerKey,
+        escrowAccount: escrow,
+        receiptTree: null,
+        moderatorProfile: null,
+        registryConfig: null,
+        sellerReputation: null,
+        ...accounts,
+      })
+      .signers([buyer])
+      .rpc();
+    return { escrow, id };
+  };
+  
+  // 由 signers 批准 payouts 并调用 release_sol，initiator 为买家
+  const releaseSol = ({ escrow, id, sellerKey = seller.publicKey }, payouts, signers, { nonce = 0, validUntil = 0 } = {}) => {
+    const message = releaseMessage(id, nonce, validUntil, payouts);
+    const recipients = payouts.map(([recipient]) => recipient);
+    return program.methods
+      .releaseSol(
+        payouts.map(([, amount]) => new BN(amount)),
+        signers.map((keypair) => Buffer.from(nacl.sign.detached(message, keypair.secretKey))),
+        new BN(validUntil)
+      )
+      .accounts({
+        initiator: buyer.publicKey,
+        escrowAccount: escrow,
+        buyer: buyer.publicKey,
+        seller: sellerKey,
+        ...reputationAccounts(sellerKey, buyer.publicKey),
+        receiptTree: null,
+        sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        recipient1: recipients[0],
+        recipient2: recipients[1] ?? null,
+        recipient3: recipients[2] ?? null,
+      })
+      .preInstructions([ed25519Instruction(message, signers)])
+      .signers([buyer])
+      .rpc();
+  };
+  
   // 初始化测试环境
   before(async () => {
     console.log("程序ID:", program.programId.toString());
@@ -273,13 +395,17 @@ describe("escrow-program 测试", () => {
           Array.from(uniqueId),
           requiredSignatures,
           new BN(unlockHours),
-          new BN(escrowAmount)
+          new BN(escrowAmount),
+          { hashlock: null, stream: null, oracle: null, moderatorFeeBpsMax: 0, moderatorFeeFixed: new BN(0), panel: null, requireRegisteredModerator: false, minSellerSettlements: new BN(0), receiptTree: null, releaseHook: null } // options
         )
         .accounts({
           payer: buyer.publicKey,
           buyer: buyer.publicKey,
           seller: seller.publicKey,
           escrowAccount: escrowAccount,
+          moderatorProfile: null,
+          registryConfig: null,
+          sellerReputation: null,
           systemProgram: SystemProgram.programId,
           rent: anchor.web3.SYSVAR_RENT_PUBKEY,
           clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
@@ -360,18 +486,24 @@ describe("escrow-program 测试", () => {
           Array.from(tokenUniqueId),
           requiredSignatures,
           new BN(unlockHours),
-          new BN(tokenAmount)
+          new BN(tokenAmount),
+          { hashlock: null, stream: null, oracle: null, moderatorFeeBpsMax: 0, moderatorFeeFixed: new BN(0), panel: null, requireRegisteredModerator: false, minSellerSettlements: new BN(0), receiptTree: null, releaseHook: null } // options
         )
         .accounts({
           payer: buyer.publicKey,
           buyer: buyer.publicKey,
           seller: seller.publicKey,
           escrowAccount: tokenEscrowAccount,
+          moderatorProfile: null,
+          registryConfig: null,
+          sellerReputation: null,
           tokenProgram: TOKEN_PROGRAM_ID,
           tokenMint: tokenMint,
           associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
           buyerTokenAccount: buyerTokenAccount,
           escrowTokenAccount: escrowTokenAccount,
+          sellerBalance: null,
+          balanceTokenAccount: null,
           systemProgram: SystemProgram.programId,
           rent: anchor.web3.SYSVAR_RENT_PUBKEY,
           clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
@@ -388,6 +520,8 @@ describe("escrow-program 测试", () => {
       const escrow = await program.account.tokenEscrow.fetch(tokenEscrowAccount);
       console.log("SPL托管账户数据:", escrow);
       assert.equal(escrow.amount.toString(), tokenAmount.toString());
+      // 9 位精度的代币：一个整币以上的结算才计入卖家历史
+      assert.equal(escrow.base.countedMinAmount.toString(), "1000000000");
       
       // 验证代币已转移到托管账户
       const tokenAccountInfo = await getAccount(provider.connection, escrowTokenAccount);
@@ -429,12 +563,16 @@ describe("escrow-program 测试", () => {
         Array.from(solUniqueId),
         1, // 只需要1个签名
         new BN(unlockHours),
-        new BN(solAmount)
+        new BN(solAmount),
+        { hashlock: null, stream: null, oracle: null, moderatorFeeBpsMax: 0, moderatorFeeFixed: new BN(0), panel: null, requireRegisteredModerator: false, minSellerSettlements: new BN(0), receiptTree: null, releaseHook: null } // options
       )
       .accounts({
         buyer: buyer.publicKey,
         seller: seller.publicKey,
         escrowAccount: solEscrowAccount,
+        moderatorProfile: null,
+        registryConfig: null,
+        sellerReputation: null,
         systemProgram: SystemProgram.programId,
         rent: anchor.web3.SYSVAR_RENT_PUBKEY,
         clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
@@ -445,6 +583,8 @@ describe("escrow-program 测试", () => {
     
     // 创建消息进行签名
     let message = Buffer.from([...solUniqueId]);
+    message = Buffer.concat([message, Buffer.from(new BN(0).toArray('le', 8))]); // nonce
+    message = Buffer.concat([message, Buffer.from(new BN(0).toArray('le', 8))]); // valid_until，0 表示不过期
     message = Buffer.concat([message, seller.publicKey.toBuffer()]);
     message = Buffer.concat([message, Buffer.from(new BN(solAmount).toArray('le', 8))]);
     
@@ -464,7 +604,8 @@ describe("escrow-program 测试", () => {
       const tx = await program.methods
         .releaseSol(
           [new BN(solAmount)],
-          [Buffer.from(buyerSignature)]
+          [Buffer.from(buyerSignature)],
+          new BN(0)
         )
         .accounts({
           initiator: buyer.publicKey,
@@ -552,12 +693,16 @@ describe("escrow-program 测试", () => {
         Array.from(tokenUniqueId),
         1, // 只需要1个签名
         new BN(unlockHours),
-        new BN(tokenAmount)
+        new BN(tokenAmount),
+        { hashlock: null, stream: null, oracle: null, moderatorFeeBpsMax: 0, moderatorFeeFixed: new BN(0), panel: null, requireRegisteredModerator: false, minSellerSettlements: new BN(0), receiptTree: null, releaseHook: null } // options
       )
       .accounts({
         buyer: buyer.publicKey,
         seller: seller.publicKey,
         escrowAccount: tokenEscrowAccount,
+        moderatorProfile: null,
+        registryConfig: null,
+        sellerReputation: null,
         systemProgram: SystemProgram.programId,
         rent: anchor.web3.SYSVAR_RENT_PUBKEY,
         clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
@@ -566,6 +711,8 @@ describe("escrow-program 测试", () => {
         associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
         buyerTokenAccount: buyerTokenAccount,
         escrowTokenAccount: escrowTokenAccount,
+        sellerBalance: null,
+        balanceTokenAccount: null,
         ed25519Program: ED25519_PROGRAM_ID,
       })
       .signers([buyer])
@@ -573,6 +720,8 @@ describe("escrow-program 测试", () => {
     
     // 创建消息进行签名
     let message = Buffer.from([...tokenUniqueId]);
+    message = Buffer.concat([message, Buffer.from(new BN(0).toArray('le', 8))]); // nonce
+    message = Buffer.concat([message, Buffer.from(new BN(0).toArray('le', 8))]); // valid_until，0 表示不过期
     message = Buffer.concat([message, recipientTokenAccount.toBuffer()]); // 添加接收方地址到消息
     message = Buffer.concat([message, Buffer.from(new BN(tokenAmount).toArray('le', 8))]);
     
@@ -592,7 +741,8 @@ describe("escrow-program 测试", () => {
       await program.methods
         .releaseToken(
           [new BN(tokenAmount)],
-          [Buffer.from(buyerSignature)]
+          [Buffer.from(buyerSignature)],
+          new BN(0)
         )
         .accounts({
           initiator: buyer.publicKey,
@@ -600,6 +750,8 @@ describe("escrow-program 测试", () => {
           tokenProgram: TOKEN_PROGRAM_ID,
           associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
           escrowTokenAccount: escrowTokenAccount,
+          sellerBalance: null,
+          balanceTokenAccount: null,
           buyer: buyer.publicKey,
           recipient1: recipientTokenAccount,
           recipient2: null,
@@ -680,12 +832,16 @@ describe("escrow-program 测试", () => {
         Array.from(splitUniqueId),
         1, // 1个签名
         new BN(unlockHours),
-        new BN(splitAmount)
+        new BN(splitAmount),
+        { hashlock: null, stream: null, oracle: null, moderatorFeeBpsMax: 3000, moderatorFeeFixed: new BN(0), panel: null, requireRegisteredModerator: false, minSellerSettlements: new BN(0), receiptTree: null, releaseHook: null } // moderator 最多获得 30%
       )
       .accounts({
         buyer: buyer.publicKey,
         seller: seller.publicKey,
         escrowAccount: splitEscrowAccount,
+        moderatorProfile: null,
+        registryConfig: null,
+        sellerReputation: null,
         systemProgram: SystemProgram.programId,
         rent: anchor.web3.SYSVAR_RENT_PUBKEY,
         clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
@@ -700,6 +856,8 @@ describe("escrow-program 测试", () => {
     
     // 创建消息进行签名
     let message = Buffer.from([...splitUniqueId]);
+    message = Buffer.concat([message, Buffer.from(new BN(0).toArray('le', 8))]); // nonce
+    message = Buffer.concat([message, Buffer.from(new BN(0).toArray('le', 8))]); // valid_until，0 表示不过期
     message = Buffer.concat([message, recipient1.toBuffer()]);
     message = Buffer.concat([message, Buffer.from(new BN(amount1).toArray('le', 8))]);
     message = Buffer.concat([message, recipient2.toBuffer()]);
@@ -748,7 +906,8 @@ describe("escrow-program 测试", () => {
         .releaseSol(
           // 方法参数
           [new BN(amount1), new BN(amount2)], 
-          [Buffer.from(signature)]
+          [Buffer.from(signature)],
+          new BN(0)
         )
         .accounts(accountsToExport)
         .instruction();
@@ -763,7 +922,8 @@ describe("escrow-program 测试", () => {
       await program.methods
         .releaseSol(
           [new BN(amount1), new BN(amount2)],
-          [Buffer.from(signature)]
+          [Buffer.from(signature)],
+          new BN(0)
         )
         .accounts({
           initiator: buyer.publicKey,
@@ -835,12 +995,16 @@ describe("escrow-program 测试", () => {
         Array.from(multiSigUniqueId),
         2, // 需要2个签名
         new BN(unlockHours),
-        new BN(multiSigAmount)
+        new BN(multiSigAmount),
+        { hashlock: null, stream: null, oracle: null, moderatorFeeBpsMax: 0, moderatorFeeFixed: new BN(0), panel: null, requireRegisteredModerator: false, minSellerSettlements: new BN(0), receiptTree: null, releaseHook: null } // options
       )
       .accounts({
         buyer: buyer.publicKey,
         seller: seller.publicKey,
         escrowAccount: multiSigEscrowAccount,
+        moderatorProfile: null,
+        registryConfig: null,
+        sellerReputation: null,
         systemProgram: SystemProgram.programId,
         rent: anchor.web3.SYSVAR_RENT_PUBKEY,
         clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
@@ -854,6 +1018,8 @@ describe("escrow-program 测试", () => {
     
     // 创建消息进行签名
     let message = Buffer.from([...multiSigUniqueId]);
+    message = Buffer.concat([message, Buffer.from(new BN(0).toArray('le', 8))]); // nonce
+    message = Buffer.concat([message, Buffer.from(new BN(0).toArray('le', 8))]); // valid_until，0 表示不过期
     message = Buffer.concat([message, recipient.toBuffer()]);
     message = Buffer.concat([message, Buffer.from(new BN(multiSigAmount).toArray('le', 8))]);
     
@@ -897,7 +1063,8 @@ describe("escrow-program 测试", () => {
         .releaseSol(
           // 方法参数
           [new BN(multiSigAmount)], 
-          [Buffer.from(buyerSignature), Buffer.from(sellerSignature)]
+          [Buffer.from(buyerSignature), Buffer.from(sellerSignature)],
+          new BN(0)
         )
         .accounts(accountsToExport)
         .instruction();
@@ -912,7 +1079,8 @@ describe("escrow-program 测试", () => {
       await program.methods
         .releaseSol(
           [new BN(multiSigAmount)],
-          [Buffer.from(buyerSignature), Buffer.from(sellerSignature)]
+          [Buffer.from(buyerSignature), Buffer.from(sellerSignature)],
+          new BN(0)
         )
         .accounts({
           initiator: buyer.publicKey,
@@ -986,12 +1154,16 @@ describe("escrow-program 测试", () => {
         Array.from(expiredUniqueId),
         2, // 需要2个签名
         new BN(shortUnlockHours * 3600), // 转换为秒
-        new BN(expiredAmount)
+        new BN(expiredAmount),
+        { hashlock: null, stream: null, oracle: null, moderatorFeeBpsMax: 0, moderatorFeeFixed: new BN(0), panel: null, requireRegisteredModerator: false, minSellerSettlements: new BN(0), receiptTree: null, releaseHook: null } // options
       )
       .accounts({
         buyer: buyer.publicKey,
         seller: seller.publicKey,
         escrowAccount: expiredEscrowAccount,
+        moderatorProfile: null,
+        registryConfig: null,
+        sellerReputation: null,
         systemProgram: SystemProgram.programId,
         rent: anchor.web3.SYSVAR_RENT_PUBKEY,
         clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
@@ -1009,6 +1181,8 @@ describe("escrow-program 测试", () => {
     
     // 创建消息进行签名
     let message = Buffer.from([...expiredUniqueId]);
+    message = Buffer.concat([message, Buffer.from(new BN(0).toArray('le', 8))]); // nonce
+    message = Buffer.concat([message, Buffer.from(new BN(0).toArray('le', 8))]); // valid_until，0 表示不过期
     message = Buffer.concat([message, recipient.toBuffer()]); // 添加接收方地址
     message = Buffer.concat([message, Buffer.from(new BN(expiredAmount).toArray('le', 8))]);
     
@@ -1051,7 +1225,8 @@ describe("escrow-program 测试", () => {
         .releaseSol(
           // 方法参数
           [new BN(expiredAmount)], 
-          [Buffer.from(sellerSignature)]
+          [Buffer.from(sellerSignature)],
+          new BN(0)
         )
         .accounts(accountsToExport)
         .instruction();
@@ -1066,7 +1241,8 @@ describe("escrow-program 测试", () => {
       await program.methods
         .releaseSol(
           [new BN(expiredAmount)],
-          [Buffer.from(sellerSignature)]
+          [Buffer.from(sellerSignature)],
+          new BN(0)
         )
         .accounts({
           initiator: seller.publicKey,
@@ -1115,6 +1291,2260 @@ describe("escrow-program 测试", () => {
     );
   });
   
+  it("批量初始化多个卖家的代币托管", async () => {
+    const sellers = [seller.publicKey, moderator.publicKey];
+    const amounts = [0.1 * LAMPORTS_PER_SOL, 0.05 * LAMPORTS_PER_SOL];
+    
+    const entries = sellers.map((sellerKey, i) => ({
+      seller: sellerKey,
+      moderator: i === 0 ? moderator.publicKey : null,
+      uniqueId: Array.from(generateRandomUniqueId()),
+      requiredSignatures: i === 0 ? 2 : 1,
+      unlockHours: new BN(unlockHours),
+      amount: new BN(amounts[i]),
+    }));
+    
+    // 每个条目依次传入 [escrow_account, escrow_token_account]
+    const escrows = entries.map((entry) => {
+      const [escrowAddress] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("token_escrow"),
+          buyer.publicKey.toBuffer(),
+          entry.seller.toBuffer(),
+          Buffer.from([entry.moderator ? 1 : 0]),
+          Buffer.from(entry.uniqueId),
+        ],
+        program.programId
+      );
+      const escrowTokenAccount = anchor.utils.token.associatedAddress({
+        mint: tokenMint,
+        owner: escrowAddress
+      });
+      return { escrowAddress, escrowTokenAccount };
+    });
+    
+    await program.methods
+      .batchInitialize(entries)
+      .accounts({
+        payer: buyer.publicKey,
+        buyer: buyer.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        tokenMint: tokenMint,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        payerTokenAccount: buyerTokenAccount,
+        systemProgram: SystemProgram.programId,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+      })
+      .remainingAccounts(escrows.flatMap(({ escrowAddress, escrowTokenAccount }) => [
+        { pubkey: escrowAddress, isSigner: false, isWritable: true },
+        { pubkey: escrowTokenAccount, isSigner: false, isWritable: true },
+        {
+          pubkey: PublicKey.findProgramAddressSync(
+            [Buffer.from("receipt"), escrowAddress.toBuffer()],
+            program.programId
+          )[0],
+          isSigner: false,
+          isWritable: false,
+        },
+      ]))
+      .signers([buyer])
+      .rpc();
+    
+    for (let i = 0; i < escrows.length; i++) {
+      const escrow = await program.account.tokenEscrow.fetch(escrows[i].escrowAddress);
+      assert.equal(escrow.base.seller.toString(), sellers[i].toString());
+      assert.equal(escrow.base.amount.toString(), amounts[i].toString());
+      
+      const tokenAccountInfo = await getAccount(provider.connection, escrows[i].escrowTokenAccount);
+      assert.equal(tokenAccountInfo.amount.toString(), amounts[i].toString());
+    }
+  });
+  
+  it("哈希时间锁：提交原像后释放给卖家", async () => {
+    const htlcUniqueId = generateRandomUniqueId();
+    const htlcAmount = 0.05 * LAMPORTS_PER_SOL;
+    const preimage = randomBytes(32);
+    const hash = createHash("sha256").update(preimage).digest();
+    
+    const [htlcEscrowAccount] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("sol_escrow"),
+        buyer.publicKey.toBuffer(),
+        seller.publicKey.toBuffer(),
+        Buffer.from([moderator.publicKey ? 1 : 0]),
+        htlcUniqueId,
+      ],
+      program.programId
+    );
+    
+    await program.methods
+      .initializeSol(
+        moderator.publicKey,
+        Array.from(htlcUniqueId),
+        requiredSignatures,
+        new BN(unlockHours),
+        new BN(htlcAmount),
+        { hashlock: { algorithm: { sha256: {} }, hash: Array.from(hash) }, stream: null, oracle: null, moderatorFeeBpsMax: 0, moderatorFeeFixed: new BN(0), panel: null, requireRegisteredModerator: false, minSellerSettlements: new BN(0), receiptTree: null, releaseHook: null }
+      )
+      .accounts({
+        payer: buyer.publicKey,
+        buyer: buyer.publicKey,
+        seller: seller.publicKey,
+        escrowAccount: htlcEscrowAccount,
+        moderatorProfile: null,
+        registryConfig: null,
+        sellerReputation: null,
+        systemProgram: SystemProgram.programId,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+      })
+      .signers([buyer])
+      .rpc();
+    
+    const balanceBefore = await provider.connection.getBalance(seller.publicKey);
+    
+    // 错误的原像应当被拒绝
+    try {
+      await program.methods
+        .claimWithPreimageSol(randomBytes(32))
+        .accounts({
+          initiator: buyer.publicKey,
+          escrowAccount: htlcEscrowAccount,
+          seller: seller.publicKey,
+          buyer: buyer.publicKey,
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+        })
+        .signers([buyer])
+        .rpc();
+      assert.fail("错误的原像不应释放资金");
+    } catch (e) {
+      expect(e.toString()).to.include("InvalidPreimage");
+    }
+    
+    await program.methods
+      .claimWithPreimageSol(preimage)
+      .accounts({
+        initiator: buyer.publicKey,
+        escrowAccount: htlcEscrowAccount,
+        seller: seller.publicKey,
+        buyer: buyer.publicKey,
+        clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+      })
+      .signers([buyer])
+      .rpc();
+    
+    const balanceAfter = await provider.connection.getBalance(seller.publicKey);
+    assert.equal(balanceAfter - balanceBefore, htlcAmount);
+  });
+  
+  it("流式托管：卖家领取已归属部分，取消时拆分剩余资金", async () => {
+    const streamUniqueId = generateRandomUniqueId();
+    const streamAmount = 0.1 * LAMPORTS_PER_SOL;
+    const now = Math.floor(Date.now() / 1000);
+    
+    const [streamEscrowAccount] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("sol_escrow"),
+        buyer.publicKey.toBuffer(),
+        seller.publicKey.toBuffer(),
+        Buffer.from([moderator.publicKey ? 1 : 0]),
+        streamUniqueId,
+      ],
+      program.programId
+    );
+    
+    await program.methods
+      .initializeSol(
+        moderator.publicKey,
+        Array.from(streamUniqueId),
+        requiredSignatures,
+        new BN(unlockHours),
+        new BN(streamAmount),
+        {
+          hashlock: null,
+          stream: { startTime: new BN(now - 60), cliffTime: null, endTime: new BN(now + 3600) },
+          oracle: null,
+          moderatorFeeBpsMax: 0,
+          moderatorFeeFixed: new BN(0),
+          panel: null,
+          requireRegisteredModerator: false,
+          minSellerSettlements: new BN(0),
+          receiptTree: null,
+          releaseHook: null,
+        }
+      )
+      .accounts({
+        payer: buyer.publicKey,
+        buyer: buyer.publicKey,
+        seller: seller.publicKey,
+        escrowAccount: streamEscrowAccount,
+        moderatorProfile: null,
+        registryConfig: null,
+        sellerReputation: null,
+        systemProgram: SystemProgram.programId,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+      })
+      .signers([buyer])
+      .rpc();
+    
+    await program.methods
+      .withdrawVestedSol()
+      .accounts({
+        seller: seller.publicKey,
+        escrowAccount: streamEscrowAccount,
+        buyer: buyer.publicKey,
+        clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+      })
+      .signers([seller])
+      .rpc();
+    
+    const afterWithdraw = await program.account.solEscrow.fetch(streamEscrowAccount);
+    assert.ok(afterWithdraw.base.stream.withdrawn.gtn(0));
+    assert.equal(
+      afterWithdraw.base.amount.add(afterWithdraw.base.stream.withdrawn).toNumber(),
+      streamAmount
+    );
+    
+    const payerBefore = await provider.connection.getBalance(buyer.publicKey);
+    
+    await program.methods
+      .cancelStreamSol()
+      .accounts({
+        initiator: moderator.publicKey,
+        escrowAccount: streamEscrowAccount,
+        seller: seller.publicKey,
+        payer: buyer.publicKey,
+        buyer: buyer.publicKey,
+        clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+      })
+      .signers([moderator])
+      .rpc();
+    
+    const payerAfter = await provider.connection.getBalance(buyer.publicKey);
+    assert.ok(payerAfter > payerBefore);
+    assert.isNull(await provider.connection.getAccountInfo(streamEscrowAccount));
+  });
+  
+  it("周期托管：未到期不能领取，取消后退回余额", async () => {
+    const recurringUniqueId = generateRandomUniqueId();
+    const amountPerPeriod = 0.01 * LAMPORTS_PER_SOL;
+    const deposit = 3 * amountPerPeriod;
+    
+    const [recurringEscrow] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("recurring_escrow"),
+        buyer.publicKey.toBuffer(),
+        seller.publicKey.toBuffer(),
+        recurringUniqueId,
+      ],
+      program.programId
+    );
+    const escrowTokenAccount = anchor.utils.token.associatedAddress({
+      mint: tokenMint,
+      owner: recurringEscrow
+    });
+    const sellerTokenAccount = anchor.utils.token.associatedAddress({
+      mint: tokenMint,
+      owner: seller.publicKey
+    });
+    
+    await program.methods
+      .initializeRecurring(
+        Array.from(recurringUniqueId),
+        new BN(amountPerPeriod),
+        new BN(30 * 24 * 3600),
+        new BN(deposit)
+      )
+      .accounts({
+        buyer: buyer.publicKey,
+        seller: seller.publicKey,
+        recurringEscrow,
+        tokenMint,
+        buyerTokenAccount,
+        escrowTokenAccount,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+        clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+      })
+      .signers([buyer])
+      .rpc();
+    
+    // 第一个周期尚未结束
+    try {
+      await program.methods
+        .claimRecurring()
+        .accounts({
+          seller: seller.publicKey,
+          recurringEscrow,
+          escrowTokenAccount,
+          tokenMint,
+          sellerTokenAccount,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+        })
+        .signers([seller])
+        .rpc();
+      assert.fail("未到期的周期不应被领取");
+    } catch (e) {
+      expect(e.toString()).to.include("NothingDue");
+    }
+    
+    await program.methods
+      .pauseRecurring()
+      .accounts({
+        buyer: buyer.publicKey,
+        recurringEscrow,
+        clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+      })
+      .signers([buyer])
+      .rpc();
+    
+    const paused = await program.account.recurringEscrow.fetch(recurringEscrow);
+    assert.isNotNull(paused.pausedAt);
+    
+    const buyerBefore = await getAccount(provider.connection, buyerTokenAccount);
+    
+    await program.methods
+      .cancelRecurring()
+      .accounts({
+        buyer: buyer.publicKey,
+        recurringEscrow,
+        escrowTokenAccount,
+        tokenMint,
+        seller: seller.publicKey,
+        sellerTokenAccount,
+        buyerTokenAccount,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+        clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+      })
+      .signers([buyer])
+      .rpc();
+    
+    const buyerAfter = await getAccount(provider.connection, buyerTokenAccount);
+    assert.equal((buyerAfter.amount - buyerBefore.amount).toString(), deposit.toString());
+    assert.isNull(await provider.connection.getAccountInfo(recurringEscrow));
+  });
+  
+  it("众筹托管：多人出资并获得贡献凭证，截止前不能取回", async () => {
+    const crowdfundUniqueId = generateRandomUniqueId();
+    const targetAmount = 0.1 * LAMPORTS_PER_SOL;
+    const share = 0.03 * LAMPORTS_PER_SOL;
+    
+    const [crowdfund] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("crowdfund"),
+        buyer.publicKey.toBuffer(),
+        seller.publicKey.toBuffer(),
+        Buffer.from([1]),
+        crowdfundUniqueId,
+      ],
+      program.programId
+    );
+    
+    await program.methods
+      .initializeCrowdfund(
+        moderator.publicKey,
+        Array.from(crowdfundUniqueId),
+        requiredSignatures,
+        new BN(targetAmount),
+        new BN(unlockHours)
+      )
+      .accounts({
+        buyer: buyer.publicKey,
+        seller: seller.publicKey,
+        crowdfund,
+        tokenMint: null,
+        crowdfundTokenAccount: null,
+        tokenProgram: null,
+        associatedTokenProgram: null,
+        systemProgram: SystemProgram.programId,
+        clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+      })
+      .signers([buyer])
+      .rpc();
+    
+    const contributionAddress = (contributor) => PublicKey.findProgramAddressSync(
+      [Buffer.from("contribution"), crowdfund.toBuffer(), contributor.toBuffer()],
+      program.programId
+    )[0];
+    
+    for (const contributor of [buyer, moderator]) {
+      await program.methods
+        .contribute(new BN(share))
+        .accounts({
+          contributor: contributor.publicKey,
+          crowdfund,
+          contribution: contributionAddress(contributor.publicKey),
+          contributorTokenAccount: null,
+          crowdfundTokenAccount: null,
+          tokenProgram: null,
+          systemProgram: SystemProgram.programId,
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+        })
+        .signers([contributor])
+        .rpc();
+    }
+    
+    const state = await program.account.crowdfundEscrow.fetch(crowdfund);
+    assert.equal(state.base.amount.toNumber(), 2 * share);
+    
+    const receipt = await program.account.contribution.fetch(contributionAddress(moderator.publicKey));
+    assert.equal(receipt.amount.toNumber(), share);
+    
+    try {
+      await program.methods
+        .reclaimContribution()
+        .accounts({
+          contributor: moderator.publicKey,
+          crowdfund,
+          contribution: contributionAddress(moderator.publicKey),
+          contributorTokenAccount: null,
+          crowdfundTokenAccount: null,
+          organizerTokenAccount: null,
+          buyer: buyer.publicKey,
+          tokenProgram: null,
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+        })
+        .signers([moderator])
+        .rpc();
+      assert.fail("截止前不应允许取回");
+    } catch (e) {
+      expect(e.toString()).to.include("TimelockNotExpired");
+    }
+  });
+  
+  it("交付预言机：证明送达后计为一个批准", async () => {
+    const oracle = Keypair.generate();
+    const oracleUniqueId = generateRandomUniqueId();
+    const oracleAmount = 0.05 * LAMPORTS_PER_SOL;
+    
+    const [oracleEscrowAccount] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("sol_escrow"),
+        buyer.publicKey.toBuffer(),
+        seller.publicKey.toBuffer(),
+        Buffer.from([0]),
+        oracleUniqueId,
+      ],
+      program.programId
+    );
+    
+    // 买家、卖家加预言机，2-of-3
+    await program.methods
+      .initializeSol(
+        null,
+        Array.from(oracleUniqueId),
+        2,
+        new BN(unlockHours),
+        new BN(oracleAmount),
+        { hashlock: null, stream: null, oracle: oracle.publicKey, moderatorFeeBpsMax: 0, moderatorFeeFixed: new BN(0), panel: null, requireRegisteredModerator: false, minSellerSettlements: new BN(0), receiptTree: null, releaseHook: null }
+      )
+      .accounts({
+        payer: buyer.publicKey,
+        buyer: buyer.publicKey,
+        seller: seller.publicKey,
+        escrowAccount: oracleEscrowAccount,
+        moderatorProfile: null,
+        registryConfig: null,
+        sellerReputation: null,
+        systemProgram: SystemProgram.programId,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+      })
+      .signers([buyer])
+      .rpc();
+    
+    // 预言机签名 escrow || delivered || timestamp
+    const deliveredAt = Math.floor(Date.now() / 1000) - 60;
+    const attestation = Buffer.concat([
+      oracleEscrowAccount.toBuffer(),
+      Buffer.from([1]),
+      Buffer.from(new BN(deliveredAt).toArray('le', 8)),
+    ]);
+    const oracleSignature = nacl.sign.detached(attestation, oracle.secretKey);
+    
+    await program.methods
+      .attestDelivery(true, new BN(deliveredAt), Buffer.from(oracleSignature))
+      .accounts({
+        initiator: seller.publicKey,
+        solEscrow: oracleEscrowAccount,
+        tokenEscrow: null,
+        clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+        sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+      })
+      .preInstructions([
+        anchor.web3.Ed25519Program.createInstructionWithPrivateKey({
+          privateKey: oracle.secretKey,
+          message: attestation,
+        }),
+      ])
+      .signers([seller])
+      .rpc();
+    
+    const escrow = await program.account.solEscrow.fetch(oracleEscrowAccount);
+    assert.equal(escrow.base.deliveredAt.toNumber(), deliveredAt);
+  });
+  
+  it("交付证明：只对付给卖家的释放计为批准，买家不能借此给自己退款", async () => {
+    const deliveryOracle = Keypair.generate();
+    const amount = 0.05 * LAMPORTS_PER_SOL;
+    const opened = await openSolEscrow({
+      amount,
+      requiredSignatures: 2,
+      options: escrowOptions({ oracle: deliveryOracle.publicKey }),
+    });
+    
+    const deliveredAt = Math.floor(Date.now() / 1000) - 60;
+    const attestation = Buffer.concat([
+      opened.escrow.toBuffer(),
+      Buffer.from([1]),
+      Buffer.from(new BN(deliveredAt).toArray('le', 8)),
+    ]);
+    await program.methods
+      .attestDelivery(true, new BN(deliveredAt), Buffer.from(nacl.sign.detached(attestation, deliveryOracle.secretKey)))
+      .accounts({
+        initiator: buyer.publicKey,
+        solEscrow: opened.escrow,
+        tokenEscrow: null,
+        clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+        sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+      })
+      .preInstructions([ed25519Instruction(attestation, [deliveryOracle])])
+      .signers([buyer])
+      .rpc();
+    
+    // 买家签名 + 交付证明不能把资金退给买家
+    try {
+      await releaseSol(opened, [[buyer.publicKey, amount]], [buyer]);
+      assert.fail("交付证明不应为退款计入批准");
+    } catch (e) {
+      assert.include(e.toString(), "InsufficientSignatures");
+    }
+    
+    // 付给卖家和买家的拆分同样不计交付证明
+    try {
+      await releaseSol(opened, [[seller.publicKey, amount / 2], [buyer.publicKey, amount / 2]], [buyer]);
+      assert.fail("交付证明不应为拆分计入批准");
+    } catch (e) {
+      assert.include(e.toString(), "InsufficientSignatures");
+    }
+    
+    // 全额付给卖家时，卖家签名 + 交付证明满足 2 个批准
+    const sellerBefore = await provider.connection.getBalance(seller.publicKey);
+    await releaseSol(opened, [[seller.publicKey, amount]], [seller]);
+    const sellerAfter = await provider.connection.getBalance(seller.publicKey);
+    assert.equal(sellerAfter - sellerBefore, amount);
+  });
+  
+  it("仲裁裁决：moderator 按基点拆分并收取不超过上限的费用", async () => {
+    const disputeUniqueId = generateRandomUniqueId();
+    const disputeAmount = 0.1 * LAMPORTS_PER_SOL;
+    
+    const [disputeEscrowAccount] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("sol_escrow"),
+        buyer.publicKey.toBuffer(),
+        seller.publicKey.toBuffer(),
+        Buffer.from([1]),
+        disputeUniqueId,
+      ],
+      program.programId
+    );
+    
+    await program.methods
+      .initializeSol(
+        moderator.publicKey,
+        Array.from(disputeUniqueId),
+        requiredSignatures,
+        new BN(unlockHours),
+        new BN(disputeAmount),
+        { hashlock: null, stream: null, oracle: null, moderatorFeeBpsMax: 500, moderatorFeeFixed: new BN(0), panel: null, requireRegisteredModerator: false, minSellerSettlements: new BN(0), receiptTree: null, releaseHook: null } // 最多 5%
+      )
+      .accounts({
+        payer: buyer.publicKey,
+        buyer: buyer.publicKey,
+        seller: seller.publicKey,
+        escrowAccount: disputeEscrowAccount,
+        moderatorProfile: null,
+        registryConfig: null,
+        sellerReputation: null,
+        systemProgram: SystemProgram.programId,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+      })
+      .signers([buyer])
+      .rpc();
+    
+    // 裁决与普通释放一样需要 2 个批准：moderator 加一方当事人
+    const resolve = (ruling, signers = [moderator, seller]) => {
+      const message = Buffer.concat([
+        disputeUniqueId,
+        Buffer.from(new BN(0).toArray('le', 8)), // nonce
+        Buffer.from(new BN(0).toArray('le', 8)), // valid_until
+        Buffer.from(new BN(ruling.buyerBps).toArray('le', 2)),
+        Buffer.from(new BN(ruling.moderatorFeeBps).toArray('le', 2)),
+      ]);
+      const signatures = signers.map((keypair) => Buffer.from(nacl.sign.detached(message, keypair.secretKey)));
+      
+      return program.methods
+        .resolveDisputeSol(ruling, new BN(0), signatures)
+        .accounts({
+          initiator: moderator.publicKey,
+          escrowAccount: disputeEscrowAccount,
+          payer: buyer.publicKey,
+          seller: seller.publicKey,
+          moderator: moderator.publicKey,
+          buyer: buyer.publicKey,
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+          sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        })
+        .preInstructions([ed25519Instruction(message, signers)])
+        .signers([moderator])
+        .rpc();
+    };
+    
+    // 仅有 moderator 签名不能结算
+    try {
+      await resolve({ buyerBps: 5000, moderatorFeeBps: 200 }, [moderator]);
+      assert.fail("仅 moderator 签名的裁决不应被接受");
+    } catch (e) {
+      expect(e.toString()).to.include("InsufficientSignatures");
+    }
+    
+    // 两方当事人签名但缺少 moderator
+    try {
+      await resolve({ buyerBps: 5000, moderatorFeeBps: 200 }, [buyer, seller]);
+      assert.fail("缺少 moderator 签名的裁决不应被接受");
+    } catch (e) {
+      expect(e.toString()).to.include("InvalidSigner");
+    }
+    
+    // 超过创建时确定的 5% 上限
+    try {
+      await resolve({ buyerBps: 5000, moderatorFeeBps: 600 });
+      assert.fail("超过上限的仲裁费不应被接受");
+    } catch (e) {
+      expect(e.toString()).to.include("ModeratorFeeTooHigh");
+    }
+    
+    const sellerBefore = await provider.connection.getBalance(seller.publicKey);
+    
+    // 买家一方 50%，moderator 收取 2%
+    await resolve({ buyerBps: 5000, moderatorFeeBps: 200 });
+    
+    // 费用向下取整，卖家获得剩余部分
+    const fee = Math.floor(disputeAmount * 200 / 10000);
+    const buyerShare = Math.floor((disputeAmount - fee) * 5000 / 10000);
+    const sellerAfter = await provider.connection.getBalance(seller.publicKey);
+    assert.equal(sellerAfter - sellerBefore, disputeAmount - fee - buyerShare);
+    assert.isNull(await provider.connection.getAccountInfo(disputeEscrowAccount));
+  });
+  
+  it("买卖双方共同更换 moderator", async () => {
+    const changeUniqueId = generateRandomUniqueId();
+    const newModerator = Keypair.generate();
+    
+    const [changeEscrowAccount] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("sol_escrow"),
+        buyer.publicKey.toBuffer(),
+        seller.publicKey.toBuffer(),
+        Buffer.from([1]),
+        changeUniqueId,
+      ],
+      program.programId
+    );
+    
+    await program.methods
+      .initializeSol(
+        moderator.publicKey,
+        Array.from(changeUniqueId),
+        requiredSignatures,
+        new BN(unlockHours),
+        new BN(0.01 * LAMPORTS_PER_SOL),
+        { hashlock: null, stream: null, oracle: null, moderatorFeeBpsMax: 0, moderatorFeeFixed: new BN(0), panel: null, requireRegisteredModerator: false, minSellerSettlements: new BN(0), receiptTree: null, releaseHook: null } // options
+      )
+      .accounts({
+        payer: buyer.publicKey,
+        buyer: buyer.publicKey,
+        seller: seller.publicKey,
+        escrowAccount: changeEscrowAccount,
+        moderatorProfile: null,
+        registryConfig: null,
+        sellerReputation: null,
+        systemProgram: SystemProgram.programId,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+      })
+      .signers([buyer])
+      .rpc();
+    
+    await program.methods
+      .changeModerator(newModerator.publicKey)
+      .accounts({
+        firstSigner: buyer.publicKey,
+        secondSigner: seller.publicKey,
+        solEscrow: changeEscrowAccount,
+        tokenEscrow: null,
+        multiAssetEscrow: null,
+        crowdfundEscrow: null,
+        moderatorProfile: null,
+        registryConfig: null,
+      })
+      .signers([buyer, seller])
+      .rpc();
+    
+    const escrow = await program.account.solEscrow.fetch(changeEscrowAccount);
+    assert.equal(escrow.base.moderator.toString(), newModerator.publicKey.toString());
+    // 更换后 nonce 递增，旧的释放授权失效
+    assert.equal(escrow.base.nonce.toNumber(), 1);
+  });
+  
+  it("仲裁小组：卖家加小组法定人数即可释放，重复签名不计数", async () => {
+    const panelUniqueId = generateRandomUniqueId();
+    const panelAmount = 0.01 * LAMPORTS_PER_SOL;
+    const arbitrators = [Keypair.generate(), Keypair.generate(), Keypair.generate()];
+    
+    const [panelEscrowAccount] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("sol_escrow"),
+        buyer.publicKey.toBuffer(),
+        seller.publicKey.toBuffer(),
+        Buffer.from([0]),
+        panelUniqueId,
+      ],
+      program.programId
+    );
+    
+    // 买家+卖家，或任一方加 2-of-3 仲裁小组
+    await program.methods
+      .initializeSol(
+        null,
+        Array.from(panelUniqueId),
+        2,
+        new BN(unlockHours),
+        new BN(panelAmount),
+        {
+          hashlock: null,
+          stream: null,
+          oracle: null,
+          moderatorFeeBpsMax: 0,
+          moderatorFeeFixed: new BN(0),
+          panel: { members: arbitrators.map((a) => a.publicKey), threshold: 2 },
+          requireRegisteredModerator: false,
+          minSellerSettlements: new BN(0),
+          receiptTree: null,
+          releaseHook: null,
+        }
+      )
+      .accounts({
+        payer: buyer.publicKey,
+        buyer: buyer.publicKey,
+        seller: seller.publicKey,
+        escrowAccount: panelEscrowAccount,
+        moderatorProfile: null,
+        registryConfig: null,
+        sellerReputation: null,
+        systemProgram: SystemProgram.programId,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+      })
+      .signers([buyer])
+      .rpc();
+    
+    let message = Buffer.from([...panelUniqueId]);
+    message = Buffer.concat([message, Buffer.from(new BN(0).toArray('le', 8))]); // nonce
+    message = Buffer.concat([message, Buffer.from(new BN(0).toArray('le', 8))]); // valid_until
+    message = Buffer.concat([message, seller.publicKey.toBuffer()]);
+    message = Buffer.concat([message, Buffer.from(new BN(panelAmount).toArray('le', 8))]);
+    
+    const sign = (keypair) => Buffer.from(nacl.sign.detached(message, keypair.secretKey));
+    const releaseAccounts = {
+      initiator: seller.publicKey,
+      escrowAccount: panelEscrowAccount,
+      clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+      systemProgram: SystemProgram.programId,
+      buyer: buyer.publicKey,
+      recipient1: seller.publicKey,
+      recipient2: null,
+      recipient3: null,
+      recipient4: null,
+      ed25519Program: ED25519_PROGRAM_ID,
+    };
+    
+    // 同一卖家签名重复两次不能满足 2 个批准
+    try {
+      await program.methods
+        .releaseSol([new BN(panelAmount)], [sign(seller), sign(seller)], new BN(0))
+        .accounts(releaseAccounts)
+        .signers([seller])
+        .rpc();
+      assert.fail("重复签名不应被计为两个批准");
+    } catch (e) {
+      assert.include(e.toString(), "InsufficientSignatures");
+    }
+    
+    const balanceBefore = await provider.connection.getBalance(seller.publicKey);
+    await program.methods
+      .releaseSol(
+        [new BN(panelAmount)],
+        [sign(seller), sign(arbitrators[0]), sign(arbitrators[2])],
+        new BN(0)
+      )
+      .accounts(releaseAccounts)
+      .signers([seller])
+      .rpc();
+    
+    const balanceAfter = await provider.connection.getBalance(seller.publicKey);
+    assert.approximately(balanceAfter - balanceBefore, panelAmount, 0.01 * LAMPORTS_PER_SOL);
+  });
+  
+  it("仲裁小组：没有 moderator 的托管由小组法定人数签署裁决", async () => {
+    const panelUniqueId = generateRandomUniqueId();
+    const panelAmount = 0.01 * LAMPORTS_PER_SOL;
+    const arbitrators = [Keypair.generate(), Keypair.generate(), Keypair.generate()];
+    
+    const [panelEscrowAccount] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("sol_escrow"),
+        buyer.publicKey.toBuffer(),
+        seller.publicKey.toBuffer(),
+        Buffer.from([0]),
+        panelUniqueId,
+      ],
+      program.programId
+    );
+    
+    await program.methods
+      .initializeSol(
+        null,
+        Array.from(panelUniqueId),
+        2,
+        new BN(unlockHours),
+        new BN(panelAmount),
+        escrowOptions({ panel: { members: arbitrators.map((a) => a.publicKey), threshold: 2 } })
+      )
+      .accounts({
+        payer: buyer.publicKey,
+        buyer: buyer.publicKey,
+        seller: seller.publicKey,
+        escrowAccount: panelEscrowAccount,
+        moderatorProfile: null,
+        registryConfig: null,
+        sellerReputation: null,
+        systemProgram: SystemProgram.programId,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+      })
+      .signers([buyer])
+      .rpc();
+    
+    const resolve = (ruling, signers) => {
+      const message = Buffer.concat([
+        panelUniqueId,
+        Buffer.from(new BN(0).toArray('le', 8)), // nonce
+        Buffer.from(new BN(0).toArray('le', 8)), // valid_until
+        Buffer.from(new BN(ruling.buyerBps).toArray('le', 2)),
+        Buffer.from(new BN(ruling.moderatorFeeBps).toArray('le', 2)),
+      ]);
+      const signatures = signers.map((keypair) => Buffer.from(nacl.sign.detached(message, keypair.secretKey)));
+      
+      return program.methods
+        .resolveDisputeSol(ruling, new BN(0), signatures)
+        .accounts({
+          initiator: buyer.publicKey,
+          escrowAccount: panelEscrowAccount,
+          payer: buyer.publicKey,
+          seller: seller.publicKey,
+          moderator: null,
+          buyer: buyer.publicKey,
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+          sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        })
+        .preInstructions([ed25519Instruction(message, signers)])
+        .signers([buyer])
+        .rpc();
+    };
+    
+    // 单个小组成员不构成法定人数
+    try {
+      await resolve({ buyerBps: 10000, moderatorFeeBps: 0 }, [arbitrators[0], buyer]);
+      assert.fail("未达法定人数的裁决不应被接受");
+    } catch (e) {
+      expect(e.toString()).to.include("InvalidSigner");
+    }
+    
+    // 没有 moderator 就没有人收取仲裁费
+    try {
+      await resolve({ buyerBps: 10000, moderatorFeeBps: 100 }, [arbitrators[0], arbitrators[1], buyer]);
+      assert.fail("没有 moderator 的托管不应收取仲裁费");
+    } catch (e) {
+      expect(e.toString()).to.include("InvalidPaymentParameters");
+    }
+    
+    // 小组法定人数加买家：全额退给买家一方
+    await resolve({ buyerBps: 10000, moderatorFeeBps: 0 }, [arbitrators[0], arbitrators[2], buyer]);
+    assert.isNull(await provider.connection.getAccountInfo(panelEscrowAccount));
+  });
+  
+  it("结算后记录买卖双方的信誉并保留结算收据", async () => {
+    const repUniqueId = generateRandomUniqueId();
+    const repAmount = 0.01 * LAMPORTS_PER_SOL;
+    
+    const [repEscrowAccount] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("sol_escrow"),
+        buyer.publicKey.toBuffer(),
+        seller.publicKey.toBuffer(),
+        Buffer.from([0]),
+        repUniqueId,
+      ],
+      program.programId
+    );
+    const [sellerReputation] = PublicKey.findProgramAddressSync(
+      [Buffer.from("seller_reputation"), seller.publicKey.toBuffer()],
+      program.programId
+    );
+    const [buyerReputation] = PublicKey.findProgramAddressSync(
+      [Buffer.from("buyer_reputation"), buyer.publicKey.toBuffer()],
+      program.programId
+    );
+    const releasedBefore = await program.account.reputation
+      .fetchNullable(sellerReputation)
+      .then((rep) => (rep ? rep.released.count.toNumber() : 0));
+    
+    await program.methods
+      .initializeSol(
+        null,
+        Array.from(repUniqueId),
+        2,
+        new BN(unlockHours),
+        new BN(repAmount),
+        { hashlock: null, stream: null, oracle: null, moderatorFeeBpsMax: 0, moderatorFeeFixed: new BN(0), panel: null, requireRegisteredModerator: false, minSellerSettlements: new BN(0), receiptTree: null, releaseHook: null }
+      )
+      .accounts({
+        payer: buyer.publicKey,
+        buyer: buyer.publicKey,
+        seller: seller.publicKey,
+        escrowAccount: repEscrowAccount,
+        moderatorProfile: null,
+        registryConfig: null,
+        sellerReputation: null,
+        systemProgram: SystemProgram.programId,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+      })
+      .signers([buyer])
+      .rpc();
+    
+    let message = Buffer.from([...repUniqueId]);
+    message = Buffer.concat([message, Buffer.from(new BN(0).toArray('le', 8))]); // nonce
+    message = Buffer.concat([message, Buffer.from(new BN(0).toArray('le', 8))]); // valid_until
+    message = Buffer.concat([message, seller.publicKey.toBuffer()]);
+    message = Buffer.concat([message, Buffer.from(new BN(repAmount).toArray('le', 8))]);
+    
+    await program.methods
+      .releaseSol(
+        [new BN(repAmount)],
+        [
+          Buffer.from(nacl.sign.detached(message, buyer.secretKey)),
+          Buffer.from(nacl.sign.detached(message, seller.secretKey)),
+        ],
+        new BN(0)
+      )
+      .accounts({
+        initiator: buyer.publicKey,
+        escrowAccount: repEscrowAccount,
+        sellerReputation,
+        buyerReputation,
+        clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+        systemProgram: SystemProgram.programId,
+        buyer: buyer.publicKey,
+        recipient1: seller.publicKey,
+        recipient2: null,
+        recipient3: null,
+        recipient4: null,
+        ed25519Program: ED25519_PROGRAM_ID,
+      })
+      .signers([buyer])
+      .rpc();
+    
+    const sellerRep = await program.account.reputation.fetch(sellerReputation);
+    assert.equal(sellerRep.party.toString(), seller.publicKey.toString());
+    assert.equal(sellerRep.released.count.toNumber(), releasedBefore + 1);
+    
+    const buyerRep = await program.account.reputation.fetch(buyerReputation);
+    assert.equal(buyerRep.party.toString(), buyer.publicKey.toString());
+    assert.isAtLeast(buyerRep.released.solVolume.toNumber(), repAmount);
+    
+    // 托管关闭后收据仍可查询
+    const [receipt] = PublicKey.findProgramAddressSync(
+      [Buffer.from("receipt"), repEscrowAccount.toBuffer()],
+      program.programId
+    );
+    const settled = await program.account.settlementReceipt.fetch(receipt);
+    assert.equal(Buffer.from(settled.record.uniqueId).toString("hex"), repUniqueId.toString("hex"));
+    assert.equal(settled.record.seller.toString(), seller.publicKey.toString());
+    assert.equal(settled.record.total.toNumber(), repAmount);
+    assert.deepEqual(Object.keys(settled.record.path), ["signatures"]);
+    assert.equal(settled.record.payouts[0].recipient.toString(), seller.publicKey.toString());
+    assert.isTrue(settled.record.sellerApproved);
+    
+    // 已结算的 unique_id 不能再次创建托管
+    try {
+      await program.methods
+        .initializeSol(
+          null,
+          Array.from(repUniqueId),
+          2,
+          new BN(unlockHours),
+          new BN(repAmount),
+          { hashlock: null, stream: null, oracle: null, moderatorFeeBpsMax: 0, moderatorFeeFixed: new BN(0), panel: null, requireRegisteredModerator: false, minSellerSettlements: new BN(0), receiptTree: null, releaseHook: null }
+        )
+        .accounts({
+          payer: buyer.publicKey,
+          buyer: buyer.publicKey,
+          seller: seller.publicKey,
+          escrowAccount: repEscrowAccount,
+          moderatorProfile: null,
+          registryConfig: null,
+          sellerReputation: null,
+          systemProgram: SystemProgram.programId,
+          rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+        })
+        .signers([buyer])
+        .rpc();
+      assert.fail("已结算的 unique_id 不应被重复使用");
+    } catch (e) {
+      expect(e.toString()).to.include("EscrowAlreadySettled");
+    }
+  });
+  
+  it("信誉：低于最小金额的结算只计入次数，不计入卖家历史门槛", async () => {
+    const { sellerReputation } = reputationAccounts(seller.publicKey, buyer.publicKey);
+    const countsBefore = await program.account.reputation.fetchNullable(sellerReputation)
+      .then((rep) => rep ? [rep.released.count.toNumber(), rep.released.counted.toNumber()] : [0, 0]);
+    
+    // 1000 lamports 远低于 0.01 SOL
+    const dust = await openSolEscrow({ amount: 1000 });
+    await releaseSol(dust, [[seller.publicKey, 1000]], [buyer]);
+    
+    let rep = await program.account.reputation.fetch(sellerReputation);
+    assert.equal(rep.released.count.toNumber(), countsBefore[0] + 1);
+    assert.equal(rep.released.counted.toNumber(), countsBefore[1]);
+    
+    // 达到最小金额的结算计入
+    const amount = 0.01 * LAMPORTS_PER_SOL;
+    const counted = await openSolEscrow({ amount });
+    await releaseSol(counted, [[seller.publicKey, amount]], [buyer]);
+    
+    rep = await program.account.reputation.fetch(sellerReputation);
+    assert.equal(rep.released.count.toNumber(), countsBefore[0] + 2);
+    assert.equal(rep.released.counted.toNumber(), countsBefore[1] + 1);
+  });
+  
+  it("压缩收据：创建与结算写入收据树，并用 Merkle 证明验证", async () => {
+    const treeKeypair = Keypair.generate();
+    await program.methods
+      .createReceiptTree()
+      .accounts({
+        authority: buyer.publicKey,
+        receiptTree: treeKeypair.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([buyer, treeKeypair])
+      .rpc();
+    
+    const treeUniqueId = generateRandomUniqueId();
+    const treeAmount = 0.01 * LAMPORTS_PER_SOL;
+    const [treeEscrowAccount] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("sol_escrow"),
+        buyer.publicKey.toBuffer(),
+        seller.publicKey.toBuffer(),
+        Buffer.from([0]),
+        treeUniqueId,
+      ],
+      program.programId
+    );
+    
+    const initTx = await program.methods
+      .initializeSol(
+        null,
+        Array.from(treeUniqueId),
+        2,
+        new BN(unlockHours),
+        new BN(treeAmount),
+        { hashlock: null, stream: null, oracle: null, moderatorFeeBpsMax: 0, moderatorFeeFixed: new BN(0), panel: null, requireRegisteredModerator: false, minSellerSettlements: new BN(0), receiptTree: treeKeypair.publicKey, releaseHook: null }
+      )
+      .accounts({
+        payer: buyer.publicKey,
+        buyer: buyer.publicKey,
+        seller: seller.publicKey,
+        escrowAccount: treeEscrowAccount,
+        receiptTree: treeKeypair.publicKey,
+        moderatorProfile: null,
+        registryConfig: null,
+        sellerReputation: null,
+        systemProgram: SystemProgram.programId,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+      })
+      .signers([buyer])
+      .rpc({ commitment: "confirmed" });
+    
+    let message = Buffer.from([...treeUniqueId]);
+    message = Buffer.concat([message, Buffer.from(new BN(0).toArray('le', 8))]); // nonce
+    message = Buffer.concat([message, Buffer.from(new BN(0).toArray('le', 8))]); // valid_until
+    message = Buffer.concat([message, seller.publicKey.toBuffer()]);
+    message = Buffer.concat([message, Buffer.from(new BN(treeAmount).toArray('le', 8))]);
+    
+    const releaseTx = await program.methods
+      .releaseSol(
+        [new BN(treeAmount)],
+        [
+          Buffer.from(nacl.sign.detached(message, buyer.secretKey)),
+          Buffer.from(nacl.sign.detached(message, seller.secretKey)),
+        ],
+        new BN(0)
+      )
+      .accounts({
+        initiator: buyer.publicKey,
+        escrowAccount: treeEscrowAccount,
+        receipt: null,
+        receiptTree: treeKeypair.publicKey,
+        clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+        systemProgram: SystemProgram.programId,
+        buyer: buyer.publicKey,
+        recipient1: seller.publicKey,
+        recipient2: null,
+        recipient3: null,
+      })
+      .signers([buyer])
+      .rpc({ commitment: "confirmed" });
+    
+    // 没有创建收据账户
+    const [receiptAddress] = PublicKey.findProgramAddressSync(
+      [Buffer.from("receipt"), treeEscrowAccount.toBuffer()],
+      program.programId
+    );
+    assert.isNull(await provider.connection.getAccountInfo(receiptAddress));
+    
+    // 从交易日志中的 ReceiptLeafAppended 事件重建叶子
+    const eventParser = new anchor.EventParser(program.programId, program.coder);
+    const appended = [];
+    for (const signature of [initTx, releaseTx]) {
+      const tx = await provider.connection.getTransaction(signature, {
+        commitment: "confirmed",
+        maxSupportedTransactionVersion: 0,
+      });
+      for (const event of eventParser.parseLogs(tx.meta.logMessages)) {
+        if (event.data.leafHash) {
+          appended.push(event.data);
+        }
+      }
+    }
+    assert.equal(appended.length, 2);
+    assert.ok(appended[0].leaf.created);
+    assert.ok(appended[1].leaf.settled);
+    
+    const hashNode = (left, right) => Buffer.from(keccak_256(Buffer.concat([Buffer.from([1]), left, right])));
+    const buildProof = (leaves, index, depth = 20) => {
+      let level = leaves.map((leaf) => Buffer.from(leaf));
+      let empty = Buffer.alloc(32);
+      let position = index;
+      const proof = [];
+      for (let i = 0; i < depth; i++) {
+        proof.push(level[position ^ 1] ?? empty);
+        const next = [];
+        for (let j = 0; j < level.length; j += 2) {
+          next.push(hashNode(level[j], level[j + 1] ?? empty));
+        }
+        level = next;
+        empty = hashNode(empty, empty);
+        position >>= 1;
+      }
+      return { proof: proof.map((node) => Array.from(node)), root: level[0] };
+    };
+    
+    const leaves = appended.map((event) => event.leafHash);
+    const { proof, root } = buildProof(leaves, 1);
+    const tree = await program.account.receiptTree.fetch(treeKeypair.publicKey);
+    assert.equal(tree.leafCount.toNumber(), 2);
+    assert.deepEqual(Buffer.from(tree.roots[tree.rootIndex.toNumber()]), root);
+    
+    await program.methods
+      .verifyReceipt(new BN(1), appended[1].leaf, proof)
+      .accounts({ receiptTree: treeKeypair.publicKey })
+      .rpc();
+    
+    // 篡改结算金额后证明不再成立
+    const tampered = appended[1].leaf;
+    tampered.settled[0].total = tampered.settled[0].total.addn(1);
+    try {
+      await program.methods
+        .verifyReceipt(new BN(1), tampered, proof)
+        .accounts({ receiptTree: treeKeypair.publicKey })
+        .rpc();
+      assert.fail("被篡改的叶子不应通过验证");
+    } catch (e) {
+      expect(e.toString()).to.include("InvalidReceiptProof");
+    }
+  });
+  
+  it("固定布局托管：释放结果与常规托管一致，消耗更少的计算单元", async () => {
+    const cuAmount = 0.01 * LAMPORTS_PER_SOL;
+    const options = { hashlock: null, stream: null, oracle: null, moderatorFeeBpsMax: 0, moderatorFeeFixed: new BN(0), panel: null, requireRegisteredModerator: false, minSellerSettlements: new BN(0), receiptTree: null, releaseHook: null };
+    const [sellerReputation] = PublicKey.findProgramAddressSync(
+      [Buffer.from("seller_reputation"), seller.publicKey.toBuffer()],
+      program.programId
+    );
+    const [buyerReputation] = PublicKey.findProgramAddressSync(
+      [Buffer.from("buyer_reputation"), buyer.publicKey.toBuffer()],
+      program.programId
+    );
+    
+    // 同样参数的常规托管与固定布局托管各释放一次，买家单签付给卖家
+    const release = async (kind) => {
+      const id = generateRandomUniqueId();
+      const prefix = kind === "fixed" ? "fixed_escrow" : "sol_escrow";
+      const [escrow] = PublicKey.findProgramAddressSync(
+        [Buffer.from(prefix), buyer.publicKey.toBuffer(), seller.publicKey.toBuffer(), Buffer.from([0]), id],
+        program.programId
+      );
+      const initAccounts = {
+        payer: buyer.publicKey,
+        buyer: buyer.publicKey,
+        seller: seller.publicKey,
+        escrowAccount: escrow,
+        receiptTree: null,
+      };
+      const init = kind === "fixed"
+        ? program.methods.initializeFixedSol(null, Array.from(id), 1, new BN(unlockHours), new BN(cuAmount), options)
+        : program.methods.initializeSol(null, Array.from(id), 1, new BN(unlockHours), new BN(cuAmount), options);
+      await init
+        .accounts(kind === "fixed" ? initAccounts : { ...initAccounts, moderatorProfile: null, registryConfig: null, sellerReputation: null })
+        .signers([buyer])
+        .rpc();
+      
+      const message = Buffer.concat([
+        id,
+        Buffer.from(new BN(0).toArray('le', 8)), // nonce
+        Buffer.from(new BN(0).toArray('le', 8)), // valid_until
+        seller.publicKey.toBuffer(),
+        Buffer.from(new BN(cuAmount).toArray('le', 8)),
+      ]);
+      const signature = Buffer.from(nacl.sign.detached(message, buyer.secretKey));
+      const releaseIx = kind === "fixed"
+        ? program.methods.releaseFixedSol([new BN(cuAmount)], [signature], new BN(0))
+        : program.methods.releaseSol([new BN(cuAmount)], [signature], new BN(0));
+      const tx = await releaseIx
+        .accounts({
+          initiator: buyer.publicKey,
+          escrowAccount: escrow,
+          buyer: buyer.publicKey,
+          seller: seller.publicKey,
+          sellerReputation,
+          buyerReputation,
+          receiptTree: null,
+          sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+          recipient1: seller.publicKey,
+          recipient2: null,
+          recipient3: null,
+        })
+        .preInstructions([
+          anchor.web3.Ed25519Program.createInstructionWithPrivateKey({
+            privateKey: buyer.secretKey,
+            message,
+          }),
+        ])
+        .signers([buyer])
+        .rpc();
+      
+      const [receipt] = PublicKey.findProgramAddressSync(
+        [Buffer.from("receipt"), escrow.toBuffer()],
+        program.programId
+      );
+      const settled = await program.account.settlementReceipt.fetch(receipt);
+      assert.equal(settled.record.total.toNumber(), cuAmount);
+      assert.equal(settled.record.seller.toString(), seller.publicKey.toString());
+      assert.isNull(await provider.connection.getAccountInfo(escrow));
+      
+      const confirmed = await provider.connection.getTransaction(tx, {
+        commitment: "confirmed",
+        maxSupportedTransactionVersion: 0,
+      });
+      return confirmed.meta.computeUnitsConsumed;
+    };
+    
+    // 先各释放一次，确保信誉账户已存在，两边都不包含创建信誉账户的开销
+    await release("borsh");
+    const borshUnits = await release("borsh");
+    const fixedUnits = await release("fixed");
+    console.log(`释放消耗的计算单元：常规托管 ${borshUnits}，固定布局托管 ${fixedUnits}，节省 ${borshUnits - fixedUnits}`);
+    assert.isBelow(fixedUnits, borshUnits);
+  });
+  
+  it("原生 SOL 代币托管：从 lamports 包装，释放时直接支付 lamports", async () => {
+    const wsolUniqueId = generateRandomUniqueId();
+    const wsolAmount = 0.01 * LAMPORTS_PER_SOL;
+    
+    const [wsolEscrowAccount] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("token_escrow"),
+        buyer.publicKey.toBuffer(),
+        seller.publicKey.toBuffer(),
+        Buffer.from([0]),
+        wsolUniqueId,
+      ],
+      program.programId
+    );
+    const escrowTokenAccount = getAssociatedTokenAddressSync(NATIVE_MINT, wsolEscrowAccount, true);
+    const [sellerReputation] = PublicKey.findProgramAddressSync(
+      [Buffer.from("seller_reputation"), seller.publicKey.toBuffer()],
+      program.programId
+    );
+    const [buyerReputation] = PublicKey.findProgramAddressSync(
+      [Buffer.from("buyer_reputation"), buyer.publicKey.toBuffer()],
+      program.programId
+    );
+    
+    // 不传付款方的代币账户，合约直接从买家的 lamports 包装
+    await program.methods
+      .initializeToken(
+        null,
+        Array.from(wsolUniqueId),
+        1,
+        new BN(unlockHours),
+        new BN(wsolAmount),
+        { hashlock: null, stream: null, oracle: null, moderatorFeeBpsMax: 0, moderatorFeeFixed: new BN(0), panel: null, requireRegisteredModerator: false, minSellerSettlements: new BN(0), receiptTree: null, releaseHook: null }
+      )
+      .accounts({
+        payer: buyer.publicKey,
+        buyer: buyer.publicKey,
+        seller: seller.publicKey,
+        escrowAccount: wsolEscrowAccount,
+        receiptTree: null,
+        tokenMint: NATIVE_MINT,
+        payerTokenAccount: null,
+        escrowTokenAccount,
+        sellerBalance: null,
+        balanceTokenAccount: null,
+        moderatorProfile: null,
+        registryConfig: null,
+        sellerReputation: null,
+      })
+      .signers([buyer])
+      .rpc();
+    
+    const vault = await getAccount(provider.connection, escrowTokenAccount);
+    assert.equal(Number(vault.amount), wsolAmount);
+    // 包装 SOL 与 SOL 托管使用相同的 0.01 SOL 门槛，而不是一个整币
+    const wsolEscrow = await program.account.tokenEscrow.fetch(wsolEscrowAccount);
+    assert.equal(wsolEscrow.base.countedMinAmount.toNumber(), 0.01 * LAMPORTS_PER_SOL);
+    const volumeBefore = await program.account.reputation.fetchNullable(sellerReputation)
+      .then((rep) => rep?.mintVolumes.find((volume) => volume.mint.equals(NATIVE_MINT))?.released.toNumber() ?? 0);
+    
+    const message = Buffer.concat([
+      wsolUniqueId,
+      Buffer.from(new BN(0).toArray('le', 8)), // nonce
+      Buffer.from(new BN(0).toArray('le', 8)), // valid_until
+      seller.publicKey.toBuffer(),
+      Buffer.from(new BN(wsolAmount).toArray('le', 8)),
+    ]);
+    const sellerBefore = await provider.connection.getBalance(seller.publicKey);
+    
+    // 接收方不传 ATA，直接收到 lamports
+    await program.methods
+      .releaseToken([new BN(wsolAmount)], [Buffer.from(nacl.sign.detached(message, buyer.secretKey))], new BN(0))
+      .accounts({
+        initiator: buyer.publicKey,
+        escrowAccount: wsolEscrowAccount,
+        sellerReputation,
+        buyerReputation,
+        receiptTree: null,
+        escrowTokenAccount,
+        sellerBalance: null,
+        balanceTokenAccount: null,
+        tokenMint: NATIVE_MINT,
+        buyer: buyer.publicKey,
+        sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        recipient1: seller.publicKey,
+        recipient1Ata: null,
+        recipient2: null,
+        recipient2Ata: null,
+        recipient3: null,
+        recipient3Ata: null,
+      })
+      .preInstructions([
+        anchor.web3.Ed25519Program.createInstructionWithPrivateKey({
+          privateKey: buyer.secretKey,
+          message,
+        }),
+      ])
+      .signers([buyer])
+      .rpc();
+    
+    const sellerAfter = await provider.connection.getBalance(seller.publicKey);
+    assert.equal(sellerAfter - sellerBefore, wsolAmount);
+    assert.isNull(await provider.connection.getAccountInfo(
+      getAssociatedTokenAddressSync(NATIVE_MINT, seller.publicKey)
+    ));
+    
+    // 代币成交额按铸币分别累计
+    const rep = await program.account.reputation.fetch(sellerReputation);
+    const volume = rep.mintVolumes.find((entry) => entry.mint.equals(NATIVE_MINT));
+    assert.equal(volume.released.toNumber() - volumeBefore, wsolAmount);
+    assert.isNull(await provider.connection.getAccountInfo(escrowTokenAccount));
+    assert.isNull(await provider.connection.getAccountInfo(wsolEscrowAccount));
+  });
+
+  it("代币托管与 SOL 托管共用时间锁规则：过期后 release_token 只需卖家签名", async () => {
+    const timeoutUniqueId = generateRandomUniqueId();
+    const timeoutAmount = 0.01 * LAMPORTS_PER_SOL;
+
+    const [timeoutEscrowAccount] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("token_escrow"),
+        buyer.publicKey.toBuffer(),
+        seller.publicKey.toBuffer(),
+        Buffer.from([0]),
+        timeoutUniqueId,
+      ],
+      program.programId
+    );
+    const escrowTokenAccount = getAssociatedTokenAddressSync(NATIVE_MINT, timeoutEscrowAccount, true);
+    const [sellerReputation] = PublicKey.findProgramAddressSync(
+      [Buffer.from("seller_reputation"), seller.publicKey.toBuffer()],
+      program.programId
+    );
+    const [buyerReputation] = PublicKey.findProgramAddressSync(
+      [Buffer.from("buyer_reputation"), buyer.publicKey.toBuffer()],
+      program.programId
+    );
+    const [receipt] = PublicKey.findProgramAddressSync(
+      [Buffer.from("receipt"), timeoutEscrowAccount.toBuffer()],
+      program.programId
+    );
+
+    // 需要买卖双方签名，但 unlock_hours 为 0，创建后即已超时
+    await program.methods
+      .initializeToken(
+        null,
+        Array.from(timeoutUniqueId),
+        2,
+        new BN(0),
+        new BN(timeoutAmount),
+        { hashlock: null, stream: null, oracle: null, moderatorFeeBpsMax: 0, moderatorFeeFixed: new BN(0), panel: null, requireRegisteredModerator: false, minSellerSettlements: new BN(0), receiptTree: null, releaseHook: null }
+      )
+      .accounts({
+        payer: buyer.publicKey,
+        buyer: buyer.publicKey,
+        seller: seller.publicKey,
+        escrowAccount: timeoutEscrowAccount,
+        receiptTree: null,
+        tokenMint: NATIVE_MINT,
+        payerTokenAccount: null,
+        escrowTokenAccount,
+        sellerBalance: null,
+        balanceTokenAccount: null,
+        moderatorProfile: null,
+        registryConfig: null,
+        sellerReputation: null,
+      })
+      .signers([buyer])
+      .rpc();
+
+    const message = Buffer.concat([
+      timeoutUniqueId,
+      Buffer.from(new BN(0).toArray('le', 8)), // nonce
+      Buffer.from(new BN(0).toArray('le', 8)), // valid_until
+      seller.publicKey.toBuffer(),
+      Buffer.from(new BN(timeoutAmount).toArray('le', 8)),
+    ]);
+
+    // 之前 release_token 不走 timelock 分支，单签会因签名不足失败
+    await program.methods
+      .releaseToken([new BN(timeoutAmount)], [Buffer.from(nacl.sign.detached(message, seller.secretKey))], new BN(0))
+      .accounts({
+        initiator: buyer.publicKey,
+        escrowAccount: timeoutEscrowAccount,
+        sellerReputation,
+        buyerReputation,
+        receipt,
+        receiptTree: null,
+        escrowTokenAccount,
+        sellerBalance: null,
+        balanceTokenAccount: null,
+        tokenMint: NATIVE_MINT,
+        buyer: buyer.publicKey,
+        sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        recipient1: seller.publicKey,
+        recipient1Ata: null,
+        recipient2: null,
+        recipient2Ata: null,
+        recipient3: null,
+        recipient3Ata: null,
+      })
+      .preInstructions([
+        anchor.web3.Ed25519Program.createInstructionWithPrivateKey({
+          privateKey: seller.secretKey,
+          message,
+        }),
+      ])
+      .signers([buyer])
+      .rpc();
+
+    const settled = await program.account.settlementReceipt.fetch(receipt);
+    assert.deepEqual(Object.keys(settled.record.path), ["timeout"]);
+    assert.deepEqual(Object.keys(settled.record.outcome), ["timedOut"]);
+    assert.isTrue(settled.record.sellerApproved);
+    assert.isNull(await provider.connection.getAccountInfo(timeoutEscrowAccount));
+  });
+
+  it("初始化通过 return data 返回托管地址与 unique_id，供 CPI 调用方记录", async () => {
+    const createdUniqueId = generateRandomUniqueId();
+    const [createdEscrowAccount] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("sol_escrow"),
+        buyer.publicKey.toBuffer(),
+        seller.publicKey.toBuffer(),
+        Buffer.from([0]),
+        createdUniqueId,
+      ],
+      program.programId
+    );
+
+    const signature = await program.methods
+      .initializeSol(
+        null,
+        Array.from(createdUniqueId),
+        1,
+        new BN(unlockHours),
+        new BN(config.testAmounts.simple),
+        { hashlock: null, stream: null, oracle: null, moderatorFeeBpsMax: 0, moderatorFeeFixed: new BN(0), panel: null, requireRegisteredModerator: false, minSellerSettlements: new BN(0), receiptTree: null, releaseHook: null }
+      )
+      .accounts({
+        payer: buyer.publicKey,
+        buyer: buyer.publicKey,
+        seller: seller.publicKey,
+        escrowAccount: createdEscrowAccount,
+        receiptTree: null,
+        moderatorProfile: null,
+        registryConfig: null,
+        sellerReputation: null,
+      })
+      .signers([buyer])
+      .rpc({ commitment: "confirmed" });
+
+    const tx = await provider.connection.getTransaction(signature, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const returnData = tx.meta.returnData;
+    assert.equal(returnData.programId.toString(), program.programId.toString());
+
+    // EscrowCreated：escrow(32) || unique_id(20)
+    const data = Buffer.from(returnData.data[0], "base64");
+    assert.equal(data.length, 52);
+    assert.equal(new PublicKey(data.subarray(0, 32)).toString(), createdEscrowAccount.toString());
+    assert.deepEqual([...data.subarray(32)], [...createdUniqueId]);
+  });
+
+  it("旧布局托管账户迁移到当前版本", async function () {
+    // 夹具由 scripts/generate-legacy-fixtures.js 生成，并通过 Anchor.toml 预加载到本地验证器
+    const fixtures = [
+      { file: "legacy-sol-escrow.json", type: "solEscrow", moderator: null, nonce: 0, oracle: null },
+      { file: "legacy-token-escrow.json", type: "tokenEscrow", moderator: new PublicKey(Buffer.alloc(32, 3)), nonce: 0, oracle: null },
+      // 版本2账户保留已有字段，版本3新增的字段取默认值
+      { file: "v2-sol-escrow.json", type: "solEscrow", moderator: new PublicKey(Buffer.alloc(32, 3)), nonce: 7, oracle: new PublicKey(Buffer.alloc(32, 5)) },
+    ];
+    
+    let migrated = 0;
+    for (const { file, type, moderator: expectedModerator, nonce, oracle } of fixtures) {
+      const fixture = JSON.parse(fs.readFileSync(`tests/fixtures/${file}`, "utf8"));
+      const escrowKey = new PublicKey(fixture.pubkey);
+      const before = await provider.connection.getAccountInfo(escrowKey);
+      if (!before || before.data[8] === 3) {
+        console.log(`集群中没有未迁移的 ${file}，跳过`);
+        continue;
+      }
+      
+      await program.methods
+        .migrateEscrow()
+        .accounts({ payer: provider.wallet.publicKey, escrowAccount: escrowKey })
+        .rpc();
+      
+      const escrow = await program.account[type].fetch(escrowKey);
+      assert.equal(escrow.base.version, 3);
+      assert.isTrue(escrow.base.isInitialized);
+      assert.equal(escrow.base.buyer.toString(), new PublicKey(Buffer.alloc(32, 1)).toString());
+      assert.equal(escrow.base.seller.toString(), new PublicKey(Buffer.alloc(32, 2)).toString());
+      assert.equal(escrow.base.moderator?.toString() ?? null, expectedModerator?.toString() ?? null);
+      assert.equal(escrow.base.requiredSignatures, 2);
+      assert.equal(escrow.base.amount.toString(), "1000000");
+      assert.equal(escrow.base.nonce.toString(), nonce.toString());
+      assert.equal(escrow.base.oracle?.toString() ?? null, oracle?.toString() ?? null);
+      assert.isNull(escrow.base.receiptTree);
+      assert.isNull(escrow.base.releaseHook);
+      assert.isFalse(escrow.base.ledger);
+      assert.isFalse(escrow.base.requireRegisteredModerator);
+      
+      // SOL 托管的资金仍在账户中，迁移只补齐租金差额
+      const after = await provider.connection.getAccountInfo(escrowKey);
+      const rent = await provider.connection.getMinimumBalanceForRentExemption(after.data.length);
+      const held = type === "solEscrow" ? 1_000_000 : 0;
+      assert.equal(after.lamports, rent + held);
+      
+      try {
+        await program.methods
+          .migrateEscrow()
+          .accounts({ payer: provider.wallet.publicKey, escrowAccount: escrowKey })
+          .rpc();
+        assert.fail("已迁移的账户不应再次迁移");
+      } catch (e) {
+        expect(e.toString()).to.include("EscrowAlreadyMigrated");
+      }
+      migrated += 1;
+    }
+    
+    if (migrated === 0) {
+      this.skip();
+    }
+  });
+  
+  it("release hook：省略账户时可跳过的 hook 不阻塞结算，必需的 hook 拒绝结算", async () => {
+    const hookAmount = 0.01 * LAMPORTS_PER_SOL;
+    const hookProgram = Keypair.generate().publicKey;
+    const [sellerReputation] = PublicKey.findProgramAddressSync(
+      [Buffer.from("seller_reputation"), seller.publicKey.toBuffer()],
+      program.programId
+    );
+    const [buyerReputation] = PublicKey.findProgramAddressSync(
+      [Buffer.from("buyer_reputation"), buyer.publicKey.toBuffer()],
+      program.programId
+    );
+    
+    // 创建带 hook 的托管，买家单签付给卖家，不传 hook 账户
+    const releaseWithoutHookAccounts = async (initiatorMaySkip) => {
+      const id = generateRandomUniqueId();
+      const [escrow] = PublicKey.findProgramAddressSync(
+        [Buffer.from("sol_escrow"), buyer.publicKey.toBuffer(), seller.publicKey.toBuffer(), Buffer.from([0]), id],
+        program.programId
+      );
+      await program.methods
+        .initializeSol(
+          null,
+          Array.from(id),
+          1,
+          new BN(unlockHours),
+          new BN(hookAmount),
+          { hashlock: null, stream: null, oracle: null, moderatorFeeBpsMax: 0, moderatorFeeFixed: new BN(0), panel: null, requireRegisteredModerator: false, minSellerSettlements: new BN(0), receiptTree: null, releaseHook: { program: hookProgram, initiatorMaySkip } }
+        )
+        .accounts({
+          payer: buyer.publicKey,
+          buyer: buyer.publicKey,
+          seller: seller.publicKey,
+          escrowAccount: escrow,
+          receiptTree: null,
+          moderatorProfile: null,
+          registryConfig: null,
+          sellerReputation: null,
+        })
+        .signers([buyer])
+        .rpc();
+      
+      const stored = await program.account.solEscrow.fetch(escrow);
+      assert.equal(stored.base.releaseHook.program.toString(), hookProgram.toString());
+      
+      const message = Buffer.concat([
+        id,
+        Buffer.from(new BN(0).toArray('le', 8)), // nonce
+        Buffer.from(new BN(0).toArray('le', 8)), // valid_until
+        seller.publicKey.toBuffer(),
+        Buffer.from(new BN(hookAmount).toArray('le', 8)),
+      ]);
+      await program.methods
+        .releaseSol([new BN(hookAmount)], [Buffer.from(nacl.sign.detached(message, buyer.secretKey))], new BN(0))
+        .accounts({
+          initiator: buyer.publicKey,
+          escrowAccount: escrow,
+          buyer: buyer.publicKey,
+          seller: seller.publicKey,
+          sellerReputation,
+          buyerReputation,
+          receiptTree: null,
+          sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+          recipient1: seller.publicKey,
+          recipient2: null,
+          recipient3: null,
+        })
+        .preInstructions([
+          anchor.web3.Ed25519Program.createInstructionWithPrivateKey({
+            privateKey: buyer.secretKey,
+            message,
+          }),
+        ])
+        .signers([buyer])
+        .rpc();
+      return escrow;
+    };
+    
+    const skipped = await releaseWithoutHookAccounts(true);
+    assert.isNull(await provider.connection.getAccountInfo(skipped));
+    
+    try {
+      await releaseWithoutHookAccounts(false);
+      assert.fail("必需的 hook 缺少账户时不应结算");
+    } catch (e) {
+      expect(e.toString()).to.include("ReleaseHookMissing");
+    }
+  });
+  
+  it("bump_nonce 使已签名的批准失效，过期的 valid_until 被拒绝", async () => {
+    const amount = 0.01 * LAMPORTS_PER_SOL;
+    const opened = await openSolEscrow({ amount });
+    const payouts = [[seller.publicKey, amount]];
+    
+    // 过期的批准即使签名正确也不能释放
+    const now = Math.floor(Date.now() / 1000);
+    try {
+      await releaseSol(opened, payouts, [buyer], { validUntil: now - 3600 });
+      assert.fail("过期的批准不应被接受");
+    } catch (e) {
+      expect(e.toString()).to.include("SignatureExpired");
+    }
+    
+    // 买家签好批准后再提升 nonce，旧批准签的是 nonce 0，不再匹配
+    await program.methods
+      .bumpNonce()
+      .accounts({
+        signer: buyer.publicKey,
+        solEscrow: opened.escrow,
+        tokenEscrow: null,
+        multiAssetEscrow: null,
+        crowdfundEscrow: null,
+        fixedEscrow: null,
+      })
+      .signers([buyer])
+      .rpc();
+    try {
+      await releaseSol(opened, payouts, [buyer], { nonce: 0 });
+      assert.fail("nonce 提升后旧批准不应被接受");
+    } catch (e) {
+      expect(e.toString()).to.include("SignatureVerificationFailed");
+    }
+    
+    // 按新 nonce 重新签名即可释放
+    await releaseSol(opened, payouts, [buyer], { nonce: 1 });
+    assert.isNull(await provider.connection.getAccountInfo(opened.escrow));
+  });
+  
+  it("bump_nonce：仲裁小组成员和预言机也能撤回授权，无关钱包不能", async () => {
+    const member = Keypair.generate();
+    const deliveryOracle = Keypair.generate();
+    const stranger = Keypair.generate();
+    const opened = await openSolEscrow({
+      amount: 0.01 * LAMPORTS_PER_SOL,
+      requiredSignatures: 2,
+      options: escrowOptions({ oracle: deliveryOracle.publicKey, panel: { members: [member.publicKey], threshold: 1 } }),
+    });
+    
+    const bumpBy = (keypair) => program.methods
+      .bumpNonce()
+      .accounts({
+        signer: keypair.publicKey,
+        solEscrow: opened.escrow,
+        tokenEscrow: null,
+        multiAssetEscrow: null,
+        crowdfundEscrow: null,
+        fixedEscrow: null,
+      })
+      .signers([keypair])
+      .rpc();
+    
+    await bumpBy(member);
+    await bumpBy(deliveryOracle);
+    try {
+      await bumpBy(stranger);
+      assert.fail("无关钱包不应能提升 nonce");
+    } catch (e) {
+      expect(e.toString()).to.include("Unauthorized");
+    }
+    
+    const escrow = await program.account.solEscrow.fetch(opened.escrow);
+    assert.equal(escrow.base.nonce.toNumber(), 2);
+  });
+  
+  it("卖家账本：账本托管存入资金池，释放时卖家份额直接记账，只能提取已结算部分", async () => {
+    const amount = 0.1 * LAMPORTS_PER_SOL;
+    const refund = 0.02 * LAMPORTS_PER_SOL;
+    const [sellerBalance] = PublicKey.findProgramAddressSync(
+      [Buffer.from("seller_balance"), seller.publicKey.toBuffer(), tokenMint.toBuffer()],
+      program.programId
+    );
+    const balanceTokenAccount = getAssociatedTokenAddressSync(tokenMint, sellerBalance, true);
+    
+    await program.methods
+      .openSellerBalance()
+      .accounts({ seller: seller.publicKey, sellerBalance, balanceTokenAccount, tokenMint })
+      .signers([seller])
+      .rpc();
+    
+    const withdraw = (value) => program.methods
+      .withdrawBalance(new BN(value))
+      .accounts({
+        seller: seller.publicKey,
+        sellerBalance,
+        balanceTokenAccount,
+        destination: recipientTokenAccount,
+      })
+      .signers([seller])
+      .rpc();
+    
+    // 账本托管不创建托管代币账户，资金直接进入卖家资金池
+    const id = generateRandomUniqueId();
+    const [escrow] = PublicKey.findProgramAddressSync(
+      [Buffer.from("token_escrow"), buyer.publicKey.toBuffer(), seller.publicKey.toBuffer(), Buffer.from([0]), id],
+      program.programId
+    );
+    await program.methods
+      .initializeToken(null, Array.from(id), 1, new BN(unlockHours), new BN(amount), escrowOptions())
+      .accounts({
+        payer: buyer.publicKey,
+        buyer: buyer.publicKey,
+        seller: seller.publicKey,
+        escrowAccount: escrow,
+        receiptTree: null,
+        tokenMint,
+        payerTokenAccount: buyerTokenAccount,
+        escrowTokenAccount: null,
+        sellerBalance,
+        balanceTokenAccount,
+        moderatorProfile: null,
+        registryConfig: null,
+        sellerReputation: null,
+      })
+      .signers([buyer])
+      .rpc();
+    
+    assert.equal((await program.account.sellerBalance.fetch(sellerBalance)).escrowed.toNumber(), amount);
+    assert.isNull(await provider.connection.getAccountInfo(getAssociatedTokenAddressSync(tokenMint, escrow, true)));
+    
+    // 未结算的托管资金不可提取
+    try {
+      await withdraw(1);
+      assert.fail("未结算的资金不应被提取");
+    } catch (e) {
+      expect(e.toString()).to.include("InsufficientFunds");
+    }
+    
+    // 卖家份额只记账，退给买家的部分从资金池转出
+    const buyerAta = getAssociatedTokenAddressSync(tokenMint, buyer.publicKey);
+    const payouts = [[seller.publicKey, amount - refund], [buyer.publicKey, refund]];
+    const message = releaseMessage(id, 0, 0, payouts);
+    const [receipt] = PublicKey.findProgramAddressSync(
+      [Buffer.from("receipt"), escrow.toBuffer()],
+      program.programId
+    );
+    await program.methods
+      .releaseToken(
+        payouts.map(([, value]) => new BN(value)),
+        [Buffer.from(nacl.sign.detached(message, buyer.secretKey))],
+        new BN(0)
+      )
+      .accounts({
+        initiator: buyer.publicKey,
+        escrowAccount: escrow,
+        ...reputationAccounts(seller.publicKey, buyer.publicKey),
+        receipt,
+        receiptTree: null,
+        escrowTokenAccount: null,
+        sellerBalance,
+        balanceTokenAccount,
+        tokenMint,
+        buyer: buyer.publicKey,
+        sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        recipient1: seller.publicKey,
+        recipient1Ata: null,
+        recipient2: buyer.publicKey,
+        recipient2Ata: buyerAta,
+        recipient3: null,
+        recipient3Ata: null,
+      })
+      .preInstructions([ed25519Instruction(message, [buyer])])
+      .signers([buyer])
+      .rpc();
+    
+    const ledger = await program.account.sellerBalance.fetch(sellerBalance);
+    assert.equal(ledger.escrowed.toNumber(), 0);
+    assert.equal(ledger.credited.toNumber(), amount - refund);
+    assert.equal(Number((await getAccount(provider.connection, balanceTokenAccount)).amount), amount - refund);
+    assert.equal(Number((await getAccount(provider.connection, buyerAta)).amount), refund);
+    assert.isNull(await provider.connection.getAccountInfo(escrow));
+    
+    // 提取不能超过已记账的余额
+    try {
+      await withdraw(amount);
+      assert.fail("提取金额不应超过可用余额");
+    } catch (e) {
+      expect(e.toString()).to.include("InsufficientFunds");
+    }
+    
+    const sellerBefore = (await getAccount(provider.connection, recipientTokenAccount)).amount;
+    await withdraw(amount - refund);
+    const sellerAfter = (await getAccount(provider.connection, recipientTokenAccount)).amount;
+    assert.equal(Number(sellerAfter - sellerBefore), amount - refund);
+    assert.equal(Number((await getAccount(provider.connection, balanceTokenAccount)).amount), 0);
+  });
+  
+  it("领取凭证：接收方领取一次，重复领取与未到期的回收被拒绝", async () => {
+    const amount = 0.02 * LAMPORTS_PER_SOL;
+    const opened = await openSolEscrow({ amount });
+    const payouts = [[seller.publicKey, 0.015 * LAMPORTS_PER_SOL], [buyer.publicKey, 0.005 * LAMPORTS_PER_SOL]];
+    const ticketOf = (owner) => PublicKey.findProgramAddressSync(
+      [Buffer.from("claim_ticket"), opened.escrow.toBuffer(), owner.toBuffer()],
+      program.programId
+    )[0];
+    const sellerTicket = ticketOf(seller.publicKey);
+    const buyerTicket = ticketOf(buyer.publicKey);
+    
+    // 与 release_sol 相同的批准消息，资金存入每个接收方的领取凭证
+    const message = releaseMessage(opened.id, 0, 0, payouts);
+    await program.methods
+      .releaseSolToClaims(
+        payouts.map(([, value]) => new BN(value)),
+        [Buffer.from(nacl.sign.detached(message, buyer.secretKey))],
+        new BN(0),
+        payouts.map(([recipient]) => recipient)
+      )
+      .accounts({
+        initiator: buyer.publicKey,
+        escrowAccount: opened.escrow,
+        ...reputationAccounts(seller.publicKey, buyer.publicKey),
+        receiptTree: null,
+        buyer: buyer.publicKey,
+        sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+      })
+      .remainingAccounts([sellerTicket, buyerTicket].map((pubkey) => ({ pubkey, isSigner: false, isWritable: true })))
+      .preInstructions([ed25519Instruction(message, [buyer])])
+      .signers([buyer])
+      .rpc();
+    assert.isNull(await provider.connection.getAccountInfo(opened.escrow));
+    
+    const ticket = await program.account.claimTicket.fetch(sellerTicket);
+    assert.equal(ticket.amount.toNumber(), payouts[0][1]);
+    assert.equal(ticket.payerAddress.toString(), buyer.publicKey.toString());
+    
+    const claim = () => program.methods
+      .claim()
+      .accounts({
+        owner: seller.publicKey,
+        claimTicket: sellerTicket,
+        destination: seller.publicKey,
+        rentPayer: buyer.publicKey,
+        ticketTokenAccount: null,
+        tokenProgram: null,
+      })
+      .signers([seller])
+      .rpc();
+    
+    const sellerBefore = await provider.connection.getBalance(seller.publicKey);
+    await claim();
+    const sellerAfter = await provider.connection.getBalance(seller.publicKey);
+    assert.equal(sellerAfter - sellerBefore, payouts[0][1]);
+    assert.isNull(await provider.connection.getAccountInfo(sellerTicket));
+    
+    // 凭证领取后即关闭，不能再次领取
+    try {
+      await claim();
+      assert.fail("凭证不应被领取两次");
+    } catch (e) {
+      expect(e.toString()).to.include("AccountNotInitialized");
+    }
+    
+    // 未过期的凭证不能退回付款方
+    try {
+      await program.methods
+        .reclaimExpiredClaim()
+        .accounts({
+          claimTicket: buyerTicket,
+          destination: buyer.publicKey,
+          rentPayer: buyer.publicKey,
+          ticketTokenAccount: null,
+          tokenProgram: null,
+        })
+        .rpc();
+      assert.fail("未过期的凭证不应被回收");
+    } catch (e) {
+      expect(e.toString()).to.include("ClaimNotExpired");
+    }
+  });
+  
+  it("过期的领取凭证只能退回付款方", async function () {
+    // 夹具由 scripts/generate-claim-fixtures.js 生成，并通过 Anchor.toml 预加载到本地验证器
+    const fixture = JSON.parse(fs.readFileSync("tests/fixtures/expired-claim-ticket.json", "utf8"));
+    const claimTicket = new PublicKey(fixture.pubkey);
+    if (!(await provider.connection.getAccountInfo(claimTicket))) {
+      console.log("集群中没有过期的领取凭证夹具，跳过");
+      this.skip();
+    }
+    
+    const payer = new PublicKey(Buffer.alloc(32, 7));
+    const rentPayer = new PublicKey(Buffer.alloc(32, 8));
+    const reclaim = (destination) => program.methods
+      .reclaimExpiredClaim()
+      .accounts({ claimTicket, destination, rentPayer, ticketTokenAccount: null, tokenProgram: null })
+      .rpc();
+    
+    try {
+      await reclaim(buyer.publicKey);
+      assert.fail("过期凭证不应退回付款方以外的账户");
+    } catch (e) {
+      expect(e.toString()).to.include("InvalidRecipient");
+    }
+    
+    const payerBefore = await provider.connection.getBalance(payer);
+    const rentPayerBefore = await provider.connection.getBalance(rentPayer);
+    await reclaim(payer);
+    assert.equal(await provider.connection.getBalance(payer) - payerBefore, 1_000_000);
+    assert.equal(await provider.connection.getBalance(rentPayer) - rentPayerBefore, fixture.account.lamports - 1_000_000);
+    assert.isNull(await provider.connection.getAccountInfo(claimTicket));
+  });
+  
+  it("多资产托管：全部存入后才能释放，金库中的多余代币退回存入方，超时后卖家单签释放，未注资时超时取消", async () => {
+    // 卖家一方存入另一种代币（例如 NFT）
+    const sellerMint = await createMint(provider.connection, seller, mintAuthority.publicKey, null, 0);
+    const sellerMintAccount = await createAccount(provider.connection, seller, sellerMint, seller.publicKey);
+    const buyerNftAccount = await createAccount(provider.connection, buyer, sellerMint, buyer.publicKey);
+    await mintTo(provider.connection, seller, sellerMint, sellerMintAccount, mintAuthority, 3);
+    
+    const buyerAmount = 0.05 * LAMPORTS_PER_SOL;
+    const sellerAmount = 1;
+    const tokenBalance = async (account) => Number((await getAccount(provider.connection, account)).amount);
+    
+    const openMultiAsset = async (hours, requiredSignatures = 1) => {
+      const id = generateRandomUniqueId();
+      const [escrow] = PublicKey.findProgramAddressSync(
+        [Buffer.from("multi_escrow"), buyer.publicKey.toBuffer(), seller.publicKey.toBuffer(), Buffer.from([0]), id],
+        program.programId
+      );
+      await program.methods
+        .initializeMultiAsset(null, Array.from(id), requiredSignatures, new BN(hours), [
+          { mint: tokenMint, depositor: buyer.publicKey, amount: new BN(buyerAmount) },
+          { mint: sellerMint, depositor: seller.publicKey, amount: new BN(sellerAmount) },
+        ])
+        .accounts({ payer: buyer.publicKey, buyer: buyer.publicKey, seller: seller.publicKey, escrowAccount: escrow })
+        .signers([buyer])
+        .rpc();
+      const vaults = [
+        getAssociatedTokenAddressSync(tokenMint, escrow, true),
+        getAssociatedTokenAddressSync(sellerMint, escrow, true),
+      ];
+      return { escrow, id, vaults };
+    };
+    
+    const deposit = ({ escrow }, depositor, mint, depositorTokenAccount, vault) => program.methods
+      .depositAsset()
+      .accounts({
+        depositor: depositor.publicKey,
+        escrowAccount: escrow,
+        tokenMint: mint,
+        depositorTokenAccount,
+        escrowTokenAccount: vault,
+      })
+      .signers([depositor])
+      .rpc();
+    
+    // 每个资产依次为 [vault, depositor, depositor_token_account]
+    const assetAccounts = ({ vaults }) => [
+      vaults[0], buyer.publicKey, buyerTokenAccount,
+      vaults[1], seller.publicKey, sellerMintAccount,
+    ].map((pubkey) => ({ pubkey, isSigner: false, isWritable: true }));
+    
+    const opened = await openMultiAsset(unlockHours);
+    await deposit(opened, buyer, tokenMint, buyerTokenAccount, opened.vaults[0]);
+    assert.equal((await program.account.multiAssetEscrow.fetch(opened.escrow)).assets[0].deposited, true);
+    
+    // 买家的代币给卖家，卖家的 NFT 给买家
+    const payouts = [
+      { mint: tokenMint, recipient: seller.publicKey, amount: new BN(buyerAmount) },
+      { mint: sellerMint, recipient: buyer.publicKey, amount: new BN(sellerAmount) },
+    ];
+    const releaseWith = (target, signers) => {
+      const message = Buffer.concat([
+        target.id,
+        Buffer.from(new BN(0).toArray('le', 8)),
+        Buffer.from(new BN(0).toArray('le', 8)),
+        ...payouts.flatMap(({ mint, recipient, amount }) => [mint.toBuffer(), recipient.toBuffer(), Buffer.from(amount.toArray('le', 8))]),
+      ]);
+      return program.methods
+        .releaseMultiAsset(payouts, signers.map((keypair) => Buffer.from(nacl.sign.detached(message, keypair.secretKey))), new BN(0))
+        .accounts({
+          initiator: buyer.publicKey,
+          escrowAccount: target.escrow,
+          ...reputationAccounts(seller.publicKey, buyer.publicKey),
+          buyer: buyer.publicKey,
+          sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        })
+        .remainingAccounts([
+          ...assetAccounts(target),
+          { pubkey: recipientTokenAccount, isSigner: false, isWritable: true },
+          { pubkey: buyerNftAccount, isSigner: false, isWritable: true },
+        ])
+        .preInstructions([ed25519Instruction(message, signers)])
+        .signers([buyer])
+        .rpc();
+    };
+    const release = () => releaseWith(opened, [buyer]);
+    
+    // 卖家尚未存入，不能释放买家已存入的资产
+    try {
+      await release();
+      assert.fail("未全部注资时不应释放");
+    } catch (e) {
+      expect(e.toString()).to.include("EscrowNotFullyFunded");
+    }
+    
+    await deposit(opened, seller, sellerMint, sellerMintAccount, opened.vaults[1]);
+    
+    // 第三方向金库转入少量代币，不影响结算，释放时退回存入方
+    await transfer(provider.connection, buyer, buyerTokenAccount, opened.vaults[0], buyer, 7);
+    
+    const sellerBefore = await tokenBalance(recipientTokenAccount);
+    const buyerBefore = await tokenBalance(buyerTokenAccount);
+    await release();
+    assert.equal(await tokenBalance(recipientTokenAccount) - sellerBefore, buyerAmount);
+    assert.equal(await tokenBalance(buyerTokenAccount) - buyerBefore, 7);
+    assert.equal(await tokenBalance(buyerNftAccount), sellerAmount);
+    for (const account of [opened.escrow, ...opened.vaults]) {
+      assert.isNull(await provider.connection.getAccountInfo(account));
+    }
+    
+    // 2-of-2 托管在超时前只有卖家签名不能释放，超时后与 release_sol 相同，卖家单签即可
+    const locked = await openMultiAsset(unlockHours, 2);
+    await deposit(locked, buyer, tokenMint, buyerTokenAccount, locked.vaults[0]);
+    await deposit(locked, seller, sellerMint, sellerMintAccount, locked.vaults[1]);
+    try {
+      await releaseWith(locked, [seller]);
+      assert.fail("超时前卖家单签不应释放");
+    } catch (e) {
+      expect(e.toString()).to.include("InsufficientSignatures");
+    }
+    
+    const expired = await openMultiAsset(0, 2);
+    await deposit(expired, buyer, tokenMint, buyerTokenAccount, expired.vaults[0]);
+    await deposit(expired, seller, sellerMint, sellerMintAccount, expired.vaults[1]);
+    const timeoutBefore = await tokenBalance(recipientTokenAccount);
+    await releaseWith(expired, [seller]);
+    assert.equal(await tokenBalance(recipientTokenAccount) - timeoutBefore, buyerAmount);
+    const [expiredReceipt] = PublicKey.findProgramAddressSync(
+      [Buffer.from("receipt"), expired.escrow.toBuffer()],
+      program.programId
+    );
+    const timedOut = await program.account.settlementReceipt.fetch(expiredReceipt);
+    assert.deepEqual(Object.keys(timedOut.record.outcome), ["timedOut"]);
+    
+    // 超时后卖家仍未存入，任一方可取消，已存入的资产退回买家
+    const unfunded = await openMultiAsset(0);
+    await deposit(unfunded, buyer, tokenMint, buyerTokenAccount, unfunded.vaults[0]);
+    const refundBefore = await tokenBalance(buyerTokenAccount);
+    await program.methods
+      .cancelMultiAsset()
+      .accounts({ initiator: seller.publicKey, escrowAccount: unfunded.escrow, buyer: buyer.publicKey })
+      .remainingAccounts(assetAccounts(unfunded))
+      .signers([seller])
+      .rpc();
+    assert.equal(await tokenBalance(buyerTokenAccount) - refundBefore, buyerAmount);
+    assert.isNull(await provider.connection.getAccountInfo(unfunded.escrow));
+  });
+  
+  it("原生 SOL 哈希时间锁代币托管：提交原像后以 lamports 支付卖家，收据记录实际支付金额", async () => {
+    const amount = 0.03 * LAMPORTS_PER_SOL;
+    const preimage = randomBytes(32);
+    const hash = createHash("sha256").update(preimage).digest();
+    const id = generateRandomUniqueId();
+    const [escrow] = PublicKey.findProgramAddressSync(
+      [Buffer.from("token_escrow"), buyer.publicKey.toBuffer(), seller.publicKey.toBuffer(), Buffer.from([0]), id],
+      program.programId
+    );
+    const escrowTokenAccount = getAssociatedTokenAddressSync(NATIVE_MINT, escrow, true);
+    const [receipt] = PublicKey.findProgramAddressSync(
+      [Buffer.from("receipt"), escrow.toBuffer()],
+      program.programId
+    );
+    
+    await program.methods
+      .initializeToken(
+        null,
+        Array.from(id),
+        1,
+        new BN(unlockHours),
+        new BN(amount),
+        escrowOptions({ hashlock: { algorithm: { sha256: {} }, hash: Array.from(hash) } })
+      )
+      .accounts({
+        payer: buyer.publicKey,
+        buyer: buyer.publicKey,
+        seller: seller.publicKey,
+        escrowAccount: escrow,
+        receiptTree: null,
+        tokenMint: NATIVE_MINT,
+        payerTokenAccount: null,
+        escrowTokenAccount,
+        sellerBalance: null,
+        balanceTokenAccount: null,
+        moderatorProfile: null,
+        registryConfig: null,
+        sellerReputation: null,
+      })
+      .signers([buyer])
+      .rpc();
+    
+    // 金库中多出的 lamports 同样付给卖家，收据按实际支付金额记录
+    const extra = 1000;
+    await provider.sendAndConfirm(
+      new anchor.web3.Transaction().add(
+        SystemProgram.transfer({ fromPubkey: buyer.publicKey, toPubkey: escrowTokenAccount, lamports: extra }),
+        createSyncNativeInstruction(escrowTokenAccount)
+      ),
+      [buyer]
+    );
+    
+    const sellerBefore = await provider.connection.getBalance(seller.publicKey);
+    await program.methods
+      .claimWithPreimageToken(preimage)
+      .accounts({
+        initiator: buyer.publicKey,
+        escrowAccount: escrow,
+        receipt,
+        receiptTree: null,
+        escrowTokenAccount,
+        tokenMint: NATIVE_MINT,
+        seller: seller.publicKey,
+        sellerTokenAccount: null,
+        buyer: buyer.publicKey,
+      })
+      .signers([buyer])
+      .rpc();
+    
+    assert.equal(await provider.connection.getBalance(seller.publicKey) - sellerBefore, amount + extra);
+    assert.isNull(await provider.connection.getAccountInfo(getAssociatedTokenAddressSync(NATIVE_MINT, seller.publicKey)));
+    assert.isNull(await provider.connection.getAccountInfo(escrowTokenAccount));
+    
+    const settled = await program.account.settlementReceipt.fetch(receipt);
+    assert.equal(settled.record.total.toNumber(), amount + extra);
+    assert.equal(settled.record.payouts[0].amount.toNumber(), amount + extra);
+  });
+  
   after(async () => {
     try {
       // 保留清理代码，简化日志
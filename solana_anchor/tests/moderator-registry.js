@@ -0,0 +1,227 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { randomBytes } from "crypto";
+import {
+  TOKEN_PROGRAM_ID,
+  ASSOCIATED_TOKEN_PROGRAM_ID,
+  createMint,
+  createAccount,
+  getAssociatedTokenAddressSync,
+  mintTo,
+  getAccount,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+describe("moderator-registry", () => {
+  anchor.setProvider(anchor.AnchorProvider.env());
+
+  const program = anchor.workspace.ModeratorRegistry;
+  const escrowProgram = anchor.workspace.EscrowProgram;
+  const provider = anchor.getProvider();
+  const payer = provider.wallet.payer;
+  const BN = anchor.BN;
+
+  const moderator = Keypair.generate();
+  const minStake = new BN(1_000_000);
+
+  const [configPDA] = anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("registry_config")],
+    program.programId
+  );
+  const [profilePDA] = anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("moderator"), moderator.publicKey.toBuffer()],
+    program.programId
+  );
+
+  let stakeMint;
+  let slashTreasury;
+  let moderatorTokenAccount;
+  let stakeVault;
+
+  before(async () => {
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(moderator.publicKey, anchor.web3.LAMPORTS_PER_SOL),
+      "confirmed"
+    );
+
+    stakeMint = await createMint(provider.connection, payer, payer.publicKey, null, 6);
+    slashTreasury = await createAccount(provider.connection, payer, stakeMint, payer.publicKey);
+    moderatorTokenAccount = await createAccount(provider.connection, payer, stakeMint, moderator.publicKey);
+    await mintTo(provider.connection, payer, stakeMint, moderatorTokenAccount, payer, 5_000_000);
+    stakeVault = getAssociatedTokenAddressSync(stakeMint, profilePDA, true);
+  });
+
+  it("Initialize registry", async () => {
+    await program.methods
+      .initializeRegistry(provider.wallet.publicKey, minStake, new BN(7 * 24 * 3600))
+      .accounts({
+        config: configPDA,
+        stakeMint,
+        slashTreasury,
+        authority: provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    const config = await program.account.registryConfig.fetch(configPDA);
+    expect(config.governance.toString()).to.equal(provider.wallet.publicKey.toString());
+    expect(config.minStake.toNumber()).to.equal(minStake.toNumber());
+  });
+
+  it("Register moderator and stake", async () => {
+    await program.methods
+      .registerModerator({
+        feeSchedule: { feeBps: 200, feeFixed: new BN(0) },
+        supportedMints: [],
+        metadataUri: "https://example.com/moderator.json",
+      })
+      .accounts({
+        moderator: moderator.publicKey,
+        config: configPDA,
+        profile: profilePDA,
+        stakeMint,
+        stakeVault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([moderator])
+      .rpc();
+
+    // Not active until the minimum stake is deposited
+    let profile = await program.account.moderatorProfile.fetch(profilePDA);
+    expect(profile.active).to.equal(false);
+
+    await program.methods
+      .stake(minStake)
+      .accounts({
+        moderator: moderator.publicKey,
+        config: configPDA,
+        profile: profilePDA,
+        moderatorTokenAccount,
+        stakeVault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([moderator])
+      .rpc();
+
+    profile = await program.account.moderatorProfile.fetch(profilePDA);
+    expect(profile.active).to.equal(true);
+    expect(profile.stake.toNumber()).to.equal(minStake.toNumber());
+  });
+
+  it("Governance slashes a moderator below the minimum stake", async () => {
+    await program.methods
+      .slashModerator(new BN(500_000), Array.from(Buffer.alloc(32, 1)))
+      .accounts({
+        governance: provider.wallet.publicKey,
+        config: configPDA,
+        profile: profilePDA,
+        stakeVault,
+        slashTreasury,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+
+    const profile = await program.account.moderatorProfile.fetch(profilePDA);
+    expect(profile.active).to.equal(false);
+    expect(profile.slashedTotal.toNumber()).to.equal(500_000);
+    expect(Number((await getAccount(provider.connection, slashTreasury)).amount)).to.equal(500_000);
+  });
+
+  it("Only governance can suspend", async () => {
+    try {
+      await program.methods
+        .setSuspended(true)
+        .accounts({
+          governance: moderator.publicKey,
+          config: configPDA,
+          profile: profilePDA,
+        })
+        .signers([moderator])
+        .rpc();
+      expect.fail("Non-governance key should not be able to suspend");
+    } catch (e) {
+      expect(e.toString()).to.include("Unauthorized");
+    }
+  });
+
+  it("Escrows check the stake against the current min_stake, not the cached active flag", async () => {
+    // Top the slashed stake back up to the minimum
+    await program.methods
+      .stake(new BN(500_000))
+      .accounts({
+        moderator: moderator.publicKey,
+        config: configPDA,
+        profile: profilePDA,
+        moderatorTokenAccount,
+        stakeVault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([moderator])
+      .rpc();
+
+    const buyer = provider.wallet.publicKey;
+    const seller = Keypair.generate().publicKey;
+    const initialize = () => {
+      const uniqueId = randomBytes(20);
+      const [escrowAccount] = PublicKey.findProgramAddressSync(
+        [Buffer.from("sol_escrow"), buyer.toBuffer(), seller.toBuffer(), Buffer.from([1]), uniqueId],
+        escrowProgram.programId
+      );
+      return escrowProgram.methods
+        .initializeSol(
+          moderator.publicKey,
+          Array.from(uniqueId),
+          2,
+          new BN(24),
+          new BN(10_000_000),
+          {
+            hashlock: null,
+            stream: null,
+            oracle: null,
+            moderatorFeeBpsMax: 0,
+            moderatorFeeFixed: new BN(0),
+            panel: null,
+            requireRegisteredModerator: true,
+            minSellerSettlements: new BN(0),
+            receiptTree: null,
+            releaseHook: null,
+          }
+        )
+        .accounts({
+          payer: buyer,
+          buyer,
+          seller,
+          escrowAccount,
+          moderatorProfile: profilePDA,
+          registryConfig: configPDA,
+          sellerReputation: null,
+          systemProgram: SystemProgram.programId,
+          rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+        })
+        .rpc();
+    };
+
+    await initialize();
+
+    // Raising min_stake leaves the cached flag set until the profile is touched again
+    const updateMinStake = (value) =>
+      program.methods
+        .updateRegistry(provider.wallet.publicKey, value, new BN(7 * 24 * 3600))
+        .accounts({ config: configPDA, authority: provider.wallet.publicKey })
+        .rpc();
+    await updateMinStake(new BN(2_000_000));
+    expect((await program.account.moderatorProfile.fetch(profilePDA)).active).to.equal(true);
+
+    try {
+      await initialize();
+      expect.fail("An understaked moderator should not be accepted");
+    } catch (e) {
+      expect(e.toString()).to.include("ModeratorNotRegistered");
+    } finally {
+      await updateMinStake(minStake);
+    }
+  });
+});
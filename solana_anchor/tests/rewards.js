@@ -0,0 +1,281 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  TOKEN_PROGRAM_ID,
+  ASSOCIATED_TOKEN_PROGRAM_ID,
+  createMint,
+  createAccount,
+  getAssociatedTokenAddressSync,
+  mintTo,
+  getAccount,
+} from "@solana/spl-token";
+import { randomBytes } from "crypto";
+import nacl from "tweetnacl";
+import { expect } from "chai";
+
+describe("rewards", () => {
+  anchor.setProvider(anchor.AnchorProvider.env());
+
+  const program = anchor.workspace.Rewards;
+  const escrowProgram = anchor.workspace.EscrowProgram;
+  const provider = anchor.getProvider();
+  const payer = provider.wallet.payer;
+  const BN = anchor.BN;
+
+  const buyer = Keypair.generate();
+  const seller = Keypair.generate();
+  const promotionId = new BN(Date.now());
+  const rewardPerBuyer = 50_000_000; // 50 tokens with 6 decimals
+  const minSettlement = 0.01 * LAMPORTS_PER_SOL;
+  const ED25519_PROGRAM_ID = new PublicKey("Ed25519SigVerify111111111111111111111111111");
+
+  const [promotionPDA] = PublicKey.findProgramAddressSync(
+    [Buffer.from("promotion"), provider.wallet.publicKey.toBuffer(), promotionId.toArrayLike(Buffer, "le", 8)],
+    program.programId
+  );
+
+  let rewardMint;
+  let rewardVault;
+  let buyerTokenAccount;
+
+  // One Ed25519 instruction carrying every signer's signature of the same message
+  const ed25519Instruction = (message, keypairs) => {
+    const headerSize = 2 + keypairs.length * 14;
+    const entrySize = 32 + 64;
+    const messageOffset = headerSize + keypairs.length * entrySize;
+    const data = Buffer.alloc(messageOffset + message.length);
+    data.writeUInt8(keypairs.length, 0);
+    keypairs.forEach((keypair, i) => {
+      const publicKeyOffset = headerSize + i * entrySize;
+      const signatureOffset = publicKeyOffset + 32;
+      const offsets = 2 + i * 14;
+      data.writeUInt16LE(signatureOffset, offsets);
+      data.writeUInt16LE(0xffff, offsets + 2);
+      data.writeUInt16LE(publicKeyOffset, offsets + 4);
+      data.writeUInt16LE(0xffff, offsets + 6);
+      data.writeUInt16LE(messageOffset, offsets + 8);
+      data.writeUInt16LE(message.length, offsets + 10);
+      data.writeUInt16LE(0xffff, offsets + 12);
+      keypair.publicKey.toBuffer().copy(data, publicKeyOffset);
+      Buffer.from(nacl.sign.detached(message, keypair.secretKey)).copy(data, signatureOffset);
+    });
+    message.copy(data, messageOffset);
+    return new anchor.web3.TransactionInstruction({ programId: ED25519_PROGRAM_ID, keys: [], data });
+  };
+
+  // Open a SOL escrow and release it to the seller, approved by the buyer and the seller
+  const settleEscrow = async (escrowSellerKeypair, { amount = minSettlement, signers = [buyer, escrowSellerKeypair] } = {}) => {
+    const escrowSeller = escrowSellerKeypair.publicKey;
+    const uniqueId = Buffer.from(randomBytes(20));
+    const [escrow] = PublicKey.findProgramAddressSync(
+      [Buffer.from("sol_escrow"), buyer.publicKey.toBuffer(), escrowSeller.toBuffer(), Buffer.from([0]), uniqueId],
+      escrowProgram.programId
+    );
+    const [sellerReputation] = PublicKey.findProgramAddressSync(
+      [Buffer.from("seller_reputation"), escrowSeller.toBuffer()],
+      escrowProgram.programId
+    );
+    const [buyerReputation] = PublicKey.findProgramAddressSync(
+      [Buffer.from("buyer_reputation"), buyer.publicKey.toBuffer()],
+      escrowProgram.programId
+    );
+
+    await escrowProgram.methods
+      .initializeSol(
+        null,
+        Array.from(uniqueId),
+        1,
+        new BN(24),
+        new BN(amount),
+        { hashlock: null, stream: null, oracle: null, moderatorFeeBpsMax: 0, moderatorFeeFixed: new BN(0), panel: null, requireRegisteredModerator: false, minSellerSettlements: new BN(0), receiptTree: null, releaseHook: null }
+      )
+      .accounts({
+        payer: buyer.publicKey,
+        buyer: buyer.publicKey,
+        seller: escrowSeller,
+        escrowAccount: escrow,
+        receiptTree: null,
+        moderatorProfile: null,
+        registryConfig: null,
+        sellerReputation: null,
+      })
+      .signers([buyer])
+      .rpc();
+
+    const message = Buffer.concat([
+      uniqueId,
+      Buffer.from(new BN(0).toArray("le", 8)), // nonce
+      Buffer.from(new BN(0).toArray("le", 8)), // valid_until
+      escrowSeller.toBuffer(),
+      Buffer.from(new BN(amount).toArray("le", 8)),
+    ]);
+    await escrowProgram.methods
+      .releaseSol(
+        [new BN(amount)],
+        signers.map((keypair) => Buffer.from(nacl.sign.detached(message, keypair.secretKey))),
+        new BN(0)
+      )
+      .accounts({
+        initiator: buyer.publicKey,
+        escrowAccount: escrow,
+        buyer: buyer.publicKey,
+        seller: escrowSeller,
+        sellerReputation,
+        buyerReputation,
+        receiptTree: null,
+        sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        recipient1: escrowSeller,
+        recipient2: null,
+        recipient3: null,
+      })
+      .preInstructions([ed25519Instruction(message, signers)])
+      .signers([buyer])
+      .rpc();
+
+    return escrow;
+  };
+
+  const claimRewards = (escrow, escrowSeller) => {
+    const [receipt] = PublicKey.findProgramAddressSync(
+      [Buffer.from("receipt"), escrow.toBuffer()],
+      escrowProgram.programId
+    );
+    const [sellerRewards] = PublicKey.findProgramAddressSync(
+      [Buffer.from("seller_rewards"), promotionPDA.toBuffer(), escrowSeller.toBuffer()],
+      program.programId
+    );
+    const [buyerReward] = PublicKey.findProgramAddressSync(
+      [Buffer.from("buyer_reward"), promotionPDA.toBuffer(), escrowSeller.toBuffer(), buyer.publicKey.toBuffer()],
+      program.programId
+    );
+    const [claim] = PublicKey.findProgramAddressSync(
+      [Buffer.from("reward_claim"), promotionPDA.toBuffer(), escrow.toBuffer()],
+      program.programId
+    );
+
+    return program.methods
+      .claimRewards()
+      .accounts({
+        buyer: buyer.publicKey,
+        promotion: promotionPDA,
+        receipt,
+        sellerRewards,
+        buyerReward,
+        claim,
+        rewardVault,
+        buyerTokenAccount,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([buyer])
+      .rpc();
+  };
+
+  before(async () => {
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(buyer.publicKey, 2 * LAMPORTS_PER_SOL),
+      "confirmed"
+    );
+
+    rewardMint = await createMint(provider.connection, payer, payer.publicKey, null, 6);
+    rewardVault = getAssociatedTokenAddressSync(rewardMint, promotionPDA, true);
+    buyerTokenAccount = await createAccount(provider.connection, payer, rewardMint, buyer.publicKey);
+  });
+
+  it("Initialize and start a promotion", async () => {
+    await program.methods
+      .initializePromotion(promotionId, new BN(500_000_000), new BN(7 * 24 * 3600), null, new BN(minSettlement))
+      .accounts({
+        promotion: promotionPDA,
+        rewardMint,
+        rewardVault,
+        authority: provider.wallet.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+    await mintTo(provider.connection, payer, rewardMint, rewardVault, payer, 1_000_000_000);
+
+    await program.methods
+      .addPromotedSellers([seller.publicKey])
+      .accounts({ promotion: promotionPDA, authority: provider.wallet.publicKey })
+      .rpc();
+    await program.methods
+      .turnOnRewards()
+      .accounts({ promotion: promotionPDA, authority: provider.wallet.publicKey })
+      .rpc();
+    await program.methods
+      .setEndDate(new BN(Math.floor(Date.now() / 1000) + 30 * 24 * 3600))
+      .accounts({ promotion: promotionPDA, authority: provider.wallet.publicKey })
+      .rpc();
+
+    const promotion = await program.account.promotion.fetch(promotionPDA);
+    expect(promotion.maxRewardPerBuyer.toNumber()).to.equal(rewardPerBuyer);
+    expect(promotion.promotedSellers.map((s) => s.toString())).to.deep.equal([seller.publicKey.toString()]);
+    expect(promotion.rewardsOn).to.equal(true);
+    expect(promotion.settlementMint).to.equal(null);
+    expect(promotion.minSettlementAmount.toNumber()).to.equal(minSettlement);
+  });
+
+  it("Releases the seller did not approve earn nothing", async () => {
+    const escrow = await settleEscrow(seller, { signers: [buyer] });
+    const receipt = await escrowProgram.account.settlementReceipt.fetch(
+      PublicKey.findProgramAddressSync([Buffer.from("receipt"), escrow.toBuffer()], escrowProgram.programId)[0]
+    );
+    expect(receipt.record.sellerApproved).to.equal(false);
+
+    try {
+      await claimRewards(escrow, seller.publicKey);
+      expect.fail("Buyer alone released to the seller");
+    } catch (e) {
+      expect(e.toString()).to.include("SellerApprovalMissing");
+    }
+  });
+
+  it("Settlements below the promotion minimum earn nothing", async () => {
+    const escrow = await settleEscrow(seller, { amount: minSettlement / 2 });
+    try {
+      await claimRewards(escrow, seller.publicKey);
+      expect.fail("Settlement is below the minimum");
+    } catch (e) {
+      expect(e.toString()).to.include("SettlementBelowMinimum");
+    }
+  });
+
+  it("Buyer claims once per settlement receipt", async () => {
+    const escrow = await settleEscrow(seller);
+    await claimRewards(escrow, seller.publicKey);
+
+    const account = await getAccount(provider.connection, buyerTokenAccount);
+    expect(Number(account.amount)).to.equal(rewardPerBuyer);
+
+    try {
+      await claimRewards(escrow, seller.publicKey);
+      expect.fail("Second claim with the same receipt should fail");
+    } catch (e) {
+      expect(e.toString()).to.include("AlreadyClaimed");
+    }
+  });
+
+  it("A second purchase from the same seller earns nothing more", async () => {
+    const escrow = await settleEscrow(seller);
+    try {
+      await claimRewards(escrow, seller.publicKey);
+      expect.fail("Buyer already received the per-seller reward");
+    } catch (e) {
+      expect(e.toString()).to.include("NoRewardAvailable");
+    }
+  });
+
+  it("Purchases from sellers that are not promoted earn nothing", async () => {
+    const otherSeller = Keypair.generate();
+    const escrow = await settleEscrow(otherSeller);
+    try {
+      await claimRewards(escrow, otherSeller.publicKey);
+      expect.fail("Seller is not promoted");
+    } catch (e) {
+      expect(e.toString()).to.include("SellerNotPromoted");
+    }
+  });
+});